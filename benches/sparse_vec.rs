@@ -0,0 +1,672 @@
+use std::{collections::HashMap, time::Duration};
+
+use criterion::{criterion_group, criterion_main, measurement::WallTime, BatchSize, BenchmarkGroup, BenchmarkId, Criterion};
+use vec_plus::vec::{adaptive_vec::AdaptiveVec, default_sparse_vec::DefaultSparseVec, gap_sparse_vec::GapSparseVec, vec_trait::{Math, UnsignedMath}, workspace::SparseWorkspace};
+
+/// ベンチマークの長さ
+const LENS: [usize; 3] = [1_000, 100_000, 10_000_000];
+/// 非デフォルト値の割合
+const DENSITIES: [f64; 3] = [0.001, 0.01, 0.1];
+/// insert / remove / get / 密にする操作を 1 回の計測で行う回数
+const OPS: usize = 100;
+
+/// 決定的な疑似乱数 (xorshift64)
+/// 実行ごとに同じ入力になるよう、外部クレートは使いません
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 <= p
+    }
+}
+
+/// 同じ内容の 3 つの表現
+struct Inputs {
+    dense: Vec<f64>,
+    sparse: DefaultSparseVec<f64>,
+    map: HashMap<usize, f64>,
+}
+
+fn dense_input(len: usize, density: f64, seed: u64) -> Vec<f64> {
+    let mut rng = Rng::new(seed);
+    (0..len)
+        .map(|_| if rng.chance(density) { 1.0 + rng.below(100) as f64 } else { 0.0 })
+        .collect()
+}
+
+fn inputs(len: usize, density: f64, seed: u64) -> Inputs {
+    let dense = dense_input(len, density, seed);
+    let sparse = dense.iter().copied().collect();
+    let map = dense
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| **v != 0.0)
+        .map(|(i, v)| (i, *v))
+        .collect();
+    Inputs { dense, sparse, map }
+}
+
+fn positions(len: usize, seed: u64) -> Vec<usize> {
+    let mut rng = Rng::new(seed);
+    (0..OPS).map(|_| rng.below(len)).collect()
+}
+
+fn param(len: usize, density: f64) -> String {
+    format!("len={len}/density={density}")
+}
+
+fn configure<'a>(c: &'a mut Criterion, name: &str) -> BenchmarkGroup<'a, WallTime> {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(3));
+    group
+}
+
+fn push(c: &mut Criterion) {
+    for (name, density) in [("push_dense", 1.0), ("push_sparse", 0.01)] {
+        let mut group = configure(c, name);
+        for len in LENS {
+            let values = dense_input(len, density, 1);
+            let p = param(len, density);
+            group.bench_with_input(BenchmarkId::new("sparse", &p), &values, |b, values| {
+                b.iter(|| {
+                    let mut svec = DefaultSparseVec::new();
+                    values.iter().for_each(|v| svec.push(*v));
+                    svec
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("vec", &p), &values, |b, values| {
+                b.iter(|| {
+                    let mut vec = Vec::new();
+                    values.iter().for_each(|v| vec.push(*v));
+                    vec
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("hashmap", &p), &values, |b, values| {
+                b.iter(|| {
+                    let mut map = HashMap::new();
+                    for (i, v) in values.iter().enumerate() {
+                        if *v != 0.0 {
+                            map.insert(i, *v);
+                        }
+                    }
+                    map
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+fn from_vec(c: &mut Criterion) {
+    let mut group = configure(c, "from_vec");
+    for len in LENS {
+        for density in DENSITIES {
+            let values = dense_input(len, density, 2);
+            group.bench_with_input(BenchmarkId::new("sparse", param(len, density)), &values, |b, values| {
+                b.iter_batched(|| values.clone(), DefaultSparseVec::from, BatchSize::LargeInput)
+            });
+        }
+    }
+    group.finish();
+}
+
+fn get_random(c: &mut Criterion) {
+    let mut group = configure(c, "get_random");
+    for len in LENS {
+        for density in DENSITIES {
+            let input = inputs(len, density, 3);
+            let at = positions(len, 4);
+            let p = param(len, density);
+            group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+                b.iter(|| at.iter().map(|&i| *input.sparse.get(i).unwrap()).sum::<f64>())
+            });
+            group.bench_function(BenchmarkId::new("vec", &p), |b| {
+                b.iter(|| at.iter().map(|&i| input.dense[i]).sum::<f64>())
+            });
+            group.bench_function(BenchmarkId::new("hashmap", &p), |b| {
+                b.iter(|| at.iter().map(|i| input.map.get(i).copied().unwrap_or(0.0)).sum::<f64>())
+            });
+        }
+    }
+    group.finish();
+}
+
+fn insert_remove_random(c: &mut Criterion) {
+    for name in ["insert_random", "remove_random"] {
+        let mut group = configure(c, name);
+        for len in LENS {
+            for density in DENSITIES {
+                let input = inputs(len, density, 5);
+                let at = positions(len, 6);
+                let p = param(len, density);
+                group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+                    b.iter_batched(
+                        || input.sparse.clone(),
+                        |mut svec| {
+                            for &i in &at {
+                                if name == "insert_random" {
+                                    svec.insert(i, 1.0);
+                                } else {
+                                    svec.remove(i.min(svec.len() - 1));
+                                }
+                            }
+                            svec
+                        },
+                        BatchSize::LargeInput,
+                    )
+                });
+                group.bench_function(BenchmarkId::new("vec", &p), |b| {
+                    b.iter_batched(
+                        || input.dense.clone(),
+                        |mut vec| {
+                            for &i in &at {
+                                if name == "insert_random" {
+                                    vec.insert(i, 1.0);
+                                } else {
+                                    vec.remove(i.min(vec.len() - 1));
+                                }
+                            }
+                            vec
+                        },
+                        BatchSize::LargeInput,
+                    )
+                });
+                // HashMap は論理位置をずらせないので、全キーを付け直す必要がある
+                group.bench_function(BenchmarkId::new("hashmap", &p), |b| {
+                    b.iter_batched(
+                        || input.map.clone(),
+                        |mut map| {
+                            for &i in &at {
+                                let shifted: HashMap<usize, f64> = if name == "insert_random" {
+                                    map.drain().map(|(k, v)| if k >= i { (k + 1, v) } else { (k, v) }).collect()
+                                } else {
+                                    map.remove(&i);
+                                    map.drain().map(|(k, v)| if k > i { (k - 1, v) } else { (k, v) }).collect()
+                                };
+                                map = shifted;
+                                if name == "insert_random" {
+                                    map.insert(i, 1.0);
+                                }
+                            }
+                            map
+                        },
+                        BatchSize::LargeInput,
+                    )
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+fn dot(c: &mut Criterion) {
+    let mut group = configure(c, "dot");
+    for len in LENS {
+        for density in DENSITIES {
+            // nnz 比 1:1 と 10:1
+            for ratio in [1.0, 10.0] {
+                let a = inputs(len, density, 7);
+                let b_in = inputs(len, density / ratio, 8);
+                let p = format!("{}/ratio={ratio}", param(len, density));
+                group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+                    b.iter(|| a.sparse.f64_dot(&b_in.sparse))
+                });
+                group.bench_function(BenchmarkId::new("vec", &p), |b| {
+                    b.iter(|| a.dense.iter().zip(&b_in.dense).map(|(x, y)| x * y).sum::<f64>())
+                });
+                group.bench_function(BenchmarkId::new("hashmap", &p), |b| {
+                    b.iter(|| {
+                        b_in.map
+                            .iter()
+                            .filter_map(|(k, y)| a.map.get(k).map(|x| x * y))
+                            .sum::<f64>()
+                    })
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+/// nnz が大きい場合の内積 (nnz = 5M、ブロック単位の走査になる)
+/// - random: len = 50M にランダムに散らばった index
+/// - clustered: 長さ 1..=128 の連続した index のかたまりが、ランダムな間隔で並ぶ
+fn dot_large(c: &mut Criterion) {
+    const NNZ: usize = 5_000_000;
+    let random = |seed: u64| {
+        let mut rng = Rng::new(seed);
+        let mut index = 0;
+        let events: Vec<(usize, u64)> = (0..NNZ)
+            .map(|_| {
+                index += 1 + rng.below(19);
+                (index, 1 + rng.below(1000) as u64)
+            })
+            .collect();
+        DefaultSparseVec::from_events(index + 1, events)
+    };
+    let clustered = |seed: u64| {
+        let mut rng = Rng::new(seed);
+        let mut index = 0;
+        let mut events: Vec<(usize, u64)> = Vec::with_capacity(NNZ);
+        while events.len() < NNZ {
+            index += 1 + rng.below(2000);
+            let run = (1 + rng.below(128)).min(NNZ - events.len());
+            events.extend((index..index + run).map(|i| (i, 1 + rng.below(1000) as u64)));
+            index += run;
+        }
+        DefaultSparseVec::from_events(index + 1, events)
+    };
+    let mut group = configure(c, "dot_large");
+    for (name, a, b_in) in [
+        ("random", random(17), random(18)),
+        ("clustered", clustered(17), clustered(18)),
+    ] {
+        let p = format!("{name}/nnz={NNZ}");
+        group.bench_function(BenchmarkId::new("u64_dot", &p), |b| b.iter(|| a.u64_dot(&b_in)));
+        group.bench_function(BenchmarkId::new("f64_dot", &p), |b| b.iter(|| a.f64_dot(&b_in)));
+    }
+    group.finish();
+}
+
+fn append(c: &mut Criterion) {
+    let mut group = configure(c, "append");
+    for len in LENS {
+        for density in DENSITIES {
+            let left = inputs(len, density, 9);
+            let right = inputs(len, density, 10);
+            let p = param(len, density);
+            group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+                b.iter_batched(
+                    || (left.sparse.clone(), right.sparse.clone()),
+                    |(mut l, mut r)| {
+                        l.append(&mut r);
+                        l
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_function(BenchmarkId::new("vec", &p), |b| {
+                b.iter_batched(
+                    || (left.dense.clone(), right.dense.clone()),
+                    |(mut l, mut r)| {
+                        l.append(&mut r);
+                        l
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_function(BenchmarkId::new("hashmap", &p), |b| {
+                b.iter_batched(
+                    || (left.map.clone(), right.map.clone()),
+                    |(mut l, r)| {
+                        l.extend(r.into_iter().map(|(k, v)| (k + len, v)));
+                        l
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+fn densify(c: &mut Criterion) {
+    let mut group = configure(c, "densify");
+    for len in LENS {
+        for density in DENSITIES {
+            let input = inputs(len, density, 11);
+            let at = positions(len, 12);
+            let p = param(len, density);
+            group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+                b.iter_batched(
+                    || input.sparse.clone(),
+                    |mut svec| {
+                        for &i in &at {
+                            svec[i] += 1.0;
+                        }
+                        svec
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_function(BenchmarkId::new("vec", &p), |b| {
+                b.iter_batched(
+                    || input.dense.clone(),
+                    |mut vec| {
+                        for &i in &at {
+                            vec[i] += 1.0;
+                        }
+                        vec
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_function(BenchmarkId::new("hashmap", &p), |b| {
+                b.iter_batched(
+                    || input.map.clone(),
+                    |mut map| {
+                        for &i in &at {
+                            *map.entry(i).or_insert(0.0) += 1.0;
+                        }
+                        map
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+/// 密な Vec への変換
+/// - collect_dense: len ちょうどを 1 回確保して埋める
+/// - iter_dense: ExactSizeIterator の collect
+/// - get_loop: get を len 回呼んで push する (以前の From<DefaultSparseVec> の実装)
+fn collect_dense(c: &mut Criterion) {
+    let mut group = configure(c, "collect_dense");
+    for len in LENS {
+        for density in DENSITIES {
+            let input = inputs(len, density, 16);
+            let p = param(len, density);
+            group.bench_function(BenchmarkId::new("collect_dense", &p), |b| {
+                b.iter(|| input.sparse.collect_dense())
+            });
+            group.bench_function(BenchmarkId::new("iter_dense", &p), |b| {
+                b.iter(|| input.sparse.iter_dense().copied().collect::<Vec<f64>>())
+            });
+            group.bench_function(BenchmarkId::new("get_loop", &p), |b| {
+                b.iter(|| {
+                    let mut vec = Vec::new();
+                    (0..len).for_each(|i| vec.push(*input.sparse.get(i).unwrap()));
+                    vec
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// 密度が閾値を越える前後で、DefaultSparseVec と AdaptiveVec を比べます
+/// - accumulate: 空の状態からランダムな位置に加算し続け、最終的に密度が約 63% になる
+/// - dot: 密度ごとの内積 (AdaptiveVec は密度 50% を超えると Dense の kernel になる)
+fn adaptive(c: &mut Criterion) {
+    let len = 100_000;
+    let mut group = configure(c, "adaptive_accumulate");
+    let mut rng = Rng::new(13);
+    let at: Vec<usize> = (0..len).map(|_| rng.below(len)).collect();
+    let p = format!("len={len}/adds={len}");
+    group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+        b.iter(|| {
+            let mut svec = DefaultSparseVec::from(vec![0.0; len]);
+            for &i in &at {
+                svec[i] += 1.0;
+            }
+            svec
+        })
+    });
+    group.bench_function(BenchmarkId::new("adaptive", &p), |b| {
+        b.iter(|| {
+            let mut avec = AdaptiveVec::from(DefaultSparseVec::from(vec![0.0; len]));
+            for &i in &at {
+                avec.set(i, avec[i] + 1.0);
+            }
+            avec
+        })
+    });
+    group.bench_function(BenchmarkId::new("vec", &p), |b| {
+        b.iter(|| {
+            let mut vec = vec![0.0; len];
+            for &i in &at {
+                vec[i] += 1.0;
+            }
+            vec
+        })
+    });
+    group.finish();
+
+    let mut group = configure(c, "adaptive_dot");
+    for density in [0.1, 0.4, 0.6, 0.9] {
+        let a = inputs(len, density, 14);
+        let b_in = inputs(len, density, 15);
+        let a_adaptive = AdaptiveVec::from(a.sparse.clone());
+        let b_adaptive = AdaptiveVec::from(b_in.sparse.clone());
+        let p = param(len, density);
+        group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+            b.iter(|| a.sparse.f64_dot(&b_in.sparse))
+        });
+        group.bench_function(BenchmarkId::new("adaptive", &p), |b| {
+            b.iter(|| a_adaptive.f64_dot(&b_adaptive))
+        });
+    }
+    group.finish();
+}
+
+/// 近い位置にまとまった insert で、DefaultSparseVec と GapSparseVec を比べます
+/// 空の状態から 100k 回挿入し、挿入位置は 1000 回ごとにランダムな中心へ移り、その後ろ 64 以内に散らばる
+fn insert_clustered(c: &mut Criterion) {
+    const INSERTS: usize = 100_000;
+    let mut rng = Rng::new(16);
+    let mut center = 0;
+    let at: Vec<usize> = (0..INSERTS)
+        .map(|k| {
+            if k % 1000 == 0 {
+                center = rng.below(k + 1);
+            }
+            (center + rng.below(64)).min(k)
+        })
+        .collect();
+    let mut group = configure(c, "insert_clustered");
+    let p = format!("inserts={INSERTS}/cluster=1000x64");
+    group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+        b.iter(|| {
+            let mut svec = DefaultSparseVec::new();
+            for &i in &at {
+                svec.insert(i, 1.0);
+            }
+            svec
+        })
+    });
+    group.bench_function(BenchmarkId::new("gap", &p), |b| {
+        b.iter(|| {
+            let mut gvec = GapSparseVec::new();
+            for &i in &at {
+                gvec.insert(i, 1.0);
+            }
+            gvec
+        })
+    });
+    group.finish();
+}
+
+/// 物理要素 200k 個のベクタで remove と insert を交互に行い、後続のインデックスをずらす負荷を比べます
+/// - front: 先頭 1% の範囲 (ほぼ全ての物理要素をずらす)
+/// - back: 末尾 1% の範囲 (ずらす物理要素はわずか)
+/// - uniform: 全体からランダム
+///
+/// insert する値の半分はデフォルト値 (物理領域はシフトせず、インデックスだけずらす)
+fn churn(c: &mut Criterion) {
+    const LEN: usize = 400_000;
+    const CHURN_OPS: usize = 1_000;
+    let input = inputs(LEN, 0.5, 17);
+    let mut group = configure(c, "churn");
+    for workload in ["front", "back", "uniform"] {
+        let mut rng = Rng::new(18);
+        let window = LEN / 100;
+        let at: Vec<(usize, f64)> = (0..CHURN_OPS)
+            .map(|_| {
+                let i = match workload {
+                    "front" => rng.below(window),
+                    "back" => LEN - 1 - rng.below(window),
+                    _ => rng.below(LEN),
+                };
+                (i, if rng.chance(0.5) { 1.0 } else { 0.0 })
+            })
+            .collect();
+        let p = format!("nnz={}/{workload}", input.sparse.nnz());
+        group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+            b.iter_batched(
+                || input.sparse.clone(),
+                |mut svec| {
+                    for &(i, v) in &at {
+                        svec.remove(i);
+                        svec.insert(i, v);
+                    }
+                    svec
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("vec", &p), |b| {
+            b.iter_batched(
+                || input.dense.clone(),
+                |mut vec| {
+                    for &(i, v) in &at {
+                        vec.remove(i);
+                        vec.insert(i, v);
+                    }
+                    vec
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// 累積ベクタ (密度 20%) に、新しいイベント (密度 2%) を減衰させながら加算します
+/// - fused: decay_and_add (1 回のマージ)
+/// - split: decay_in_place の後に merge_with で加算 (物理要素を 2 回走査)
+/// - vec: 密な Vec の全要素に掛けてから加算
+fn decay(c: &mut Criterion) {
+    const LEN: usize = 1_000_000;
+    let acc_input = inputs(LEN, 0.2, 19);
+    let events_input = inputs(LEN, 0.02, 20);
+    let (acc, events) = (&acc_input.sparse, &events_input.sparse);
+    let mut group = configure(c, "decay");
+    let p = format!("nnz={}+{}", acc.nnz(), events.nnz());
+    group.bench_function(BenchmarkId::new("fused", &p), |b| {
+        b.iter_batched(
+            || acc.clone(),
+            |mut svec| {
+                svec.decay_and_add(0.9, events, 1e-6);
+                svec
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function(BenchmarkId::new("split", &p), |b| {
+        b.iter_batched(
+            || acc.clone(),
+            |mut svec| {
+                svec.decay_in_place(0.9, 1e-6);
+                svec.merge_with(events.iter().map(|(i, v)| (i, *v)), |acc, v| *acc += v);
+                svec
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function(BenchmarkId::new("vec", &p), |b| {
+        b.iter_batched(
+            || acc_input.dense.clone(),
+            |mut vec| {
+                for (elem, event) in vec.iter_mut().zip(&events_input.dense) {
+                    *elem = *elem * 0.9 + event;
+                    if elem.abs() < 1e-6 {
+                        *elem = 0.0;
+                    }
+                }
+                vec
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+/// 累積ベクタ (密度 1%) に、ランダムな位置のイベント (index の重複を含む) を加算します
+/// - sparse: scatter_add (集約してから 1 回のマージ)
+/// - workspace: scatter_add_with_workspace (整列と集約の領域を使い回す)
+/// - from_events: 空のベクタからイベントだけで構築
+/// - vec: 密な Vec への加算
+fn scatter_add(c: &mut Criterion) {
+    const LEN: usize = 1_000_000;
+    let input = inputs(LEN, 0.01, 21);
+    let mut group = configure(c, "scatter_add");
+    for events_len in [1_000, 100_000] {
+        let mut rng = Rng::new(22);
+        let events: Vec<(usize, f64)> = (0..events_len).map(|_| (rng.below(LEN), 1.0 + rng.below(10) as f64)).collect();
+        let p = format!("nnz={}/events={events_len}", input.sparse.nnz());
+        group.bench_function(BenchmarkId::new("sparse", &p), |b| {
+            b.iter_batched(
+                || input.sparse.clone(),
+                |mut svec| {
+                    svec.scatter_add(events.iter().copied());
+                    svec
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        let mut ws = SparseWorkspace::new();
+        group.bench_function(BenchmarkId::new("workspace", &p), |b| {
+            b.iter_batched(
+                || input.sparse.clone(),
+                |mut svec| {
+                    svec.scatter_add_with_workspace(events.iter().copied(), &mut ws);
+                    svec
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("from_events", &p), |b| {
+            b.iter(|| DefaultSparseVec::from_events(LEN, events.iter().copied()))
+        });
+        group.bench_function(BenchmarkId::new("vec", &p), |b| {
+            b.iter_batched(
+                || input.dense.clone(),
+                |mut vec| {
+                    for &(i, v) in &events {
+                        vec[i] += v;
+                    }
+                    vec
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    push,
+    from_vec,
+    get_random,
+    insert_remove_random,
+    dot,
+    dot_large,
+    append,
+    densify,
+    collect_dense,
+    adaptive,
+    insert_clustered,
+    churn,
+    decay,
+    scatter_add
+);
+criterion_main!(benches);
@@ -11,6 +11,7 @@ fn main() {
     svec.push(0);
     svec.insert(4, 100);
     println!("{}", svec.get(0).unwrap());
+    #[allow(deprecated)]
     let a = svec.get_mut(2).unwrap();
     *a = 100;
     let vec = vec![1, 2, 3, 4, 5];
@@ -0,0 +1,632 @@
+use std::{
+    fmt::{self, Debug},
+    mem,
+    ops::{AddAssign, Index, Mul},
+};
+
+use num::{Num, ToPrimitive};
+
+use super::{
+    default_sparse_vec::{pairwise_sum, DefaultSparseVec},
+    normal_vec_trait::NormalVecMethods,
+    vec_trait::{assert_same_len, i128_mul_add, u128_mul_add, Math, ReductionOrder, SignedElement, SignedMath, UnsignedElement, UnsignedMath},
+};
+
+/// AdaptiveThresholds構造体の定義
+/// AdaptiveVec が表現を切り替える密度 (デフォルト値でない要素数 / len)
+/// - promote: 変更操作の後に密度がこれを超えると Dense に切り替えます
+/// - demote: prune の時点で密度がこれを下回ると Sparse に戻します (None なら戻しません)
+///
+/// 2 つの閾値の間では表現を変えないので、境界付近で切り替えを繰り返しません
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveThresholds {
+    pub promote: f64,
+    pub demote: Option<f64>,
+}
+
+impl Default for AdaptiveThresholds {
+    /// 密度 50% を超えると index 配列の分だけ Vec より大きく遅くなるので、そこで Dense にします
+    #[inline(always)]
+    fn default() -> Self {
+        AdaptiveThresholds {
+            promote: 0.5,
+            demote: Some(0.25),
+        }
+    }
+}
+
+impl AdaptiveThresholds {
+    #[inline(always)]
+    pub fn new(promote: f64, demote: Option<f64>) -> Self {
+        AdaptiveThresholds { promote, demote }
+    }
+
+    /// assert_validメソッドの実装
+    /// ! : 0 < promote <= 1 かつ 0 <= demote <= promote でない場合は panic
+    #[inline(always)]
+    fn assert_valid(&self) {
+        assert!(self.promote > 0.0 && self.promote <= 1.0, "promote threshold must be in (0, 1]");
+        if let Some(demote) = self.demote {
+            assert!(demote >= 0.0 && demote <= self.promote, "demote threshold must be in [0, promote]");
+        }
+    }
+}
+
+/// AdaptiveStorage列挙型の定義
+/// AdaptiveVec の現在の表現
+#[derive(Clone)]
+pub enum AdaptiveStorage<T: Default + PartialEq> {
+    Sparse(DefaultSparseVec<T>),
+    Dense(Vec<T>),
+}
+
+/// AdaptiveVec構造体の定義
+/// 密度に応じて DefaultSparseVec<T> と Vec<T> を切り替えるベクタ
+/// - 要素を増やす操作の後に密度が promote を超えると Dense に切り替えます
+/// - Sparse への切り替えは prune を呼んだときだけ行います
+/// - どちらの表現でも値とデフォルト値は変わりません (変換は clone だけで、値の比較や丸めは行いません)
+///
+/// Dense の間はデフォルト値でない要素数を数えておくので、nnz / density は O(1) です
+#[derive(Clone)]
+pub struct AdaptiveVec<T: Default + PartialEq + Clone> {
+    storage: AdaptiveStorage<T>,
+    default: T,
+    dense_nnz: usize,
+    thresholds: AdaptiveThresholds,
+}
+
+impl<T: Default + PartialEq + Clone> AdaptiveVec<T> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_thresholds(AdaptiveThresholds::default())
+    }
+
+    /// with_thresholdsメソッドの実装
+    /// ! : 閾値が不正な場合は panic
+    #[inline(always)]
+    pub fn with_thresholds(thresholds: AdaptiveThresholds) -> Self {
+        thresholds.assert_valid();
+        AdaptiveVec {
+            storage: AdaptiveStorage::Sparse(DefaultSparseVec::new()),
+            default: T::default(),
+            dense_nnz: 0,
+            thresholds,
+        }
+    }
+
+    /// from_sparseメソッドの実装
+    /// svec のデフォルト値をそのまま引き継ぎます
+    /// 密度が promote を超えていれば Dense にします
+    pub fn from_sparse(svec: DefaultSparseVec<T>, thresholds: AdaptiveThresholds) -> Self {
+        thresholds.assert_valid();
+        let mut avec = AdaptiveVec {
+            default: svec.default_value().clone(),
+            storage: AdaptiveStorage::Sparse(svec),
+            dense_nnz: 0,
+            thresholds,
+        };
+        avec.maybe_promote();
+        avec
+    }
+
+    /// from_denseメソッドの実装
+    /// デフォルト値は T::default() です
+    /// 密度が promote 以下なら Sparse にします
+    pub fn from_dense(vec: Vec<T>, thresholds: AdaptiveThresholds) -> Self {
+        thresholds.assert_valid();
+        let default = T::default();
+        let dense_nnz = vec.iter().filter(|elem| **elem != default).count();
+        let mut avec = AdaptiveVec {
+            storage: AdaptiveStorage::Dense(vec),
+            default,
+            dense_nnz,
+            thresholds,
+        };
+        if avec.density() <= thresholds.promote {
+            avec.demote();
+        }
+        avec
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn thresholds(&self) -> AdaptiveThresholds {
+        self.thresholds
+    }
+
+    /// set_thresholdsメソッドの実装
+    /// 表現はすぐには変えず、次の変更操作 / prune から新しい閾値を使います
+    /// ! : 閾値が不正な場合は panic
+    #[inline(always)]
+    pub fn set_thresholds(&mut self, thresholds: AdaptiveThresholds) {
+        thresholds.assert_valid();
+        self.thresholds = thresholds;
+    }
+
+    /// storageメソッドの実装
+    /// 現在の表現
+    #[must_use]
+    #[inline(always)]
+    pub fn storage(&self) -> &AdaptiveStorage<T> {
+        &self.storage
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn is_dense(&self) -> bool {
+        matches!(self.storage, AdaptiveStorage::Dense(_))
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            AdaptiveStorage::Sparse(svec) => svec.len(),
+            AdaptiveStorage::Dense(vec) => vec.len(),
+        }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// nnzメソッドの実装
+    /// Sparse の場合は物理要素数、Dense の場合はデフォルト値でない要素数
+    #[must_use]
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        match &self.storage {
+            AdaptiveStorage::Sparse(svec) => svec.nnz(),
+            AdaptiveStorage::Dense(_) => self.dense_nnz,
+        }
+    }
+
+    /// densityメソッドの実装
+    /// nnz / len (空の場合は 0)
+    #[must_use]
+    #[inline(always)]
+    pub fn density(&self) -> f64 {
+        match self.len() {
+            0 => 0.0,
+            len => self.nnz() as f64 / len as f64,
+        }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        match &self.storage {
+            AdaptiveStorage::Sparse(svec) => svec.get(index),
+            AdaptiveStorage::Dense(vec) => vec.get(index),
+        }
+    }
+
+    /// setメソッドの実装
+    /// `index` 番目の要素を elem にします
+    /// ! : index が len 以上の場合は panic
+    /// - Sparse でデフォルト値を代入する場合は物理要素を削除します
+    pub fn set(&mut self, index: usize, elem: T) {
+        assert!(index < self.len(), "index out of bounds: the len is {} but the index is {index}", self.len());
+        match &mut self.storage {
+            AdaptiveStorage::Sparse(svec) => {
+                if elem == self.default {
+                    svec.remove_stored(index);
+                } else {
+                    #[allow(deprecated)]
+                    let slot = svec.get_mut(index).unwrap();
+                    *slot = elem;
+                }
+            }
+            AdaptiveStorage::Dense(vec) => {
+                let old = mem::replace(&mut vec[index], elem);
+                self.dense_nnz -= (old != self.default) as usize;
+                self.dense_nnz += (vec[index] != self.default) as usize;
+            }
+        }
+        self.maybe_promote();
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, elem: T) {
+        match &mut self.storage {
+            AdaptiveStorage::Sparse(svec) => svec.push(elem),
+            AdaptiveStorage::Dense(vec) => {
+                self.dense_nnz += (elem != self.default) as usize;
+                vec.push(elem);
+            }
+        }
+        self.maybe_promote();
+    }
+
+    /// insertメソッドの実装
+    /// ! : index が len より大きい場合は panic
+    #[inline(always)]
+    pub fn insert(&mut self, index: usize, elem: T) {
+        match &mut self.storage {
+            AdaptiveStorage::Sparse(svec) => svec.insert(index, elem),
+            AdaptiveStorage::Dense(vec) => {
+                self.dense_nnz += (elem != self.default) as usize;
+                vec.insert(index, elem);
+            }
+        }
+        self.maybe_promote();
+    }
+
+    #[inline(always)]
+    pub fn pop(&mut self) -> Option<T> {
+        match &mut self.storage {
+            AdaptiveStorage::Sparse(svec) => svec.pop(),
+            AdaptiveStorage::Dense(vec) => {
+                let elem = vec.pop()?;
+                self.dense_nnz -= (elem != self.default) as usize;
+                Some(elem)
+            }
+        }
+    }
+
+    /// removeメソッドの実装
+    /// ! : index が len 以上の場合は panic
+    #[inline(always)]
+    pub fn remove(&mut self, index: usize) -> T {
+        match &mut self.storage {
+            AdaptiveStorage::Sparse(svec) => svec.remove(index),
+            AdaptiveStorage::Dense(vec) => {
+                let elem = vec.remove(index);
+                self.dense_nnz -= (elem != self.default) as usize;
+                elem
+            }
+        }
+    }
+
+    /// pruneメソッドの実装
+    /// - Sparse: デフォルト値と等しくなった物理要素を削除します
+    /// - Dense: 密度が demote を下回っていれば Sparse に戻します
+    pub fn prune(&mut self) {
+        match &mut self.storage {
+            AdaptiveStorage::Sparse(svec) => svec.compact_stored_defaults(),
+            AdaptiveStorage::Dense(_) => {
+                if self.thresholds.demote.is_some_and(|demote| self.density() < demote) {
+                    self.demote();
+                }
+            }
+        }
+    }
+
+    /// into_sparseメソッドの実装
+    /// 表現に関わらず DefaultSparseVec<T> として取り出します
+    pub fn into_sparse(mut self) -> DefaultSparseVec<T> {
+        self.demote();
+        match self.storage {
+            AdaptiveStorage::Sparse(svec) => svec,
+            AdaptiveStorage::Dense(_) => unreachable!(),
+        }
+    }
+
+    /// into_denseメソッドの実装
+    /// 表現に関わらず Vec<T> として取り出します
+    pub fn into_dense(mut self) -> Vec<T> {
+        self.promote();
+        match self.storage {
+            AdaptiveStorage::Dense(vec) => vec,
+            AdaptiveStorage::Sparse(_) => unreachable!(),
+        }
+    }
+
+    /// maybe_promoteメソッドの実装
+    /// Sparse で密度が promote を超えていれば Dense に切り替えます
+    #[inline(always)]
+    fn maybe_promote(&mut self) {
+        if !self.is_dense() && self.density() > self.thresholds.promote {
+            self.promote();
+        }
+    }
+
+    /// promoteメソッドの実装
+    /// Dense に切り替えます (既に Dense なら何もしない) O(len)
+    fn promote(&mut self) {
+        let svec = match mem::replace(&mut self.storage, AdaptiveStorage::Dense(Vec::new())) {
+            AdaptiveStorage::Sparse(svec) => svec,
+            dense => {
+                self.storage = dense;
+                return;
+            }
+        };
+        let (len, default, indices, values) = svec.into_parts();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "vec_plus",
+            len,
+            nnz = indices.len(),
+            bytes_moved = len * mem::size_of::<T>(),
+            "adaptive vec promoted to dense"
+        );
+        let mut vec = vec![default; len];
+        let mut dense_nnz = 0;
+        for (index, elem) in indices.into_iter().zip(values) {
+            dense_nnz += (elem != self.default) as usize;
+            vec[index] = elem;
+        }
+        self.dense_nnz = dense_nnz;
+        self.storage = AdaptiveStorage::Dense(vec);
+    }
+
+    /// demoteメソッドの実装
+    /// Sparse に切り替えます (既に Sparse なら何もしない) O(len)
+    /// デフォルト値と等しい要素は物理領域に入りません
+    fn demote(&mut self) {
+        let vec = match mem::replace(&mut self.storage, AdaptiveStorage::Dense(Vec::new())) {
+            AdaptiveStorage::Dense(vec) => vec,
+            sparse => {
+                self.storage = sparse;
+                return;
+            }
+        };
+        let len = vec.len();
+        let mut indices = Vec::with_capacity(self.dense_nnz);
+        let mut values = Vec::with_capacity(self.dense_nnz);
+        for (index, elem) in vec.into_iter().enumerate() {
+            if elem != self.default {
+                indices.push(index);
+                values.push(elem);
+            }
+        }
+        self.dense_nnz = 0;
+        self.storage = AdaptiveStorage::Sparse(
+            DefaultSparseVec::from_parts(len, self.default.clone(), indices, values)
+                .expect("dense vec always gives well-formed parts"),
+        );
+    }
+
+    /// dense_pairメソッドの実装
+    /// 両方が Dense の場合は 2 つの Vec を返します
+    /// ! : 長さが異なる場合は panic (内積の長さの検査を兼ねます)
+    #[inline(always)]
+    fn dense_pair<'a>(&'a self, other: &'a Self) -> Option<(&'a [T], &'a [T])> {
+        assert_same_len(self.len(), other.len());
+        match (&self.storage, &other.storage) {
+            (AdaptiveStorage::Dense(a), AdaptiveStorage::Dense(b)) => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    /// for_each_matchedメソッドの実装
+    /// self と other の両方でデフォルト値でない index について、
+    /// index 昇順に f(self の値, other の値) を呼び出します
+    /// ! : len が異なる場合は panic
+    /// 表現の組ごとに走査方法を変えます
+    /// - Sparse × Sparse: 物理要素同士をマージ O(nnz_a + nnz_b)
+    /// - Sparse × Dense: 物理要素の index で Dense を参照 O(nnz)
+    /// - Dense × Dense: 先頭から並べて走査 O(len)
+    ///
+    /// どの組でも f に渡る値の列は同じなので、内積の結果は表現に依りません
+    fn for_each_matched<F>(&self, other: &Self, mut f: F)
+    where
+        F: FnMut(&T, &T),
+    {
+        assert_same_len(self.len(), other.len());
+        let (a_default, b_default) = (&self.default, &other.default);
+        match (&self.storage, &other.storage) {
+            (AdaptiveStorage::Sparse(a), AdaptiveStorage::Sparse(b)) => {
+                let (a_ind, a_val) = a.as_pairs();
+                let (b_ind, b_val) = b.as_pairs();
+                let mut i = 0;
+                let mut j = 0;
+                while i < a_ind.len() && j < b_ind.len() {
+                    if a_ind[i] < b_ind[j] {
+                        i += 1;
+                    } else if a_ind[i] > b_ind[j] {
+                        j += 1;
+                    } else {
+                        if a_val[i] != *a_default && b_val[j] != *b_default {
+                            f(&a_val[i], &b_val[j]);
+                        }
+                        i += 1;
+                        j += 1;
+                    }
+                }
+            }
+            (AdaptiveStorage::Sparse(a), AdaptiveStorage::Dense(b)) => {
+                let (a_ind, a_val) = a.as_pairs();
+                for (index, a_elem) in a_ind.iter().zip(a_val) {
+                    if let Some(b_elem) = b.get(*index) {
+                        if *a_elem != *a_default && *b_elem != *b_default {
+                            f(a_elem, b_elem);
+                        }
+                    }
+                }
+            }
+            (AdaptiveStorage::Dense(a), AdaptiveStorage::Sparse(b)) => {
+                let (b_ind, b_val) = b.as_pairs();
+                for (index, b_elem) in b_ind.iter().zip(b_val) {
+                    if let Some(a_elem) = a.get(*index) {
+                        if *a_elem != *a_default && *b_elem != *b_default {
+                            f(a_elem, b_elem);
+                        }
+                    }
+                }
+            }
+            (AdaptiveStorage::Dense(a), AdaptiveStorage::Dense(b)) => {
+                for (a_elem, b_elem) in a.iter().zip(b) {
+                    if *a_elem != *a_default && *b_elem != *b_default {
+                        f(a_elem, b_elem);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Clone> Default for AdaptiveVec<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default + PartialEq + Clone + Debug> Debug for AdaptiveVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.len()).map(|i| self.get(i).unwrap()))
+            .finish()
+    }
+}
+
+impl<T: Default + PartialEq + Clone> Index<usize> for AdaptiveVec<T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn index(&self, index: usize) -> &Self::Output {
+        let len = self.len();
+        self.get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the len is {len} but the index is {index}"))
+    }
+}
+
+impl<T: Default + PartialEq + Clone> From<DefaultSparseVec<T>> for AdaptiveVec<T> {
+    #[inline(always)]
+    fn from(svec: DefaultSparseVec<T>) -> Self {
+        Self::from_sparse(svec, AdaptiveThresholds::default())
+    }
+}
+
+impl<T: Default + PartialEq + Clone> From<Vec<T>> for AdaptiveVec<T> {
+    #[inline(always)]
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_dense(vec, AdaptiveThresholds::default())
+    }
+}
+
+impl<T: Default + PartialEq + Clone> From<AdaptiveVec<T>> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn from(avec: AdaptiveVec<T>) -> Self {
+        avec.into_sparse()
+    }
+}
+
+impl<T: Default + PartialEq + Clone> From<AdaptiveVec<T>> for Vec<T> {
+    #[inline(always)]
+    fn from(avec: AdaptiveVec<T>) -> Self {
+        avec.into_dense()
+    }
+}
+
+impl<T: Default + PartialEq + Clone> NormalVecMethods<T> for AdaptiveVec<T> {
+    #[inline(always)]
+    fn n_push(&mut self, elem: T) {
+        self.push(elem);
+    }
+
+    #[inline(always)]
+    fn n_pop(&mut self) -> Option<T> {
+        self.pop()
+    }
+
+    #[inline(always)]
+    fn n_insert(&mut self, index: usize, elem: T) {
+        self.insert(index, elem);
+    }
+
+    #[inline(always)]
+    fn n_remove(&mut self, index: usize) -> T {
+        self.remove(index)
+    }
+
+    #[inline(always)]
+    fn n_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        match &self.storage {
+            AdaptiveStorage::Sparse(svec) => Box::new(svec.iter_dense()),
+            AdaptiveStorage::Dense(vec) => Box::new(vec.iter()),
+        }
+    }
+}
+
+/// 内積の実装
+/// デフォルト値が 0 であることを前提に、両方でデフォルト値でない index の積だけを加算します
+/// ただし Dense × Dense の整数の内積と昇順の加算 (ReductionOrder::Ascending) は、
+/// 分岐を無くすため全要素の積を先頭から加算します
+/// - 0 の積を足すだけなので、整数は同じ結果、浮動小数点も -0.0 と 0.0 の違い以外は同じ結果です
+/// - ! : 片方に inf / NaN がある場合は、相手が 0 でも NaN が加算されます
+impl<T> Math<T> for AdaptiveVec<T>
+where
+    T: Num + Default + PartialEq + Clone + AddAssign + Mul<Output = T> + ToPrimitive,
+{
+    /// 積は各値を f64 に変換してから計算します
+    #[inline(always)]
+    fn f64_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f64 {
+        let to_f64 = |v: &T| v.to_f64().expect("value out of range for f64");
+        match order {
+            ReductionOrder::Ascending => {
+                let mut sum: f64 = 0.0;
+                match self.dense_pair(other) {
+                    Some((a, b)) => a.iter().zip(b).for_each(|(a, b)| sum += to_f64(a) * to_f64(b)),
+                    None => self.for_each_matched(other, |a, b| sum += to_f64(a) * to_f64(b)),
+                }
+                sum
+            }
+            ReductionOrder::PairwiseTree => {
+                let mut products = Vec::new();
+                self.for_each_matched(other, |a, b| products.push(to_f64(a) * to_f64(b)));
+                pairwise_sum(&products)
+            }
+        }
+    }
+
+    /// 積は各値を f32 に変換してから計算します
+    #[inline(always)]
+    fn f32_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f32 {
+        let to_f32 = |v: &T| v.to_f32().expect("value out of range for f32");
+        match order {
+            ReductionOrder::Ascending => {
+                let mut sum: f32 = 0.0;
+                match self.dense_pair(other) {
+                    Some((a, b)) => a.iter().zip(b).for_each(|(a, b)| sum += to_f32(a) * to_f32(b)),
+                    None => self.for_each_matched(other, |a, b| sum += to_f32(a) * to_f32(b)),
+                }
+                sum
+            }
+            ReductionOrder::PairwiseTree => {
+                let mut products = Vec::new();
+                self.for_each_matched(other, |a, b| products.push(to_f32(a) * to_f32(b)));
+                pairwise_sum(&products)
+            }
+        }
+    }
+}
+
+impl<T> UnsignedMath<T> for AdaptiveVec<T>
+where
+    T: Num + Default + PartialEq + Clone + UnsignedElement,
+{
+    #[inline(always)]
+    fn u128_dot(&self, other: &Self) -> u128 {
+        let mut sum: u128 = 0;
+        match self.dense_pair(other) {
+            Some((a, b)) => a.iter().zip(b).for_each(|(a, b)| sum = u128_mul_add(sum, a.widen(), b.widen())),
+            None => self.for_each_matched(other, |a, b| sum = u128_mul_add(sum, a.widen(), b.widen())),
+        }
+        sum
+    }
+}
+
+impl<T> SignedMath<T> for AdaptiveVec<T>
+where
+    T: Num + Default + PartialEq + Clone + SignedElement,
+{
+    #[inline(always)]
+    fn i128_dot(&self, other: &Self) -> i128 {
+        let mut sum: i128 = 0;
+        match self.dense_pair(other) {
+            Some((a, b)) => a.iter().zip(b).for_each(|(a, b)| sum = i128_mul_add(sum, a.widen(), b.widen())),
+            None => self.for_each_matched(other, |a, b| sum = i128_mul_add(sum, a.widen(), b.widen())),
+        }
+        sum
+    }
+}
@@ -0,0 +1,260 @@
+use std::{alloc::{GlobalAlloc, Layout, System}, cell::Cell, fmt::{self, Write}, hint::black_box};
+
+use super::default_sparse_vec::DefaultSparseVec;
+
+thread_local! {
+    /// このスレッドで数えた確保の回数 (alloc / alloc_zeroed / realloc)
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    /// このスレッドで確保して、まだ解放していないバイト数 (他のスレッドで解放されると負にもなります)
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+    /// peak_bytes の計測中の LIVE_BYTES の最大値
+    static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+/// CountingAlloc構造体の定義
+/// System に委譲しつつ、スレッドごとに確保の回数とバイト数を数えるアロケータ
+/// テストのバイナリで `#[global_allocator]` に設定してから count_allocations / peak_bytes / audit_allocations を使います
+/// - 解放は回数には数えず、バイト数だけを減らします
+pub struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    #[inline(always)]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        bump();
+        track(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    #[inline(always)]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        bump();
+        track(layout.size() as isize);
+        System.alloc_zeroed(layout)
+    }
+
+    #[inline(always)]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        bump();
+        track(new_size as isize - layout.size() as isize);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    #[inline(always)]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[inline(always)]
+fn bump() {
+    // スレッドの終了処理中は数えません
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+/// track関数の実装
+/// 確保しているバイト数を delta だけ増減して、最大値を更新します
+#[inline(always)]
+fn track(delta: isize) {
+    let _ = LIVE_BYTES.try_with(|live| {
+        live.set(live.get() + delta);
+        let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live.get())));
+    });
+}
+
+/// count_allocations関数の実装
+/// f を実行し、その間にこのスレッドで行われた確保の回数を返します
+/// ! : CountingAlloc が global_allocator でない場合は常に 0
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let out = f();
+    (out, ALLOCATIONS.with(Cell::get) - before)
+}
+
+/// peak_bytes関数の実装
+/// f を実行し、その間にこのスレッドで同時に確保していたバイト数の最大値 (実行前からの増分) を返します
+/// ! : CountingAlloc が global_allocator でない場合は常に 0
+pub fn peak_bytes<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = LIVE_BYTES.with(Cell::get);
+    PEAK_BYTES.with(|peak| peak.set(before));
+    let out = f();
+    (out, (PEAK_BYTES.with(Cell::get) - before) as usize)
+}
+
+/// is_counting関数の実装
+/// CountingAlloc が global_allocator として確保を数えていれば true
+pub fn is_counting() -> bool {
+    count_allocations(|| drop(black_box(Box::new(0u8)))).1 > 0
+}
+
+/// AllocAuditEntry構造体の定義
+/// audit_allocations の 1 行 (操作、想定する確保の回数、実測した回数)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocAuditEntry {
+    pub operation: &'static str,
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl AllocAuditEntry {
+    #[must_use]
+    #[inline(always)]
+    pub fn is_ok(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+impl fmt::Display for AllocAuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<40} expected {:>2}, actual {:>2}", self.operation, self.expected, self.actual)?;
+        if !self.is_ok() {
+            write!(f, "  <-- mismatch")?;
+        }
+        Ok(())
+    }
+}
+
+/// 監査する 1 行 (操作名、想定する確保の回数、準備、計測する操作)
+/// 準備 (prepare) の確保は数えません
+type AuditCase = (&'static str, usize, fn() -> DefaultSparseVec<i64>, fn(&mut DefaultSparseVec<i64>));
+
+/// 空のベクタ
+fn empty() -> DefaultSparseVec<i64> {
+    DefaultSparseVec::new()
+}
+
+/// 物理要素 2 個、容量 4 のベクタ (len 6)
+fn small() -> DefaultSparseVec<i64> {
+    let mut vec = DefaultSparseVec::with_capacity(4);
+    vec.extend([0, 1, 0, 0, 2, 0]);
+    vec
+}
+
+/// 容量いっぱい (物理要素 2 個、容量 2) のベクタ
+fn full() -> DefaultSparseVec<i64> {
+    let mut vec = small();
+    vec.shrink_to_fit();
+    vec
+}
+
+/// 確保の回数の表
+/// DefaultSparseVec の主な操作が何回確保するか (物理領域はインデックスと値の 2 つの確保です)
+/// - 0 の行は確保しないことの保証です (new / default や空のベクタの読み取り、フォーマットなど)
+/// - 容量内の書き込みは確保しません。容量を超えると 2 回 (インデックスの確保と値の realloc)
+const AUDIT_TABLE: &[AuditCase] = &[
+    // 作成
+    ("new", 0, empty, |vec| *vec = black_box(DefaultSparseVec::new())),
+    ("default", 0, empty, |vec| *vec = black_box(DefaultSparseVec::default())),
+    ("with_default", 0, empty, |vec| *vec = black_box(DefaultSparseVec::with_default(7))),
+    ("with_capacity(0)", 0, empty, |vec| *vec = black_box(DefaultSparseVec::with_capacity(0))),
+    ("with_capacity(16)", 2, empty, |vec| *vec = black_box(DefaultSparseVec::with_capacity(16))),
+    ("with_nnz_capacity(1000, 0)", 0, empty, |vec| *vec = black_box(DefaultSparseVec::with_nnz_capacity(1000, 0))),
+    ("from(Vec::new())", 0, empty, |vec| *vec = black_box(DefaultSparseVec::from(Vec::new()))),
+    ("from(vec![0; 8]) (only the input Vec)", 1, empty, |vec| *vec = black_box(DefaultSparseVec::from(vec![0; 8]))),
+    // 空のベクタの読み取り
+    ("is_empty / len / nnz (empty)", 0, empty, |vec| {
+        black_box((vec.is_empty(), vec.len(), vec.nnz()));
+    }),
+    ("get (empty)", 0, empty, |vec| {
+        black_box(vec.get(0));
+    }),
+    ("iter / iter_dense (empty)", 0, empty, |vec| {
+        black_box((vec.iter().count(), vec.iter_dense().count()));
+    }),
+    ("clone (empty)", 0, empty, |vec| drop(black_box(vec.clone()))),
+    ("collect_dense (empty)", 0, empty, |vec| drop(black_box(vec.collect_dense()))),
+    ("Debug {:?} / {:#?} / {:+?} (empty)", 0, empty, |vec| {
+        write!(Sink, "{vec:?} {vec:#?} {vec:+?}").unwrap();
+    }),
+    ("Display (empty)", 0, empty, |vec| write!(Sink, "{vec}").unwrap()),
+    ("clear / truncate / shrink_to_fit (empty)", 0, empty, |vec| {
+        vec.clear();
+        vec.truncate(0);
+        vec.shrink_to_fit();
+    }),
+    ("reserve(0)", 0, empty, |vec| vec.reserve(0)),
+    // 空のベクタへの書き込み
+    ("push default (empty)", 0, empty, |vec| vec.push(0)),
+    ("push non-default (empty)", 2, empty, |vec| vec.push(1)),
+    // 物理要素のあるベクタ
+    ("get / iter / iter_dense", 0, small, |vec| {
+        black_box((vec.get(1), vec.iter().count(), vec.iter_dense().count()));
+    }),
+    ("Debug {:?} / {:#?} / {:+?}", 0, small, |vec| {
+        write!(Sink, "{vec:?} {vec:#?} {vec:+?}").unwrap();
+    }),
+    ("Display", 0, small, |vec| write!(Sink, "{vec}").unwrap()),
+    ("iter_mut / retain / truncate", 0, small, |vec| {
+        vec.iter_mut().for_each(|(_, elem)| *elem += 1);
+        vec.retain(|_, elem| *elem > 2);
+        vec.truncate(3);
+    }),
+    ("push non-default (within capacity)", 0, small, |vec| vec.push(3)),
+    ("push non-default (at capacity)", 2, full, |vec| vec.push(3)),
+    ("insert / remove (within capacity)", 0, small, |vec| {
+        vec.insert(0, 5);
+        vec.remove(0);
+    }),
+    ("set (non-default, then default)", 0, small, |vec| {
+        vec.set(0, 5);
+        vec.set(0, 0);
+    }),
+    ("pop / clear", 0, small, |vec| {
+        vec.pop();
+        vec.clear();
+    }),
+    ("clone", 2, small, |vec| drop(black_box(vec.clone()))),
+    ("collect_dense / to_dense", 2, small, |vec| {
+        drop(black_box(vec.collect_dense()));
+        drop(black_box(vec.to_dense()));
+    }),
+    ("Vec::from (consuming)", 1, small, |vec| drop(black_box(Vec::from(std::mem::take(vec))))),
+    ("write_dense_into", 0, small, |vec| {
+        let mut out = [0; 6];
+        vec.write_dense_into(&mut out);
+        black_box(out);
+    }),
+    ("shrink_to_fit", 2, small, |vec| vec.shrink_to_fit()),
+];
+
+/// Sink構造体の定義
+/// 何も書き込まない fmt::Write (フォーマット自体の確保だけを数えるため)
+struct Sink;
+
+impl Write for Sink {
+    #[inline(always)]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        black_box(s);
+        Ok(())
+    }
+}
+
+/// audit_allocations関数の実装
+/// AUDIT_TABLE の各操作を実行して、確保の回数を実測します
+/// ! : CountingAlloc が global_allocator でない場合は panic
+pub fn audit_allocations() -> Vec<AllocAuditEntry> {
+    assert!(is_counting(), "audit_allocations requires #[global_allocator] static A: CountingAlloc = CountingAlloc;");
+    AUDIT_TABLE
+        .iter()
+        .map(|&(operation, expected, prepare, run)| {
+            let mut vec = prepare();
+            let ((), actual) = count_allocations(|| run(&mut vec));
+            AllocAuditEntry { operation, expected, actual }
+        })
+        .collect()
+}
+
+/// assert_allocation_audit関数の実装
+/// audit_allocations の結果が全て表の通りであることを確かめます
+/// ! : 一致しない行があれば、表全体を表示して panic
+pub fn assert_allocation_audit() {
+    let entries = audit_allocations();
+    if entries.iter().all(AllocAuditEntry::is_ok) {
+        return;
+    }
+    let mut report = String::new();
+    for entry in &entries {
+        writeln!(report, "{entry}").unwrap();
+    }
+    panic!("allocation audit failed\n{report}");
+}
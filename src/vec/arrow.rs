@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use arrow_array::{
+    types::{Float32Type, Float64Type, Int32Type, Int64Type, UInt32Type, UInt64Type},
+    Array, ArrayRef, ArrowPrimitiveType, ListArray, PrimitiveArray, StructArray, UInt64Array,
+};
+use arrow_buffer::{ArrowNativeType, OffsetBuffer};
+use arrow_schema::{ArrowError, DataType, Field, Fields};
+
+use super::default_sparse_vec::DefaultSparseVec;
+
+/// Arrow の列に変換できる要素型
+/// `ArrowType` は対応する Arrow のプリミティブ型
+pub trait ArrowSparseElement: Default + PartialEq + ArrowNativeType {
+    type ArrowType: ArrowPrimitiveType<Native = Self>;
+}
+
+macro_rules! impl_arrow_sparse_element {
+    ($($native:ty => $arrow:ty),* $(,)?) => {
+        $(
+            impl ArrowSparseElement for $native {
+                type ArrowType = $arrow;
+            }
+        )*
+    };
+}
+
+impl_arrow_sparse_element!(
+    f32 => Float32Type,
+    f64 => Float64Type,
+    i32 => Int32Type,
+    i64 => Int64Type,
+    u32 => UInt32Type,
+    u64 => UInt64Type,
+);
+
+/// SparseVecBatch構造体の定義
+/// 複数の DefaultSparseVec を 1 つの StructArray として保持します
+/// 列構成は Parquet にそのまま書き出せる 3 列
+/// - len: UInt64 (論理的な長さ)
+/// - indices: List<UInt64> (物理要素の論理インデックス、昇順)
+/// - values: List<T> (物理要素の値)
+#[derive(Debug, Clone)]
+pub struct SparseVecBatch {
+    array: StructArray,
+}
+
+impl SparseVecBatch {
+    /// from_vecsメソッドの実装
+    /// ベクタ列を StructArray に変換します
+    /// 物理要素だけを書き出すので、サイズは nnz に比例します
+    pub fn from_vecs<T: ArrowSparseElement>(vecs: &[DefaultSparseVec<T>]) -> Self {
+        let lens = UInt64Array::from_iter_values(vecs.iter().map(|v| v.len() as u64));
+        let offsets = OffsetBuffer::<i32>::from_lengths(vecs.iter().map(|v| v.nnz()));
+
+        let indices = UInt64Array::from_iter_values(
+            vecs.iter().flat_map(|v| v.as_pairs().0.iter().map(|&i| i as u64)),
+        );
+        let values = PrimitiveArray::<T::ArrowType>::from_iter_values(
+            vecs.iter().flat_map(|v| v.as_pairs().1.iter().copied()),
+        );
+
+        let indices = ListArray::new(
+            Arc::new(Field::new_list_field(DataType::UInt64, false)),
+            offsets.clone(),
+            Arc::new(indices),
+            None,
+        );
+        let values = ListArray::new(
+            Arc::new(Field::new_list_field(T::ArrowType::DATA_TYPE, false)),
+            offsets,
+            Arc::new(values),
+            None,
+        );
+
+        let array = StructArray::new(
+            Self::fields(T::ArrowType::DATA_TYPE),
+            vec![Arc::new(lens) as ArrayRef, Arc::new(indices), Arc::new(values)],
+            None,
+        );
+        SparseVecBatch { array }
+    }
+
+    /// try_newメソッドの実装
+    /// 外部から読み込んだ StructArray を受け取ります
+    /// 列名と列の型だけを検査し、中身の検査は to_vecs で行います
+    pub fn try_new(array: StructArray) -> Result<Self, ArrowError> {
+        let batch = SparseVecBatch { array };
+        batch.column::<UInt64Array>("len")?;
+        let indices = batch.column::<ListArray>("indices")?;
+        if indices.value_type() != DataType::UInt64 {
+            return Err(invalid("indices must be a list of UInt64"));
+        }
+        batch.column::<ListArray>("values")?;
+        Ok(batch)
+    }
+
+    /// lenメソッドの実装
+    /// 含まれるベクタの本数
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.array.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn as_struct_array(&self) -> &StructArray {
+        &self.array
+    }
+
+    #[inline(always)]
+    pub fn into_struct_array(self) -> StructArray {
+        self.array
+    }
+
+    /// to_vecsメソッドの実装
+    /// StructArray をベクタ列に戻します
+    /// 次の場合はエラーを返します
+    /// - 列の型が T と一致しない、または null を含む
+    /// - indices と values の要素数が行ごとに一致しない
+    /// - indices が狭義単調増加でない、または len 以上の index を含む
+    pub fn to_vecs<T: ArrowSparseElement>(&self) -> Result<Vec<DefaultSparseVec<T>>, ArrowError> {
+        let lens = self.column::<UInt64Array>("len")?;
+        let indices = self.column::<ListArray>("indices")?;
+        let values = self.column::<ListArray>("values")?;
+        if lens.null_count() != 0 || indices.null_count() != 0 || values.null_count() != 0 {
+            return Err(invalid("sparse vector batch must not contain nulls"));
+        }
+
+        let index_values = indices
+            .values()
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .ok_or_else(|| invalid("indices must be a list of UInt64"))?;
+        let value_values = values
+            .values()
+            .as_any()
+            .downcast_ref::<PrimitiveArray<T::ArrowType>>()
+            .ok_or_else(|| invalid("values type does not match the requested element type"))?;
+        if index_values.null_count() != 0 || value_values.null_count() != 0 {
+            return Err(invalid("sparse vector batch must not contain nulls"));
+        }
+
+        let index_offsets = indices.value_offsets();
+        let value_offsets = values.value_offsets();
+        let mut vecs = Vec::with_capacity(self.len());
+        for row in 0..self.len() {
+            let len = usize::try_from(lens.value(row))
+                .map_err(|_| invalid(format!("row {row}: len does not fit in usize")))?;
+            let (start, end) = (index_offsets[row] as usize, index_offsets[row + 1] as usize);
+            if value_offsets[row + 1] - value_offsets[row] != index_offsets[row + 1] - index_offsets[row] {
+                return Err(invalid(format!("row {row}: indices and values have different lengths")));
+            }
+            let value_start = value_offsets[row] as usize;
+
+            let row_indices = &index_values.values()[start..end];
+            let mut prev: Option<u64> = None;
+            for &index in row_indices {
+                if prev.is_some_and(|p| p >= index) {
+                    return Err(invalid(format!("row {row}: indices are not strictly increasing")));
+                }
+                if index >= len as u64 {
+                    return Err(invalid(format!("row {row}: index {index} out of bounds for len {len}")));
+                }
+                prev = Some(index);
+            }
+
+            let row_values = &value_values.values()[value_start..value_start + (end - start)];
+            vecs.push(DefaultSparseVec::from_sorted_iter_unchecked(
+                len,
+                row_indices.iter().map(|&i| i as usize).zip(row_values.iter().copied()),
+            ));
+        }
+        Ok(vecs)
+    }
+
+    fn fields(value_type: DataType) -> Fields {
+        Fields::from(vec![
+            Field::new("len", DataType::UInt64, false),
+            Field::new(
+                "indices",
+                DataType::List(Arc::new(Field::new_list_field(DataType::UInt64, false))),
+                false,
+            ),
+            Field::new(
+                "values",
+                DataType::List(Arc::new(Field::new_list_field(value_type, false))),
+                false,
+            ),
+        ])
+    }
+
+    fn column<A: Array + 'static>(&self, name: &str) -> Result<&A, ArrowError> {
+        self.array
+            .column_by_name(name)
+            .ok_or_else(|| invalid(format!("missing column {name:?}")))?
+            .as_any()
+            .downcast_ref::<A>()
+            .ok_or_else(|| invalid(format!("column {name:?} has an unexpected type")))
+    }
+}
+
+impl From<SparseVecBatch> for StructArray {
+    #[inline(always)]
+    fn from(batch: SparseVecBatch) -> Self {
+        batch.array
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> ArrowError {
+    ArrowError::InvalidArgumentError(msg.into())
+}
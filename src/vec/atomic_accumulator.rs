@@ -0,0 +1,148 @@
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::default_sparse_vec::DefaultSparseVec;
+
+/// AtomicValue trait の定義
+/// AtomicDenseAccumulator に格納できる 64 bit の値
+/// - to_bits / from_bits: AtomicU64 に格納するビット列との変換
+/// - atomic_add: cell に value をアトミックに加算します
+pub trait AtomicValue: Copy + Default + PartialEq {
+    fn to_bits(self) -> u64;
+    fn from_bits(bits: u64) -> Self;
+    fn atomic_add(cell: &AtomicU64, value: Self);
+}
+
+/// u64 の加算は fetch_add なので、あふれた場合は wrapping します
+impl AtomicValue for u64 {
+    #[inline(always)]
+    fn to_bits(self) -> u64 {
+        self
+    }
+
+    #[inline(always)]
+    fn from_bits(bits: u64) -> Self {
+        bits
+    }
+
+    #[inline(always)]
+    fn atomic_add(cell: &AtomicU64, value: Self) {
+        cell.fetch_add(value, Ordering::Relaxed);
+    }
+}
+
+/// f64 の加算は compare_exchange のループです
+/// ! : 加算の順番はスレッドのスケジュールで変わるので、結果は丸め誤差の分だけ実行ごとに異なることがあります
+impl AtomicValue for f64 {
+    #[inline(always)]
+    fn to_bits(self) -> u64 {
+        f64::to_bits(self)
+    }
+
+    #[inline(always)]
+    fn from_bits(bits: u64) -> Self {
+        f64::from_bits(bits)
+    }
+
+    #[inline(always)]
+    fn atomic_add(cell: &AtomicU64, value: Self) {
+        let mut current = cell.load(Ordering::Relaxed);
+        loop {
+            let next = (f64::from_bits(current) + value).to_bits();
+            match cell.compare_exchange_weak(current, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+/// AtomicDenseAccumulator構造体の定義
+/// 複数のスレッドから同時に加算できる、長さ固定の密な加算用バッファ
+/// - add は &self で呼べるので、&AtomicDenseAccumulator を各スレッドに渡して並列に加算します
+/// - 並列の加算が終わったら into_sparse でスパースベクタに戻します
+/// - 長さ len の AtomicU64 を確保するので、メモリは nnz ではなく len に比例します
+///
+/// 加算は Relaxed で行います。結果は加算したスレッドの join (または同等の同期) の後に読んでください
+pub struct AtomicDenseAccumulator<T> {
+    cells: Vec<AtomicU64>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: AtomicValue> AtomicDenseAccumulator<T> {
+    /// newメソッドの実装
+    /// 全て T::default() の長さ len のバッファを作ります
+    pub fn new(len: usize) -> Self {
+        let zero = T::default().to_bits();
+        AtomicDenseAccumulator {
+            cells: (0..len).map(|_| AtomicU64::new(zero)).collect(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// from_sparseメソッドの実装
+    /// svec を密にした値で初期化したバッファを作ります (スパース分部は svec のデフォルト値) O(len)
+    pub fn from_sparse(svec: &DefaultSparseVec<T>) -> Self {
+        let fill = svec.default_value().to_bits();
+        let mut cells: Vec<AtomicU64> = (0..svec.len()).map(|_| AtomicU64::new(fill)).collect();
+        let (ind, val) = svec.as_pairs();
+        for (&index, elem) in ind.iter().zip(val) {
+            *cells[index].get_mut() = elem.to_bits();
+        }
+        AtomicDenseAccumulator {
+            cells,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// addメソッドの実装
+    /// index の値に value をアトミックに加算します (複数のスレッドから同時に呼べます)
+    /// - index が len 以上の場合は panic
+    #[inline(always)]
+    pub fn add(&self, index: usize, value: T) {
+        let len = self.cells.len();
+        let cell = self
+            .cells
+            .get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the len is {len} but the index is {index}"));
+        T::atomic_add(cell, value);
+    }
+
+    /// getメソッドの実装
+    /// index の現在の値 (範囲外の場合は None)
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.cells
+            .get(index)
+            .map(|cell| T::from_bits(cell.load(Ordering::Relaxed)))
+    }
+
+    /// into_sparseメソッドの実装
+    /// default と等しくない値だけを物理要素にしたスパースベクタを作ります O(len)
+    pub fn into_sparse(self, default: T) -> DefaultSparseVec<T> {
+        let len = self.cells.len();
+        let mut indices = Vec::new();
+        let mut values = Vec::new();
+        for (index, cell) in self.cells.into_iter().enumerate() {
+            let elem = T::from_bits(cell.into_inner());
+            if elem != default {
+                indices.push(index);
+                values.push(elem);
+            }
+        }
+        DefaultSparseVec::from_parts(len, default, indices, values)
+            .expect("accumulator entries are always in index order")
+    }
+}
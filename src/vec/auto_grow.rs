@@ -0,0 +1,123 @@
+use std::{
+    fmt::{self, Debug},
+    ops::{Deref, DerefMut, Index, IndexMut},
+};
+
+use super::default_sparse_vec::DefaultSparseVec;
+
+/// AutoGrowSparseVec構造体の定義
+/// len 以上の index への書き込みで len を自動的に伸ばす (grow-on-write) DefaultSparseVec のラッパー
+/// - 伸ばした分はデフォルト値で、物理領域は使いません (O(1))
+/// - 伸ばした場合、len は書き込んだ index + 1 になります
+/// - 読み込みは伸ばしません。get は len 以上で None、`[]` は len 以上でデフォルト値への参照を返します
+/// - それ以外の API は Deref / DerefMut で DefaultSparseVec のものをそのまま使えます (そちらは通常通り厳密です)
+pub struct AutoGrowSparseVec<T: Default + PartialEq> {
+    inner: DefaultSparseVec<T>,
+}
+
+impl<T: Default + PartialEq> AutoGrowSparseVec<T> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        AutoGrowSparseVec { inner: DefaultSparseVec::new() }
+    }
+
+    /// with_defaultメソッドの実装
+    /// DefaultSparseVec::with_default の grow-on-write 版
+    #[inline(always)]
+    pub fn with_default(default: T) -> Self {
+        AutoGrowSparseVec { inner: DefaultSparseVec::with_default(default) }
+    }
+
+    /// into_innerメソッドの実装
+    /// 通常の (厳密な) DefaultSparseVec に戻します
+    #[inline(always)]
+    pub fn into_inner(self) -> DefaultSparseVec<T> {
+        self.inner
+    }
+
+    /// grow_forメソッドの実装
+    /// index に書き込めるよう、必要なら len を index + 1 に伸ばします
+    #[inline(always)]
+    fn grow_for(&mut self, index: usize) {
+        if index >= self.inner.len() {
+            let new_len = index.checked_add(1).expect("capacity overflow");
+            self.inner.grow_len(new_len);
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Clone> AutoGrowSparseVec<T> {
+    /// setメソッドの実装
+    /// DefaultSparseVec::set と同じですが、len 以上なら先に len を index + 1 に伸ばします
+    /// - elem がデフォルト値なら物理要素を取り除きます (伸ばすだけで物理領域は使いません)
+    /// - 伸ばした位置の前の値はデフォルト値です
+    #[inline(always)]
+    pub fn set(&mut self, index: usize, elem: T) -> T {
+        self.grow_for(index);
+        self.inner.set(index, elem)
+    }
+}
+
+impl<T: Default + PartialEq> Deref for AutoGrowSparseVec<T> {
+    type Target = DefaultSparseVec<T>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T: Default + PartialEq> DerefMut for AutoGrowSparseVec<T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+/// len 以上の index はパニックせずにデフォルト値への参照を返します (JavaScript の疎な配列の読み込みと同じ扱い)
+impl<T: Default + PartialEq> Index<usize> for AutoGrowSparseVec<T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn index(&self, index: usize) -> &Self::Output {
+        self.inner.get(index).unwrap_or(self.inner.default_value())
+    }
+}
+
+/// len 以上の index は先に len を index + 1 に伸ばします
+/// ! : DefaultSparseVec の IndexMut と同じく、デフォルト値の位置は物理要素にしてから &mut を返します
+///     (デフォルト値を書き込むと物理要素が残るので、set を使ってください)
+impl<T: Default + PartialEq + Clone> IndexMut<usize> for AutoGrowSparseVec<T> {
+    #[inline(always)]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        self.grow_for(index);
+        &mut self.inner[index]
+    }
+}
+
+impl<T: Default + PartialEq> Default for AutoGrowSparseVec<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default + PartialEq + Clone> Clone for AutoGrowSparseVec<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        AutoGrowSparseVec { inner: self.inner.clone() }
+    }
+}
+
+impl<T: Default + PartialEq> From<DefaultSparseVec<T>> for AutoGrowSparseVec<T> {
+    #[inline(always)]
+    fn from(inner: DefaultSparseVec<T>) -> Self {
+        AutoGrowSparseVec { inner }
+    }
+}
+
+impl<T: Default + PartialEq + Debug> Debug for AutoGrowSparseVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.inner, f)
+    }
+}
@@ -0,0 +1,222 @@
+use std::{fmt::Debug, ops::Index};
+
+use super::{default_sparse_vec::DefaultSparseVec, error::ValidationMode, normal_vec_trait::NormalVecMethods};
+
+/// CheckedSparseVec構造体の定義 (feature = "shadow")
+/// Vec<T> から DefaultSparseVec<T> へ移行するときの結合テスト用ラッパ
+/// 全ての操作を DefaultSparseVec<T> と Vec<T> の両方に行い、結果が一致することを確かめます
+/// - 不一致の場合は (操作, index, 期待値, 実際の値) を含むメッセージで panic
+/// - 変更操作の後は全要素を比較するので O(len) かかります
+/// - 検査は debug_assertions が有効なときだけ行います
+pub struct CheckedSparseVec<T: Default + PartialEq + Clone + Debug> {
+    sparse: DefaultSparseVec<T>,
+    shadow: Vec<T>,
+}
+
+impl<T: Default + PartialEq + Clone + Debug> CheckedSparseVec<T> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        CheckedSparseVec {
+            sparse: DefaultSparseVec::new(),
+            shadow: Vec::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.check_result("len", None, &self.shadow.len(), &self.sparse.len());
+        self.sparse.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.check_result("is_empty", None, &self.shadow.is_empty(), &self.sparse.is_empty());
+        self.sparse.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let got = self.sparse.get(index);
+        self.check_result("get", Some(index), &self.shadow.get(index), &got);
+        got
+    }
+
+    pub fn push(&mut self, elem: T) {
+        self.shadow.push(elem.clone());
+        self.sparse.push(elem);
+        self.check_all("push");
+    }
+
+    pub fn push_front(&mut self, elem: T) {
+        self.shadow.insert(0, elem.clone());
+        self.sparse.push_front(elem);
+        self.check_all("push_front");
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let expected = self.shadow.pop();
+        let got = self.sparse.pop();
+        self.check_result("pop", None, &expected, &got);
+        self.check_all("pop");
+        got
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let expected = if self.shadow.is_empty() { None } else { Some(self.shadow.remove(0)) };
+        let got = self.sparse.pop_front();
+        self.check_result("pop_front", None, &expected, &got);
+        self.check_all("pop_front");
+        got
+    }
+
+    pub fn insert(&mut self, index: usize, elem: T) {
+        self.shadow.insert(index, elem.clone());
+        self.sparse.insert(index, elem);
+        self.check_all("insert");
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        let expected = self.shadow.remove(index);
+        let got = self.sparse.remove(index);
+        self.check_result("remove", Some(index), &expected, &got);
+        self.check_all("remove");
+        got
+    }
+
+    pub fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.shadow.push(elem.clone());
+            self.sparse.push(elem);
+        }
+        self.check_all("extend");
+    }
+
+    pub fn clear(&mut self) {
+        self.shadow.clear();
+        self.sparse.clear();
+        self.check_all("clear");
+    }
+
+    /// as_sparseメソッドの実装
+    /// 検査対象の DefaultSparseVec
+    #[inline(always)]
+    pub fn as_sparse(&self) -> &DefaultSparseVec<T> {
+        &self.sparse
+    }
+
+    /// as_shadowメソッドの実装
+    /// 比較用の Vec
+    #[inline(always)]
+    pub fn as_shadow(&self) -> &[T] {
+        &self.shadow
+    }
+
+    /// into_innerメソッドの実装
+    /// 検査を終えて DefaultSparseVec を取り出します
+    #[inline(always)]
+    pub fn into_inner(self) -> DefaultSparseVec<T> {
+        self.check_all("into_inner");
+        self.sparse
+    }
+
+    /// check_resultメソッドの実装
+    /// 操作の返り値を比較します
+    #[inline(always)]
+    fn check_result<R: PartialEq + Debug>(&self, op: &str, index: Option<usize>, expected: &R, got: &R) {
+        if cfg!(debug_assertions) && expected != got {
+            match index {
+                Some(index) => panic!(
+                    "shadow mismatch in {op}: index {index}: expected {expected:?}, got {got:?}"
+                ),
+                None => panic!("shadow mismatch in {op}: expected {expected:?}, got {got:?}"),
+            }
+        }
+    }
+
+    /// check_allメソッドの実装
+    /// 変更操作の後に物理要素の並び (index が狭義単調増加で len 未満) を検査し、len と全要素を比較します
+    fn check_all(&self, op: &str) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        if let Err(err) = self.sparse.validate(ValidationMode::Lenient) {
+            panic!("invariant violated in {op}: {err}");
+        }
+        if self.shadow.len() != self.sparse.len() {
+            panic!(
+                "shadow mismatch in {op}: len: expected {}, got {}",
+                self.shadow.len(),
+                self.sparse.len()
+            );
+        }
+        for (index, expected) in self.shadow.iter().enumerate() {
+            let got = self.sparse.get(index);
+            if got != Some(expected) {
+                panic!("shadow mismatch in {op}: index {index}: expected {expected:?}, got {got:?}");
+            }
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Clone + Debug> Default for CheckedSparseVec<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default + PartialEq + Clone + Debug> From<Vec<T>> for CheckedSparseVec<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let checked = CheckedSparseVec {
+            sparse: DefaultSparseVec::from(vec.clone()),
+            shadow: vec,
+        };
+        checked.check_all("from");
+        checked
+    }
+}
+
+impl<T: Default + PartialEq + Clone + Debug> Index<usize> for CheckedSparseVec<T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn index(&self, index: usize) -> &Self::Output {
+        let len = self.len();
+        self.get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the len is {len} but the index is {index}"))
+    }
+}
+
+impl<T: Default + PartialEq + Clone + Debug> NormalVecMethods<T> for CheckedSparseVec<T> {
+    fn n_push(&mut self, elem: T) {
+        self.shadow.push(elem.clone());
+        self.sparse.n_push(elem);
+        self.check_all("n_push");
+    }
+
+    fn n_pop(&mut self) -> Option<T> {
+        let expected = self.shadow.pop();
+        let got = self.sparse.n_pop();
+        self.check_result("n_pop", None, &expected, &got);
+        self.check_all("n_pop");
+        got
+    }
+
+    fn n_insert(&mut self, index: usize, elem: T) {
+        self.shadow.insert(index, elem.clone());
+        self.sparse.n_insert(index, elem);
+        self.check_all("n_insert");
+    }
+
+    fn n_remove(&mut self, index: usize) -> T {
+        let expected = self.shadow.remove(index);
+        let got = self.sparse.n_remove(index);
+        self.check_result("n_remove", Some(index), &expected, &got);
+        self.check_all("n_remove");
+        got
+    }
+
+    fn n_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        self.sparse.n_iter()
+    }
+}
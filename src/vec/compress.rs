@@ -0,0 +1,384 @@
+use std::io::{self, Read, Write};
+
+use super::{
+    default_sparse_vec::DefaultSparseVec,
+    error::SparseVecError,
+    format::{DeserializeConfig, FormatVersion, FORMAT_VERSION},
+};
+
+/// 圧縮形式の先頭 4 バイト
+const MAGIC: &[u8; 4] = b"VPSZ";
+
+/// CompressedValue trait の定義 (feature = "compress")
+/// write_compressed / read_compressed で値をバイト列にする方法
+/// - TAG: 型ごとに異なる値。読み込み時に書き込んだときの型と一致するか確かめます
+/// - SIZE: 1 要素のバイト数
+/// - read_le: 不正なバイト列 (bool の 2 など) の場合は None
+pub trait CompressedValue: Sized {
+    const TAG: u8;
+    const SIZE: usize;
+    fn write_le(&self, out: &mut Vec<u8>);
+    fn read_le(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_compressed_value {
+    ($($ty:ty => $tag:expr),* $(,)?) => {
+        $(
+            impl CompressedValue for $ty {
+                const TAG: u8 = $tag;
+                const SIZE: usize = std::mem::size_of::<$ty>();
+
+                #[inline(always)]
+                fn write_le(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                #[inline(always)]
+                fn read_le(bytes: &[u8]) -> Option<Self> {
+                    Some(<$ty>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_compressed_value!(
+    u8 => 1, u16 => 2, u32 => 3, u64 => 4,
+    i8 => 5, i16 => 6, i32 => 7, i64 => 8,
+    f32 => 9, f64 => 10,
+);
+
+impl CompressedValue for bool {
+    const TAG: u8 = 11;
+    const SIZE: usize = 1;
+
+    #[inline(always)]
+    fn write_le(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    #[inline(always)]
+    fn read_le(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// 圧縮形式での読み書き (feature = "compress")
+///
+/// 形式 (整数は全て little endian)
+/// - ヘッダ: MAGIC "VPSZ", FORMAT_VERSION の major (u8) と minor (u8), 値の型の TAG (u8), len (u64), nnz (u64), デフォルト値
+/// - index: バイト数 (u64) と、前の index との差分を LEB128 の可変長整数で並べたもの
+/// - 値: バイト数 (u64) と、値を並べたバイト列を zstd で圧縮したもの
+/// - 末尾: ここまでの全バイトの CRC-32 (u32)
+impl<T: Default + PartialEq + CompressedValue> DefaultSparseVec<T> {
+    /// write_compressedメソッドの実装
+    /// 圧縮形式で w に書き込みます
+    /// - level: zstd の圧縮レベル (0 は zstd の既定値)
+    pub fn write_compressed<W: Write>(&self, mut w: W, level: i32) -> io::Result<()> {
+        let (indices, values) = self.as_pairs();
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(FORMAT_VERSION.major);
+        out.push(FORMAT_VERSION.minor);
+        out.push(T::TAG);
+        out.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        out.extend_from_slice(&(indices.len() as u64).to_le_bytes());
+        self.default_value().write_le(&mut out);
+
+        let mut index_stream = Vec::with_capacity(indices.len());
+        let mut prev = 0;
+        for &index in indices {
+            write_varint(&mut index_stream, (index - prev) as u64);
+            prev = index;
+        }
+        out.extend_from_slice(&(index_stream.len() as u64).to_le_bytes());
+        out.extend_from_slice(&index_stream);
+
+        let mut raw_values = Vec::with_capacity(values.len() * T::SIZE);
+        values.iter().for_each(|elem| elem.write_le(&mut raw_values));
+        let value_block = zstd::bulk::compress(&raw_values, level)?;
+        out.extend_from_slice(&(value_block.len() as u64).to_le_bytes());
+        out.extend_from_slice(&value_block);
+
+        out.extend_from_slice(&crc32(&out).to_le_bytes());
+        w.write_all(&out)
+    }
+
+    /// read_compressedメソッドの実装
+    /// write_compressed で書き込んだデータを読み込みます
+    /// チェックサム、形式、index の並びを全て確かめてからベクタを作ります
+    /// ! : 不正なデータと新しい major のデータは ErrorKind::InvalidData の io::Error になり、中身は SparseVecError です
+    /// ! : 上限無し (DeserializeConfig::default()) で読み込みます。信頼できない入力は read_compressed_with
+    ///
+    /// 古い major のデータは upgrade_from で変換してから読み込みます
+    #[inline(always)]
+    pub fn read_compressed<R: Read>(r: R) -> io::Result<Self> {
+        Self::read_compressed_with(r, DeserializeConfig::default())
+    }
+
+    /// read_compressed_withメソッドの実装
+    /// read_compressed と同じですが、config の上限 (max_len / max_nnz) を超えるデータは LimitExceeded にします
+    /// - ヘッダの len と nnz を先に読んで確かめ、index と値のブロックはヘッダから決まる大きさまでしか読みません
+    ///   (index の差分は 1 つ 10 バイト以下、値のブロックは nnz 個の値を圧縮した大きさ以下)
+    /// - 読み込みは届いた分だけ少しずつ確保するので、申告された大きさで一度に確保しません
+    pub fn read_compressed_with<R: Read>(r: R, config: DeserializeConfig) -> io::Result<Self> {
+        let data = read_bounded::<T, R>(r, config)?;
+        Self::decode_compressed(&data).map_err(invalid_data)
+    }
+
+    /// decode_compressedメソッドの実装
+    /// 全体を読み込んだバイト列から復元します
+    fn decode_compressed(data: &[u8]) -> Result<Self, SparseVecError> {
+        let body = checked_body(data)?;
+        let mut reader = ByteReader { data: body };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(SparseVecError::InvalidFormat { reason: "bad magic" });
+        }
+        let major = reader.take(1)?[0];
+        if major != FORMAT_VERSION.major {
+            let version = FormatVersion::new(major, 0);
+            version.check_readable()?;
+            return Self::decode_compressed(&upgrade_from(version, data)?);
+        }
+        // 同じ major なら新しい minor も読める
+        reader.take(1)?;
+        if reader.take(1)?[0] != T::TAG {
+            return Err(SparseVecError::InvalidFormat { reason: "value type mismatch" });
+        }
+        let len = reader.read_usize()?;
+        let nnz = reader.read_usize()?;
+        let default = T::read_le(reader.take(T::SIZE)?)
+            .ok_or(SparseVecError::InvalidFormat { reason: "invalid default value" })?;
+
+        let index_bytes = reader.read_usize()?;
+        let mut index_stream = ByteReader { data: reader.take(index_bytes)? };
+        // 差分は 1 要素 1 バイト以上なので、nnz はバイト数を超えません (巨大な nnz で確保しないため)
+        if nnz > index_bytes {
+            return Err(SparseVecError::InvalidFormat { reason: "nnz larger than index stream" });
+        }
+        let mut indices = Vec::with_capacity(nnz);
+        let mut prev: usize = 0;
+        for position in 0..nnz {
+            let delta = usize::try_from(index_stream.read_varint()?)
+                .map_err(|_| SparseVecError::InvalidFormat { reason: "index overflow" })?;
+            // 先頭以外の差分が 0 なら index が増えていない (同じ index が 2 回)
+            if position > 0 && delta == 0 {
+                return Err(SparseVecError::InvalidFormat { reason: "non-monotonic indices" });
+            }
+            prev = prev
+                .checked_add(delta)
+                .ok_or(SparseVecError::InvalidFormat { reason: "index overflow" })?;
+            if prev >= len {
+                return Err(SparseVecError::InvalidFormat { reason: "index out of range" });
+            }
+            indices.push(prev);
+        }
+        if !index_stream.data.is_empty() {
+            return Err(SparseVecError::InvalidFormat { reason: "trailing index bytes" });
+        }
+
+        let value_bytes = reader.read_usize()?;
+        let value_block = reader.take(value_bytes)?;
+        if !reader.data.is_empty() {
+            return Err(SparseVecError::InvalidFormat { reason: "trailing bytes" });
+        }
+        let raw_len = nnz * T::SIZE;
+        let raw_values = zstd::bulk::decompress(value_block, raw_len)
+            .map_err(|_| SparseVecError::InvalidFormat { reason: "corrupt value block" })?;
+        if raw_values.len() != raw_len {
+            return Err(SparseVecError::InvalidFormat { reason: "value count mismatch" });
+        }
+        let values = raw_values
+            .chunks_exact(T::SIZE.max(1))
+            .map(|bytes| T::read_le(bytes).ok_or(SparseVecError::InvalidFormat { reason: "invalid value" }))
+            .collect::<Result<Vec<T>, _>>()?;
+
+        DefaultSparseVec::from_parts(len, default, indices, values)
+    }
+}
+
+/// read_bounded関数の実装
+/// ヘッダの大きさの申告を確かめながら、圧縮形式の 1 つ分のバイト列を r から読みます
+/// - 読み込めない major (新しい major など) は、その後ろを読まずに UnsupportedVersion
+/// - 形式を確かめるのは decode_compressed です (ここではバイト数の上限だけを確かめます)
+fn read_bounded<T: CompressedValue, R: Read>(mut r: R, config: DeserializeConfig) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    read_exact_into(&mut r, &mut data, MAGIC.len() + 1)?;
+    let major = data[MAGIC.len()];
+    FormatVersion::new(major, 0).check_readable().map_err(invalid_data)?;
+    // 1.x は minor を持ちません
+    let minor = usize::from(major >= 2);
+    read_exact_into(&mut r, &mut data, minor + 1 + 8 + 8 + T::SIZE + 8)?;
+    let header = &data[MAGIC.len() + 1 + minor + 1..];
+    let field = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+    let len = usize::try_from(field(0)).unwrap_or(usize::MAX);
+    let nnz = usize::try_from(field(8)).unwrap_or(usize::MAX);
+    let index_bytes = field(16 + T::SIZE);
+    config.check_len(len).map_err(invalid_data)?;
+    if nnz > config.max_nnz {
+        return Err(invalid_data(SparseVecError::LimitExceeded { field: "nnz", limit: config.max_nnz }));
+    }
+    if index_bytes > (nnz as u64).saturating_mul(10) {
+        return Err(invalid_data(SparseVecError::InvalidFormat { reason: "index stream larger than nnz" }));
+    }
+    read_exact_into(&mut r, &mut data, block_bytes(index_bytes, 8)?)?;
+    let value_bytes = u64::from_le_bytes(data[data.len() - 8..].try_into().unwrap());
+    let value_bound = zstd::zstd_safe::compress_bound(nnz.saturating_mul(T::SIZE)) as u64;
+    if value_bytes > value_bound {
+        return Err(invalid_data(SparseVecError::InvalidFormat { reason: "value block larger than nnz" }));
+    }
+    read_exact_into(&mut r, &mut data, block_bytes(value_bytes, 4)?)?;
+    Ok(data)
+}
+
+/// read_exact_into関数の実装
+/// r からちょうど count バイトを data の末尾に読み足します (足りない場合は InvalidFormat の truncated)
+/// 届いた分だけ確保するので、大きな count でも先に count バイトを確保しません
+fn read_exact_into<R: Read>(r: &mut R, data: &mut Vec<u8>, count: usize) -> io::Result<()> {
+    let read = r.take(count as u64).read_to_end(data)?;
+    if read < count {
+        return Err(invalid_data(SparseVecError::InvalidFormat { reason: "truncated" }));
+    }
+    Ok(())
+}
+
+/// block_bytes関数の実装
+/// ブロックのバイト数と、その後ろに続く固定長の部分 (trailer バイト) を足したバイト数
+#[inline(always)]
+fn block_bytes(bytes: u64, trailer: usize) -> io::Result<usize> {
+    usize::try_from(bytes)
+        .ok()
+        .and_then(|bytes| bytes.checked_add(trailer))
+        .ok_or_else(|| invalid_data(SparseVecError::InvalidFormat { reason: "length overflow" }))
+}
+
+#[inline(always)]
+fn invalid_data(err: SparseVecError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// upgrade_from関数の実装 (feature = "compress")
+/// version の形式で書き込まれた圧縮形式のデータを、現在の形式 (FORMAT_VERSION) に変換します
+/// - 1.x: ヘッダの major の後に minor が無いので、minor 0 を挟んでチェックサムを付け直します
+/// - 現在の major: そのままコピーします
+/// - 新しい major は UnsupportedVersion、version とデータのヘッダが合わない場合は InvalidFormat
+///
+/// 形式を変える場合は、ここに 1 つ前の major からの変換を追加します
+pub fn upgrade_from(version: FormatVersion, data: &[u8]) -> Result<Vec<u8>, SparseVecError> {
+    version.check_readable()?;
+    if version.major == FORMAT_VERSION.major {
+        return Ok(data.to_vec());
+    }
+    let body = checked_body(data)?;
+    if body.len() < MAGIC.len() + 1 || &body[..MAGIC.len()] != MAGIC {
+        return Err(SparseVecError::InvalidFormat { reason: "bad magic" });
+    }
+    if body[MAGIC.len()] != version.major {
+        return Err(SparseVecError::InvalidFormat { reason: "version mismatch" });
+    }
+    // 1.x -> 2.0
+    let mut out = Vec::with_capacity(data.len() + 1);
+    out.extend_from_slice(MAGIC);
+    out.push(2);
+    out.push(0);
+    out.extend_from_slice(&body[MAGIC.len() + 1..]);
+    out.extend_from_slice(&crc32(&out).to_le_bytes());
+    Ok(out)
+}
+
+/// checked_body関数の実装
+/// 末尾の CRC-32 を確かめて、それより前のバイト列を返します
+fn checked_body(data: &[u8]) -> Result<&[u8], SparseVecError> {
+    let body_len = data
+        .len()
+        .checked_sub(4)
+        .ok_or(SparseVecError::InvalidFormat { reason: "truncated" })?;
+    let (body, crc) = data.split_at(body_len);
+    let expected = u32::from_le_bytes(crc.try_into().unwrap());
+    let actual = crc32(body);
+    if expected != actual {
+        return Err(SparseVecError::InvalidFormat { reason: "checksum mismatch" });
+    }
+    Ok(body)
+}
+
+/// ByteReader構造体の定義
+/// 先頭から読み進めるバイト列 (足りない場合は InvalidFormat)
+struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    #[inline(always)]
+    fn take(&mut self, count: usize) -> Result<&'a [u8], SparseVecError> {
+        if self.data.len() < count {
+            return Err(SparseVecError::InvalidFormat { reason: "truncated" });
+        }
+        let (head, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(head)
+    }
+
+    #[inline(always)]
+    fn read_usize(&mut self) -> Result<usize, SparseVecError> {
+        let value = u64::from_le_bytes(self.take(8)?.try_into().unwrap());
+        usize::try_from(value).map_err(|_| SparseVecError::InvalidFormat { reason: "length overflow" })
+    }
+
+    /// read_varintメソッドの実装
+    /// LEB128 の可変長整数 (最大 10 バイト) を読みます
+    fn read_varint(&mut self) -> Result<u64, SparseVecError> {
+        let mut value: u64 = 0;
+        for shift in (0..70).step_by(7) {
+            let byte = self.take(1)?[0];
+            if shift == 63 && byte > 1 {
+                return Err(SparseVecError::InvalidFormat { reason: "varint overflow" });
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SparseVecError::InvalidFormat { reason: "varint overflow" })
+    }
+}
+
+/// write_varint関数の実装
+/// LEB128 の可変長整数を書き込みます
+#[inline(always)]
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// CRC-32 (IEEE 802.3) の表
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// crc32関数の実装
+#[inline(always)]
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes
+        .iter()
+        .fold(!0u32, |crc, &byte| CRC_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8))
+}
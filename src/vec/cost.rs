@@ -0,0 +1,59 @@
+use super::default_sparse_vec::DefaultSparseVec;
+
+/// OpKind列挙型の定義
+/// DefaultSparseVec::cost_hint で見積もる構造的な操作
+/// - Insert(index): insert(index, 非デフォルト値)
+/// - Remove(index): remove(index)
+/// - Append { other_nnz }: 物理要素数 other_nnz のベクタの append (OpKind::append で作れます)
+/// - Densify: collect_dense
+/// - Sort: build_value_index (物理要素を値の順に並べる)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpKind {
+    Insert(usize),
+    Remove(usize),
+    Append { other_nnz: usize },
+    Densify,
+    Sort,
+}
+
+impl OpKind {
+    /// appendメソッドの実装
+    /// other を append する操作
+    #[must_use]
+    #[inline(always)]
+    pub fn append<T: Default + PartialEq>(other: &DefaultSparseVec<T>) -> Self {
+        OpKind::Append { other_nnz: other.nnz() }
+    }
+}
+
+/// OpCost構造体の定義
+/// 操作のコストの上限の見積もり
+/// - bytes_moved: コピー / 移動するバイト数 (再確保によるコピーを含む)
+/// - entries_touched: 読み書きする物理要素 (または密な要素) の数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OpCost {
+    pub bytes_moved: usize,
+    pub entries_touched: usize,
+}
+
+impl OpCost {
+    /// 上限なしの予算
+    pub const UNLIMITED: OpCost = OpCost {
+        bytes_moved: usize::MAX,
+        entries_touched: usize::MAX,
+    };
+
+    #[must_use]
+    #[inline(always)]
+    pub fn new(bytes_moved: usize, entries_touched: usize) -> Self {
+        OpCost { bytes_moved, entries_touched }
+    }
+
+    /// fits_inメソッドの実装
+    /// 両方の項目が budget 以下なら true
+    #[must_use]
+    #[inline(always)]
+    pub fn fits_in(&self, budget: OpCost) -> bool {
+        self.bytes_moved <= budget.bytes_moved && self.entries_touched <= budget.entries_touched
+    }
+}
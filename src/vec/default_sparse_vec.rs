@@ -1,28 +1,80 @@
-use std::{alloc::{alloc, dealloc, realloc, Layout}, collections::HashMap, fmt::{self, Debug}, marker::PhantomData, mem, ops::{Index, IndexMut}, ptr::{self, NonNull}};
+use std::{alloc::{alloc, dealloc, handle_alloc_error, realloc, Layout}, collections::{BTreeMap, HashMap}, fmt::{self, Debug}, hash::{Hash, Hasher}, marker::PhantomData, mem, ops::{AddAssign, Bound, Index, IndexMut, Range, RangeBounds}, ptr::{self, NonNull}};
+#[cfg(debug_assertions)]
+use std::sync::atomic::AtomicUsize;
 
-use num::Num;
+use num::{Float, Num, ToPrimitive};
 
-use super::{normal_vec_trait::NormalVecMethods, vec_trait::Math};
+use super::{cost::{OpCost, OpKind}, entry::{Entry, OccupiedEntry, VacantEntry}, error::{SparseVecError, ValidationMode}, iter::{Drain, Gaps, IntoIter, IterDense, ModCheck, SparseIter, SparseIterMut}, normal_vec_trait::NormalVecMethods, read_only::ReadOnlySparseVec, sparse_vec_view::{SparseVecView, SparseVecViewMut, ViewMerge}, summary::{SparseStats, SparseVecSummary}, transparent::{TransparentWrapper, WrappedView}, vec_trait::{assert_same_len, EdgeMode, Math, ReductionOrder, SignedElement, SignedMath, UnsignedElement, UnsignedMath}};
+
+/// tracing でイベントを出す O(nnz) シフトの最小要素数 (feature = "tracing")
+#[cfg(feature = "tracing")]
+const TRACE_SHIFT_NNZ: usize = 4096;
+/// tracing でイベントを出す grow の最小バイト数 (feature = "tracing")
+#[cfg(feature = "tracing")]
+const TRACE_GROW_BYTES: usize = 1 << 20;
+/// 内積をブロック単位の走査に切り替える nnz (両方のベクタがこれ以上の場合)
+const DOT_LARGE_NNZ: usize = 1 << 20;
+/// ブロック単位の走査で 1 度に処理する index の数 (usize 4096 個 = 32 KiB)
+const DOT_BLOCK: usize = 4096;
+/// tuning_report で shrink_to_fit を勧める未使用容量の最小バイト数
+const TUNING_WASTE_BYTES: usize = 4096;
+/// find_stored_hinted でヒントの前後に確認する物理位置の数
+const HINT_WINDOW: usize = 8;
+/// Display (`{}`) で書く物理要素の数 (`{:.N}` で変えられます)
+const DISPLAY_ENTRIES: usize = 16;
+/// 解放・空けた物理領域を上書きするバイト (feature = "debug-poison" かつ debug ビルドの場合)
+#[cfg(feature = "debug-poison")]
+pub const POISON_BYTE: u8 = 0xDE;
 
 /// <T> のdefault値をスパースするSparseVectorの実装
 /// Vecの実装を参考にします
 /// src : https://doc.rust-jp.rs/rust-nomicon-ja/vec.html
 ///     : https://doc.rust-lang.org/std/vec/struct.Vec.html
-
-
-pub struct DefaultSparseVec<T: Default + PartialEq + Clone> {
+/// ! : debug ビルドでは反復中の変更を検出する変更回数を持つので、release より 1 ワード大きくなります
+///     size_of やレイアウトがビルドによらず同じであることに依存しないでください
+pub struct DefaultSparseVec<T: Default + PartialEq> {
     buf: RawDefaultSparseVec<T>,
     raw_len: usize,
     len: usize,
     default: T,
+    /// 設定したときだけ確保するポリシーとキャッシュ (Extras を参照)
+    /// 何も設定していないベクタは物理領域 (3 ワード) と raw_len / len / default、このポインタ 1 つだけです
+    extras: Option<Box<Extras<T>>>,
+    /// 変更回数 (debug ビルドのみ、on_mutate で増やし、イテレータが変わっていないことを確かめます)
+    /// ! : このフィールドがあるので、debug と release で構造体の大きさとレイアウトが異なります
+    ///     (repr(Rust) なのでレイアウトに依存するコードはありませんが、size_of は 1 ワード違います)
+    #[cfg(debug_assertions)]
+    mods: AtomicUsize,
 }
 
-impl<T: Default + PartialEq + Clone> DefaultSparseVec<T> {
+impl<T: Default + PartialEq> DefaultSparseVec<T> {
+    /// val_ptrメソッドの実装 (crate 内部用)
+    /// - 読み書きできるのは物理位置 0..raw_len の範囲だけです (未確保の場合は dangling)
     #[inline(always)]
-    fn val_ptr(&self) -> *mut T { self.buf.val_ptr.as_ptr() }
+    pub(crate) fn val_ptr(&self) -> *mut T { self.buf.val_ptr.as_ptr() }
 
+    /// ind_ptrメソッドの実装 (crate 内部用)
+    /// - val_ptr と同じく、物理位置 0..raw_len の範囲だけが有効です
     #[inline(always)]
-    fn ind_ptr(&self) -> *mut usize { self.buf.ind_ptr.as_ptr() }
+    pub(crate) fn ind_ptr(&self) -> *mut usize { self.buf.ind_ptr.as_ptr() }
+
+    /// debug_check_rawメソッドの実装
+    /// 物理領域をスライスとして渡す前の検査 (debug ビルドのみ)
+    /// - raw_len が容量以下で、物理要素があるなら領域が確保済み (dangling でない) こと
+    #[inline(always)]
+    fn debug_check_raw(&self) {
+        debug_assert!(self.raw_len <= self.cap(), "raw_len {} exceeds capacity {}", self.raw_len, self.cap());
+        debug_assert!(self.raw_len == 0 || self.cap() != 0, "stored elements without an allocated buffer");
+        debug_assert!(
+            self.cap() != 0 || (self.val_ptr() == NonNull::dangling().as_ptr() && self.ind_ptr() == NonNull::dangling().as_ptr()),
+            "capacity 0 with an allocated buffer"
+        );
+        debug_assert!(
+            self.raw_len == 0 || unsafe { *self.ind_ptr().add(self.raw_len - 1) } < self.len,
+            "stored index at or beyond len {}",
+            self.len
+        );
+    }
 
     #[inline(always)]
     fn cap(&self) -> usize { self.buf.cap }
@@ -63,30 +115,109 @@ impl<T: Default + PartialEq + Clone> DefaultSparseVec<T> {
     }
 
     /// newメソッドの実装
+    /// 確保しません (T::default() 自体が確保しない限り)。Default も同じです
+    /// - 各操作の確保の回数は alloc_audit (testing feature) の表を参照してください
     #[inline(always)]
     pub fn new() -> Self {
+        Self::with_default(T::default())
+    }
+
+    /// with_capacityメソッドの実装
+    /// cap == 0 なら確保しません
+    /// - ZST は何も確保しません (reserve と同じ)
+    #[inline(always)]
+    pub fn with_capacity(cap: usize) -> Self {
+        Self::with_default_and_capacity(T::default(), cap)
+    }
+
+    /// with_defaultメソッドの実装
+    /// T::default() の代わりに `default` をスパースする値 (物理的に格納しない値) にした空のベクタを作ります
+    /// - push / insert / get / remove などは全てこの値と比較します
+    #[inline(always)]
+    pub fn with_default(default: T) -> Self {
         DefaultSparseVec {
             buf: RawDefaultSparseVec::new(),
             raw_len: 0,
             len: 0,
-            default: Default::default(),
+            default,
+            extras: None,
+            #[cfg(debug_assertions)]
+            mods: AtomicUsize::new(0),
         }
     }
 
+    /// with_default_and_capacityメソッドの実装
+    /// with_default と同じですが、物理要素 cap 個分の領域を先に確保します
     #[inline(always)]
-    pub fn with_capacity(cap: usize) -> Self {
-        let mut vec = DefaultSparseVec {
-            buf: RawDefaultSparseVec::new(),
-            raw_len: 0,
-            len: 0,
-            default: Default::default(),
-        };
-        vec.buf.cap = cap;
-        vec.buf.cap_set();
+    pub fn with_default_and_capacity(default: T, cap: usize) -> Self {
+        let mut vec = Self::with_default(default);
+        // reserve_exact と同じ確保の経路 (cap == 0 なら何も確保しません)
+        vec.reserve_exact(cap);
+        vec
+    }
+
+    /// with_nnz_capacityメソッドの実装
+    /// 長さ len の全てデフォルト値のベクタを、物理要素 expected_nnz 個分の容量で作ります
+    /// - expected_nnz 個までの書き込み (set など) では再確保しません
+    #[inline(always)]
+    pub fn with_nnz_capacity(len: usize, expected_nnz: usize) -> Self {
+        let mut vec = Self::with_capacity(expected_nnz);
+        vec.grow_len(len);
         vec
     }
 
+    /// from_density_estimateメソッドの実装
+    /// with_nnz_capacity の物理要素数を len * density (切り上げ) で見積もる版
+    /// ! : density が 0.0..=1.0 の範囲外 (NaN を含む) の場合は panic
+    #[inline(always)]
+    pub fn from_density_estimate(len: usize, density: f64) -> Self {
+        assert!((0.0..=1.0).contains(&density), "density must be in 0.0..=1.0 (is {density})");
+        Self::with_nnz_capacity(len, expected_nnz(len, density))
+    }
+
+    /// builderメソッドの実装
+    /// 長さ・物理要素数の見積もり・デフォルト値などをまとめて指定して作るビルダー (DefaultSparseVecBuilder を参照)
+    #[must_use]
+    #[inline(always)]
+    pub fn builder() -> DefaultSparseVecBuilder<T> {
+        DefaultSparseVecBuilder {
+            len: 0,
+            density: None,
+            nnz: None,
+            default: None,
+            sparsify: None,
+        }
+    }
+
+    /// from_vec_with_defaultメソッドの実装
+    /// From<Vec<T>> の `default` を指定する版 (`default` と等しい要素は格納しません)
+    pub fn from_vec_with_default(vec: Vec<T>, default: T) -> Self {
+        let mut svec = Self::with_default(default);
+        vec.into_iter().for_each(|elem| svec.push(elem));
+        svec.shrink_to_fit();
+        svec
+    }
+
+    /// from_map_with_defaultメソッドの実装
+    /// index -> 値 の HashMap から、len = 最大の index + 1 のベクタを作ります O(n log n)
+    /// - map に無い index と、`default` と等しい値は格納しません
+    pub fn from_map_with_default(map: HashMap<usize, T>, default: T) -> Self {
+        let mut pairs: Vec<(usize, T)> = map.into_iter().collect();
+        pairs.sort_unstable_by_key(|(index, _)| *index);
+        let len = pairs.last().map_or(0, |(index, _)| index + 1);
+        let mut svec = Self::with_default(default);
+        for (index, elem) in pairs {
+            if elem != svec.default {
+                svec.raw_push(index, elem);
+            }
+        }
+        svec.len = len;
+        svec.shrink_to_fit();
+        svec
+    }
+
     // is_emptyメソッドの実装
+    #[must_use]
     #[inline(always)]
     pub fn is_empty(&self) -> bool {
         self.len == 0
@@ -94,91 +225,487 @@ impl<T: Default + PartialEq + Clone> DefaultSparseVec<T> {
 
     /// capacityメソッドの実装
     /// スパースベクトルの現在の容量を取得
+    /// - ZST は値の領域が要らないので、Vec と同じく usize::MAX を返します
+    ///   (物理的に格納する ZST の index の領域は格納するときに確保し、heap_size_bytes に含まれます)
+    #[must_use]
     #[inline(always)]
     pub fn capacity(&self) -> usize {
-        self.cap()
+        self.buf.capacity()
     }
 
     /// reserveメソッドの実装
-    /// スパースベクトルの容量を増やす
-    /// 既に確保されている容量よりも小さい場合は何もしない
-    /// 既に確保されている容量よりも大きい場合は、新しい容量に再確保する
+    /// スパースベクトルの容量を、少なくとも raw_len + additional にする
+    /// 既に確保されている容量で足りる場合は何もしない
+    /// 足りない場合は Vec と同じく、必要な分と今の容量の 2 倍の大きい方に再確保する (reserve(1) + push の繰り返しも償却 O(1))
+    /// - ちょうどの容量にしたい場合は reserve_exact
+    /// - ZST は何もしません (容量を指定する API は全て同じ、RawDefaultSparseVec::try_plan_capacity を参照)
+    /// - 必要なバイト数が isize::MAX を超える場合は panic、確保に失敗した場合は handle_alloc_error
     #[inline(always)]
     pub fn reserve(&mut self, additional: usize) {
-        let new_cap = self.raw_len + additional;
-        if new_cap > self.cap() {
-            self.buf.cap = new_cap;
-            self.buf.re_cap_set();
+        self.try_reserve(additional).unwrap_or_else(|err| alloc_failure(err));
+    }
+
+    /// reserve_exactメソッドの実装
+    /// reserve と同じですが、足りない場合はちょうど raw_len + additional に再確保する
+    #[inline(always)]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.try_reserve_exact(additional).unwrap_or_else(|err| alloc_failure(err));
+    }
+
+    /// try_reserveメソッドの実装
+    /// reserve と同じですが、失敗した場合は何も変更せずにエラーを返します (panic / handle_alloc_error しません)
+    /// - raw_len + additional の領域のバイト数が isize::MAX を超える: CapacityOverflow
+    /// - アロケータが確保に失敗した: AllocFailed
+    #[inline(always)]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), SparseVecError> {
+        self.on_mutate();
+        let required = self.raw_len.checked_add(additional).ok_or(SparseVecError::CapacityOverflow)?;
+        if required > self.cap() {
+            self.buf.try_plan_capacity(required.max(self.cap().saturating_mul(2)))?;
+        }
+        Ok(())
+    }
+
+    /// try_reserve_exactメソッドの実装
+    /// reserve_exact の panic しない版 (エラーは try_reserve と同じ)
+    #[inline(always)]
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), SparseVecError> {
+        self.on_mutate();
+        let required = self.raw_len.checked_add(additional).ok_or(SparseVecError::CapacityOverflow)?;
+        if required > self.cap() {
+            self.buf.try_plan_capacity(required)?;
+        }
+        Ok(())
+    }
+
+    /// try_reserve_storedメソッドの実装
+    /// 物理要素を additional 個書き込むための領域を確保します (容量は reserve と同じく増やします)
+    /// reserve と違い ZST でも index の領域を確保するので、このあと物理要素を直接書き込む内部の操作はこちらを使います
+    #[inline(always)]
+    fn try_reserve_stored(&mut self, additional: usize) -> Result<(), SparseVecError> {
+        let required = self.raw_len.checked_add(additional).ok_or(SparseVecError::CapacityOverflow)?;
+        if required > self.cap() {
+            self.buf.try_set_capacity(required.max(self.cap().saturating_mul(2)))?;
         }
+        Ok(())
     }
 
     /// shrink_to_fitメソッドの実装
     /// スパースベクトルの容量を現在の長さに合わせる
     /// 既に確保されている容量と現在の長さが同じ場合は何もしない
+    /// - raw_len が 0 の場合は領域を解放して未確保の状態に戻す (大きさ 0 の realloc は行いません)
+    /// - ZST は何もしません
     #[inline(always)]
     pub fn shrink_to_fit(&mut self) {
-        if self.raw_len < self.cap() {
-            self.buf.cap = self.raw_len;
-            self.buf.re_cap_set();
+        self.shrink_to(0);
+    }
+
+    /// shrink_toメソッドの実装
+    /// スパースベクトルの容量を max(raw_len, min_capacity) まで縮める
+    /// 既に確保されている容量がそれ以下の場合は何もしない
+    /// - 縮めた結果が 0 なら shrink_to_fit と同じく領域を解放する
+    /// - ZST は何もしません
+    #[inline(always)]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.on_mutate();
+        let new_cap = self.raw_len.max(min_capacity);
+        if new_cap < self.cap() {
+            self.buf.plan_capacity(new_cap);
+        }
+    }
+
+    /// shrink_stepメソッドの実装
+    /// shrink_to_fit を複数回に分けて行います
+    /// 1 回の呼び出しで最大 `max_bytes` 分 (最低 1 要素) のインデックスと値を nnz ちょうどの新しい領域へコピーし、
+    /// 全てコピーし終えた呼び出しで領域を差し替え、true を返します
+    /// - コピー中も読み書きは通常通りできます (読み込みは古い領域のまま)
+    /// - 途中で変更操作を行うとコピー途中の状態は破棄され、次の呼び出しでやり直します
+    /// - ZST は何もせずに true を返します (shrink_to_fit と同じ)
+    /// - 値が変わるのは &mut self を通す場合だけであるように T: Copy に限ります
+    ///   (Copy の型は UnsafeCell を持たないので、&self から書き換えられてコピー済みの値が古くなることはありません)
+    ///
+    /// ```compile_fail
+    /// use std::cell::RefCell;
+    /// use vec_plus::vec::default_sparse_vec::DefaultSparseVec;
+    ///
+    /// let mut vec = DefaultSparseVec::<RefCell<String>>::new();
+    /// vec.shrink_step(64);
+    /// ```
+    pub fn shrink_step(&mut self, max_bytes: usize) -> bool
+    where
+        T: Copy,
+    {
+        if self.raw_len == self.cap() || !RawDefaultSparseVec::<T>::PLANS_CAPACITY {
+            self.drop_shrink_state();
+            return true;
+        }
+        if self.raw_len == 0 {
+            // コピーするものが無いので確保済みの領域を解放するだけ
+            self.buf.set_capacity(0);
+            self.drop_shrink_state();
+            return true;
+        }
+
+        let raw_len = self.raw_len;
+        let state = self.extras.get_or_insert_with(Box::default).shrink.get_or_insert_with(|| {
+            let mut target = RawDefaultSparseVec::new();
+            target.set_capacity(raw_len);
+            ShrinkState { target, copied: 0 }
+        });
+        let count = (max_bytes / Self::SHRINK_ENTRY_BYTES).clamp(1, raw_len - state.copied);
+        debug_assert!(count * Self::SHRINK_ENTRY_BYTES <= max_bytes.max(Self::SHRINK_ENTRY_BYTES));
+        unsafe {
+            // インデックスも値も &mut self を通してしか変わらず、変われば on_mutate で破棄される
+            ptr::copy_nonoverlapping(
+                self.buf.ind_ptr.as_ptr().add(state.copied),
+                state.target.ind_ptr.as_ptr().add(state.copied),
+                count,
+            );
+            ptr::copy_nonoverlapping(
+                self.buf.val_ptr.as_ptr().add(state.copied),
+                state.target.val_ptr.as_ptr().add(state.copied),
+                count,
+            );
+        }
+        state.copied += count;
+        if state.copied < raw_len {
+            return false;
+        }
+
+        // 全てコピー済みなので、差し替えるだけ (古い領域は値を drop せずに解放される)
+        let state = self.extras.as_mut().and_then(|extras| extras.shrink.take()).unwrap();
+        self.buf = state.target;
+        true
+    }
+
+    /// shrink_step で 1 要素 (インデックスと値) をコピーするバイト数
+    const SHRINK_ENTRY_BYTES: usize = mem::size_of::<usize>() + mem::size_of::<T>();
+
+    /// drop_shrink_stateメソッドの実装
+    /// shrink_step のコピー途中の状態があれば破棄します
+    #[inline(always)]
+    fn drop_shrink_state(&mut self) {
+        if let Some(extras) = &mut self.extras {
+            extras.shrink = None;
+        }
+    }
+
+    /// extras_mutメソッドの実装
+    /// ポリシーやキャッシュを書き込むときに使います (Extras が無ければここで確保します)
+    #[inline(always)]
+    fn extras_mut(&mut self) -> &mut Extras<T> {
+        self.extras.get_or_insert_with(Box::default)
+    }
+
+    /// nnz_capメソッドの実装
+    #[inline(always)]
+    fn nnz_cap(&self) -> Option<NnzCap<T>> {
+        self.extras.as_ref().and_then(|extras| extras.nnz_cap)
+    }
+
+    /// sparsifyメソッドの実装
+    #[inline(always)]
+    fn sparsify(&self) -> Option<Sparsify<T>> {
+        self.extras.as_ref().and_then(|extras| extras.sparsify)
+    }
+
+    /// densifiedメソッドの実装
+    /// get_mut / IndexMut でデフォルト値の位置に物理要素を作ったことがあるかどうか
+    #[inline(always)]
+    fn densified(&self) -> bool {
+        self.extras.as_ref().is_some_and(|extras| extras.densified)
+    }
+
+    /// on_mutateメソッドの実装
+    /// 物理領域を変更する操作の先頭で呼ばれるフック
+    /// 途中まで進んだ shrink_step のコピーと、build_value_index の索引は古くなるので破棄します
+    /// debug ビルドでは変更回数を増やします (作成済みのイテレータは次の next で panic します)
+    /// - Extras を持たないベクタでは None の確認だけです
+    #[inline(always)]
+    fn on_mutate(&mut self) {
+        if let Some(extras) = &mut self.extras {
+            extras.shrink = None;
+            extras.value_index = None;
+        }
+        #[cfg(debug_assertions)]
+        {
+            let mods = self.mods.get_mut();
+            *mods = mods.wrapping_add(1);
+        }
+    }
+
+    /// mod_checkメソッドの実装 (crate 内部用)
+    /// 今の変更回数を覚えた ModCheck を返します (release ビルドでは何も持ちません)
+    #[inline(always)]
+    pub(crate) fn mod_check(&self) -> ModCheck<'_> {
+        #[cfg(debug_assertions)]
+        {
+            ModCheck::new(&self.mods)
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            ModCheck::none()
+        }
+    }
+
+    /// has_value_indexメソッドの実装
+    /// build_value_index で作った索引が残っているかどうか
+    #[must_use]
+    #[inline(always)]
+    pub fn has_value_index(&self) -> bool {
+        self.extras.as_ref().is_some_and(|extras| extras.value_index.is_some())
+    }
+
+    /// drop_value_indexメソッドの実装
+    /// build_value_index で作った索引を解放します
+    #[inline(always)]
+    pub fn drop_value_index(&mut self) {
+        if let Some(extras) = &mut self.extras {
+            extras.value_index = None;
         }
     }
 
     /// nnzメソッドの実装
     /// スパースベクトル長の取得
+    #[must_use]
     #[inline(always)]
     pub fn nnz(&self) -> usize {
         self.raw_len
     }
 
     /// lenメソッドの実装
+    #[must_use]
     #[inline(always)]
     pub fn len(&self) -> usize {
         self.len
     }
 
+    /// default_valueメソッドの実装
+    /// 物理的に格納されない (スパースされる) 値
+    #[must_use]
+    #[inline(always)]
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    /// sparsify_policyメソッドの実装
+    /// 書き込み時に値をデフォルト値とみなす判定 (set_sparsify_policy で設定、既定は Exact)
+    #[must_use]
+    #[inline(always)]
+    pub fn sparsify_policy(&self) -> SparsifyPolicy<T> {
+        self.sparsify().map_or(SparsifyPolicy::Exact, |sparsify| sparsify.policy)
+    }
+
+    /// is_defaultメソッドの実装
+    /// elem を物理的に格納しない (デフォルト値とみなす) かどうか
+    /// 書き込み時の判定は全てここを通します
+    #[inline(always)]
+    pub(crate) fn is_default(&self, elem: &T) -> bool {
+        match &self.sparsify() {
+            None => *elem == self.default,
+            Some(sparsify) => sparsify.matches(elem, &self.default),
+        }
+    }
+
     /// clearメソッドの実装
+    /// 物理的に存在する値をその場で drop し、空のベクタにします O(nnz)
+    /// - len に依らず、デフォルト値の clone も行いません
+    /// - 確保済みの容量 (capacity) はそのまま再利用します
     #[inline(always)]
     pub fn clear(&mut self) {
-        while let Some(_) = self.pop() {}
+        self.reset(0);
+    }
+
+    /// resetメソッドの実装
+    /// 物理的に存在する値を drop し、長さ `new_len` の全てデフォルト値のベクタにします
+    /// - 確保済みの容量 (capacity) はそのまま再利用します
+    #[inline(always)]
+    pub fn reset(&mut self, new_len: usize) {
+        self.drop_stored();
+        self.len = new_len;
+    }
+
+    /// truncateメソッドの実装
+    /// 先頭 new_len 個の要素だけを残します (new_len が len 以上なら何もしません) O(log nnz + 取り除く物理要素数)
+    /// - index が new_len 以上の物理要素は戻る前に drop するので、以降の as_pairs などには現れません
+    /// - 確保済みの容量 (capacity) はそのままです
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len >= self.len {
+            return;
+        }
+        self.on_mutate();
+        let raw_len = self.raw_len;
+        let keep = self.as_pairs().0.partition_point(|&index| index < new_len);
+        // drop 中に panic しても二重解放にならないよう、先に raw_len と len を縮めておく
+        self.raw_len = keep;
+        self.len = new_len;
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.val_ptr().add(keep), raw_len - keep));
+        }
+        self.buf.poison(keep, raw_len);
+    }
+
+    /// grow_lenメソッドの実装 (crate 内部用)
+    /// len を new_len に伸ばします (増えた分はデフォルト値で、物理領域は変更しません) O(1)
+    #[inline(always)]
+    pub(crate) fn grow_len(&mut self, new_len: usize) {
+        debug_assert!(new_len >= self.len);
+        self.on_mutate();
+        self.len = new_len;
+    }
+
+    /// drop_storedメソッドの実装
+    /// 物理的に存在する値をその場で drop し、raw_len を 0 にします
+    /// - len と容量は変更しません
+    #[inline(always)]
+    fn drop_stored(&mut self) {
+        self.on_mutate();
+        let raw_len = self.raw_len;
+        // drop 中に panic しても二重解放にならないよう、先に raw_len を 0 にしておく
+        self.raw_len = 0;
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.val_ptr(), raw_len));
+        }
+        self.buf.poison(0, raw_len);
+    }
+
+    /// compact_stored_defaultsメソッドの実装
+    /// デフォルト値と等しくなった (SparsifyPolicy でデフォルト値とみなす) 物理要素を drop し、残りを前に詰めます O(nnz)
+    pub(crate) fn compact_stored_defaults(&mut self) {
+        self.on_mutate();
+        let raw_len = self.raw_len;
+        // 比較や drop 中に panic しても二重解放にならないよう、先に raw_len を 0 にしておく
+        self.raw_len = 0;
+        let mut w = 0;
+        for r in 0..raw_len {
+            unsafe {
+                if self.is_default(&*self.val_ptr().add(r)) {
+                    ptr::drop_in_place(self.val_ptr().add(r));
+                } else {
+                    if w != r {
+                        ptr::copy_nonoverlapping(self.val_ptr().add(r), self.val_ptr().add(w), 1);
+                        *self.ind_ptr().add(w) = *self.ind_ptr().add(r);
+                    }
+                    w += 1;
+                }
+            }
+        }
+        self.raw_len = w;
+    }
+
+    /// retainメソッドの実装
+    /// Vec::retain と同じく、f(index, &値) が false の要素を取り除いて後ろを前に詰めます O(len + nnz)
+    /// - f はスパース分部 (デフォルト値) を含む全ての論理インデックスについて、昇順に 1 回ずつ呼ばれます
+    /// - 1 回の走査で、それまでに取り除いた論理位置の数だけ残った物理要素の index を引きながら詰めます
+    ///   (取り除いたスパース分部は物理領域に触れず、ずらす量が増えるだけです)
+    /// - f が panic した場合も、それまでに取り除いた要素を除いた正しいベクタが残ります
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &T) -> bool,
+    {
+        self.on_mutate();
+        let default: *const T = &self.default;
+        let len = self.len;
+        let mut guard = RetainGuard::new(self, true);
+        let mut next = 0;
+        while guard.read < guard.raw_len {
+            let index = guard.index_at(guard.read);
+            for i in next..index {
+                if !f(i, unsafe { &*default }) {
+                    guard.shift += 1;
+                }
+            }
+            next = index + 1;
+            let keep = f(index, unsafe { &*guard.vec.val_ptr().add(guard.read) });
+            guard.step(keep);
+        }
+        for i in next..len {
+            if !f(i, unsafe { &*default }) {
+                guard.shift += 1;
+            }
+        }
+    }
+
+    /// retain_storedメソッドの実装
+    /// 物理要素だけに f(index, &値) を呼び、false の物理要素を取り除きます O(nnz)
+    /// - 取り除いた位置はデフォルト値になり、len や他の要素の index は変わりません
+    /// - f が panic した場合も、それまでに取り除いた要素を除いた正しいベクタが残ります
+    pub fn retain_stored<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &T) -> bool,
+    {
+        self.on_mutate();
+        let mut guard = RetainGuard::new(self, false);
+        while guard.read < guard.raw_len {
+            let index = guard.index_at(guard.read);
+            let keep = f(index, unsafe { &*guard.vec.val_ptr().add(guard.read) });
+            guard.step(keep);
+        }
     }
 
     /// pushメソッドの実装
     #[inline(always)]
     pub fn push(&mut self, elem: T) {
-        if self.raw_len == self.cap() {
-            self.buf.grow();
+        self.on_mutate();
+        if !self.is_default(&elem) {
+            self.make_room();
         }
-        if self.default != elem {
+        self.push_stored(elem).unwrap_or_else(|err| alloc_failure(err));
+    }
+
+    /// push_storedメソッドの実装
+    /// push / try_push の本体 (物理要素数の上限は呼び出し側で確かめておく)
+    /// - 失敗した場合は何も変更せずにエラーを返します
+    #[inline(always)]
+    fn push_stored(&mut self, elem: T) -> Result<(), SparseVecError> {
+        let len = self.len.checked_add(1).ok_or(SparseVecError::CapacityOverflow)?;
+        if !self.is_default(&elem) {
+            if self.raw_len == self.cap() {
+                self.buf.try_grow()?;
+            }
             unsafe {
-                ptr::write(self.val_ptr().offset(self.raw_len as isize), elem);
-                ptr::write(self.ind_ptr().offset(self.raw_len as isize), self.len);
+                ptr::write(self.val_ptr().add(self.raw_len), elem);
+                ptr::write(self.ind_ptr().add(self.raw_len), self.len);
             }
             self.raw_len += 1;
         }
-        self.len += 1;
+        self.len = len;
+        Ok(())
     }
 
-    /// popメソッドの実装
+    /// push_frontメソッドの実装
+    /// 先頭に要素を追加します
+    /// - 既存の物理インデックスは全て +1 (シフトと同じ 1 パスで更新)
+    /// - `elem` がデフォルト値なら物理領域には書き込まない
     #[inline(always)]
-    pub fn pop(&mut self) -> Option<T> {
-        if self.len == 0 {
-            return None;
-        }
-        // 空らずraw_len =< len であることが保証されている
-        let pop_elem = 
-            if self.raw_len == self.len {
-                self.raw_len -= 1;
-                unsafe {
-                    Some(ptr::read(self.val_ptr().offset(self.raw_len as isize)))
+    pub fn push_front(&mut self, elem: T) {
+        self.on_mutate();
+        let stored = !self.is_default(&elem);
+        self.trace_shift("push_front", 0, stored);
+        if stored {
+            self.make_room();
+            if self.raw_len == self.cap() {
+                self.buf.grow();
+            }
+            unsafe {
+                // 値は後ろへまとめてシフト
+                ptr::copy(self.val_ptr(), self.val_ptr().add(1), self.raw_len);
+                // インデックスはシフトと +1 を同時に行う
+                for i in (0..self.raw_len).rev() {
+                    *self.ind_ptr().add(i + 1) = *self.ind_ptr().add(i) + 1;
                 }
-            } else {
-                Some(self.default.clone())
-            };
-        self.len -= 1;
-        pop_elem
+                ptr::write(self.val_ptr(), elem);
+                ptr::write(self.ind_ptr(), 0);
+            }
+            self.raw_len += 1;
+        } else {
+            self.inc_indices_from(0);
+        }
+        self.len += 1;
     }
 
     /// getメソッドの実装
+    #[must_use]
     #[inline(always)]
     pub fn get(&self, index: usize) -> Option<&T> {
         if index >= self.len {
@@ -186,196 +713,225 @@ impl<T: Default + PartialEq + Clone> DefaultSparseVec<T> {
         }
         match self.ind_binary_search(&index) {
             Ok(i) => {
-                let val = unsafe { &*self.val_ptr().offset(i as isize) };
+                let val = unsafe { &*self.val_ptr().add(i) };
                 Some(val)
             }
             Err(_) => Some(&self.default),
         }
     }
 
-    // get_mutメソッドの実装
-    // このメソッドは、指定されたインデックスの要素を変更するために使用されます。
-    // ! : スパース分部の要素をわたすためにわざと値を生成します
-    // ! : 無駄にデフォルト値を生成するので、このメソッドは避けるべきです
-    #[deprecated(note = "このメソッドは避けるべきです. 
-                        スパース分部の実値を渡すため、スパース分部の値を無駄に生成します.
-                        default値以外を代入する場合は問題ありません.")]
+    /// get_with_edgeメソッドの実装
+    /// index が範囲外 (負、または len 以上) の場合に mode に従って読みます O(log nnz)
+    /// (EdgeMode::Default ならデフォルト値、len が 0 ならどのモードでもデフォルト値)
+    #[must_use]
     #[inline(always)]
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        if index >= self.len {
+    pub fn get_with_edge(&self, index: isize, mode: EdgeMode) -> &T {
+        match mode.resolve(index, self.len) {
+            Some(index) => self.get(index).unwrap(),
+            None => &self.default,
+        }
+    }
+
+    /// firstメソッドの実装
+    /// 先頭の要素 (空なら None) O(1)
+    /// 先頭の物理要素の index が 0 ならその値、そうでなければデフォルト値です
+    #[must_use]
+    #[inline(always)]
+    pub fn first(&self) -> Option<&T> {
+        if self.len == 0 {
+            return None;
+        }
+        match self.as_pairs() {
+            ([0, ..], [elem, ..]) => Some(elem),
+            _ => Some(&self.default),
+        }
+    }
+
+    /// lastメソッドの実装
+    /// 末尾の要素 (空なら None) O(1)
+    /// 最後の物理要素の index が len - 1 ならその値、そうでなければデフォルト値です
+    #[must_use]
+    #[inline(always)]
+    pub fn last(&self) -> Option<&T> {
+        if self.len == 0 {
             return None;
         }
+        match self.as_pairs() {
+            ([.., index], [.., elem]) if *index == self.len - 1 => Some(elem),
+            _ => Some(&self.default),
+        }
+    }
+
+    /// containsメソッドの実装
+    /// value と == で等しい要素があるかどうか
+    /// - value がデフォルト値でスパース分部がある (nnz < len) なら物理要素を見ずに true O(1)
+    /// - それ以外は物理要素を先頭から比べます O(nnz)
+    #[must_use]
+    pub fn contains(&self, value: &T) -> bool {
+        if self.raw_len < self.len && *value == self.default {
+            return true;
+        }
+        self.as_pairs().1.contains(value)
+    }
+
+    /// get_explicitメソッドの実装
+    /// index 番目が物理的に格納されていればその値、スパース分部または範囲外なら None O(log nnz)
+    /// (get と違い、「格納された 0」と「スパース分部の 0」を区別できます)
+    #[must_use]
+    #[inline(always)]
+    pub fn get_explicit(&self, index: usize) -> Option<&T> {
         match self.ind_binary_search(&index) {
-            Ok(i) => {
-                let val = unsafe { &mut *self.val_ptr().offset(i as isize) };
-                Some(val)
-            }
-            Err(i) => {
-                if self.raw_len == self.cap() {
-                    self.buf.grow();
-                }
-                unsafe {
-                    let src = i as isize;
-                    let dst = src + 1;
-                    let count = self.raw_len - i;
-                    ptr::copy(
-                        self.val_ptr().offset(src),
-                        self.val_ptr().offset(dst),
-                        count,
-                    );
-                    ptr::copy(
-                        self.ind_ptr().offset(src),
-                        self.ind_ptr().offset(dst),
-                        count,
-                    );
-                    ptr::write(self.val_ptr().offset(i as isize), self.default.clone());
-                    ptr::write(self.ind_ptr().offset(i as isize), index);
-                }
-                self.raw_len += 1;
-                let val = unsafe { &mut *self.val_ptr().offset(i as isize) };
-                Some(val)
-            },
+            Ok(i) => Some(&self.as_pairs().1[i]),
+            Err(_) => None,
+        }
+    }
+
+    /// is_explicitメソッドの実装
+    /// index 番目が物理的に格納されているかどうか (スパース分部または範囲外なら false) O(log nnz)
+    #[must_use]
+    #[inline(always)]
+    pub fn is_explicit(&self, index: usize) -> bool {
+        self.ind_binary_search(&index).is_ok()
+    }
+
+    /// contains_indexメソッドの実装
+    /// is_explicit と同じです
+    #[must_use]
+    #[inline(always)]
+    pub fn contains_index(&self, index: usize) -> bool {
+        self.is_explicit(index)
+    }
+
+    /// find_storedメソッドの実装
+    /// 物理要素から論理インデックス index を二分探索します O(log nnz)
+    /// 見つかったら Ok(物理位置)、見つからなければ Err(挿入すべき物理位置)
+    /// - index が len 以上でも Err(nnz) を返します (panic しません)
+    #[inline(always)]
+    pub fn find_stored(&self, index: usize) -> Result<usize, usize> {
+        self.ind_binary_search(&index)
+    }
+
+    /// find_stored_hintedメソッドの実装
+    /// find_stored と同じ結果を、物理位置 hint の近くから探します
+    /// - hint の前後 HINT_WINDOW 個の範囲に答えがあれば O(1)、無ければ二分探索 O(log nnz)
+    /// - hint は前回の結果の物理位置 (Ok / Err の中身) を渡す想定で、nnz を超えていても構いません
+    #[inline(always)]
+    pub fn find_stored_hinted(&self, index: usize, hint: usize) -> Result<usize, usize> {
+        let ind = self.as_pairs().0;
+        let lo = hint.saturating_sub(HINT_WINDOW).min(ind.len());
+        let hi = hint.saturating_add(HINT_WINDOW).min(ind.len());
+        // 答えが [lo, hi] に収まるのは ind[lo - 1] < index <= ind[hi] の場合
+        let above_lo = lo == 0 || ind[lo - 1] < index;
+        let below_hi = hi == ind.len() || index <= ind[hi];
+        if above_lo && below_hi {
+            let pos = ind[lo..hi].iter().position(|&i| i >= index).map_or(hi, |k| lo + k);
+            return if pos < ind.len() && ind[pos] == index { Ok(pos) } else { Err(pos) };
+        }
+        self.ind_binary_search(&index)
+    }
+
+    /// get_hintedメソッドの実装
+    /// find_stored_hinted で探す get です
+    /// 値と、次の呼び出しに渡すヒント (今回の物理位置) を返します
+    /// - index を昇順に読んでいく場合、1 回あたり O(1) になります
+    #[must_use]
+    #[inline(always)]
+    pub fn get_hinted(&self, index: usize, hint: usize) -> Option<(&T, usize)> {
+        if index >= self.len {
+            return None;
+        }
+        match self.find_stored_hinted(index, hint) {
+            Ok(i) => Some((unsafe { &*self.val_ptr().add(i) }, i)),
+            Err(i) => Some((&self.default, i)),
         }
     }
 
     /// insertメソッド
     /// 「index 番目に新しい要素を割り込む」という動作
-    /// - 後続要素のインデックスは常に +1 シフト
+    /// 密な形に直したときの Vec::insert と常に同じ結果になります
+    /// - 論理インデックスが `index` 以上の物理要素は全てインデックスを +1
+    ///   (`index` に既に物理要素がある場合、その要素は新しい要素の後ろになる)
     /// - `elem` が非デフォルト値なら物理領域に書き込む (raw_len += 1)
     /// - `elem` がデフォルト値なら物理領域には書き込まない（スパース化）
-    ///
+    /// - index が len より大きい場合は panic (空のベクタには index 0 にのみ挿入できます)
     #[inline(always)]
     pub fn insert(&mut self, index: usize, elem: T) {
-        assert!(index <= self.len, "index out of bounds");
+        self.on_mutate();
+        assert!(index <= self.len, "insertion index (is {index}) should be <= len (is {})", self.len);
+        if !self.is_default(&elem) {
+            self.make_room();
+        }
+        self.insert_stored(index, elem).unwrap_or_else(|err| alloc_failure(err));
+    }
 
+    /// insert_storedメソッドの実装
+    /// insert / try_insert の本体 (index <= len と物理要素数の上限は呼び出し側で確かめておく)
+    /// - 失敗した場合は何も変更せずにエラーを返します
+    #[inline(always)]
+    fn insert_stored(&mut self, index: usize, elem: T) -> Result<(), SparseVecError> {
         // 挿入により論理的な長さは常に +1
-        self.len += 1;
-
+        let len = self.len.checked_add(1).ok_or(SparseVecError::CapacityOverflow)?;
+        let stored = !self.is_default(&elem);
+        #[cfg(debug_assertions)]
+        let hint = self.cost_hint(OpKind::Insert(index));
+        #[cfg(debug_assertions)]
+        let realloc_bytes = if stored && self.raw_len == self.cap() { self.raw_len * (mem::size_of::<T>() + mem::size_of::<usize>()) } else { 0 };
         // シフト時に書き込み先が必要なので、raw_len == cap なら grow する
-        if self.raw_len == self.cap() {
-            self.buf.grow();
+        if stored && self.raw_len == self.cap() {
+            self.buf.try_grow()?;
         }
+        self.len = len;
 
-        // ind_binary_search で挿入ポイント i を特定
+        // ind_binary_search で論理インデックスが index 以上になる最初の位置 i を特定
         // (すでに同じ index があっても、そこに割り込む)
         let i = match self.ind_binary_search(&index) {
             Ok(pos) => pos,
             Err(pos) => pos,
         };
+        self.trace_shift("insert", i, stored);
+        #[cfg(debug_assertions)]
+        {
+            let val_bytes = if stored { mem::size_of::<T>() } else { 0 };
+            let shifted = self.raw_len - i;
+            self.debug_check_cost(hint, shifted + 1, shifted * (val_bytes + mem::size_of::<usize>()) + realloc_bytes);
+        }
 
-        unsafe {
-            // まず後ろの要素をまとめて1つ後ろへシフト
-            let src = i as isize;
-            let dst = src + 1;
-            let count = self.raw_len - i;
-
-            // 値をコピー (memmove 相当)
-            ptr::copy(
-                self.val_ptr().offset(src),
-                self.val_ptr().offset(dst),
-                count,
-            );
-            // インデックスをコピー
-            ptr::copy(
-                self.ind_ptr().offset(src),
-                self.ind_ptr().offset(dst),
-                count,
-            );
-
-            // シフトされた要素のインデックス値を +1
-            for offset in (i + 1)..(self.raw_len + 1) {
-                *self.ind_ptr().offset(offset as isize) += 1;
-            }
-        }
-
-        // `elem` がデフォルト値なら物理的には書き込まずスパース化
-        if elem != self.default {
+        if stored {
             unsafe {
-                // シフトしたスロット i に書き込み
-                ptr::write(self.val_ptr().offset(i as isize), elem);
-                ptr::write(self.ind_ptr().offset(i as isize), index);
+                // 値を後ろへまとめてシフト (memmove 相当)
+                ptr::copy(
+                    self.val_ptr().add(i),
+                    self.val_ptr().add(i + 1),
+                    self.raw_len - i,
+                );
+                // インデックスはシフトと +1 を同時に行う
+                for offset in (i..self.raw_len).rev() {
+                    *self.ind_ptr().add(offset + 1) = *self.ind_ptr().add(offset) + 1;
+                }
+                // 空いたスロット i に書き込み
+                ptr::write(self.val_ptr().add(i), elem);
+                ptr::write(self.ind_ptr().add(i), index);
             }
             // 非デフォルト値なので raw_len も増やす
             self.raw_len += 1;
+        } else {
+            // `elem` がデフォルト値なら物理的には書き込まずスパース化
+            // 物理領域はシフトせず、後続のインデックスだけ +1
+            self.inc_indices_from(i);
         }
-    }
-
-    /// removeメソッド
-    /// 
-    /// `index` 番目の要素を削除し、削除した要素を返します。
-    /// - 論理インデックス `index` が物理的に存在すれば、その値を返す
-    /// - 物理的になければ（= デフォルト扱いだった）デフォルト値を返す
-    /// 
-    /// いずれにせよ後ろの要素（論理インデックスが `index` より大きい要素）は
-    /// インデックスを 1 つ前にシフトします。
-    #[inline(always)]
-    pub fn remove(&mut self, index: usize) -> T {
-        assert!(index < self.len, "index out of bounds");
-        
-        // 論理的な要素数は常に1つ減る
-        self.len -= 1;
-
-        match self.ind_binary_search(&index) {
-            Ok(i) => {
-                // 今回削除する要素を読みだす
-                let removed_val = unsafe {
-                    ptr::read(self.val_ptr().offset(i as isize))
-                };
-
-                // `i` 番目を削除するので、後ろを前にシフト
-                let count = self.raw_len - i - 1;
-                if count > 0 {
-                    unsafe {
-                        // 値をコピーして前につめる
-                        ptr::copy(
-                            self.val_ptr().offset(i as isize + 1),
-                            self.val_ptr().offset(i as isize),
-                            count
-                        );
-                        // インデックスもコピーして前につめる
-                        ptr::copy(
-                            self.ind_ptr().offset(i as isize + 1),
-                            self.ind_ptr().offset(i as isize),
-                            count
-                        );
-                        // シフトした後のインデックスは全て -1 (1つ前に詰める)
-                        for offset in i..(self.raw_len - 1) {
-                            *self.ind_ptr().offset(offset as isize) -= 1;
-                        }
-                    }
-                }
-                // 物理的な要素数は 1 減
-                self.raw_len -= 1;
-
-                // 取り除いた要素を返す
-                removed_val
-            }
-            Err(i) => {
-                // index は詰める必要があるので、i 以降の要素のインデックスを -1
-                // （たとえば “要素自体は無い” けど、後ろにある要素は
-                //  論理インデックスが 1 つ前になる）
-                if i < self.raw_len {
-                    unsafe {
-                        for offset in i..self.raw_len {
-                            *self.ind_ptr().offset(offset as isize) -= 1;
-                        }
-                    }
-                }
-
-                // “もともと物理要素が無い” のだから、デフォルト値を返す
-                self.default.clone()
-            }
-        }
+        Ok(())
     }
 
     /// 2つのスパースベクタを “連結” する append 実装例
-    /// - `other` は消費 (ムーブ) して、自分に要素をつけ足す
+    /// - `other` の要素を全て自分の後ろへ移動し、`other` は空 (len 0) にします (Vec::append と同じ)
     /// - `other` のインデックスは自分の `len` 分だけシフト
+    /// - `other` の容量はそのまま残るので、続けて使えます
     #[inline(always)]
-    pub fn append(&mut self, other: Self) {
+    pub fn append(&mut self, other: &mut Self) {
+        self.on_mutate();
+        other.on_mutate();
         let other_len = other.len();
         let other_raw_len = other.nnz();
-        let other_default = other.default.clone();
 
         // 1) 相手が空なら何もしないで終了
         if other_len == 0 {
@@ -385,35 +941,38 @@ impl<T: Default + PartialEq + Clone> DefaultSparseVec<T> {
         // 2) デフォルト値が異なる場合はエラーとする
         //    (同じスパース化の基準でなければ連結できない)
         assert!(
-            self.default == other_default,
+            self.default == other.default,
             "default value mismatch"
         );
+        self.check_room(other_raw_len);
 
         // 3) “論理インデックス” の連結位置を決める (ここでは self.len)
         let offset = self.len;
+        let new_len = self.len.checked_add(other_len).expect("capacity overflow");
+        #[cfg(debug_assertions)]
+        {
+            let entry_bytes = mem::size_of::<T>() + mem::size_of::<usize>();
+            let realloc_bytes = if self.raw_len + other_raw_len > self.cap() { self.raw_len * entry_bytes } else { 0 };
+            self.debug_check_cost(self.cost_hint(OpKind::append(other)), other_raw_len, other_raw_len * entry_bytes + realloc_bytes);
+        }
 
-        // 4) 自分の長さ (論理) は other 分だけ伸びる
-        self.len += other_len;
-
-        // 5) キャパが足りなければ拡張
+        // 4) キャパが足りなければ拡張
         //    raw_len + other_raw_len 分必要
         if self.raw_len + other_raw_len > self.cap() {
-            // 例えば reserve は “cap が足りない分だけ” 確保するようにする
-            self.reserve(self.raw_len + other_raw_len - self.cap());
+            // reserve の引数は raw_len からの追加分 (直接書き込むので ZST でも index の領域を確保します)
+            self.try_reserve_stored(other_raw_len).unwrap_or_else(|err| alloc_failure(err));
         }
 
-        // 6) 相手が物理的にも空でなければ(= other_raw_len>0) コピーする
+        // 5) 相手が物理的にも空でなければ(= other_raw_len>0) 値を移動する
         if other_raw_len > 0 {
             unsafe {
-                // まず、相手の ind_ptr / val_ptr からコピー
-                //   - コピー先は self.ind_ptr().add(self.raw_len)
-                //   - コピー元は other.ind_ptr()
-                ptr::copy(
+                // &mut の借用なので、2 つの領域は重なりません
+                ptr::copy_nonoverlapping(
                     other.ind_ptr(),
                     self.ind_ptr().add(self.raw_len),
                     other_raw_len,
                 );
-                ptr::copy(
+                ptr::copy_nonoverlapping(
                     other.val_ptr(),
                     self.val_ptr().add(self.raw_len),
                     other_raw_len,
@@ -424,252 +983,3405 @@ impl<T: Default + PartialEq + Clone> DefaultSparseVec<T> {
                     *self.ind_ptr().add(self.raw_len + i) += offset;
                 }
             }
-
-            // raw_len も伸ばす
-            self.raw_len += other_raw_len;
+
+            // raw_len も伸ばす
+            self.raw_len += other_raw_len;
+        }
+        self.len = new_len;
+
+        // 6) 値の所有権は自分に移ったので、相手は物理要素を持たない空のベクタにする
+        //    (ここで raw_len を戻さないと、相手の drop で同じ値を二重に drop してしまう)
+        other.raw_len = 0;
+        other.len = 0;
+        other.buf.poison(0, other_raw_len);
+        self.enforce_nnz_cap();
+    }
+
+    /// pop_storedメソッドの実装
+    /// 物理的に存在する要素のうち、最も後ろのものを取り出します
+    /// - 取り出した位置はデフォルト値扱いになり、len は変わりません
+    /// - デフォルト値を clone しないので T: Clone を要求しません
+    #[inline(always)]
+    pub fn pop_stored(&mut self) -> Option<(usize, T)> {
+        self.on_mutate();
+        if self.raw_len == 0 {
+            return None;
+        }
+        self.raw_len -= 1;
+        unsafe {
+            Some((
+                ptr::read(self.ind_ptr().add(self.raw_len)),
+                ptr::read(self.val_ptr().add(self.raw_len)),
+            ))
+        }
+    }
+
+    /// remove_storedメソッドの実装
+    /// 論理インデックス `index` に物理的に存在する値を取り出します
+    /// - 取り出した位置はデフォルト値扱いになり、len や後続のインデックスは変わりません
+    /// - 物理的に存在しない場合は None
+    /// - デフォルト値を clone しないので T: Clone を要求しません
+    #[inline(always)]
+    pub fn remove_stored(&mut self, index: usize) -> Option<T> {
+        self.on_mutate();
+        let i = self.ind_binary_search(&index).ok()?;
+        Some(self.remove_at(i))
+    }
+
+    /// remove_atメソッドの実装 (crate 内部用)
+    /// 物理位置 i の要素を取り出して後ろを前に詰めます (len は変わりません)
+    #[inline(always)]
+    pub(crate) fn remove_at(&mut self, i: usize) -> T {
+        unsafe {
+            let removed_val = ptr::read(self.val_ptr().add(i));
+            let count = self.raw_len - i - 1;
+            ptr::copy(self.val_ptr().add(i + 1), self.val_ptr().add(i), count);
+            ptr::copy(self.ind_ptr().add(i + 1), self.ind_ptr().add(i), count);
+            self.raw_len -= 1;
+            removed_val
+        }
+    }
+
+    /// inc_indices_fromメソッドの実装
+    /// 物理位置 from 以降のインデックスを全て +1 します O(nnz - from)
+    /// デフォルト値の insert / push_front のように、値を動かさずにインデックスだけずらす場合に使います
+    /// - スライスの iter_mut で一括して更新するので、ベクトル化されます (from == nnz なら何もしません)
+    /// - 値も動かす場合は、コピーと ±1 を同じループで行う方が 1 パス少なく速いので、ここは通しません
+    ///
+    /// ずらし自体を無くすには、GapSparseVec の shift のように後続のインデックスを相対値で持つ必要があります
+    /// (push_front / pop_front 用に検討した offset フィールドと同じ設計で、全ての読み込みに加算が入るので採用していません)
+    #[inline(always)]
+    fn inc_indices_from(&mut self, from: usize) {
+        if from >= self.raw_len {
+            return;
+        }
+        let tail = unsafe { std::slice::from_raw_parts_mut(self.ind_ptr().add(from), self.raw_len - from) };
+        tail.iter_mut().for_each(|index| *index += 1);
+    }
+
+    /// dec_indices_fromメソッドの実装
+    /// 物理位置 from 以降のインデックスを全て -1 します (inc_indices_from の逆)
+    #[inline(always)]
+    fn dec_indices_from(&mut self, from: usize) {
+        if from >= self.raw_len {
+            return;
+        }
+        let tail = unsafe { std::slice::from_raw_parts_mut(self.ind_ptr().add(from), self.raw_len - from) };
+        tail.iter_mut().for_each(|index| *index -= 1);
+    }
+
+    /// insert_vacantメソッドの実装 (crate 内部用)
+    /// 物理要素の無い論理インデックス index に elem を格納し、その物理位置を返します (len は変わりません)
+    /// - 物理要素数の上限は CapPolicy に従います
+    #[inline(always)]
+    pub(crate) fn insert_vacant(&mut self, index: usize, elem: T) -> usize {
+        // 上限で物理要素が削除されると位置がずれるので、make_room の後に探します
+        self.make_room();
+        let i = self.ind_binary_search(&index).unwrap_err();
+        self.insert_at(i, index, elem);
+        i
+    }
+
+    /// insert_atメソッドの実装
+    /// 物理位置 i に (index, elem) を割り込ませます (len は変わりません)
+    /// - i は ind_binary_search の Err の位置であること (物理要素数の上限は呼び出し側で確かめておく)
+    #[inline(always)]
+    fn insert_at(&mut self, i: usize, index: usize, elem: T) {
+        if self.raw_len == self.cap() {
+            self.buf.grow();
+        }
+        unsafe {
+            let count = self.raw_len - i;
+            ptr::copy(self.val_ptr().add(i), self.val_ptr().add(i + 1), count);
+            ptr::copy(self.ind_ptr().add(i), self.ind_ptr().add(i + 1), count);
+            ptr::write(self.val_ptr().add(i), elem);
+            ptr::write(self.ind_ptr().add(i), index);
+        }
+        self.raw_len += 1;
+    }
+
+    /// max_nnzメソッドの実装
+    /// set_max_nnz で設定した物理要素数の上限
+    #[must_use]
+    #[inline(always)]
+    pub fn max_nnz(&self) -> Option<usize> {
+        self.nnz_cap().map(|cap| cap.max)
+    }
+
+    /// cap_policyメソッドの実装
+    #[must_use]
+    #[inline(always)]
+    pub fn cap_policy(&self) -> CapPolicy {
+        self.extras.as_ref().map_or(CapPolicy::default(), |extras| extras.cap_policy)
+    }
+
+    /// set_cap_policyメソッドの実装
+    /// 物理要素数が上限を超えそうなときの動作を設定します (既定は EvictSmallest)
+    #[inline(always)]
+    pub fn set_cap_policy(&mut self, policy: CapPolicy) {
+        self.extras_mut().cap_policy = policy;
+    }
+
+    /// try_pushメソッドの実装
+    /// push と同じですが、以下の場合は何もせずにエラーを返します (panic / handle_alloc_error しません)
+    /// - 物理要素数の上限を超える: NnzCapExceeded (CapPolicy に関係なく削除は行いません)
+    /// - 容量が isize::MAX バイトを超える / len が usize::MAX: CapacityOverflow
+    /// - 確保に失敗した: AllocFailed
+    pub fn try_push(&mut self, elem: T) -> Result<(), SparseVecError> {
+        if !self.is_default(&elem) {
+            self.has_room()?;
+        }
+        self.on_mutate();
+        self.push_stored(elem)
+    }
+
+    /// try_insertメソッドの実装
+    /// insert と同じですが、以下の場合は何もせずにエラーを返します (panic / handle_alloc_error しません)
+    /// - index が len より大きい: IndexOutOfBounds
+    /// - それ以外は try_push と同じ
+    pub fn try_insert(&mut self, index: usize, elem: T) -> Result<(), SparseVecError> {
+        if index > self.len {
+            return Err(SparseVecError::IndexOutOfBounds { index, len: self.len });
+        }
+        if !self.is_default(&elem) {
+            self.has_room()?;
+        }
+        self.on_mutate();
+        self.insert_stored(index, elem)
+    }
+
+    /// has_roomメソッドの実装
+    /// 物理要素を 1 つ追加できるかどうか
+    #[inline(always)]
+    fn has_room(&self) -> Result<(), SparseVecError> {
+        match self.nnz_cap() {
+            Some(cap) if self.raw_len >= cap.max => Err(SparseVecError::NnzCapExceeded { max_nnz: cap.max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// make_roomメソッドの実装
+    /// 物理要素を 1 つ追加する直前に呼び、上限に達していれば CapPolicy に従って空きを作ります
+    #[inline(always)]
+    fn make_room(&mut self) {
+        if let Some(cap) = self.nnz_cap() {
+            if self.raw_len >= cap.max {
+                self.evict(self.raw_len + 1 - cap.max, cap);
+            }
+        }
+    }
+
+    /// check_roomメソッドの実装
+    /// まとめて `additional` 個追加する前に呼び、CapPolicy::Error で上限を超えるなら panic します
+    /// 削除するポリシーの場合は追加後に enforce_nnz_cap で削除します
+    #[inline(always)]
+    fn check_room(&self, additional: usize) {
+        if let Err(err) = self.room_for(additional) {
+            panic!("{err}");
+        }
+    }
+
+    /// room_forメソッドの実装
+    /// check_room の panic しない版
+    #[inline(always)]
+    fn room_for(&self, additional: usize) -> Result<(), SparseVecError> {
+        match self.nnz_cap() {
+            Some(cap) if self.cap_policy() == CapPolicy::Error && self.raw_len + additional > cap.max => {
+                Err(SparseVecError::NnzCapExceeded { max_nnz: cap.max })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// nnz_budgetメソッドの実装 (crate 内部用)
+    /// CapPolicy::Error のときに、あと何個物理要素を追加できるか
+    /// 削除するポリシーや上限が無い場合は usize::MAX
+    #[inline(always)]
+    pub(crate) fn nnz_budget(&self) -> usize {
+        match self.nnz_cap() {
+            Some(cap) if self.cap_policy() == CapPolicy::Error => cap.max.saturating_sub(self.raw_len),
+            _ => usize::MAX,
+        }
+    }
+
+    /// enforce_nnz_capメソッドの実装
+    /// 物理要素数が上限を超えていれば CapPolicy に従って削除します
+    #[inline(always)]
+    fn enforce_nnz_cap(&mut self) {
+        if let Some(cap) = self.nnz_cap() {
+            if self.raw_len > cap.max {
+                self.evict(self.raw_len - cap.max, cap);
+            }
+        }
+    }
+
+    /// evictメソッドの実装
+    /// CapPolicy に従って物理要素を `count` 個削除します
+    /// 1 個あたり O(nnz)、配列は昇順のまま保たれます
+    fn evict(&mut self, count: usize, cap: NnzCap<T>) {
+        self.on_mutate();
+        match self.cap_policy() {
+            CapPolicy::Error => panic!("{}", SparseVecError::NnzCapExceeded { max_nnz: cap.max }),
+            CapPolicy::EvictSmallest => {
+                for _ in 0..count {
+                    let mut smallest = 0;
+                    let mut smallest_mag = f64::INFINITY;
+                    for (i, elem) in self.as_pairs().1.iter().enumerate() {
+                        let mag = (cap.magnitude)(elem);
+                        if mag < smallest_mag {
+                            smallest = i;
+                            smallest_mag = mag;
+                        }
+                    }
+                    drop(self.remove_at(smallest));
+                }
+            }
+            CapPolicy::EvictOldestIndex => {
+                for _ in 0..count {
+                    drop(self.remove_at(0));
+                }
+            }
+        }
+    }
+
+    /// from_sorted_iter_uncheckedメソッドの実装 (crate 内部用)
+    /// index 昇順に並んだ (index, value) 列から長さ `len` のベクタを構築します
+    /// デフォルト値と等しい値は物理領域に書き込みません
+    /// ! : index が狭義単調増加かつ len 未満であることは呼び出し側が保証すること
+    pub(crate) fn from_sorted_iter_unchecked<I>(len: usize, iter: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        let mut svec = Self::new();
+        for (index, elem) in iter {
+            debug_assert!(index < len, "index out of bounds");
+            if elem != svec.default {
+                svec.raw_push(index, elem);
+            }
+        }
+        svec.len = len;
+        svec
+    }
+
+    /// count_stored_defaultsメソッドの実装
+    /// デフォルト値と等しいのに物理的に格納されている要素の数 O(nnz)
+    #[must_use]
+    #[inline(always)]
+    pub fn count_stored_defaults(&self) -> usize {
+        self.as_pairs().1.iter().filter(|elem| self.is_default(elem)).count()
+    }
+
+    /// iter_stored_defaultsメソッドの実装
+    /// デフォルト値と等しいのに物理的に格納されている要素の論理インデックスを昇順で返します
+    #[inline(always)]
+    pub fn iter_stored_defaults(&self) -> impl Iterator<Item = usize> + '_ {
+        let (ind, val) = self.as_pairs();
+        ind.iter()
+            .zip(val)
+            .filter(|(_, elem)| self.is_default(elem))
+            .map(|(&index, _)| index)
+    }
+
+    /// gapsメソッドの実装
+    /// 物理要素が無い (デフォルト値の) 論理インデックスの極大な範囲を昇順に返します O(nnz)
+    /// - 物理要素が無ければ 0..len の 1 つ (len が 0 なら何も返しません)
+    /// - 格納されたデフォルト値は物理要素として扱います
+    #[inline(always)]
+    pub fn gaps(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        Gaps::new(self.as_pairs().0.iter().copied(), self.len)
+    }
+
+    /// largest_gapメソッドの実装
+    /// gaps のうち最も長い範囲 (同じ長さなら前のもの)、範囲が無ければ None O(nnz)
+    #[must_use]
+    #[inline(always)]
+    pub fn largest_gap(&self) -> Option<Range<usize>> {
+        Gaps::new(self.as_pairs().0.iter().copied(), self.len).largest()
+    }
+
+    /// coverageメソッドの実装
+    /// 1 - (gaps の長さの合計 / len) O(1)
+    /// 物理要素のある論理位置の割合なので nnz / len と同じです (len が 0 なら欠けが無いので 1.0)
+    #[must_use]
+    #[inline(always)]
+    pub fn coverage(&self) -> f64 {
+        coverage(self.len, self.raw_len)
+    }
+
+    /// validateメソッドの実装
+    /// 内部表現の整合性を O(nnz) で検査します
+    /// - 物理インデックスが狭義単調増加で、全て len 未満であること
+    /// - デフォルト値と等しい物理要素は Strict ならエラー、Lenient なら件数を Ok で返します
+    pub fn validate(&self, mode: ValidationMode) -> Result<usize, SparseVecError> {
+        let mut stored_defaults = 0;
+        let (ind, val) = self.as_pairs();
+        for (position, (&index, elem)) in ind.iter().zip(val).enumerate() {
+            if position > 0 && ind[position - 1] >= index {
+                return Err(SparseVecError::UnsortedIndices { position });
+            }
+            if index >= self.len {
+                return Err(SparseVecError::IndexOutOfBounds { index, len: self.len });
+            }
+            if self.is_default(elem) {
+                if mode == ValidationMode::Strict {
+                    return Err(SparseVecError::StoredDefault { index });
+                }
+                stored_defaults += 1;
+            }
+        }
+        Ok(stored_defaults)
+    }
+
+    /// normalizeメソッドの実装
+    /// デフォルト値と等しい物理要素を drop し、残りを前に詰めます O(nnz)
+    /// - 容量はそのままです (必要なら shrink_to_fit)
+    #[inline(always)]
+    pub fn normalize(&mut self) {
+        self.compact_stored_defaults();
+    }
+
+    /// wasted_capacityメソッドの実装
+    /// 確保済みで使われていない物理要素の数 (cap - nnz)
+    #[inline(always)]
+    fn wasted_capacity(&self) -> usize {
+        self.cap() - self.raw_len
+    }
+
+    /// densityメソッドの実装
+    /// nnz / len (len が 0 の場合は 0.0)
+    #[must_use]
+    #[inline(always)]
+    pub fn density(&self) -> f64 {
+        if self.len == 0 {
+            0.0
+        } else {
+            self.raw_len as f64 / self.len as f64
+        }
+    }
+
+    /// count_defaultメソッドの実装
+    /// 物理要素が無い (スパース分部の) 論理要素の数 len - nnz
+    /// - 格納されたデフォルト値は数えません (count_stored_defaults を参照)
+    #[must_use]
+    #[inline(always)]
+    pub fn count_default(&self) -> usize {
+        self.len - self.raw_len
+    }
+
+    /// heap_size_bytesメソッドの実装
+    /// 物理領域として確保しているバイト数 cap * (size_of::<T>() + size_of::<usize>())
+    /// - ZST は値の領域を確保しないので cap * size_of::<usize>() (index の領域だけ)
+    /// - 要素自身が持つヒープ領域 (String の中身など) は含みません
+    #[must_use]
+    #[inline(always)]
+    pub fn heap_size_bytes(&self) -> usize {
+        self.cap() * (mem::size_of::<T>() + mem::size_of::<usize>())
+    }
+
+    /// statsメソッドの実装
+    /// len / nnz / cap / density / heap_size_bytes をまとめて返します O(1)
+    /// 値の統計が必要な場合は summarize
+    #[must_use]
+    #[inline(always)]
+    pub fn stats(&self) -> SparseStats {
+        SparseStats {
+            len: self.len,
+            nnz: self.raw_len,
+            cap: self.capacity(),
+            density: self.density(),
+            heap_bytes: self.heap_size_bytes(),
+        }
+    }
+
+    /// cost_hintメソッドの実装
+    /// op を今のベクタに行った場合のコストの上限を、実際には行わずに見積もります O(log nnz)
+    /// - index の更新だけの物理要素も usize ぶん動かしたものとして数えます
+    /// - 物理領域が足りない場合は、再確保で全物理要素をコピーする分を含みます
+    /// - Sort は比較の回数を 4 * nnz * (ceil(log2 nnz) + 1) 以下と見積もり、1 回の比較で物理要素 2 つを読み、
+    ///   順序の配列の usize を 1 つ動かすものとして数えます (値そのものは動かしません)
+    /// - debug ビルドでは insert / remove / append / build_value_index が実際に動かした量がこの見積もり以下であることを確かめます
+    #[must_use]
+    pub fn cost_hint(&self, op: OpKind) -> OpCost {
+        let entry_bytes = mem::size_of::<T>() + mem::size_of::<usize>();
+        // additional 個を書き込むのに再確保が必要なら、既存の物理要素を全てコピーする
+        let realloc_bytes = |additional: usize| {
+            if self.raw_len.saturating_add(additional) > self.cap() {
+                self.raw_len * entry_bytes
+            } else {
+                0
+            }
+        };
+        let shifted_from = |index: usize| self.raw_len - self.ind_binary_search(&index).unwrap_or_else(|pos| pos);
+        match op {
+            OpKind::Insert(index) => {
+                let shifted = shifted_from(index);
+                OpCost::new(shifted * entry_bytes + realloc_bytes(1), shifted + 1)
+            }
+            OpKind::Remove(index) => {
+                let shifted = shifted_from(index);
+                OpCost::new(shifted * entry_bytes, shifted)
+            }
+            OpKind::Append { other_nnz } => OpCost::new(
+                other_nnz.saturating_mul(entry_bytes).saturating_add(realloc_bytes(other_nnz)),
+                other_nnz,
+            ),
+            OpKind::Densify => OpCost::new(self.len.saturating_mul(mem::size_of::<T>()), self.len),
+            OpKind::Sort => {
+                let levels = self.raw_len.next_power_of_two().trailing_zeros() as usize + 1;
+                let comparisons = self.raw_len.saturating_mul(levels).saturating_mul(4);
+                OpCost::new(
+                    self.raw_len.saturating_add(comparisons).saturating_mul(mem::size_of::<usize>()),
+                    comparisons.saturating_mul(2),
+                )
+            }
+        }
+    }
+
+    /// try_with_budgetメソッドの実装
+    /// op の cost_hint が budget に収まる場合だけ f を呼び、その結果を返します
+    /// 収まらない場合は何もせずに OverBudget を返します
+    /// ! : f が実際に行う操作は確かめません (op と f を一致させるのは呼び出し側の責任です)
+    #[inline(always)]
+    pub fn try_with_budget<R, F>(&mut self, budget: OpCost, op: OpKind, f: F) -> Result<R, SparseVecError>
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let cost = self.cost_hint(op);
+        if !cost.fits_in(budget) {
+            return Err(SparseVecError::OverBudget { cost, budget });
+        }
+        Ok(f(self))
+    }
+
+    /// debug_check_costメソッドの実装
+    /// 実際に動かした物理要素数とバイト数が cost_hint の見積もり以下であることを確かめます (debug ビルドのみ)
+    #[cfg(debug_assertions)]
+    #[inline(always)]
+    fn debug_check_cost(&self, hint: OpCost, entries: usize, bytes: usize) {
+        assert!(
+            entries <= hint.entries_touched && bytes <= hint.bytes_moved,
+            "cost_hint underestimated: {entries} entries / {bytes} bytes moved against {hint:?}"
+        );
+    }
+
+    /// tuning_reportメソッドの実装
+    /// メモリ効率についての簡単な診断を 1 行ずつ返します
+    /// - 未使用の容量が nnz より多く、TUNING_WASTE_BYTES 以上ある場合は shrink_to_fit
+    /// - デフォルト値と等しい物理要素がある場合は normalize
+    /// - get_mut / IndexMut でデフォルト値の位置を物理要素にしたことがある場合
+    /// - 同じ len の Vec<T> より多くのメモリを使っている場合
+    ///
+    /// 問題が無い場合は "no issues found" の 1 行です
+    #[must_use]
+    pub fn tuning_report(&self) -> String {
+        let entry_bytes = mem::size_of::<T>() + mem::size_of::<usize>();
+        let mut lines = Vec::new();
+        let wasted = self.wasted_capacity();
+        if wasted > self.raw_len && wasted * entry_bytes >= TUNING_WASTE_BYTES {
+            lines.push(format!(
+                "capacity {} for nnz {} ({} bytes unused) — consider shrink_to_fit",
+                self.cap(),
+                self.raw_len,
+                wasted * entry_bytes
+            ));
+        }
+        let stored_defaults = self.count_stored_defaults();
+        if stored_defaults > 0 {
+            lines.push(format!(
+                "{stored_defaults} stored entries equal the default — call normalize()"
+            ));
+        }
+        if self.densified() {
+            lines.push(
+                "get_mut / IndexMut has densified default positions — call normalize() after writes that may leave defaults"
+                    .to_string(),
+            );
+        }
+        let sparse_bytes = self.raw_len * entry_bytes;
+        let dense_bytes = self.len * mem::size_of::<T>();
+        if mem::size_of::<T>() != 0 && self.raw_len > 0 && sparse_bytes >= dense_bytes {
+            lines.push(format!(
+                "density {:.4} uses {} bytes against {} bytes for a dense Vec — consider AdaptiveVec or Vec",
+                self.density(),
+                sparse_bytes,
+                dense_bytes
+            ));
+        }
+        if lines.is_empty() {
+            lines.push("no issues found".to_string());
+        }
+        lines.join("\n")
+    }
+
+    /// narrowest_index_widthメソッドの実装
+    /// len と全ての index を表せる最も狭い整数型
+    /// - len そのものが収まることを条件にします (len == u32::MAX + 1 なら U64)
+    #[must_use]
+    #[inline(always)]
+    pub fn narrowest_index_width(&self) -> IndexWidth {
+        IndexWidth::for_len(self.len)
+    }
+
+    /// split_at_mut_viewメソッドの実装
+    /// slice::split_at_mut のスパース版
+    /// 論理範囲 [0, mid) と [mid, len) の 2 つの可変ビューに分割します
+    /// - mid が len より大きい場合は panic
+    #[inline(always)]
+    pub fn split_at_mut_view(&mut self, mid: usize) -> (SparseVecViewMut<'_, T>, SparseVecViewMut<'_, T>) {
+        let mut views = self.disjoint_views_mut(&[mid]);
+        let right = views.pop().unwrap();
+        let left = views.pop().unwrap();
+        (left, right)
+    }
+
+    /// disjoint_views_mutメソッドの実装
+    /// `bounds` を区切り位置として、論理範囲を互いに重ならない可変ビューに分割します
+    /// (bounds = [a, b] なら [0, a), [a, b), [b, len) の 3 つ)
+    ///
+    /// - 各ビューは自分の範囲の値だけを変更でき、別スレッドに渡せます
+    /// - ビューで新しく書き込んだ値はビューごとのオーバーフローバッファに溜められ、
+    ///   最後のビューが drop されたときにまとめて本体にマージされます
+    /// - bounds が昇順でない、または len より大きい場合は panic
+    pub fn disjoint_views_mut(&mut self, bounds: &[usize]) -> Vec<SparseVecViewMut<'_, T>> {
+        self.on_mutate();
+        let mut prev = 0;
+        for &bound in bounds {
+            assert!(prev <= bound && bound <= self.len, "view bounds must be sorted and within len");
+            prev = bound;
+        }
+
+        let (raw_len, len) = (self.raw_len, self.len);
+        let view_count = bounds.len() + 1;
+        let (ind, val) = (self.ind_ptr(), self.val_ptr());
+        let merge_budget = self.nnz_budget();
+        // ビューは本体を指すポインタだけを持ちます (最後のビューが drop 中に本体へマージするため)
+        let parent: *mut Self = self;
+        let merge = ViewMerge::new(parent, view_count, merge_budget);
+        let default: *const T = unsafe { ptr::addr_of!((*parent).default) };
+        let ind_all: &[usize] = unsafe { std::slice::from_raw_parts(ind, raw_len) };
+
+        let mut views = Vec::with_capacity(view_count);
+        let (mut start, mut first) = (0, 0);
+        for end in bounds.iter().copied().chain(std::iter::once(len)) {
+            // ind は論理インデックス昇順なので、end 未満の物理要素数で物理領域を重ならないように分割できる
+            let split = ind_all.partition_point(|&i| i < end);
+            // [first, split) は他のビューと重ならず、本体の可変借用 ('_) の間は動きません
+            views.push(unsafe {
+                SparseVecViewMut::new(start, end, ind.add(first), val.add(first), split - first, default, merge.clone())
+            });
+            start = end;
+            first = split;
+        }
+        views
+    }
+
+    /// merge_unstored_sortedメソッドの実装 (crate 内部用)
+    /// まだ物理的に存在しない index への (index, value) 列を物理領域にマージします
+    /// 末尾から前へ向かって 1 パスでシフトしながら書き込み、デフォルト値は書き込みません
+    /// ! : entries は index 昇順・重複無しで、既存の物理インデックスと衝突しないこと
+    pub(crate) fn merge_unstored_sorted(&mut self, entries: Vec<(usize, T)>) {
+        self.on_mutate();
+        let mut entries: Vec<(usize, T)> = entries
+            .into_iter()
+            .filter(|(_, elem)| *elem != self.default)
+            .collect();
+        if entries.is_empty() {
+            return;
+        }
+        // SparseVecViewMut の drop から呼ばれるので panic しません (確保の失敗は Vec と同じく handle_alloc_error)
+        // (entries も既存の物理要素もメモリ上にあるので、CapacityOverflow は実際には起きません)
+        if let Err(err) = self.try_reserve_stored(entries.len()) {
+            let bytes = match err {
+                SparseVecError::AllocFailed { bytes } => bytes,
+                _ => isize::MAX as usize,
+            };
+            handle_alloc_error(Layout::from_size_align(bytes, 1).unwrap_or(Layout::new::<u8>()));
+        }
+
+        let mut i = self.raw_len;
+        let mut w = self.raw_len + entries.len();
+        while let Some((index, elem)) = entries.pop() {
+            // index より後ろにある既存の要素を末尾側へ移動
+            while i > 0 && unsafe { *self.ind_ptr().add(i - 1) } > index {
+                i -= 1;
+                w -= 1;
+                unsafe {
+                    ptr::copy(self.val_ptr().add(i), self.val_ptr().add(w), 1);
+                    *self.ind_ptr().add(w) = *self.ind_ptr().add(i);
+                }
+            }
+            w -= 1;
+            debug_assert!(i == 0 || unsafe { *self.ind_ptr().add(i - 1) } != index);
+            unsafe {
+                ptr::write(self.val_ptr().add(w), elem);
+                ptr::write(self.ind_ptr().add(w), index);
+            }
+            self.raw_len += 1;
+        }
+        self.enforce_nnz_cap();
+    }
+
+    /// from_partsメソッドの実装 (crate 内部用)
+    /// 外部から読み込んだ部品を検査してからベクタを組み立てます
+    /// - indices は狭義単調増加で全て len 未満であること
+    /// - default と等しい値は格納しません
+    pub(crate) fn from_parts(len: usize, default: T, indices: Vec<usize>, values: Vec<T>) -> Result<Self, SparseVecError> {
+        if indices.len() != values.len() {
+            return Err(SparseVecError::LengthMismatch { indices: indices.len(), values: values.len() });
+        }
+        for (position, &index) in indices.iter().enumerate() {
+            if position > 0 && indices[position - 1] >= index {
+                return Err(SparseVecError::UnsortedIndices { position });
+            }
+            if index >= len {
+                return Err(SparseVecError::IndexOutOfBounds { index, len });
+            }
+        }
+
+        let mut svec = if values.is_empty() { Self::new() } else { Self::with_capacity(values.len()) };
+        svec.default = default;
+        for (index, elem) in indices.into_iter().zip(values) {
+            if elem != svec.default {
+                svec.raw_push(index, elem);
+            }
+        }
+        svec.len = len;
+        Ok(svec)
+    }
+
+    /// into_partsメソッドの実装 (crate 内部用)
+    /// (len, default, indices, values) に分解します
+    pub(crate) fn into_parts(mut self) -> (usize, T, Vec<usize>, Vec<T>) {
+        self.on_mutate();
+        let indices = self.as_pairs().0.to_vec();
+        let mut values = Vec::with_capacity(self.raw_len);
+        let raw_len = self.raw_len;
+        // 値の所有権を values に移すので、self の drop では値を drop させない
+        self.raw_len = 0;
+        for i in 0..raw_len {
+            values.push(unsafe { ptr::read(self.val_ptr().add(i)) });
+        }
+        (self.len, mem::take(&mut self.default), indices, values)
+    }
+
+    /// transmute_valuesメソッドの実装
+    /// 値の型を U (T を包む #[repr(transparent)] の newtype) に読み替えます O(1)
+    /// 物理領域をそのまま再利用し、値の clone やコピーは行いません
+    /// - len / デフォルト値 / set_max_nnz の上限とポリシーは引き継ぎます
+    #[inline(always)]
+    pub fn transmute_values<U>(self) -> DefaultSparseVec<U>
+    where
+        U: TransparentWrapper<T> + Default + PartialEq,
+    {
+        // 安全性: U: TransparentWrapper<T> なので T と U は同じレイアウトで、値は相互に有効
+        unsafe { self.reinterpret() }
+    }
+
+    /// into_inner_valuesメソッドの実装
+    /// transmute_values の逆で、newtype である T の値を中身の U に戻します O(1)
+    #[inline(always)]
+    pub fn into_inner_values<U>(self) -> DefaultSparseVec<U>
+    where
+        T: TransparentWrapper<U>,
+        U: Default + PartialEq,
+    {
+        // 安全性: T: TransparentWrapper<U> なので T と U は同じレイアウトで、値は相互に有効
+        unsafe { self.reinterpret() }
+    }
+
+    /// viewメソッドの実装
+    /// 読み取り専用のビューを返します O(1)
+    #[must_use]
+    #[inline(always)]
+    pub fn view(&self) -> SparseVecView<'_, T> {
+        let (ind, val) = self.as_pairs();
+        SparseVecView::new(self.len, ind, val, &self.default)
+    }
+
+    /// reversed_viewメソッドの実装
+    /// index i を len - 1 - i として読む逆向きのビューを返します O(1)
+    /// a.view().f64_dot(&b.reversed_view()) で、b を反転したコピーを作らずに内積を計算できます
+    #[must_use]
+    #[inline(always)]
+    pub fn reversed_view(&self) -> SparseVecView<'_, T> {
+        self.view().reversed()
+    }
+
+    /// read_onlyメソッドの実装
+    /// 確保・変更を行わないメソッドだけを公開する ReadOnlySparseVec を返します O(1)
+    #[must_use]
+    #[inline(always)]
+    pub fn read_only(&self) -> ReadOnlySparseVec<'_, T> {
+        ReadOnlySparseVec::new(self)
+    }
+
+    /// as_wrapped_viewメソッドの実装
+    /// transmute_values の借用版です O(1)
+    /// 物理領域を U の値として読む WrappedView を返します
+    #[inline(always)]
+    pub fn as_wrapped_view<U: TransparentWrapper<T>>(&self) -> WrappedView<'_, U> {
+        let (ind, val) = self.as_pairs();
+        // 安全性: U は T の #[repr(transparent)] なので、&[T] / &T をそのまま &[U] / &U として読めます
+        unsafe {
+            WrappedView::new(
+                self.len,
+                ind,
+                std::slice::from_raw_parts(val.as_ptr().cast::<U>(), val.len()),
+                &*(&self.default as *const T).cast::<U>(),
+            )
+        }
+    }
+
+    /// reinterpretメソッドの実装
+    /// 物理領域とデフォルト値を U の値として読み替えた DefaultSparseVec<U> を作ります
+    /// # Safety
+    /// T と U のどちらかがもう一方の TransparentWrapper であること
+    unsafe fn reinterpret<U: Default + PartialEq>(self) -> DefaultSparseVec<U> {
+        let mut this = mem::ManuallyDrop::new(self);
+        // Extras (shrink_step のコピー途中の領域を含む) を取り出しておく (以降 this は drop しません)
+        let extras = this.extras.take();
+        // 引数の &T と &U は ABI 互換なので、関数ポインタも読み替えられます
+        let extras = extras.map(|extras| Box::new(Extras {
+            shrink: None,
+            nnz_cap: extras.nnz_cap.map(|cap| NnzCap {
+                max: cap.max,
+                magnitude: mem::transmute::<fn(&T) -> f64, fn(&U) -> f64>(cap.magnitude),
+            }),
+            cap_policy: extras.cap_policy,
+            sparsify: extras.sparsify.map(|sparsify| Sparsify {
+            policy: match sparsify.policy {
+                SparsifyPolicy::Exact => SparsifyPolicy::Exact,
+                SparsifyPolicy::AbsTolerance(tol) => SparsifyPolicy::AbsTolerance(tol),
+                SparsifyPolicy::Custom(f) => SparsifyPolicy::Custom(mem::transmute::<fn(&T, &T) -> bool, fn(&U, &U) -> bool>(f)),
+            },
+                to_f64: mem::transmute::<extern "C" fn(&T) -> f64, extern "C" fn(&U) -> f64>(sparsify.to_f64),
+            }),
+            densified: extras.densified,
+            value_index: None,
+        }));
+        DefaultSparseVec {
+            buf: RawDefaultSparseVec {
+                val_ptr: this.buf.val_ptr.cast::<U>(),
+                ind_ptr: this.buf.ind_ptr,
+                cap: this.buf.cap,
+                _marker: PhantomData,
+            },
+            raw_len: this.raw_len,
+            len: this.len,
+            default: ptr::read((&this.default as *const T).cast::<U>()),
+            extras,
+            #[cfg(debug_assertions)]
+            mods: AtomicUsize::new(0),
+        }
+    }
+
+    /// sparse_vec! マクロ用の構築関数 (index 付き)
+    /// index の並びが不正な場合は panic
+    #[doc(hidden)]
+    pub fn __from_macro_parts(len: usize, default: T, indices: Vec<usize>, values: Vec<T>) -> Self {
+        match Self::from_parts(len, default, indices, values) {
+            Ok(svec) => svec,
+            Err(err) => panic!("sparse_vec!: {err}"),
+        }
+    }
+
+    /// sparse_vec! マクロ用の構築関数 (密なリテラル)
+    #[doc(hidden)]
+    pub fn __from_macro_dense(dense: Vec<T>) -> Self {
+        let len = dense.len();
+        let default = T::default();
+        let (indices, values): (Vec<usize>, Vec<T>) = dense
+            .into_iter()
+            .enumerate()
+            .filter(|(_, elem)| *elem != default)
+            .unzip();
+        Self::__from_macro_parts(len, default, indices, values)
+    }
+
+    /// raw_pushメソッドの実装
+    /// 物理領域の末尾に (index, elem) をそのまま書き込みます
+    /// ! : index が既存の物理インデックスより大きいことは呼び出し側が保証すること
+    /// ! : len は更新しないので、呼び出し側で設定すること
+    #[inline(always)]
+    fn raw_push(&mut self, index: usize, elem: T) {
+        self.on_mutate();
+        if self.raw_len == self.cap() {
+            self.buf.grow();
+        }
+        unsafe {
+            ptr::write(self.val_ptr().add(self.raw_len), elem);
+            ptr::write(self.ind_ptr().add(self.raw_len), index);
+        }
+        self.raw_len += 1;
+    }
+
+    /// trace_shiftメソッドの実装
+    /// 物理位置 i 以降を動かす操作で、動かす要素数が TRACE_SHIFT_NNZ 以上なら tracing のイベントを出します
+    /// - moves_values: 値も動かす場合は true (false ならインデックスの更新だけ)
+    /// - feature = "tracing" が無効な場合は何もしません
+    #[cfg(feature = "tracing")]
+    #[inline(always)]
+    fn trace_shift(&self, op: &'static str, i: usize, moves_values: bool) {
+        let count = self.raw_len - i;
+        if count >= TRACE_SHIFT_NNZ {
+            let val_bytes = if moves_values { mem::size_of::<T>() } else { 0 };
+            tracing::trace!(
+                target: "vec_plus",
+                op,
+                len = self.len,
+                nnz = self.raw_len,
+                bytes_moved = count * (val_bytes + mem::size_of::<usize>()),
+                "O(nnz) shift"
+            );
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[inline(always)]
+    fn trace_shift(&self, _op: &'static str, _i: usize, _moves_values: bool) {}
+
+    /// iterメソッドの実装
+    /// スパース分部を含みません (スパース分部が必要な場合は iter_dense)
+    /// - 物理要素の (index, 値) を index の狭義単調増加の順に返します
+    ///   (insert / remove / append などをどう組み合わせても保たれる保証です)
+    /// - 長さは nnz で、後ろからも取り出せます
+    #[must_use]
+    #[inline(always)]
+    pub fn iter(&self) -> SparseIter<'_, T> {
+        let (ind, val) = self.as_pairs();
+        SparseIter::new(ind, val, self.mod_check())
+    }
+
+    /// iter_denseメソッドの実装
+    /// スパース分部を含む全要素を論理インデックス順に返します
+    /// スパース分部はデフォルト値への参照です
+    /// ExactSizeIterator なので、collect は 1 回の確保で済みます
+    #[must_use]
+    #[inline(always)]
+    pub fn iter_dense(&self) -> IterDense<'_, T> {
+        let (ind, val) = self.as_pairs();
+        IterDense::new(self.len, ind, val, &self.default, self.mod_check())
+    }
+
+    /// iter_mutメソッドの実装
+    /// スパース分部を含みません
+    /// ! : 値をデフォルト値にしても物理要素は残ります (count_stored_defaults / validate で検出できます)
+    ///
+    /// - iter と同じく index の狭義単調増加の順に返します
+    /// - index は値で返すので、インデックス配列の順序や範囲は壊せません
+    #[must_use]
+    #[inline(always)]
+    pub fn iter_mut(&mut self) -> SparseIterMut<'_, T> {
+        self.on_mutate();
+        self.debug_check_raw();
+        // 変更回数は共有の借用で読むので、配列は as_pairs_mut (self 全体の可変借用) を通さずに作ります
+        let (ind, val) = unsafe {
+            (
+                std::slice::from_raw_parts(self.ind_ptr(), self.raw_len),
+                std::slice::from_raw_parts_mut(self.val_ptr(), self.raw_len),
+            )
+        };
+        SparseIterMut::new(ind, val, self.mod_check())
+    }
+
+    /// as_pairsメソッドの実装
+    /// 物理要素の (インデックス配列, 値配列) を返します
+    /// 2 つの配列は常に同じ長さ (nnz) で、i 番目同士が同じ物理要素です
+    /// インデックス配列は狭義単調増加で、全て len 未満です
+    /// (len を減らす操作 pop / pop_front / remove / truncate / clear / reset / retain は、戻る前に
+    ///  論理的に取り除いた物理要素を配列から外すので、取り直した配列がそれらを含むことはありません)
+    /// - スライスの先頭ポインタを FFI などに渡す場合、有効なのは次の変更操作までです
+    ///   (再確保・shrink・clear・drop の後は解放済み、または debug-poison で POISON_BYTE に上書きされた領域です)
+    #[must_use]
+    #[inline(always)]
+    pub fn as_pairs(&self) -> (&[usize], &[T]) {
+        self.debug_check_raw();
+        unsafe {
+            (
+                std::slice::from_raw_parts(self.ind_ptr(), self.raw_len),
+                std::slice::from_raw_parts(self.val_ptr(), self.raw_len),
+            )
+        }
+    }
+
+    /// as_pairs_mutメソッドの実装
+    /// as_pairs と同じ組を返しますが、値配列だけ変更できます
+    /// (インデックス配列を書き換えると上の保証が崩れるので不変のまま)
+    /// ! : 値をデフォルト値にしても物理要素は残ります (count_stored_defaults / validate で検出できます)
+    /// - ポインタの有効期間は as_pairs と同じです
+    #[inline(always)]
+    pub fn as_pairs_mut(&mut self) -> (&[usize], &mut [T]) {
+        self.on_mutate();
+        self.debug_check_raw();
+        unsafe {
+            (
+                std::slice::from_raw_parts(self.ind_ptr(), self.raw_len),
+                std::slice::from_raw_parts_mut(self.val_ptr(), self.raw_len),
+            )
+        }
+    }
+
+    //// as_sliceメソッドの実装
+    /// - ポインタの有効期間は as_pairs と同じです
+    #[must_use]
+    #[deprecated(note = "use as_pairs, which ties indices and values together")]
+    #[inline(always)]
+    pub fn as_slice_val(&self) -> &[T] {
+        self.as_pairs().1
+    }
+
+    /// as_pairs のインデックス配列と同じく、狭義単調増加で全て len 未満です
+    #[must_use]
+    #[deprecated(note = "use as_pairs, which ties indices and values together")]
+    #[inline(always)]
+    pub fn as_slice_ind(&self) -> &[usize] {
+        self.as_pairs().0
+    }
+
+    #[deprecated(note = "use as_pairs_mut; writing defaults here silently breaks normalization")]
+    #[inline(always)]
+    pub fn as_mut_slice_val(&mut self) -> &mut [T] {
+        self.as_pairs_mut().1
+    }
+
+    /// ! : インデックスの並びや範囲を壊すと、以降の全ての操作の結果は未定義です
+    /// - ポインタの有効期間は as_pairs と同じです
+    #[deprecated(note = "use as_pairs; stored indices must stay strictly increasing")]
+    #[inline(always)]
+    pub fn as_mut_slice_ind(&mut self) -> &mut [usize] {
+        self.on_mutate();
+        self.debug_check_raw();
+        unsafe {
+            std::slice::from_raw_parts_mut(self.ind_ptr(), self.raw_len)
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Clone> DefaultSparseVec<T> {
+    /// clear_to_default_ofメソッドの実装
+    /// 物理的に存在する値を drop し、`template` と同じデフォルト値・長さの
+    /// 全てデフォルト値のベクタにします
+    /// - 確保済みの容量 (capacity) はそのまま再利用します
+    #[inline(always)]
+    pub fn clear_to_default_of(&mut self, template: &Self) {
+        self.reset(template.len);
+        self.default = template.default.clone();
+    }
+
+    /// collect_denseメソッドの実装
+    /// スパース分部をデフォルト値で埋めた Vec<T> を作ります O(len)
+    /// 領域は len ちょうどを 1 回だけ確保し、再確保は行いません
+    /// (物理要素の間をデフォルト値で埋めてから物理要素を書き込む、を繰り返します)
+    #[must_use]
+    pub fn collect_dense(&self) -> Vec<T> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "vec_plus",
+            len = self.len,
+            nnz = self.raw_len,
+            bytes_moved = self.len * mem::size_of::<T>(),
+            "densify to Vec"
+        );
+        let mut vec = Vec::with_capacity(self.len);
+        let (ind, val) = self.as_pairs();
+        for (index, elem) in ind.iter().zip(val) {
+            vec.resize(*index, self.default.clone());
+            vec.push(elem.clone());
+        }
+        vec.resize(self.len, self.default.clone());
+        vec
+    }
+
+    /// to_denseメソッドの実装
+    /// collect_dense と同じです (self を消費しない Vec<T> への変換)
+    #[must_use]
+    #[inline(always)]
+    pub fn to_dense(&self) -> Vec<T> {
+        self.collect_dense()
+    }
+
+    /// write_dense_intoメソッドの実装
+    /// スパース分部をデフォルト値で埋めた全要素を out に書き込みます O(len)
+    /// 確保しないので、使い回すバッファへの展開に使えます
+    /// ! : out.len() が len と異なる場合は panic
+    pub fn write_dense_into(&self, out: &mut [T]) {
+        assert!(out.len() == self.len, "destination length ({}) does not match the len ({})", out.len(), self.len);
+        let (ind, val) = self.as_pairs();
+        let mut next = 0;
+        for (&index, elem) in ind.iter().zip(val) {
+            out[next..index].fill(self.default.clone());
+            out[index].clone_from(elem);
+            next = index + 1;
+        }
+        out[next..].fill(self.default.clone());
+    }
+
+    /// to_sparse_mapメソッドの実装
+    /// 物理的に存在する要素だけを index -> 値 の HashMap にします O(nnz)
+    /// デフォルト値と等しい物理要素 (密にされた要素など) は含みません
+    /// ! : 含まれない index はデフォルト値です。全 index が必要な場合は to_full_map
+    #[must_use]
+    #[inline(always)]
+    pub fn to_sparse_map(&self) -> HashMap<usize, T> {
+        self.stored_non_default().collect()
+    }
+
+    /// to_full_mapメソッドの実装
+    /// 0..len の全ての index を含む HashMap にします
+    /// ! : O(len) の要素を確保するので、len が大きい場合は高コストです
+    #[must_use]
+    pub fn to_full_map(&self) -> HashMap<usize, T> {
+        let mut map = HashMap::with_capacity(self.len);
+        self.fill_full(|index, elem| {
+            map.insert(index, elem);
+        });
+        map
+    }
+
+    /// to_sparse_btreeメソッドの実装
+    /// to_sparse_map の BTreeMap 版 (index 昇順)
+    #[must_use]
+    #[inline(always)]
+    pub fn to_sparse_btree(&self) -> BTreeMap<usize, T> {
+        self.stored_non_default().collect()
+    }
+
+    /// to_full_btreeメソッドの実装
+    /// to_full_map の BTreeMap 版 (index 昇順)
+    /// ! : O(len) の要素を確保するので、len が大きい場合は高コストです
+    #[must_use]
+    pub fn to_full_btree(&self) -> BTreeMap<usize, T> {
+        let mut map = BTreeMap::new();
+        self.fill_full(|index, elem| {
+            map.insert(index, elem);
+        });
+        map
+    }
+
+    /// stored_non_defaultメソッドの実装
+    /// デフォルト値と等しくない物理要素を (index, 値の clone) で返します
+    #[inline(always)]
+    fn stored_non_default(&self) -> impl Iterator<Item = (usize, T)> + '_ {
+        self.iter()
+            .filter(|(_, elem)| **elem != self.default)
+            .map(|(index, elem)| (index, elem.clone()))
+    }
+
+    /// fill_fullメソッドの実装
+    /// 0..len の全ての (index, 値) を昇順に f に渡します
+    /// 物理要素との突き合わせはマージ走査で行うので O(len)
+    fn fill_full<F: FnMut(usize, T)>(&self, mut f: F) {
+        let mut stored = self.iter().peekable();
+        for index in 0..self.len {
+            match stored.next_if(|(i, _)| *i == index) {
+                Some((_, elem)) => f(index, elem.clone()),
+                None => f(index, self.default.clone()),
+            }
+        }
+    }
+
+    /// popメソッドの実装
+    /// - 末尾が物理的に無い場合はデフォルト値の clone を返します
+    #[inline(always)]
+    pub fn pop(&mut self) -> Option<T> {
+        self.on_mutate();
+        if self.len == 0 {
+            return None;
+        }
+        // 末尾の論理要素が物理的にあるのは、最後の物理要素の index が len - 1 の場合だけ
+        let last = self.len - 1;
+        let pop_elem = 
+            if self.as_pairs().0.last() == Some(&last) {
+                self.raw_len -= 1;
+                let elem = unsafe { ptr::read(self.val_ptr().add(self.raw_len)) };
+                self.buf.poison(self.raw_len, self.raw_len + 1);
+                Some(elem)
+            } else {
+                Some(self.default.clone())
+            };
+        self.len -= 1;
+        pop_elem
+    }
+
+    /// pop_frontメソッドの実装
+    /// 先頭の要素を取り出します
+    /// - 既存の物理インデックスは全て -1 (シフトと同じ 1 パスで更新)
+    /// - 先頭が物理的に無い場合はデフォルト値の clone を返す
+    #[inline(always)]
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.on_mutate();
+        if self.len == 0 {
+            return None;
+        }
+        let pop_elem = if self.raw_len > 0 && unsafe { *self.ind_ptr() } == 0 {
+            unsafe {
+                let elem = ptr::read(self.val_ptr());
+                // 値は前へまとめてシフト
+                ptr::copy(self.val_ptr().add(1), self.val_ptr(), self.raw_len - 1);
+                // インデックスはシフトと -1 を同時に行う
+                for i in 1..self.raw_len {
+                    *self.ind_ptr().add(i - 1) = *self.ind_ptr().add(i) - 1;
+                }
+                self.raw_len -= 1;
+                elem
+            }
+        } else {
+            self.dec_indices_from(0);
+            self.default.clone()
+        };
+        self.len -= 1;
+        Some(pop_elem)
+    }
+
+    /// entryメソッドの実装
+    /// index 番目の物理要素の有無に応じた Entry を返します O(log nnz)
+    /// Occupied の間に値がデフォルト値になった場合、drop 時に物理要素を取り除きます (nnz が増え続けません)
+    /// - index が len 以上の場合は panic
+    pub fn entry(&mut self, index: usize) -> Entry<'_, T> {
+        assert!(index < self.len, "index out of bounds: the len is {} but the index is {index}", self.len);
+        self.on_mutate();
+        match self.ind_binary_search(&index) {
+            Ok(pos) => Entry::Occupied(OccupiedEntry::new(self, index, pos)),
+            Err(_) => Entry::Vacant(VacantEntry::new(self, index)),
+        }
+    }
+
+    /// setメソッドの実装
+    /// index 番目を elem にして、前の論理的な値を返します O(log nnz + 移動する物理要素数)
+    /// get_mut と違い、スパース化を保ちます
+    /// - 物理要素あり + 非デフォルト値: 上書き (nnz は変わらない)
+    /// - 物理要素あり + デフォルト値: 物理要素を取り除く (nnz - 1)
+    /// - 物理要素なし + 非デフォルト値: 物理要素を追加 (nnz + 1、上限は CapPolicy に従う)
+    /// - 物理要素なし + デフォルト値: 何もしない (デフォルト値の clone を返す)
+    /// - index が len 以上の場合は panic
+    pub fn set(&mut self, index: usize, elem: T) -> T {
+        assert!(index < self.len, "index out of bounds: the len is {} but the index is {index}", self.len);
+        self.on_mutate();
+        let to_default = self.is_default(&elem);
+        match self.ind_binary_search(&index) {
+            Ok(i) if to_default => self.remove_at(i),
+            Ok(i) => unsafe { mem::replace(&mut *self.val_ptr().add(i), elem) },
+            Err(_) if to_default => self.default.clone(),
+            Err(_) => {
+                self.insert_vacant(index, elem);
+                self.default.clone()
+            }
+        }
+    }
+
+    // get_mutメソッドの実装
+    // このメソッドは、指定されたインデックスの要素を変更するために使用されます。
+    // ! : スパース分部の要素をわたすためにわざと値を生成します
+    // ! : 無駄にデフォルト値を生成するので、このメソッドは避けるべきです
+    // ! : 値を書き込むだけなら set を使ってください (デフォルト値を書き込んでも物理要素が残りません)
+    #[deprecated(note = "このメソッドは避けるべきです. 
+                        スパース分部の実値を渡すため、スパース分部の値を無駄に生成します.
+                        default値以外を代入する場合は問題ありません. 値の書き込みには set を使ってください.")]
+    #[inline(always)]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.on_mutate();
+        if index >= self.len {
+            return None;
+        }
+        if let Ok(i) = self.ind_binary_search(&index) {
+            return Some(unsafe { &mut *self.val_ptr().add(i) });
+        }
+        // 上限で物理要素が削除されると位置がずれるので、探し直す
+        self.make_room();
+        match self.ind_binary_search(&index) {
+            Ok(_) => unreachable!(),
+            Err(i) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    target: "vec_plus",
+                    index,
+                    len = self.len,
+                    nnz = self.raw_len,
+                    bytes_moved = (self.raw_len - i) * (mem::size_of::<T>() + mem::size_of::<usize>()),
+                    "get_mut densified a default entry"
+                );
+                self.extras_mut().densified = true;
+                let elem = self.default.clone();
+                self.insert_at(i, index, elem);
+                let val = unsafe { &mut *self.val_ptr().add(i) };
+                Some(val)
+            },
+        }
+    }
+
+    /// removeメソッド
+    /// 
+    /// `index` 番目の要素を削除し、削除した要素を返します。
+    /// - 論理インデックス `index` が物理的に存在すれば、その値を返す
+    /// - 物理的になければ（= デフォルト扱いだった）デフォルト値の clone を返す
+    /// 
+    /// いずれにせよ後ろの要素（論理インデックスが `index` より大きい要素）は
+    /// インデックスを 1 つ前にシフトします。
+    /// - index が len 以上の場合は panic (空のベクタでは常に panic)
+    #[inline(always)]
+    pub fn remove(&mut self, index: usize) -> T {
+        self.on_mutate();
+        assert!(index < self.len, "removal index (is {index}) should be < len (is {})", self.len);
+        #[cfg(debug_assertions)]
+        let hint = self.cost_hint(OpKind::Remove(index));
+        
+        // 論理的な要素数は常に1つ減る
+        self.len -= 1;
+
+        match self.ind_binary_search(&index) {
+            Ok(i) => {
+                // 今回削除する要素を読みだす
+                let removed_val = unsafe {
+                    ptr::read(self.val_ptr().add(i))
+                };
+
+                // `i` 番目を削除するので、後ろを前にシフト
+                self.trace_shift("remove", i + 1, true);
+                let count = self.raw_len - i - 1;
+                #[cfg(debug_assertions)]
+                self.debug_check_cost(hint, count, count * (mem::size_of::<T>() + mem::size_of::<usize>()));
+                if count > 0 {
+                    unsafe {
+                        // 値をコピーして前につめる
+                        ptr::copy(
+                            self.val_ptr().add(i + 1),
+                            self.val_ptr().add(i),
+                            count
+                        );
+                        // インデックスは前につめるのと -1 を同時に行う (コピーしてから -1 するより 1 パス少ない)
+                        for offset in i..(self.raw_len - 1) {
+                            *self.ind_ptr().add(offset) = *self.ind_ptr().add(offset + 1) - 1;
+                        }
+                    }
+                }
+                // 物理的な要素数は 1 減
+                self.raw_len -= 1;
+
+                // 取り除いた要素を返す
+                removed_val
+            }
+            Err(i) => {
+                // index は詰める必要があるので、i 以降の要素のインデックスを -1
+                // （たとえば “要素自体は無い” けど、後ろにある要素は
+                //  論理インデックスが 1 つ前になる）
+                self.trace_shift("remove", i, false);
+                #[cfg(debug_assertions)]
+                self.debug_check_cost(hint, self.raw_len - i, (self.raw_len - i) * mem::size_of::<usize>());
+                self.dec_indices_from(i);
+
+                // “もともと物理要素が無い” のだから、デフォルト値を返す
+                self.default.clone()
+            }
+        }
+    }
+
+    /// append_allメソッドの実装
+    /// shards を順に自分の後ろへ連結し (shards の値は clone します)、連結後の (len, nnz) を返します
+    /// - 先に全ての shard を検査してから 1 回だけ領域を確保し、その後で値を書き込みます
+    /// - エラーの場合、自分は呼び出し前と全く同じ状態のままです
+    ///   (デフォルト値の不一致、len / nnz のあふれ、CapPolicy::Error の上限、確保の失敗)
+    /// - len が 0 の shard は append と同じく何もしません (デフォルト値も比べません)
+    ///
+    /// ! : clone が panic した場合は、書き込んだ分を取り除いて元の len に戻します (容量は戻しません)
+    pub fn append_all(&mut self, shards: &[Self]) -> Result<(usize, usize), SparseVecError> {
+        let mut new_len = self.len;
+        let mut added: usize = 0;
+        for (position, shard) in shards.iter().enumerate() {
+            if shard.len == 0 {
+                continue;
+            }
+            if shard.default != self.default {
+                return Err(SparseVecError::DefaultMismatch { position });
+            }
+            new_len = new_len.checked_add(shard.len).ok_or(SparseVecError::CapacityOverflow)?;
+            added = added.checked_add(shard.raw_len).ok_or(SparseVecError::CapacityOverflow)?;
+        }
+        self.room_for(added)?;
+        let new_raw_len = self.raw_len.checked_add(added).ok_or(SparseVecError::CapacityOverflow)?;
+        if new_raw_len > self.cap() {
+            self.buf.try_set_capacity(new_raw_len)?;
+        }
+
+        self.on_mutate();
+        let old_len = self.len;
+        // 書き込む index が常に len 未満になるよう、先に len を伸ばしておく
+        self.len = new_len;
+        let guard = TruncateOnUnwind { vec: self, len: old_len };
+        let mut offset = old_len;
+        for shard in shards {
+            for (index, elem) in shard.iter() {
+                guard.vec.raw_push(offset + index, elem.clone());
+            }
+            offset += shard.len;
+        }
+        mem::forget(guard);
+        self.enforce_nnz_cap();
+        Ok((self.len, self.raw_len))
+    }
+
+    /// swap_removeメソッドの実装
+    /// index 番目の要素を取り除いて返し、最後の要素をその位置に移します (Vec::swap_remove と同じ) O(log nnz + 移動する物理要素数)
+    /// - 後ろの要素の index を書き換えないので、順序が不要なら remove より速くなります
+    /// - 最後の要素を pop してから index 番目に set します (どちらがデフォルト値かで、上書き / 物理要素の追加 / 削除 / 何もしない)
+    /// - index が len 以上の場合は panic
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "swap_remove index (is {index}) should be < len (is {})", self.len);
+        let last = self.pop().expect("vector is not empty");
+        if index == self.len {
+            return last;
+        }
+        self.set(index, last)
+    }
+
+    /// drainメソッドの実装
+    /// 論理範囲 range の要素を取り除き、取り除いた要素を論理インデックス順に返す Drain を返します
+    /// - スパース分部はデフォルト値の clone を返します
+    /// - Drain の drop 時に、後ろの物理要素を 1 回の移動で前に詰め、同じパスで index から range の長さを引きます
+    /// - 途中で drop した場合、まだ返していない要素も取り除きます
+    /// - range が len を超える場合、始まりが終わりより後ろの場合は panic
+    ///
+    /// ! : Drain を mem::forget した場合、ベクタは range の始まりで切り詰められた状態になります
+    ///     (後ろの要素はリークしますが、二重解放や壊れた状態にはなりません)
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T> {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s.checked_add(1).expect("drain range start overflows usize"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e.checked_add(1).expect("drain range end overflows usize"),
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end, "drain range starts at {start} but ends at {end}");
+        assert!(end <= self.len, "drain range end {end} out of bounds for len {}", self.len);
+        self.on_mutate();
+        let (ind, _) = self.as_pairs();
+        let phys_start = ind.partition_point(|&index| index < start);
+        let phys_end = phys_start + ind[phys_start..].partition_point(|&index| index < end);
+        let raw_len = self.raw_len;
+        let new_len = self.len - (end - start);
+        // Drain がリークしても壊れた状態にならないよう、先に range の始まりで切り詰めておく
+        // (drop 時に close_drain が後ろを詰めて戻します)
+        self.raw_len = phys_start;
+        self.len = start;
+        let (ind, val) = (self.ind_ptr(), self.val_ptr());
+        Drain::new(self, ind, val, start..end, phys_start..phys_end, raw_len, new_len)
+    }
+
+    /// close_drainメソッドの実装 (crate 内部用)
+    /// Drain の drop から呼ばれ、物理位置 [tail_start, tail_end) を raw_len の後ろへ移し、同じパスで index から removed を引きます
+    /// - [raw_len, tail_start) の値は読み出し済み、または drop 済みであること
+    pub(crate) fn close_drain(&mut self, tail_start: usize, tail_end: usize, removed: usize, new_len: usize) {
+        let dst = self.raw_len;
+        let tail = tail_end - tail_start;
+        if tail > 0 {
+            unsafe {
+                if dst != tail_start {
+                    ptr::copy(self.val_ptr().add(tail_start), self.val_ptr().add(dst), tail);
+                }
+                for i in 0..tail {
+                    *self.ind_ptr().add(dst + i) = *self.ind_ptr().add(tail_start + i) - removed;
+                }
+            }
+        }
+        self.buf.poison(dst + tail, tail_end);
+        self.raw_len = dst + tail;
+        self.len = new_len;
+    }
+
+    /// merge_withメソッドの実装
+    /// (index, value) の更新列を既存の物理要素に合成します
+    /// - index に物理要素があれば combine(&mut 既存の値, value)
+    /// - 無ければデフォルト値の clone に combine してから追加
+    /// - 合成結果がデフォルト値になった位置は物理領域から取り除きます
+    ///
+    /// ! : updates の index が狭義単調増加でない、または len 以上の場合は panic (ベクタは変更しません)
+    /// ! : CapPolicy::Error で新しい物理要素が上限を超える場合も panic
+    pub fn merge_with<I, F>(&mut self, updates: I, combine: F)
+    where
+        I: IntoIterator<Item = (usize, T)>,
+        F: FnMut(&mut T, T),
+    {
+        if let Err(err) = self.try_merge_with(updates, combine) {
+            panic!("{err}");
+        }
+    }
+
+    /// try_merge_withメソッドの実装
+    /// merge_with の panic しない版
+    /// updates の並びや上限を先に確かめ、エラーの場合はベクタを変更しません
+    pub fn try_merge_with<I, F>(&mut self, updates: I, combine: F) -> Result<(), SparseVecError>
+    where
+        I: IntoIterator<Item = (usize, T)>,
+        F: FnMut(&mut T, T),
+    {
+        let mut updates: Vec<(usize, T)> = updates.into_iter().collect();
+        self.check_updates(&updates)?;
+        self.merge_in_place(&mut updates, combine)
+    }
+
+    /// check_updatesメソッドの実装 (crate 内部用)
+    /// merge_in_place に渡す updates の index が狭義単調増加で全て len 未満か確かめます
+    pub(crate) fn check_updates(&self, updates: &[(usize, T)]) -> Result<(), SparseVecError> {
+        for (position, (index, _)) in updates.iter().enumerate() {
+            if position > 0 && updates[position - 1].0 >= *index {
+                return Err(SparseVecError::UnsortedIndices { position });
+            }
+            if *index >= self.len {
+                return Err(SparseVecError::IndexOutOfBounds { index: *index, len: self.len });
+            }
+        }
+        Ok(())
+    }
+
+    /// merge_in_placeメソッドの実装 (crate 内部用)
+    /// merge_with の本体で、updates の検査を行いません
+    /// - updates の index は狭義単調増加で全て len 未満であること
+    /// - 追加される物理要素の数だけ先に領域を確保し、後ろから 1 回で合成します O(nnz + updates)
+    /// - combine / clone / 比較が panic しても、それまでの要素は失われず二重解放もしません
+    ///   (合成途中の隙間は MergeGap が詰めます)
+    /// - CapPolicy::Error で上限を超える場合と確保に失敗した場合は何も変更せずにエラーを返します
+    /// - updates は末尾から取り出して空にします (確保済みの容量は残るので再利用できます)
+    pub(crate) fn merge_in_place<F>(&mut self, updates: &mut Vec<(usize, T)>, mut combine: F) -> Result<(), SparseVecError>
+    where
+        F: FnMut(&mut T, T),
+    {
+        debug_assert!(updates.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        debug_assert!(updates.last().is_none_or(|(index, _)| *index < self.len));
+        let new_entries = updates
+            .iter()
+            .filter(|(index, _)| self.ind_binary_search(index).is_err())
+            .count();
+        self.room_for(new_entries)?;
+        self.on_mutate();
+        if updates.is_empty() {
+            return Ok(());
+        }
+        while self.cap() < self.raw_len + new_entries {
+            self.buf.try_grow()?;
+        }
+
+        let end = self.raw_len + new_entries;
+        let mut gap = MergeGap {
+            read: self.raw_len,
+            write: end,
+            end,
+            vec: self,
+        };
+        while let Some((index, elem)) = updates.pop() {
+            // index より後ろにある既存の要素を末尾側へ移動
+            while gap.read > 0 && gap.index_at(gap.read - 1) > index {
+                gap.move_back();
+            }
+            if gap.read > 0 && gap.index_at(gap.read - 1) == index {
+                let slot = unsafe { &mut *gap.vec.val_ptr().add(gap.read - 1) };
+                combine(slot, elem);
+                if gap.vec.is_default(slot) {
+                    gap.read -= 1;
+                    unsafe { ptr::drop_in_place(gap.vec.val_ptr().add(gap.read)) };
+                } else {
+                    gap.move_back();
+                }
+            } else {
+                let mut value = gap.vec.default.clone();
+                combine(&mut value, elem);
+                if !gap.vec.is_default(&value) {
+                    gap.write -= 1;
+                    unsafe {
+                        ptr::write(gap.vec.val_ptr().add(gap.write), value);
+                        ptr::write(gap.vec.ind_ptr().add(gap.write), index);
+                    }
+                }
+            }
+        }
+        // デフォルト値になった分の隙間を詰めて raw_len を確定する
+        drop(gap);
+        self.enforce_nnz_cap();
+        Ok(())
+    }
+
+    /// selectメソッドの実装
+    /// `mask` が true の位置だけ値を残し、それ以外はデフォルト値にします
+    /// - 長さ (len) は変わりません
+    /// - self と mask の物理インデックスを 1 回のマージで走査し、密化はしません
+    /// - self と mask の長さが異なる場合は panic
+    #[must_use]
+    pub fn select(&self, mask: &DefaultSparseVec<bool>) -> Self {
+        self.try_select(mask).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// try_selectメソッドの実装
+    /// select の panic しない版
+    /// - self と mask の長さが異なる場合は LenMismatch
+    pub fn try_select(&self, mask: &DefaultSparseVec<bool>) -> Result<Self, SparseVecError> {
+        if self.len != mask.len {
+            return Err(SparseVecError::LenMismatch { len: self.len, other: mask.len });
+        }
+
+        let mut out = Self::new();
+        out.default = self.default.clone();
+        let mut m = 0;
+        for i in 0..self.raw_len {
+            let index = unsafe { *self.ind_ptr().add(i) };
+            // mask 側のカーソルを index まで進める
+            while m < mask.raw_len && unsafe { *mask.ind_ptr().add(m) } < index {
+                m += 1;
+            }
+            if mask.mask_value_at(m, index) {
+                let elem = unsafe { (*self.val_ptr().add(i)).clone() };
+                out.raw_push(index, elem);
+            }
+        }
+        out.len = self.len;
+        Ok(out)
+    }
+
+    /// compress_selectメソッドの実装
+    /// `mask` が true の位置だけを前に詰めて残します (numpy の boolean indexing 相当)
+    /// - 長さは mask 中の true の個数になります
+    /// - self と mask の物理インデックスを 1 回のマージで走査し、密化はしません
+    /// - self と mask の長さが異なる場合は panic
+    #[must_use]
+    pub fn compress_select(&self, mask: &DefaultSparseVec<bool>) -> Self {
+        self.try_compress_select(mask).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// try_compress_selectメソッドの実装
+    /// compress_select の panic しない版
+    /// - self と mask の長さが異なる場合は LenMismatch
+    pub fn try_compress_select(&self, mask: &DefaultSparseVec<bool>) -> Result<Self, SparseVecError> {
+        if self.len != mask.len {
+            return Err(SparseVecError::LenMismatch { len: self.len, other: mask.len });
+        }
+
+        let mut out = Self::new();
+        out.default = self.default.clone();
+        // mask の物理要素のうち、カーソルより前にある true / false の個数
+        let mut stored_true = 0;
+        let mut stored_false = 0;
+        let mut m = 0;
+        for i in 0..self.raw_len {
+            let index = unsafe { *self.ind_ptr().add(i) };
+            while m < mask.raw_len && unsafe { *mask.ind_ptr().add(m) } < index {
+                if unsafe { *mask.val_ptr().add(m) } {
+                    stored_true += 1;
+                } else {
+                    stored_false += 1;
+                }
+                m += 1;
+            }
+            if mask.mask_value_at(m, index) {
+                // index より前にある true の個数が詰めた後の位置になる
+                let rank = if mask.default { index - stored_false } else { stored_true };
+                let elem = unsafe { (*self.val_ptr().add(i)).clone() };
+                out.raw_push(rank, elem);
+            }
+        }
+        // 残りの mask を数えきって true の総数を求める
+        while m < mask.raw_len {
+            if unsafe { *mask.val_ptr().add(m) } {
+                stored_true += 1;
+            } else {
+                stored_false += 1;
+            }
+            m += 1;
+        }
+        out.len = if mask.default { mask.len - stored_false } else { stored_true };
+        Ok(out)
+    }
+
+    /// partitionメソッドの実装
+    /// 各位置を pred(index, &値) が true なら 1 つ目、false なら 2 つ目に振り分けます
+    /// - 両方とも元と同じ長さで、振り分けられなかった位置はデフォルト値になります
+    /// - 物理要素を 1 回走査するだけです O(nnz)
+    /// - スパース分部はどちらに振り分けてもデフォルト値なので、pred を呼びません
+    #[must_use]
+    pub fn partition<F>(&self, pred: F) -> (Self, Self)
+    where
+        F: Fn(usize, &T) -> bool,
+    {
+        let mut selected = Self::new();
+        let mut rest = Self::new();
+        selected.default = self.default.clone();
+        rest.default = self.default.clone();
+        let (ind, val) = self.as_pairs();
+        for (&index, elem) in ind.iter().zip(val) {
+            if *elem == self.default {
+                continue;
+            }
+            let out = if pred(index, elem) { &mut selected } else { &mut rest };
+            out.raw_push(index, elem.clone());
+        }
+        selected.len = self.len;
+        rest.len = self.len;
+        (selected, rest)
+    }
+
+    /// partition_compactメソッドの実装
+    /// partition と同じく振り分けますが、各出力は振り分けられた位置だけを前に詰めて持ちます
+    /// (2 つの長さの和は元の長さ)
+    /// - 物理要素は 1 回走査するだけです
+    /// - スパース分部は連続したデフォルト値の区間ごとに pred(区間の先頭の index, &デフォルト値) を 1 回だけ呼び、
+    ///   区間全体を同じ出力に振り分けます (長さをまとめて進めるので O(nnz))
+    #[must_use]
+    pub fn partition_compact<F>(&self, pred: F) -> (Self, Self)
+    where
+        F: Fn(usize, &T) -> bool,
+    {
+        let mut selected = Self::new();
+        let mut rest = Self::new();
+        selected.default = self.default.clone();
+        rest.default = self.default.clone();
+        // [start, end) を pred(start, elem) の振り分け先に置きます (区間の要素はすべて elem と等しい)
+        let mut place = |start: usize, end: usize, elem: &T| {
+            if start < end {
+                let out = if pred(start, elem) { &mut selected } else { &mut rest };
+                if *elem != self.default {
+                    out.raw_push(out.len, elem.clone());
+                }
+                out.len += end - start;
+            }
+        };
+        let (ind, val) = self.as_pairs();
+        let mut next = 0;
+        for (&index, elem) in ind.iter().zip(val) {
+            place(next, index, &self.default);
+            place(index, index + 1, elem);
+            next = index + 1;
+        }
+        place(next, self.len, &self.default);
+        (selected, rest)
+    }
+}
+
+impl<T: Default + PartialEq + PartialOrd + Clone> DefaultSparseVec<T> {
+    /// clamp_in_placeメソッドの実装
+    /// 全要素の値を [min, max] に収めます (dense の clamp と同じ結果)
+    /// 値の変換を 1 パスで行ってから、デフォルト値になった要素をまとめて詰めます
+    /// - デフォルト値が [min, max] の外にある場合は、デフォルト値も収めます (スパース分部の値が変わります)
+    /// - min > max の場合は panic
+    pub fn clamp_in_place(&mut self, min: T, max: T) {
+        assert!(min <= max, "min must be less than or equal to max");
+        let clamp = |elem: &mut T| {
+            if *elem < min {
+                *elem = min.clone();
+            } else if *elem > max {
+                *elem = max.clone();
+            }
+        };
+        self.as_pairs_mut().1.iter_mut().for_each(&clamp);
+        clamp(&mut self.default);
+        self.compact_stored_defaults();
+    }
+
+    /// relu_in_placeメソッドの実装
+    /// 物理要素の値をデフォルト値との max にし、デフォルト値になった要素を削除します
+    pub fn relu_in_place(&mut self) {
+        self.on_mutate();
+        let vals = unsafe { std::slice::from_raw_parts_mut(self.val_ptr(), self.raw_len) };
+        for elem in vals {
+            if *elem < self.default {
+                *elem = self.default.clone();
+            }
+        }
+        self.compact_stored_defaults();
+    }
+
+    /// threshold_in_placeメソッドの実装
+    /// |v| < t の物理要素をデフォルト値にして削除します
+    /// 残った要素の符号はそのままです
+    pub fn threshold_in_place(&mut self, t: T)
+    where
+        T: Num,
+    {
+        self.on_mutate();
+        let vals = unsafe { std::slice::from_raw_parts_mut(self.val_ptr(), self.raw_len) };
+        for elem in vals {
+            let below = if *elem < T::zero() {
+                T::zero() - elem.clone() < t
+            } else {
+                *elem < t
+            };
+            if below {
+                *elem = self.default.clone();
+            }
+        }
+        self.compact_stored_defaults();
+    }
+
+    /// build_value_indexメソッドの実装
+    /// 物理要素を値の降順に並べた索引を作って保持します O(nnz log nnz)
+    /// - 以降の top_k は索引から O(k) で取り出します
+    /// - 物理領域を変更する操作 (値を書き換えられる get_mut / iter_mut なども含む) で自動的に破棄されます
+    /// - 索引は nnz 個の usize を使います (不要になったら drop_value_index)
+    pub fn build_value_index(&mut self) {
+        let val = self.as_pairs().1;
+        #[cfg(debug_assertions)]
+        let hint = self.cost_hint(OpKind::Sort);
+        #[cfg(debug_assertions)]
+        let comparisons = std::cell::Cell::new(0usize);
+        let mut order: Vec<usize> = (0..val.len()).collect();
+        order.sort_unstable_by(|&a, &b| {
+            #[cfg(debug_assertions)]
+            comparisons.set(comparisons.get() + 1);
+            desc_by_value(&val[a], &val[b]).then(a.cmp(&b))
+        });
+        #[cfg(debug_assertions)]
+        self.debug_check_cost(hint, 2 * comparisons.get(), (val.len() + comparisons.get()) * mem::size_of::<usize>());
+        self.extras_mut().value_index = Some(order);
+    }
+
+    /// top_kメソッドの実装
+    /// 値が大きい順に k 個の物理要素を (index, 値) で返します
+    /// - 同じ値は index の昇順、比較できない値 (NaN など) は最後
+    /// - スパース分部 (デフォルト値) は含みません (nnz < k なら nnz 個)
+    /// - build_value_index の索引があれば O(k)、無ければ O(nnz + k log k)
+    #[must_use]
+    pub fn top_k(&self, k: usize) -> Vec<(usize, &T)> {
+        let (ind, val) = self.as_pairs();
+        let k = k.min(val.len());
+        let entry = |pos: usize| (ind[pos], &val[pos]);
+        if let Some(order) = self.extras.as_ref().and_then(|extras| extras.value_index.as_ref()) {
+            return order[..k].iter().map(|&pos| entry(pos)).collect();
+        }
+        if k == 0 {
+            return Vec::new();
+        }
+        let cmp = |&a: &usize, &b: &usize| desc_by_value(&val[a], &val[b]).then(a.cmp(&b));
+        let mut order: Vec<usize> = (0..val.len()).collect();
+        order.select_nth_unstable_by(k - 1, cmp);
+        order.truncate(k);
+        order.sort_unstable_by(cmp);
+        order.into_iter().map(entry).collect()
+    }
+}
+
+/// desc_by_value関数の実装
+/// 値の降順の比較 (比較できない値 (NaN など) は最後にまとめます)
+#[inline(always)]
+fn desc_by_value<T: PartialOrd>(a: &T, b: &T) -> std::cmp::Ordering {
+    let unordered = |elem: &T| elem.partial_cmp(elem).is_none();
+    match (unordered(a), unordered(b)) {
+        (false, false) => b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal),
+        (a_unordered, b_unordered) => a_unordered.cmp(&b_unordered),
+    }
+}
+
+impl<T: Default + PartialEq + Clone + AddAssign> DefaultSparseVec<T> {
+    /// from_eventsメソッドの実装
+    /// (index, value) のイベント列から長さ `len` のベクタを構築します
+    /// - イベントをソートし、重複 index を 1 パスで合計します
+    /// - 合計がデフォルト値になった位置は物理領域に書き込みません
+    /// - index が len 以上のイベントがある場合は panic
+    pub fn from_events<I>(len: usize, events: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        Self::try_from_events(len, events).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// try_from_eventsメソッドの実装
+    /// from_events の panic しない版
+    /// - index が len 以上のイベントがある場合は、そのうち最小の index を IndexOutOfBounds で返します
+    pub fn try_from_events<I>(len: usize, events: I) -> Result<Self, SparseVecError>
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        let mut svec = Self::new();
+        let events = svec.aggregate_events(len, events)?;
+        for (index, elem) in events {
+            if elem != svec.default {
+                svec.raw_push(index, elem);
+            }
+        }
+        svec.len = len;
+        Ok(svec)
+    }
+
+    /// scatter_addメソッドの実装
+    /// (index, value) のイベント列を既存のベクタに加算します
+    /// - イベントは集約してから既存の物理要素と 1 回のマージで合成します
+    /// - 加算結果がデフォルト値になった位置は物理領域から取り除きます
+    /// - index が len 以上のイベントがある場合は panic (ベクタは変更しません)
+    pub fn scatter_add<I>(&mut self, events: I)
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        if let Err(err) = self.try_scatter_add(events) {
+            panic!("{err}");
+        }
+    }
+
+    /// try_scatter_addメソッドの実装
+    /// scatter_add の panic しない版
+    /// 次の場合は何も変更せずにエラーを返します
+    /// - index が len 以上のイベントがある場合は、そのうち最小の index を IndexOutOfBounds
+    /// - CapPolicy::Error で新しい物理要素が上限を超える場合は NnzCapExceeded
+    pub fn try_scatter_add<I>(&mut self, events: I) -> Result<(), SparseVecError>
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        let mut events = self.aggregate_events(self.len, events)?;
+        self.on_mutate();
+        // 加算結果がデフォルト値に戻る場合も含めて、新しく増えうる物理要素の分で上限を確かめる
+        self.merge_in_place(&mut events, |acc, elem| *acc += elem)
+    }
+
+    /// aggregate_eventsメソッドの実装
+    /// イベントを index 順に安定ソートし、同じ index の値を順に加算して 1 つにまとめます
+    fn aggregate_events<I>(&self, len: usize, events: I) -> Result<Vec<(usize, T)>, SparseVecError>
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        let mut events: Vec<(usize, T)> = events.into_iter().collect();
+        events.sort_by_key(|(index, _)| *index);
+        self.aggregate_sorted(len, &mut events)?;
+        Ok(events)
+    }
+
+    /// aggregate_sortedメソッドの実装 (crate 内部用)
+    /// index 順に安定ソート済みのイベントの、同じ index の値を順に加算して
+    /// 1 つにまとめます (その場で詰めるので確保は行いません)
+    /// - デフォルト値は足しません (既存の値やデフォルト値への加算は merge_in_place で 1 回だけ行います)
+    /// - index が len 以上のイベントがある場合は、加算する前に最小の index を IndexOutOfBounds で返します
+    pub(crate) fn aggregate_sorted(&self, len: usize, events: &mut Vec<(usize, T)>) -> Result<(), SparseVecError> {
+        // ソート済みなので、len 以上の index は末尾にまとまっています
+        let in_bounds = events.partition_point(|(index, _)| *index < len);
+        if let Some(&(index, _)) = events.get(in_bounds) {
+            return Err(SparseVecError::IndexOutOfBounds { index, len });
+        }
+        let mut w = 0;
+        for r in 0..events.len() {
+            let index = events[r].0;
+            let elem = mem::take(&mut events[r].1);
+            if w > 0 && events[w - 1].0 == index {
+                events[w - 1].1 += elem;
+            } else {
+                events[w] = (index, elem);
+                w += 1;
+            }
+        }
+        events.truncate(w);
+        Ok(())
+    }
+}
+
+impl<T: Default + PartialEq + ToPrimitive> DefaultSparseVec<T> {
+    /// set_max_nnzメソッドの実装
+    /// 物理要素数 (nnz) の上限を設定します (None で解除)
+    /// 上限を超える追加は CapPolicy に従って扱います
+    /// - Error: panic (try_push / try_insert はエラーを返す)
+    /// - EvictSmallest: 絶対値 (to_f64) が最も小さい物理要素を削除してから追加
+    /// - EvictOldestIndex: 論理インデックスが最も小さい物理要素を削除してから追加
+    ///
+    /// 既に上限を超えている場合は、その場で CapPolicy に従います
+    /// - cap が Some(0) の場合は panic
+    pub fn set_max_nnz(&mut self, cap: Option<usize>) {
+        self.extras_mut().nnz_cap = cap.map(|max| {
+            assert!(max > 0, "max_nnz must be greater than 0");
+            NnzCap {
+                max,
+                magnitude: |elem: &T| elem.to_f64().map_or(0.0, f64::abs),
+            }
+        });
+        self.enforce_nnz_cap();
+    }
+
+    /// set_sparsify_policyメソッドの実装
+    /// 書き込み時 (push / insert / merge など) に値をデフォルト値とみなす判定を設定します
+    /// デフォルト値とみなした値は物理的に格納しません
+    /// - 既に格納されている値のうち、新しい判定でデフォルト値とみなすものはその場で取り除きます
+    /// - AbsTolerance は to_f64 で比較します (変換できない値は NaN として扱い、デフォルト値とみなしません)
+    pub fn set_sparsify_policy(&mut self, policy: SparsifyPolicy<T>) {
+        self.extras_mut().sparsify = Sparsify::new(policy);
+        self.compact_stored_defaults();
+    }
+
+    /// summarizeメソッドの実装
+    /// デバッグ用の統計を O(nnz) の 1 パスで集計します
+    /// 各項目の意味は SparseVecSummary を参照
+    #[must_use]
+    pub fn summarize(&self) -> SparseVecSummary {
+        let mut min: Option<f64> = None;
+        let mut max: Option<f64> = None;
+        let mut sum = 0.0;
+        let mut counted = 0usize;
+        let mut stored_defaults = 0;
+        let mut largest_gap = 0;
+        let mut next = 0;
+        let (ind, val) = self.as_pairs();
+        for (&index, elem) in ind.iter().zip(val) {
+            if self.is_default(elem) {
+                stored_defaults += 1;
+            }
+            if let Some(value) = elem.to_f64().filter(|v| !v.is_nan()) {
+                min = Some(min.map_or(value, |m| m.min(value)));
+                max = Some(max.map_or(value, |m| m.max(value)));
+                sum += value;
+                counted += 1;
+            }
+            largest_gap = largest_gap.max(index - next);
+            next = index + 1;
+        }
+        largest_gap = largest_gap.max(self.len - next);
+
+        SparseVecSummary {
+            len: self.len,
+            nnz: self.raw_len,
+            density: self.density(),
+            min,
+            max,
+            mean: if counted == 0 { None } else { Some(sum / counted as f64) },
+            stored_defaults,
+            first_index: ind.first().copied(),
+            last_index: ind.last().copied(),
+            largest_gap,
+        }
+    }
+
+    /// softmaxメソッドの実装
+    /// 全要素 (スパース分部を含む) の softmax を f64 で計算します O(nnz)
+    /// スパース分部の len - nnz 個もデフォルト値の exp ぶんの重みを持つので、物理要素だけの softmax とは異なります
+    /// - 最大値を引いてから exp を取ります (オーバーフローしません)
+    /// - epsilon 未満の結果は 0.0 にします (結果のデフォルト値も同じ)
+    /// - 結果のデフォルト値はスパース分部の確率 (epsilon 未満なら 0.0) で、それと等しい値は格納しません
+    /// - f64 に変換できない値は NaN として扱います
+    #[must_use]
+    pub fn softmax(&self, epsilon: f64) -> DefaultSparseVec<f64> {
+        let to_f64 = |elem: &T| elem.to_f64().unwrap_or(f64::NAN);
+        let (ind, val) = self.as_pairs();
+        let sparse = self.len - self.raw_len;
+        let default = to_f64(&self.default);
+        let mut max = if sparse > 0 { default } else { f64::NEG_INFINITY };
+        for elem in val {
+            max = max.max(to_f64(elem));
+        }
+        let mut total = sparse as f64 * (default - max).exp();
+        for elem in val {
+            total += (to_f64(elem) - max).exp();
+        }
+        let prune = |p: f64| if p < epsilon { 0.0 } else { p };
+
+        let mut result = DefaultSparseVec::<f64>::new();
+        result.default = if sparse > 0 { prune((default - max).exp() / total) } else { 0.0 };
+        for (&index, elem) in ind.iter().zip(val) {
+            let p = prune((to_f64(elem) - max).exp() / total);
+            if p != result.default {
+                result.raw_push(index, p);
+            }
+        }
+        result.len = self.len;
+        result
+    }
+}
+
+impl<T: Default + PartialEq + Float> DefaultSparseVec<T> {
+    /// normalize_l1_in_placeメソッドの実装
+    /// 物理要素の絶対値の和が 1 になるように、全ての物理要素を同じ数で割ります O(nnz)
+    /// len は変わりません (全てデフォルト値の場合は何もしません)
+    /// ! : デフォルト値が 0 でない場合は panic (スパース分部の値が変わってしまうため)
+    pub fn normalize_l1_in_place(&mut self) {
+        assert!(self.default.is_zero(), "normalize_l1_in_place requires a zero default");
+        let norm = self.as_pairs().1.iter().fold(T::zero(), |acc, elem| acc + elem.abs());
+        self.scale_stored_by(norm);
+    }
+
+    /// normalize_l2_in_placeメソッドの実装
+    /// 物理要素の 2 乗和が 1 になるように、全ての物理要素を同じ数で割ります O(nnz)
+    /// len は変わりません (全てデフォルト値の場合は何もしません)
+    /// ! : デフォルト値が 0 でない場合は panic
+    /// - 最大の絶対値で割ってから 2 乗するので、途中でオーバーフローしません
+    pub fn normalize_l2_in_place(&mut self) {
+        assert!(self.default.is_zero(), "normalize_l2_in_place requires a zero default");
+        let val = self.as_pairs().1;
+        let peak = val.iter().fold(T::zero(), |acc, elem| acc.max(elem.abs()));
+        if peak.is_zero() {
+            return;
+        }
+        let sum = val.iter().fold(T::zero(), |acc, elem| {
+            let scaled = *elem / peak;
+            acc + scaled * scaled
+        });
+        self.scale_stored_by(sum.sqrt() * peak);
+    }
+
+    /// decay_in_placeメソッドの実装
+    /// 全ての物理要素に factor を掛け、絶対値が epsilon 未満になった物理要素を取り除きます O(nnz)
+    /// ストリーミングの累積値を古いものほど小さくする (時間減衰) ためのものです
+    /// ! : デフォルト値が 0 でない場合は panic (スパース分部には factor が掛からず、減衰が定義できないため)
+    ///
+    /// - 0 になった物理要素は epsilon に関係なく取り除きます
+    /// - NaN の物理要素は残します
+    pub fn decay_in_place(&mut self, factor: T, epsilon: T) {
+        assert!(self.default.is_zero(), "decay_in_place requires a zero default");
+        self.on_mutate();
+        let mut w = 0;
+        for r in 0..self.raw_len {
+            unsafe {
+                let elem = *self.val_ptr().add(r) * factor;
+                if !Self::decays_away(elem, epsilon) {
+                    *self.val_ptr().add(w) = elem;
+                    *self.ind_ptr().add(w) = *self.ind_ptr().add(r);
+                    w += 1;
+                }
+            }
+        }
+        // T: Float は Copy なので、取り除いた値の drop は不要です
+        self.raw_len = w;
+    }
+
+    /// decay_and_addメソッドの実装
+    /// 全ての物理要素に factor を掛けて new_events を加算し、epsilon による取り除きまでを 1 回のマージで行います
+    /// - 物理要素ごとに self * factor + new_events を計算し、絶対値が epsilon 未満なら取り除きます
+    /// - new_events にしか無い位置は新しい物理要素として追加します
+    /// - 先にインデックス配列だけで追加数を数えて領域を確保し、後ろから合成するので、値は 1 回ずつしか読み書きしません O(nnz + new_events.nnz)
+    ///
+    /// ! : どちらかのデフォルト値が 0 でない場合、len が異なる場合は panic
+    /// ! : CapPolicy::Error で新しい物理要素が上限を超える場合も panic (ベクタは変更しません)
+    pub fn decay_and_add(&mut self, factor: T, new_events: &Self, epsilon: T) {
+        assert!(self.default.is_zero(), "decay_and_add requires a zero default");
+        assert!(new_events.default.is_zero(), "decay_and_add requires new_events to have a zero default");
+        assert_same_len(self.len, new_events.len);
+        let (ind, _) = self.as_pairs();
+        let (new_ind, new_val) = new_events.as_pairs();
+        let mut added = 0;
+        let mut i = 0;
+        for index in new_ind {
+            while i < ind.len() && ind[i] < *index {
+                i += 1;
+            }
+            if i == ind.len() || ind[i] != *index {
+                added += 1;
+            }
+        }
+        if let Err(err) = self.room_for(added) {
+            panic!("{err}");
+        }
+        self.on_mutate();
+        while self.cap() < self.raw_len + added {
+            if let Err(err) = self.buf.try_grow() {
+                panic!("{err}");
+            }
+        }
+
+        // 後ろから合成して [write, end) に詰める (write は常に読み出し位置以上)
+        let end = self.raw_len + added;
+        let mut read = self.raw_len;
+        let mut j = new_ind.len();
+        let mut write = end;
+        while read > 0 || j > 0 {
+            let own = if read > 0 { Some(unsafe { *self.ind_ptr().add(read - 1) }) } else { None };
+            let (index, elem) = match (own, j.checked_sub(1).map(|k| new_ind[k])) {
+                (Some(a), Some(b)) if a == b => {
+                    read -= 1;
+                    j -= 1;
+                    (a, unsafe { *self.val_ptr().add(read) } * factor + new_val[j])
+                }
+                (Some(a), Some(b)) if a > b => {
+                    read -= 1;
+                    (a, unsafe { *self.val_ptr().add(read) } * factor)
+                }
+                (Some(a), None) => {
+                    read -= 1;
+                    (a, unsafe { *self.val_ptr().add(read) } * factor)
+                }
+                (_, Some(b)) => {
+                    j -= 1;
+                    (b, new_val[j])
+                }
+                (None, None) => unreachable!(),
+            };
+            if !Self::decays_away(elem, epsilon) {
+                write -= 1;
+                unsafe {
+                    *self.val_ptr().add(write) = elem;
+                    *self.ind_ptr().add(write) = index;
+                }
+            }
+        }
+        // 取り除いた分だけ前に空いた隙間を詰める
+        if write > 0 {
+            unsafe {
+                ptr::copy(self.val_ptr().add(write), self.val_ptr(), end - write);
+                ptr::copy(self.ind_ptr().add(write), self.ind_ptr(), end - write);
+            }
+        }
+        self.raw_len = end - write;
+        self.enforce_nnz_cap();
+    }
+
+    /// decays_awayメソッドの実装
+    /// 減衰後の値を取り除くかどうか (0、または絶対値が epsilon 未満)
+    #[inline(always)]
+    fn decays_away(elem: T, epsilon: T) -> bool {
+        elem.is_zero() || elem.abs() < epsilon
+    }
+
+    /// scale_stored_byメソッドの実装
+    /// 全ての物理要素を norm で割り、0 になった物理要素を取り除きます
+    /// - norm が 0 の場合は何もしません
+    fn scale_stored_by(&mut self, norm: T) {
+        if norm.is_zero() {
+            return;
+        }
+        for elem in self.as_pairs_mut().1 {
+            *elem = *elem / norm;
+        }
+        // 非正規化数の場合、割った結果が 0 になることがあります
+        self.compact_stored_defaults();
+    }
+}
+
+impl DefaultSparseVec<bool> {
+    /// mask_value_atメソッドの実装
+    /// マージ走査中のカーソル `m` (index 以上の最初の物理位置) から
+    /// 論理インデックス `index` の値を取り出します
+    #[inline(always)]
+    fn mask_value_at(&self, m: usize, index: usize) -> bool {
+        if m < self.raw_len && unsafe { *self.ind_ptr().add(m) } == index {
+            unsafe { *self.val_ptr().add(m) }
+        } else {
+            self.default
+        }
+    }
+}
+
+unsafe impl<T: Send + Default + PartialEq> Send for DefaultSparseVec<T> {}
+/// &DefaultSparseVec<T> から &T を読めるので、共有には T: Sync が必要です
+/// ```compile_fail
+/// use std::cell::Cell;
+/// use vec_plus::vec::default_sparse_vec::DefaultSparseVec;
+/// fn assert_sync<S: Sync>() {}
+/// assert_sync::<DefaultSparseVec<Cell<u32>>>();
+/// ```
+unsafe impl<T: Sync + Default + PartialEq> Sync for DefaultSparseVec<T> {}
+
+impl<T: Default + PartialEq + Clone + Num> DefaultSparseVec<T> {
+    /// try_div_assignメソッドの実装
+    /// 物理要素を other の同じ位置の値 (物理要素が無ければ other のデフォルト値) で割ります O(nnz + other.nnz)
+    /// - 自分のスパース分部はデフォルト値のままです (割りません)
+    /// - 割る数が 0 になる物理要素があれば、最初の index を DivisionByZero で返し、何も変更しません
+    /// - 結果がデフォルト値になった位置は物理領域から取り除きます
+    /// - len が異なる場合は LenMismatch を返し、何も変更しません
+    pub fn try_div_assign(&mut self, other: &Self) -> Result<(), SparseVecError> {
+        self.try_partial_assign(other, |a, b| a / b)
+    }
+
+    /// div_assign_or_defaultメソッドの実装
+    /// try_div_assign と同じですが、割る数が 0 の位置はエラーにせずデフォルト値にします
+    /// ! : len が異なる場合は panic
+    pub fn div_assign_or_default(&mut self, other: &Self) {
+        self.partial_assign_or_default(other, |a, b| a / b);
+    }
+
+    /// try_div_scalarメソッドの実装
+    /// 物理要素を divisor で割ります (スパース分部はデフォルト値のまま)
+    /// - divisor が 0 で物理要素がある場合は、最初の index を DivisionByZero で返し、何も変更しません
+    pub fn try_div_scalar(&mut self, divisor: T) -> Result<(), SparseVecError> {
+        self.try_partial_scalar(divisor, |a, b| a / b)
+    }
+
+    /// try_rem_assignメソッドの実装
+    /// try_div_assign の剰余版
+    pub fn try_rem_assign(&mut self, other: &Self) -> Result<(), SparseVecError> {
+        self.try_partial_assign(other, |a, b| a % b)
+    }
+
+    /// rem_assign_or_defaultメソッドの実装
+    /// div_assign_or_default の剰余版
+    /// ! : len が異なる場合は panic
+    pub fn rem_assign_or_default(&mut self, other: &Self) {
+        self.partial_assign_or_default(other, |a, b| a % b);
+    }
+
+    /// try_rem_scalarメソッドの実装
+    /// try_div_scalar の剰余版
+    pub fn try_rem_scalar(&mut self, divisor: T) -> Result<(), SparseVecError> {
+        self.try_partial_scalar(divisor, |a, b| a % b)
+    }
+
+    /// try_partial_assignメソッドの実装
+    /// try_div_assign / try_rem_assign の本体
+    /// 先に len と割る数を全て確かめてから書き換えるので、エラーの場合は何も変更しません
+    fn try_partial_assign<F: Fn(T, T) -> T>(&mut self, other: &Self, op: F) -> Result<(), SparseVecError> {
+        if self.len != other.len {
+            return Err(SparseVecError::LenMismatch { len: self.len, other: other.len });
+        }
+        let (ind, _) = self.as_pairs();
+        if let Some((&index, _)) = ind.iter().zip(aligned_values(ind, other)).find(|(_, divisor)| divisor.is_zero()) {
+            return Err(SparseVecError::DivisionByZero { index });
+        }
+        self.partial_assign_or_default(other, op);
+        Ok(())
+    }
+
+    /// partial_assign_or_defaultメソッドの実装
+    /// 自分の物理要素と other の同じ位置の値を 1 回のマージで組にして op を適用します
+    /// 割る数が 0 の位置はデフォルト値にし、最後にデフォルト値になった物理要素を取り除きます
+    fn partial_assign_or_default<F: Fn(T, T) -> T>(&mut self, other: &Self, op: F) {
+        assert!(self.len == other.len, "divisor length mismatch ({} and {})", self.len, other.len);
+        let default = self.default.clone();
+        let (ind, val) = self.as_pairs_mut();
+        for (elem, divisor) in val.iter_mut().zip(aligned_values(ind, other)) {
+            *elem = if divisor.is_zero() {
+                default.clone()
+            } else {
+                op(elem.clone(), divisor.clone())
+            };
+        }
+        self.compact_stored_defaults();
+    }
+
+    /// try_partial_scalarメソッドの実装
+    /// try_div_scalar / try_rem_scalar の本体
+    fn try_partial_scalar<F: Fn(T, T) -> T>(&mut self, divisor: T, op: F) -> Result<(), SparseVecError> {
+        if divisor.is_zero() {
+            return match self.as_pairs().0.first() {
+                Some(&index) => Err(SparseVecError::DivisionByZero { index }),
+                None => Ok(()),
+            };
+        }
+        for elem in self.as_pairs_mut().1 {
+            *elem = op(elem.clone(), divisor.clone());
+        }
+        self.compact_stored_defaults();
+        Ok(())
+    }
+}
+
+/// aligned_values関数の実装
+/// 昇順のインデックス列 ind の各位置について、other のその位置の値 (物理要素が無ければデフォルト値) を返します
+/// other の物理要素を 1 回だけ前から走査します O(ind.len() + other.nnz)
+fn aligned_values<'a, T: Default + PartialEq>(ind: &'a [usize], other: &'a DefaultSparseVec<T>) -> impl Iterator<Item = &'a T> + 'a {
+    let (other_ind, other_val) = other.as_pairs();
+    let mut j = 0;
+    ind.iter().map(move |&index| {
+        while j < other_ind.len() && other_ind[j] < index {
+            j += 1;
+        }
+        match other_ind.get(j) {
+            Some(&stored) if stored == index => &other_val[j],
+            _ => other.default_value(),
+        }
+    })
+}
+
+impl<T: Default + PartialEq + Clone> Clone for DefaultSparseVec<T> {
+    /// 容量 nnz の新しい領域を確保し、index をコピーして値を 1 つずつ clone します
+    /// - T::clone が panic しても、それまでに clone した値だけが drop されます
+    fn clone(&self) -> Self {
+        let mut out = DefaultSparseVec {
+            buf: RawDefaultSparseVec::new(),
+            raw_len: 0,
+            len: self.len,
+            default: self.default.clone(),
+            extras: self.extras.as_ref().map(|extras| Box::new(extras.settings())),
+            #[cfg(debug_assertions)]
+            mods: AtomicUsize::new(0),
+        };
+        out.buf.set_capacity(self.raw_len);
+        let (ind, val) = self.as_pairs();
+        for (i, (&index, elem)) in ind.iter().zip(val).enumerate() {
+            unsafe {
+                ptr::write(out.val_ptr().add(i), elem.clone());
+                ptr::write(out.ind_ptr().add(i), index);
+            }
+            // 書き込んだ分だけ drop の対象にする
+            out.raw_len = i + 1;
+        }
+        // 物理位置は同じ並びなので、値の順序の索引もそのまま使える
+        if let (Some(out_extras), Some(extras)) = (&mut out.extras, &self.extras) {
+            out_extras.value_index = extras.value_index.clone();
+        }
+        out
+    }
+}
+
+
+impl<T: Default + PartialEq> Drop for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        // 物理的に存在する値だけをその場で drop する
+        // デフォルト値を clone する必要がないので T: Clone を要求しない
+        self.drop_stored();
+    }
+}
+
+impl<T: Default + PartialEq + fmt::Display> DefaultSparseVec<T> {
+    /// write_compactメソッドの実装
+    /// `len=10 nnz=3 {2: 5, 4: 1, ...}` の形式 (先頭 max_entries 個の物理要素、残りは ...) で w に書き込みます
+    /// - デフォルト値が T::default() でない場合は末尾に ` default=値` を付けます
+    /// - core::fmt だけを使い、確保を行いません (固定長のバッファへのログなど)
+    /// - Display (`{}`) はこれで書きます (`{:.N}` で max_entries を N にできます)
+    /// - w がエラーを返した場合 (バッファが足りないなど) は、そこまでを書いてエラーを返します
+    pub fn write_compact<W: fmt::Write>(&self, w: &mut W, max_entries: usize) -> fmt::Result {
+        write!(w, "len={} nnz={} {{", self.len, self.raw_len)?;
+        for (k, (index, elem)) in self.iter().take(max_entries).enumerate() {
+            if k > 0 {
+                w.write_str(", ")?;
+            }
+            write!(w, "{index}: {elem}")?;
+        }
+        if self.raw_len > max_entries {
+            w.write_str(if max_entries > 0 { ", ...}" } else { "...}" })?;
+        } else {
+            w.write_str("}")?;
+        }
+        if self.default != T::default() {
+            write!(w, " default={}", self.default)?;
+        }
+        Ok(())
+    }
+
+    /// write_dense_csvメソッドの実装
+    /// スパース分部を含む全要素をカンマ区切り (`1,0,0,5`) で w に書き込みます O(len)
+    /// - 小さなベクタ向けです (len 個の値を書きます)
+    /// - write_compact と同じく確保を行いません。Display の `{:#}` はこれで書きます
+    pub fn write_dense_csv<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        for (i, elem) in self.iter_dense().enumerate() {
+            if i > 0 {
+                w.write_str(",")?;
+            }
+            write!(w, "{elem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Default + PartialEq + fmt::Display> fmt::Display for DefaultSparseVec<T> {
+    /// `{}` は write_compact (物理要素は DISPLAY_ENTRIES 個まで、`{:.N}` なら N 個まで)、`{:#}` は write_dense_csv
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.write_dense_csv(f)
+        } else {
+            let max_entries = f.precision().unwrap_or(DISPLAY_ENTRIES);
+            self.write_compact(f, max_entries)
+        }
+    }
+}
+
+/// StoredEntries構造体の定義
+/// {:#?} で物理要素の (index, 値) を確保無しで並べるためのラッパー
+struct StoredEntries<'a, T: Default + PartialEq>(&'a DefaultSparseVec<T>);
+
+impl<T: Default + PartialEq + Debug> Debug for StoredEntries<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter()).finish()
+    }
+}
+
+impl<T: Default + PartialEq + Debug> Debug for DefaultSparseVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.sign_plus() {
+            f.debug_struct("DefaultSparseVec")
+                .field("buf", &self.buf)
+                .field("raw_len", &self.raw_len)
+                .field("len", &self.len)
+                .field("default", &self.default)
+                .field("wasted_capacity", &self.wasted_capacity())
+                .field("wasted_bytes", &(self.wasted_capacity() * (mem::size_of::<T>() + mem::size_of::<usize>())))
+                .field("density", &self.density())
+                .field("densified", &self.densified())
+                .finish()
+        } else if f.alternate() {
+            write!(f, "DefaultSparseVec({:?})", StoredEntries(self))
+        } else {
+            f.debug_list().entries(self.iter_dense()).finish()
+        }
+    }
+}
+
+/// 論理的な内容 (len と全 index の値) で比較します O(nnz + other.nnz)
+/// - 物理要素の持ち方 (デフォルト値と等しい物理要素の有無、容量、デフォルト値そのもの) には依存しません
+impl<T: Default + PartialEq> PartialEq for DefaultSparseVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.len != other.len {
+            return false;
+        }
+        let (a_ind, a_val) = self.as_pairs();
+        let (b_ind, b_val) = other.as_pairs();
+        // どちらかに物理要素がある位置の数
+        let mut covered = 0;
+        let (mut i, mut j) = (0, 0);
+        while i < a_ind.len() || j < b_ind.len() {
+            let a_index = a_ind.get(i).copied().unwrap_or(usize::MAX);
+            let b_index = b_ind.get(j).copied().unwrap_or(usize::MAX);
+            let equal = match a_index.cmp(&b_index) {
+                std::cmp::Ordering::Less => {
+                    i += 1;
+                    a_val[i - 1] == other.default
+                }
+                std::cmp::Ordering::Greater => {
+                    j += 1;
+                    self.default == b_val[j - 1]
+                }
+                std::cmp::Ordering::Equal => {
+                    i += 1;
+                    j += 1;
+                    a_val[i - 1] == b_val[j - 1]
+                }
+            };
+            if !equal {
+                return false;
+            }
+            covered += 1;
+        }
+        // 両方ともスパースな位置が残っていれば、デフォルト値どうしを比べる
+        covered == self.len || self.default == other.default
+    }
+}
+
+impl<T: Default + Eq> Eq for DefaultSparseVec<T> {}
+
+/// Eq と整合するハッシュ
+/// len と、値が T::default() でない論理要素の (index, 値) を index の昇順でハッシュします
+/// - デフォルト値が T::default() なら O(nnz) (デフォルト値と等しい物理要素は飛ばします)
+/// - デフォルト値が T::default() でない場合は、スパース分部も値を持つので O(len)
+impl<T: Default + Eq + Hash> Hash for DefaultSparseVec<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        let zero = T::default();
+        if self.default == zero {
+            for (index, elem) in self.iter() {
+                if *elem != zero {
+                    index.hash(state);
+                    elem.hash(state);
+                }
+            }
+        } else {
+            for (index, elem) in self.iter_dense().enumerate() {
+                if *elem != zero {
+                    index.hash(state);
+                    elem.hash(state);
+                }
+            }
+        }
+    }
+}
+
+/// 密なベクタとの比較 (len と全 index の値が等しければ true) O(len)
+impl<T: Default + PartialEq> PartialEq<Vec<T>> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn eq(&self, other: &Vec<T>) -> bool {
+        self.len == other.len() && self.iter_dense().eq(other.iter())
+    }
+}
+
+impl<T: Default + PartialEq> PartialEq<DefaultSparseVec<T>> for Vec<T> {
+    #[inline(always)]
+    fn eq(&self, other: &DefaultSparseVec<T>) -> bool {
+        other == self
+    }
+}
+
+impl<T: Default + PartialEq> Index<usize> for DefaultSparseVec<T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn index(&self, index: usize) -> &Self::Output {
+        let len = self.len();
+        self.get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the len is {len} but the index is {index}"))
+    }
+}
+
+impl<T: Default + PartialEq + Clone> IndexMut<usize> for DefaultSparseVec<T> {
+    /// #warning
+    /// このメソッドは、非推奨のget_mutメソッドを使用しています
+    /// スパース分部に物理要素を作るので、デフォルト値を書き込むと物理要素が残ります (値の書き込みには set を使ってください)
+    #[inline(always)]
+    #[allow(deprecated)]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        let len = self.len();
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the len is {len} but the index is {index}"))
+    }
+}
+
+impl <T: Default + PartialEq> Default for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default + PartialEq> From<Vec<T>> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn from(vec: Vec<T>) -> Self {
+        DefaultSparseVec::from_vec_with_default(vec, T::default())
+    }
+}
+
+/// FromIteratorの実装
+/// 要素を先頭から push します
+/// std の FromIterator<Result<A, E>> for Result<V, E> により、
+/// Result<T, E> のイテレータは最初のエラーで止まる Result<DefaultSparseVec<T>, E> に collect できます
+/// (途中まで作ったベクタは drop されます)
+impl<T: Default + PartialEq> FromIterator<T> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut svec = DefaultSparseVec::new();
+        svec.extend(iter);
+        svec
+    }
+}
+
+/// FromIteratorの実装 (index 付き)
+/// (index, value) の組から作成します
+/// - len は最大の index + 1
+/// - 同じ index が複数ある場合は後のものが残ります
+/// - Result<(usize, T), E> のイテレータも上と同様に collect できます
+impl<T: Default + PartialEq> FromIterator<(usize, T)> for DefaultSparseVec<T> {
+    fn from_iter<I: IntoIterator<Item = (usize, T)>>(iter: I) -> Self {
+        let mut pairs: Vec<(usize, T)> = iter.into_iter().collect();
+        sort_pairs_last_wins(&mut pairs);
+        let len = pairs.last().map_or(0, |(index, _)| index + 1);
+        Self::from_sorted_iter_unchecked(len, pairs)
+    }
+}
+
+/// Extendの実装
+/// 末尾に 1 つずつ push します
+impl<T: Default + PartialEq> Extend<T> for DefaultSparseVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+/// Extendの実装 (index 付き)
+/// (index, value) の組を set と同じ意味で書き込みます O(nnz + 組の数)
+/// - len 以上の index があれば、len を最大の index + 1 に伸ばします (間はデフォルト値)
+/// - 同じ index が複数ある場合は後のものが残ります
+/// - 組が index の昇順ならソートせず、そうでなければ安定ソートしてから 1 回のマージで書き込みます
+/// - 物理要素数の上限は CapPolicy に従います (Error の場合は panic)
+/// - index に usize::MAX がある場合 (len が usize::MAX + 1 になる) は panic
+/// - panic する場合 (上限を超える、index が usize::MAX) は len を含めて何も変更しません
+impl<T: Default + PartialEq + Clone> Extend<(usize, T)> for DefaultSparseVec<T> {
+    fn extend<I: IntoIterator<Item = (usize, T)>>(&mut self, iter: I) {
+        let mut updates: Vec<(usize, T)> = iter.into_iter().collect();
+        sort_pairs_last_wins(&mut updates);
+        let old_len = self.len;
+        if let Some(&(last, _)) = updates.last() {
+            if last >= self.len {
+                let new_len = last.checked_add(1).expect("extend index usize::MAX is out of range (len would overflow)");
+                self.grow_len(new_len);
+            }
+        }
+        if let Err(err) = self.merge_in_place(&mut updates, |slot, elem| *slot = elem) {
+            // merge_in_place はエラーのとき何も変更しないので、伸ばした len だけ戻してから panic する
+            self.len = old_len;
+            alloc_failure(err);
+        }
+    }
+}
+
+/// coverage関数の実装 (crate 内部用)
+/// 長さ len、物理要素 nnz 個のベクタの coverage (len が 0 なら 1.0)
+#[inline(always)]
+pub(crate) fn coverage(len: usize, nnz: usize) -> f64 {
+    if len == 0 {
+        1.0
+    } else {
+        1.0 - (len - nnz) as f64 / len as f64
+    }
+}
+
+/// sort_pairs_last_wins関数の実装
+/// (index, value) の組を index の狭義単調増加にします
+/// - 既に昇順 (同じ index の連続を含む) ならソートしません
+/// - 同じ index の中では後のものを残します
+fn sort_pairs_last_wins<T>(pairs: &mut Vec<(usize, T)>) {
+    if !pairs.is_sorted_by_key(|(index, _)| *index) {
+        // 安定ソートなので、同じ index の中では後のものが最後に来る
+        pairs.sort_by_key(|(index, _)| *index);
+    }
+    // dedup_by は前の要素を残すので、後の要素と入れ替えてから取り除く
+    pairs.dedup_by(|later, earlier| {
+        if later.0 == earlier.0 {
+            mem::swap(later, earlier);
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// HashMap からの変換
+/// key を論理インデックスとして、len は最大の key + 1 (間はデフォルト値) O(n log n)
+/// - key をソートしてから 1 回で物理領域を作ります
+/// - デフォルト値と等しい値は格納しません
+impl<T: Default + PartialEq> From<HashMap<usize, T>> for DefaultSparseVec<T> {
+    fn from(map: HashMap<usize, T>) -> Self {
+        let mut pairs: Vec<(usize, T)> = map.into_iter().collect();
+        // key は重複しないので不安定ソートで十分
+        pairs.sort_unstable_by_key(|(index, _)| *index);
+        let len = pairs.last().map_or(0, |(index, _)| index + 1);
+        Self::from_sorted_iter_unchecked(len, pairs)
+    }
+}
+
+/// BTreeMap からの変換
+/// From<HashMap> と同じですが、key は既に昇順なのでソートしません O(n)
+impl<T: Default + PartialEq> From<BTreeMap<usize, T>> for DefaultSparseVec<T> {
+    fn from(map: BTreeMap<usize, T>) -> Self {
+        let len = map.last_key_value().map_or(0, |(index, _)| index + 1);
+        Self::from_sorted_iter_unchecked(len, map)
+    }
+}
+
+/// IntoIteratorの実装
+/// 全要素を論理インデックス順に所有権ごと取り出します
+/// スパース分部はデフォルト値の clone です
+impl<T: Default + PartialEq + Clone> IntoIterator for DefaultSparseVec<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        let mut this = mem::ManuallyDrop::new(self);
+        // Extras (shrink_step のコピー途中の領域を含む) を解放しておく (以降 this は drop しません)
+        drop(this.extras.take());
+        // 物理領域とデフォルト値の所有権を IntoIter に移します
+        // 残りのフィールドは drop の必要がありません
+        let (buf, default) = unsafe { (ptr::read(&this.buf), ptr::read(&this.default)) };
+        IntoIter::new(buf, this.raw_len, this.len, default)
+    }
+}
+
+impl<'a, T: Default + PartialEq> IntoIterator for &'a DefaultSparseVec<T> {
+    type Item = &'a T;
+    type IntoIter = IterDense<'a, T>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_dense()
+    }
+}
+
+/// Vec<T> への変換 O(len)
+/// collect_dense と同じく len ちょうどを 1 回だけ確保しますが、物理要素は clone せずに移動します
+/// (物理領域は値を drop せずに解放します)
+impl<T: Default + PartialEq + Clone> From<DefaultSparseVec<T>> for Vec<T> {
+    fn from(mut svec: DefaultSparseVec<T>) -> Self {
+        svec.on_mutate();
+        let raw_len = svec.raw_len;
+        // 値の所有権を vec に移すので、svec の drop では値を drop させない
+        // (途中で default の clone が panic した場合、残りの物理要素はリークします)
+        svec.raw_len = 0;
+        let default = mem::take(&mut svec.default);
+        let mut vec = Vec::with_capacity(svec.len);
+        for i in 0..raw_len {
+            let (index, elem) = unsafe { (*svec.ind_ptr().add(i), ptr::read(svec.val_ptr().add(i))) };
+            vec.resize(index, default.clone());
+            vec.push(elem);
+        }
+        vec.resize(svec.len, default);
+        vec
+    }
+}
+
+/// HashMap への変換
+/// ! : 非推奨です。物理要素をデフォルト値と等しいものも含めてそのまま返すだけで、
+///     全 index を含む map ではありません
+///     to_sparse_map (物理要素のみ) か to_full_map (0..len 全て) を使ってください
+impl<T: Default + PartialEq + Clone> From<DefaultSparseVec<T>> for HashMap<usize, T> {
+    #[inline(always)]
+    fn from(svec: DefaultSparseVec<T>) -> Self {
+        let mut map = HashMap::new();
+        svec.iter().for_each(|(index, elem)| {
+            map.insert(index, elem.clone());
+        });
+        map
+    }
+}
+
+impl<T: Default + PartialEq + Clone> NormalVecMethods<T> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn n_push(&mut self, elem: T) {
+        self.push(elem);
+    }
+
+    #[inline(always)]
+    fn n_pop(&mut self) -> Option<T> {
+        self.pop()
+    }
+
+    #[inline(always)]
+    fn n_insert(&mut self, index: usize, elem: T) {
+        self.insert(index, elem);
+    }
+
+    #[inline(always)]
+    fn n_remove(&mut self, index: usize) -> T {
+        self.remove(index)
+    }
+
+    #[inline(always)]
+    fn n_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.iter_dense())
+    }
+}
+
+/// 内積は SparseVecView の実装に委譲します (view はコピーを行いません)
+impl<T: Default + PartialEq + ToPrimitive> Math<T> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn f64_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f64 {
+        self.view().f64_dot_with_order(&other.view(), order)
+    }
+
+    #[inline(always)]
+    fn f32_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f32 {
+        self.view().f32_dot_with_order(&other.view(), order)
+    }
+}
+
+impl<T: Default + PartialEq + UnsignedElement> UnsignedMath<T> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn u128_dot(&self, other: &Self) -> u128 {
+        self.view().u128_dot(&other.view())
+    }
+}
+
+impl<T: Default + PartialEq + SignedElement> SignedMath<T> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn i128_dot(&self, other: &Self) -> i128 {
+        self.view().i128_dot(&other.view())
+    }
+}
+
+/// for_each_matched_sorted関数の実装 (crate 内部用)
+/// 狭義単調増加の index 配列 2 つの両方にある index について、
+/// index 昇順に f(self の値, other の値) を呼び出します (SparseVecView の内積の本体)
+/// - 両方の長さが DOT_LARGE_NNZ 以上なら for_each_matched_blocked を使います (呼び出し順は同じ)
+#[inline(always)]
+pub(crate) fn for_each_matched_sorted<T, F>(self_ind: &[usize], self_val: &[T], other_ind: &[usize], other_val: &[T], mut f: F)
+where
+    F: FnMut(&T, &T),
+{
+    debug_assert!(self_ind.len() == self_val.len() && other_ind.len() == other_val.len());
+    if self_ind.len() >= DOT_LARGE_NNZ && other_ind.len() >= DOT_LARGE_NNZ {
+        for_each_matched_blocked(self_ind, self_val, other_ind, other_val, f);
+        return;
+    }
+    let mut i = 0;
+    let mut j = 0;
+    while i < self_ind.len() && j < other_ind.len() {
+        let self_index = self_ind[i];
+        let other_index = other_ind[j];
+        if self_index < other_index {
+            i += 1;
+        } else if self_index > other_index {
+            j += 1;
+        } else {
+            f(&self_val[i], &other_val[j]);
+            i += 1;
+            j += 1;
+        }
+    }
+}
+
+/// for_each_matched_blocked関数の実装
+/// nnz が大きい場合の for_each_matched_sorted
+/// - index 配列を DOT_BLOCK 個ずつのブロックで走査し、次のブロックを先読み (prefetch) します
+/// - 前のブロックで進む側が頻繁に入れ替わった (index がランダムに混ざっている) 場合は、
+///   カーソルの前進を比較結果から計算する分岐の無いループを使います
+///   入れ替わりが少ない (index がかたまっている) 場合は分岐予測が当たるので、通常のループを使います
+/// - 値の配列は一致した index の分だけ読みます
+///
+/// f を呼ぶ順番は for_each_matched_sorted と同じなので、浮動小数点の結果もビット単位で一致します
+fn for_each_matched_blocked<T, F>(self_ind: &[usize], self_val: &[T], other_ind: &[usize], other_val: &[T], mut f: F)
+where
+    F: FnMut(&T, &T),
+{
+    // 値の配列は get_unchecked で読むので、長さが index 配列と同じことを先に確かめる
+    assert!(self_ind.len() == self_val.len() && other_ind.len() == other_val.len());
+    let mut i = 0;
+    let mut j = 0;
+    let mut self_end = DOT_BLOCK.min(self_ind.len());
+    let mut other_end = DOT_BLOCK.min(other_ind.len());
+    prefetch_block(self_ind, self_end);
+    prefetch_block(other_ind, other_end);
+    // 進む側が入れ替わった回数が走査した要素数の 1/4 を超えたら分岐の無いループにする
+    let mut branchless = true;
+    loop {
+        let start = i + j;
+        let mut switches = 0usize;
+        let mut prev = false;
+        if branchless {
+            while i < self_end && j < other_end {
+                let (self_index, other_index) = unsafe { (*self_ind.get_unchecked(i), *other_ind.get_unchecked(j)) };
+                if self_index == other_index {
+                    unsafe { f(self_val.get_unchecked(i), other_val.get_unchecked(j)); }
+                }
+                let dir = self_index < other_index;
+                switches += (dir != prev) as usize;
+                prev = dir;
+                i += (self_index <= other_index) as usize;
+                j += (other_index <= self_index) as usize;
+            }
+        } else {
+            while i < self_end && j < other_end {
+                let (self_index, other_index) = unsafe { (*self_ind.get_unchecked(i), *other_ind.get_unchecked(j)) };
+                if self_index < other_index {
+                    switches += (!prev) as usize;
+                    prev = true;
+                    i += 1;
+                } else if self_index > other_index {
+                    switches += prev as usize;
+                    prev = false;
+                    j += 1;
+                } else {
+                    unsafe { f(self_val.get_unchecked(i), other_val.get_unchecked(j)); }
+                    switches += prev as usize;
+                    prev = false;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        let steps = i + j - start;
+        branchless = switches * 4 > steps;
+        if i == self_ind.len() || j == other_ind.len() {
+            break;
+        }
+        if i == self_end {
+            self_end = (self_end + DOT_BLOCK).min(self_ind.len());
+            prefetch_block(self_ind, self_end);
+        }
+        if j == other_end {
+            other_end = (other_end + DOT_BLOCK).min(other_ind.len());
+            prefetch_block(other_ind, other_end);
         }
     }
+}
 
-    /// extendメソッドの実装
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
+/// prefetch_block関数の実装
+/// ind[start..start + DOT_BLOCK] の各ページ (4 KiB) の先頭を先読みします
+/// ハードウェアの先読みはページ境界を越えないので、ページの先頭だけを指示すれば残りは連続読み出しとして先読みされます
+/// (キャッシュラインごとに指示すると命令数が増えて遅くなりました)
+/// - x86_64 以外では何もしません
+#[inline(always)]
+fn prefetch_block(ind: &[usize], start: usize) {
+    #[cfg(target_arch = "x86_64")]
     {
-        for elem in iter {
-            self.push(elem);
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        const STRIDE: usize = 4096 / mem::size_of::<usize>();
+        let end = (start + DOT_BLOCK).min(ind.len());
+        let mut k = start;
+        while k < end {
+            // 安全性: k < ind.len() なので範囲内のポインタ。prefetch はメモリを読まないヒントです
+            unsafe { _mm_prefetch::<_MM_HINT_T0>(ind.as_ptr().add(k) as *const i8); }
+            k += STRIDE;
         }
     }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = (ind, start);
+}
 
-    /// iterメソッドの実装(仮)
-    /// スパース分部を含みません
-    /// スパース分部が必要な場合はNormalVecMethods trait実装
-    #[inline(always)]
-    pub fn iter(&self) -> impl Iterator<Item = (&usize, &T)> {
-        (0..self.raw_len).map(move |i| {
-            let val: &T = unsafe { &*self.val_ptr().offset(i as isize) };
-            let ind: &usize = unsafe { &*self.ind_ptr().offset(i as isize) };
-            (ind, val)
-        })
+/// pairwise_sum関数の実装
+/// 配列を常に半分 (len / 2) で分割して 2 分木状に加算します
+/// 分割位置が入力長だけで決まるため、結果は決定的です
+pub(crate) fn pairwise_sum<F: Float>(values: &[F]) -> F {
+    const BLOCK: usize = 8;
+    if values.len() <= BLOCK {
+        values.iter().fold(F::zero(), |acc, &v| acc + v)
+    } else {
+        let (left, right) = values.split_at(values.len() / 2);
+        pairwise_sum(left) + pairwise_sum(right)
     }
+}
 
-    /// iter_mutメソッドの実装(仮)
-    /// スパース分部を含みません
-    /// スパース分部が必要な場合はNormalVecMethods trait実装
+
+/// CapPolicy列挙型の定義
+/// set_max_nnz の上限を超える追加をどう扱うか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CapPolicy {
+    /// panic する (try_push / try_insert はエラーを返す)
+    Error,
+    /// 絶対値が最も小さい物理要素を削除する
+    #[default]
+    EvictSmallest,
+    /// 論理インデックスが最も小さい物理要素を削除する
+    EvictOldestIndex,
+}
+
+/// SparsifyPolicy列挙型の定義
+/// 書き込み時に値をデフォルト値とみなす (物理的に格納しない) かどうかの判定 (set_sparsify_policy で設定)
+/// - 判定はデフォルト値と == の値を常に含みます
+pub enum SparsifyPolicy<T> {
+    /// デフォルト値と == の場合だけ (既定)
+    Exact,
+    /// to_f64 した値とデフォルト値の差の絶対値が、この値以下の場合
+    AbsTolerance(f64),
+    /// 関数 (値, デフォルト値) が true を返す場合
+    /// ! : シリアライズできません
+    /// ! : 関数は panic してはいけません (書き込み判定は unwind しない境界越しに呼ぶので、panic すると abort します)
+    Custom(fn(&T, &T) -> bool),
+}
+
+impl<T> Clone for SparsifyPolicy<T> {
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&mut usize, &mut T)> {
-        (0..self.raw_len).map(move |i| {
-            let val: &mut T = unsafe { &mut *self.val_ptr().offset(i as isize) };
-            let ind: &mut usize = unsafe { &mut *self.ind_ptr().offset(i as isize) };
-            (ind, val)
-        })
+    fn clone(&self) -> Self {
+        *self
     }
+}
 
-    //// as_sliceメソッドの実装
+impl<T> Copy for SparsifyPolicy<T> {}
+
+impl<T> Default for SparsifyPolicy<T> {
     #[inline(always)]
-    pub fn as_slice_val(&self) -> &[T] {
-        unsafe {
-            std::slice::from_raw_parts(self.val_ptr(), self.raw_len)
-        }
+    fn default() -> Self {
+        SparsifyPolicy::Exact
     }
+}
 
-    #[inline(always)]
-    pub fn as_slice_ind(&self) -> &[usize] {
-        unsafe {
-            std::slice::from_raw_parts(self.ind_ptr(), self.raw_len)
+impl<T> PartialEq for SparsifyPolicy<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SparsifyPolicy::Exact, SparsifyPolicy::Exact) => true,
+            (SparsifyPolicy::AbsTolerance(a), SparsifyPolicy::AbsTolerance(b)) => a == b,
+            (SparsifyPolicy::Custom(a), SparsifyPolicy::Custom(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => false,
         }
     }
+}
 
-    #[inline(always)]
-    pub fn as_mut_slice_val(&mut self) -> &mut [T] {
-        unsafe {
-            std::slice::from_raw_parts_mut(self.val_ptr(), self.raw_len)
+impl<T> Debug for SparsifyPolicy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SparsifyPolicy::Exact => f.write_str("Exact"),
+            SparsifyPolicy::AbsTolerance(tol) => f.debug_tuple("AbsTolerance").field(tol).finish(),
+            SparsifyPolicy::Custom(_) => f.write_str("Custom(..)"),
         }
     }
+}
+
+/// Sparsify構造体の定義
+/// Exact 以外の SparsifyPolicy と、AbsTolerance で使う変換関数
+/// (NnzCap と同じく set_sparsify_policy の時点で T: ToPrimitive から作ります)
+/// - 判定の関数は extern "C" (unwind しない) 越しに呼ぶので、try_push などの経路に panic の分岐を作りません
+struct Sparsify<T> {
+    policy: SparsifyPolicy<T>,
+    to_f64: extern "C" fn(&T) -> f64,
+}
 
+impl<T: PartialEq> Sparsify<T> {
+    /// matchesメソッドの実装
+    /// elem を default とみなすかどうか
     #[inline(always)]
-    pub fn as_mut_slice_ind(&mut self) -> &mut [usize] {
-        unsafe {
-            std::slice::from_raw_parts_mut(self.ind_ptr(), self.raw_len)
+    fn matches(&self, elem: &T, default: &T) -> bool {
+        match self.policy {
+            SparsifyPolicy::Exact => elem == default,
+            SparsifyPolicy::AbsTolerance(tol) => {
+                elem == default || ((self.to_f64)(elem) - (self.to_f64)(default)).abs() <= tol
+            }
+            SparsifyPolicy::Custom(f) => elem == default || call_custom(f, elem, default),
         }
     }
 }
 
-unsafe impl<T: Send + Default + PartialEq + Clone> Send for DefaultSparseVec<T> {}
-unsafe impl<T: Send + Default + PartialEq + Clone> Sync for DefaultSparseVec<T> {}
+/// call_custom関数の実装
+/// SparsifyPolicy::Custom の関数を呼ぶ、unwind しない境界
+/// ! : f が panic した場合は unwind せずに abort します
+// crate の中からしか呼ばないので、Rust の fn ポインタを引数にしても問題ありません
+#[allow(improper_ctypes_definitions)]
+extern "C" fn call_custom<T>(f: fn(&T, &T) -> bool, elem: &T, default: &T) -> bool {
+    f(elem, default)
+}
 
-impl<T: Default + PartialEq + Clone> Clone for DefaultSparseVec<T> {
-    fn clone(&self) -> Self {
-        // RawDefaultSparseVec を「deep_clone」して新しいポインタを持たせる
-        let new_buf = self.buf.deep_clone(self.raw_len);
+/// to_f64_or_nan関数の実装
+/// Sparsify::to_f64 の既定 (変換できない値は NaN)
+/// ! : T::to_f64 が panic した場合は unwind せずに abort します
+extern "C" fn to_f64_or_nan<T: ToPrimitive>(elem: &T) -> f64 {
+    elem.to_f64().unwrap_or(f64::NAN)
+}
 
-        DefaultSparseVec {
-            buf: new_buf,
-            raw_len: self.raw_len,
-            len: self.len,
-            default: self.default.clone(),
+impl<T: ToPrimitive> Sparsify<T> {
+    /// newメソッドの実装
+    /// Exact なら None (判定は == だけなので持ちません)
+    /// - AbsTolerance は to_f64 で比較します (変換できない値は NaN として扱います)
+    #[inline(always)]
+    fn new(policy: SparsifyPolicy<T>) -> Option<Self> {
+        match policy {
+            SparsifyPolicy::Exact => None,
+            policy => Some(Sparsify {
+                policy,
+                to_f64: to_f64_or_nan::<T>,
+            }),
         }
     }
 }
 
-
-impl<T: Default + PartialEq + Clone> Drop for DefaultSparseVec<T> {
+impl<T> Clone for Sparsify<T> {
     #[inline(always)]
-    fn drop(&mut self) {
-        while let Some(_) = self.pop() {}
+    fn clone(&self) -> Self {
+        *self
     }
 }
 
-impl<T: Default + PartialEq + Clone + Debug> Debug for DefaultSparseVec<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if f.sign_plus() {
-            f.debug_struct("DefaultSparseVec")
-                .field("buf", &self.buf)
-                .field("raw_len", &self.raw_len)
-                .field("len", &self.len)
-                .field("default", &self.default)
-                .finish()
-        } else if f.alternate() {
-            write!(f, "DefaultSparseVec({:?})", self.iter().collect::<Vec<_>>())
-        } else {
-            f.debug_list().entries((0..self.len).map(|i| self.get(i).unwrap())).finish()
-        }
-    }
+/// DefaultSparseVecBuilder構造体の定義
+/// DefaultSparseVec::builder が返す、長さ・物理要素数の見積もり・デフォルト値・SparsifyPolicy をまとめて指定するビルダー
+/// - 物理要素数の見積もりは nnz が優先で、無ければ len * density (どちらも無ければ確保しません)
+/// - 見積もり以下の物理要素数なら build / build_from の後の書き込みで再確保しません
+/// - 見積もりが外れても通常通り (2 倍ずつ) 伸びるだけです
+pub struct DefaultSparseVecBuilder<T> {
+    len: usize,
+    density: Option<f64>,
+    nnz: Option<usize>,
+    default: Option<T>,
+    sparsify: Option<Sparsify<T>>,
 }
 
-impl<T: Default + PartialEq + Clone> Index<usize> for DefaultSparseVec<T> {
-    type Output = T;
+impl<T: Default + PartialEq> DefaultSparseVecBuilder<T> {
+    /// lenメソッドの実装
+    /// 論理長 (build では全てデフォルト値の長さ、build_from では見積もりにだけ使います)
+    #[must_use]
+    #[inline(always)]
+    pub fn len(mut self, len: usize) -> Self {
+        self.len = len;
+        self
+    }
 
+    /// densityメソッドの実装
+    /// 物理要素の割合の見積もり (nnz ≒ len * density)
+    /// ! : density が 0.0..=1.0 の範囲外 (NaN を含む) の場合は panic
+    #[must_use]
     #[inline(always)]
-    fn index(&self, index: usize) -> &Self::Output {
-        self.get(index).expect("index out of bounds")
+    pub fn density(mut self, density: f64) -> Self {
+        assert!((0.0..=1.0).contains(&density), "density must be in 0.0..=1.0 (is {density})");
+        self.density = Some(density);
+        self
     }
-}
 
-impl<T: Default + PartialEq + Clone> IndexMut<usize> for DefaultSparseVec<T> {
-    /// #warning
-    /// このメソッドは、非推奨のget_mutメソッドを使用しています
+    /// nnzメソッドの実装
+    /// 物理要素数の見積もり (density より優先します)
+    #[must_use]
     #[inline(always)]
-    #[warn(deprecated)]
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        self.get_mut(index).expect("index out of bounds")
+    pub fn nnz(mut self, nnz: usize) -> Self {
+        self.nnz = Some(nnz);
+        self
     }
-}
 
-impl <T: Default + PartialEq + Clone> Default for DefaultSparseVec<T> {
+    /// default_valueメソッドの実装
+    /// with_default と同じく、T::default() の代わりにスパースする値を指定します
+    #[must_use]
     #[inline(always)]
-    fn default() -> Self {
-        Self::new()
+    pub fn default_value(mut self, default: T) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    /// buildメソッドの実装
+    /// 長さ len の全てデフォルト値のベクタを、見積もった物理要素数の容量で作ります (set などで埋める用途)
+    #[must_use]
+    pub fn build(self) -> DefaultSparseVec<T> {
+        let len = self.len;
+        let nnz = self.expected_nnz(len);
+        let mut vec = self.into_empty(nnz);
+        vec.grow_len(len);
+        vec
     }
-}
 
-impl<T: Default + PartialEq + Clone> From<Vec<T>> for DefaultSparseVec<T> {
+    /// build_fromメソッドの実装
+    /// 見積もった物理要素数の容量を確保してから、iter の要素を先頭から push します (From<Vec<T>> / extend の見積もり付き版)
+    /// - len を指定していなければ、iter の size_hint の下限を len として見積もります
+    /// - 結果の len は iter の要素数です (shrink_to_fit はしません)
+    pub fn build_from<I: IntoIterator<Item = T>>(self, iter: I) -> DefaultSparseVec<T> {
+        let iter = iter.into_iter();
+        let len = if self.len == 0 { iter.size_hint().0 } else { self.len };
+        let nnz = self.expected_nnz(len);
+        let mut vec = self.into_empty(nnz);
+        vec.extend(iter);
+        vec
+    }
+
+    /// expected_nnzメソッドの実装
+    /// 長さ len での物理要素数の見積もり
     #[inline(always)]
-    fn from(vec: Vec<T>) -> Self {
-        let mut svec = DefaultSparseVec::new();
-        vec.into_iter().for_each(|elem| svec.push(elem));
-        svec.shrink_to_fit();
-        svec
+    fn expected_nnz(&self, len: usize) -> usize {
+        match (self.nnz, self.density) {
+            (Some(nnz), _) => nnz,
+            (None, Some(density)) => expected_nnz(len, density),
+            (None, None) => 0,
+        }
+    }
+
+    /// into_emptyメソッドの実装
+    /// 容量 nnz の空のベクタにデフォルト値と SparsifyPolicy を設定して返します
+    #[inline(always)]
+    fn into_empty(self, nnz: usize) -> DefaultSparseVec<T> {
+        let mut vec = DefaultSparseVec::with_default_and_capacity(self.default.unwrap_or_default(), nnz);
+        if self.sparsify.is_some() {
+            vec.extras_mut().sparsify = self.sparsify;
+        }
+        vec
     }
 }
 
-impl<T: Default + PartialEq + Clone> From<HashMap<usize, T>> for DefaultSparseVec<T> {
+impl<T: Default + PartialEq + ToPrimitive> DefaultSparseVecBuilder<T> {
+    /// sparsify_policyメソッドの実装
+    /// set_sparsify_policy と同じ判定を最初から設定します
+    #[must_use]
     #[inline(always)]
-    fn from(map: HashMap<usize, T>) -> Self {
-        let mut svec = DefaultSparseVec::new();
-        map.into_iter().for_each(|(index, elem)| svec.insert(index, elem));
-        svec.shrink_to_fit();
-        svec
+    pub fn sparsify_policy(mut self, policy: SparsifyPolicy<T>) -> Self {
+        self.sparsify = Sparsify::new(policy);
+        self
     }
 }
 
-impl<T: Default + PartialEq + Clone> Into<Vec<T>> for DefaultSparseVec<T> {
+/// expected_nnz関数の実装
+/// len * density を切り上げた物理要素数の見積もり (len を超えません)
+#[inline(always)]
+fn expected_nnz(len: usize, density: f64) -> usize {
+    ((len as f64 * density).ceil() as usize).min(len)
+}
+
+impl<T> Copy for Sparsify<T> {}
+
+/// IndexWidth列挙型の定義
+/// index を格納する整数の幅 (narrowest_index_width の結果)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IndexWidth {
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IndexWidth {
+    /// for_lenメソッドの実装
+    /// 長さ len のベクタの index を表せる最も狭い幅 (len 自体も収まること)
     #[inline(always)]
-    fn into(self) -> Vec<T> {
-        let mut vec = Vec::new();
-        (0..self.len()).for_each(|i| vec.push(self.get(i).unwrap().clone()));
-        vec
+    pub fn for_len(len: usize) -> Self {
+        let len = len as u64;
+        if len <= u64::from(u8::MAX) {
+            IndexWidth::U8
+        } else if len <= u64::from(u16::MAX) {
+            IndexWidth::U16
+        } else if len <= u64::from(u32::MAX) {
+            IndexWidth::U32
+        } else {
+            IndexWidth::U64
+        }
+    }
+
+    /// bytesメソッドの実装
+    /// 1 つの index のバイト数
+    #[inline(always)]
+    pub fn bytes(self) -> usize {
+        match self {
+            IndexWidth::U8 => 1,
+            IndexWidth::U16 => 2,
+            IndexWidth::U32 => 4,
+            IndexWidth::U64 => 8,
+        }
     }
 }
 
-impl<T: Default + PartialEq + Clone> Into<HashMap<usize, T>> for DefaultSparseVec<T> {
+/// NnzCap構造体の定義
+/// 物理要素数の上限と、EvictSmallest で使う大きさの関数
+/// (set_max_nnz の時点で T: ToPrimitive から作るので、他のメソッドは ToPrimitive を要求しません)
+struct NnzCap<T> {
+    max: usize,
+    magnitude: fn(&T) -> f64,
+}
+
+impl<T> Clone for NnzCap<T> {
     #[inline(always)]
-    fn into(self) -> HashMap<usize, T> {
-        let mut map = HashMap::new();
-        self.iter().for_each(|(index, elem)| {
-            map.insert(*index, elem.clone());
-        });
-        map
+    fn clone(&self) -> Self {
+        *self
     }
 }
 
-impl<T: Default + PartialEq + Clone> NormalVecMethods<T> for DefaultSparseVec<T> {
+impl<T> Copy for NnzCap<T> {}
+
+/// MergeGap構造体の定義
+/// merge_in_place の合成途中の状態
+/// 物理位置 [0, read) と [write, end) が有効な要素で、[read, write) は隙間です
+/// drop 時 (panic による巻き戻しを含む) に後ろ側を前に詰めて raw_len を確定します
+struct MergeGap<'a, T: Default + PartialEq> {
+    vec: &'a mut DefaultSparseVec<T>,
+    read: usize,
+    write: usize,
+    end: usize,
+}
+
+impl<T: Default + PartialEq> MergeGap<'_, T> {
     #[inline(always)]
-    fn n_push(&mut self, elem: T) {
-        if self.raw_len == self.cap() {
-            self.buf.grow();
+    fn index_at(&self, i: usize) -> usize {
+        unsafe { *self.vec.ind_ptr().add(i) }
+    }
+
+    /// move_backメソッドの実装
+    /// 隙間の直前の要素を隙間の末尾へ移動します
+    #[inline(always)]
+    fn move_back(&mut self) {
+        self.read -= 1;
+        self.write -= 1;
+        if self.read != self.write {
+            unsafe {
+                ptr::copy_nonoverlapping(self.vec.val_ptr().add(self.read), self.vec.val_ptr().add(self.write), 1);
+                *self.vec.ind_ptr().add(self.write) = *self.vec.ind_ptr().add(self.read);
+            }
         }
-        if self.default == elem {
+    }
+}
+
+impl<T: Default + PartialEq> Drop for MergeGap<'_, T> {
+    fn drop(&mut self) {
+        let tail = self.end - self.write;
+        if self.read != self.write {
             unsafe {
-                ptr::write(self.val_ptr().offset(self.raw_len as isize), elem);
-                ptr::write(self.ind_ptr().offset(self.raw_len as isize), self.len);
+                ptr::copy(self.vec.val_ptr().add(self.write), self.vec.val_ptr().add(self.read), tail);
+                ptr::copy(self.vec.ind_ptr().add(self.write), self.vec.ind_ptr().add(self.read), tail);
             }
-            self.raw_len += 1;
         }
-        self.len += 1;
+        self.vec.raw_len = self.read + tail;
+    }
+}
+
+/// TruncateOnUnwind構造体の定義
+/// drop 時 (panic による巻き戻し) に vec を len で切り詰めます
+/// 正常に終わった場合は mem::forget します
+struct TruncateOnUnwind<'a, T: Default + PartialEq> {
+    vec: &'a mut DefaultSparseVec<T>,
+    len: usize,
+}
+
+impl<T: Default + PartialEq> Drop for TruncateOnUnwind<'_, T> {
+    fn drop(&mut self) {
+        self.vec.truncate(self.len);
     }
+}
+
+/// RetainGuard構造体の定義
+/// retain / retain_stored の走査途中の状態
+/// 物理位置 [0, write) が残す要素 (index は詰め済み)、[read, raw_len) が未処理の要素です
+/// drop 時 (panic による巻き戻しを含む) に未処理の要素を write の後ろへ詰め、index から shift を引いて raw_len と len を確定します
+struct RetainGuard<'a, T: Default + PartialEq> {
+    vec: &'a mut DefaultSparseVec<T>,
+    raw_len: usize,
+    read: usize,
+    write: usize,
+    /// これまでに取り除いた論理位置の数
+    shift: usize,
+    /// 物理要素を取り除いたときに後ろを詰めるか (retain_stored では false)
+    shifts: bool,
+}
 
+impl<'a, T: Default + PartialEq> RetainGuard<'a, T> {
     #[inline(always)]
-    fn n_pop(&mut self) -> Option<T> {
-        if self.len == 0 {
-            return None;
+    fn new(vec: &'a mut DefaultSparseVec<T>, shifts: bool) -> Self {
+        let raw_len = vec.raw_len;
+        RetainGuard {
+            vec,
+            raw_len,
+            read: 0,
+            write: 0,
+            shift: 0,
+            shifts,
         }
-        // 空らずraw_len =< len であることが保証されている
-        let pop_elem = 
-            if self.raw_len == self.len {
-                self.raw_len -= 1;
-                unsafe {
-                    Some(ptr::read(self.val_ptr().offset(self.raw_len as isize)))
+    }
+
+    #[inline(always)]
+    fn index_at(&self, i: usize) -> usize {
+        unsafe { *self.vec.ind_ptr().add(i) }
+    }
+
+    /// stepメソッドの実装
+    /// 未処理の先頭の要素を、keep なら残す側へ移し、そうでなければ drop して取り除きます
+    #[inline(always)]
+    fn step(&mut self, keep: bool) {
+        let read = self.read;
+        // drop が panic しても二重に drop しないよう、先に read を進める
+        self.read += 1;
+        unsafe {
+            if keep {
+                if read != self.write {
+                    ptr::copy_nonoverlapping(self.vec.val_ptr().add(read), self.vec.val_ptr().add(self.write), 1);
                 }
+                *self.vec.ind_ptr().add(self.write) = *self.vec.ind_ptr().add(read) - self.shift;
+                self.write += 1;
             } else {
-                Some(self.default.clone())
-            };
-        self.len -= 1;
-        pop_elem
+                if self.shifts {
+                    self.shift += 1;
+                }
+                ptr::drop_in_place(self.vec.val_ptr().add(read));
+            }
+        }
+    }
+}
+
+impl<T: Default + PartialEq> Drop for RetainGuard<'_, T> {
+    fn drop(&mut self) {
+        let tail = self.raw_len - self.read;
+        unsafe {
+            if self.read != self.write {
+                ptr::copy(self.vec.val_ptr().add(self.read), self.vec.val_ptr().add(self.write), tail);
+            }
+            for i in self.read..self.raw_len {
+                *self.vec.ind_ptr().add(self.write + i - self.read) = *self.vec.ind_ptr().add(i) - self.shift;
+            }
+        }
+        self.vec.raw_len = self.write + tail;
+        self.vec.len -= self.shift;
     }
+}
+
+/// Extras構造体の定義
+/// DefaultSparseVec のうち、設定したベクタだけが持つポリシーとキャッシュ
+/// 全て既定値のベクタは Box を確保せず (extras が None)、on_mutate のリセットも行いません
+/// - 設定: nnz_cap / cap_policy (set_max_nnz)、sparsify (set_sparsify_policy)、densified (tuning_report 用)
+/// - キャッシュ: shrink (shrink_step)、value_index (build_value_index)
+///   物理領域から作るので、on_mutate で破棄し、clone でも引き継ぎません (value_index は Clone で別に複製します)
+struct Extras<T> {
+    shrink: Option<ShrinkState<T>>,
+    nnz_cap: Option<NnzCap<T>>,
+    cap_policy: CapPolicy,
+    sparsify: Option<Sparsify<T>>,
+    densified: bool,
+    value_index: Option<Vec<usize>>,
+}
 
+impl<T> Default for Extras<T> {
     #[inline(always)]
-    fn n_insert(&mut self, index: usize, elem: T) {
-        self.insert(index, elem);
+    fn default() -> Self {
+        Extras {
+            shrink: None,
+            nnz_cap: None,
+            cap_policy: CapPolicy::default(),
+            sparsify: None,
+            densified: false,
+            value_index: None,
+        }
     }
 }
 
-impl<T> Math<T> for DefaultSparseVec<T>
-    where
-    T: Num + Default + PartialEq + Clone + std::ops::AddAssign + std::ops::Mul<Output = T> + Into<u64>,
-{
+impl<T> Extras<T> {
+    /// settingsメソッドの実装
+    /// キャッシュを除いた設定だけのコピー
     #[inline(always)]
-    fn u64_dot(&self, other: &Self) -> u64 {
-        let mut sum: u64 = 0;
-        let mut self_iter = self.iter();
-        let mut other_iter = other.iter();
-        let mut self_current = self_iter.next();
-        let mut other_current = other_iter.next();
-
-        while self_current.is_some() && other_current.is_some() {
-            if self_current.unwrap().0 < other_current.unwrap().0 {
-                self_current = self_iter.next();
-            } else if self_current.unwrap().0 > other_current.unwrap().0 {
-                other_current = other_iter.next();
-            } else {
-                sum += (self_current.unwrap().1.clone() * other_current.unwrap().1.clone()).into();
-                self_current = self_iter.next();
-                other_current = other_iter.next();
-            }
+    fn settings(&self) -> Self {
+        Extras {
+            nnz_cap: self.nnz_cap,
+            cap_policy: self.cap_policy,
+            sparsify: self.sparsify,
+            densified: self.densified,
+            ..Extras::default()
         }
-        sum
     }
 }
 
+/// ShrinkState構造体の定義
+/// shrink_step のコピー途中の状態
+/// target には先頭 copied 個のインデックスと値がコピーされています (T: Copy なので target は値を drop しません)
+struct ShrinkState<T> {
+    target: RawDefaultSparseVec<T>,
+    copied: usize,
+}
 
 /// RawDefaultSparseVec構造体の定義
 /// T: スパースするデータの型
@@ -678,146 +4390,226 @@ impl<T> Math<T> for DefaultSparseVec<T>
 /// cap: スパースするデータの容量
 /// _marker: 所有権管理用のPhantomData
 #[derive(Debug,)]
-struct RawDefaultSparseVec<T> {
+pub(crate) struct RawDefaultSparseVec<T> {
     val_ptr: NonNull<T>,
     ind_ptr: NonNull<usize>,
     /// cap 定義
     /// 0 => メモリ未確保 (flag)
-    /// usize::MAX =>  zero size struct (ZST) として定義 処理の簡略化を実施 (flag)
     /// _ => 実際のcap
+    /// ! : 「cap > 0 ⇔ index の領域 (と非 ZST なら値の領域) が確保済み」を常に保ちます
+    ///   (容量の変更は全て try_set_capacity を通し、0 への変更は解放して new() の状態に戻します)
+    /// ZST は値の領域を確保せず val_ptr は常に dangling ですが、index は物理要素を格納する場合
+    /// (PartialEq がデフォルト値と等しくないとする場合や IndexMut で密にした場合) に備えて通常通り確保します
     cap: usize,
     _marker: PhantomData<T>, // 所有権管理用にPhantomDataを追加
 }
 
 impl<T> RawDefaultSparseVec<T> {
+    /// val_ptrメソッドの実装
+    /// - 確保済みなら cap 要素ぶんの領域を指します (未確保の場合と、ZST の値側は dangling)
+    /// - 初期化済みかどうかは持ち主 (DefaultSparseVec の raw_len) だけが知っています
     #[inline(always)]
-    fn new() -> Self {
-        // 効率化: zero size struct (ZST)をusize::MAXと定義 ある種のフラグとして使用
-        let cap = if mem::size_of::<T>() == 0 { std::usize::MAX } else { 0 }; 
+    pub(crate) fn val_ptr(&self) -> *mut T {
+        self.val_ptr.as_ptr()
+    }
+
+    /// ind_ptrメソッドの実装
+    /// - val_ptr と同じです
+    #[inline(always)]
+    pub(crate) fn ind_ptr(&self) -> *mut usize {
+        self.ind_ptr.as_ptr()
+    }
+
+    /// poisonメソッドの実装
+    /// 物理位置 from..to の値と index の領域を POISON_BYTE で上書きします
+    /// - feature = "debug-poison" かつ debug ビルドの場合だけ有効で、それ以外は何もしません
+    /// - 値は drop 済み (または読み出し済み) であること
+    #[inline(always)]
+    fn poison(&self, from: usize, to: usize) {
+        if from < to {
+            unsafe {
+                poison_slots(self.val_ptr().add(from), to - from);
+                poison_slots(self.ind_ptr().add(from), to - from);
+            }
+        }
+    }
 
+    #[inline(always)]
+    fn new() -> Self {
         RawDefaultSparseVec {
             // 効率化: 空のポインタを代入しておく メモリ確保を遅延させる
             val_ptr: NonNull::dangling(),
             // 効率化: 空のポインタを代入しておく メモリ確保を遅延させる
             ind_ptr: NonNull::dangling(),
-            cap: cap,
+            cap: 0,
             _marker: PhantomData,
         }
     }
 
+    /// try_layoutsメソッドの実装
+    /// cap 要素ぶんの (値, index) のメモリレイアウトを返します
+    /// - サイズが isize::MAX を超える場合は CapacityOverflow (32bit 環境ではすぐに届きます)
     #[inline(always)]
-    fn grow(&mut self) {
-        unsafe {
-            let val_elem_size = mem::size_of::<T>();
-            let ind_elem_size = mem::size_of::<usize>();
-
-            // 安全性: ZSTの場合growはcapを超えた場合にしか呼ばれない
-            // これは必然的にオーバーフローしていることをしめしている
-            assert!(val_elem_size != 0, "capacity overflow");
-
-            // アライメントの取得 適切なメモリ確保を行うため
-            let t_align = mem::align_of::<T>();
-            let usize_align = mem::align_of::<usize>();
-
-            // アロケーション
-            let (new_cap, val_ptr, ind_ptr): (usize, *mut T, *mut usize) = 
-                if self.cap == 0 {
-                    let new_val_layout = Layout::from_size_align(val_elem_size, t_align).expect("Failed to create memory layout");
-                    let new_ind_layout = Layout::from_size_align(ind_elem_size, usize_align).expect("Failed to create memory layout");
-                    (
-                        1,
-                        alloc(new_val_layout) as *mut T,
-                        alloc(new_ind_layout) as *mut usize,
-                    )
-                } else {
-                    // 効率化: cap * 2 でメモリを確保する 見た目上はO(log n)の増加を実現
-                    let new_cap = self.cap * 2;
-                    let new_val_layout = Layout::from_size_align(val_elem_size * self.cap, t_align).expect("Failed to create memory layout for reallocation");
-                    let new_ind_layout = Layout::from_size_align(ind_elem_size * self.cap, usize_align).expect("Failed to create memory layout for reallocation");
-                    (
-                        new_cap,
-                        realloc(self.val_ptr.as_ptr() as *mut u8, new_val_layout, val_elem_size * new_cap) as *mut T,
-                        realloc(self.ind_ptr.as_ptr() as *mut u8, new_ind_layout, ind_elem_size * new_cap) as *mut usize,
-                    )
-                };
+    fn try_layouts(cap: usize) -> Result<(Layout, Layout), SparseVecError> {
+        match (Layout::array::<T>(cap), Layout::array::<usize>(cap)) {
+            (Ok(val_layout), Ok(ind_layout)) => Ok((val_layout, ind_layout)),
+            _ => Err(SparseVecError::CapacityOverflow),
+        }
+    }
 
-            // アロケーション失敗時の処理
-            if val_ptr.is_null() || ind_ptr.is_null() {
-                oom();
+    /// try_growメソッドの実装
+    /// 容量を 2 倍 (未確保なら 1) にします
+    /// - 失敗した場合は何も変更せずにエラーを返します
+    #[inline(always)]
+    fn try_grow(&mut self) -> Result<(), SparseVecError> {
+        // 効率化: cap * 2 でメモリを確保する 見た目上はO(log n)の増加を実現
+        let new_cap = if self.cap == 0 {
+            1
+        } else {
+            self.cap.checked_mul(2).ok_or(SparseVecError::CapacityOverflow)?
+        };
+        // grow は物理領域が満杯のときだけ呼ばれるので、nnz == cap
+        #[cfg(feature = "tracing")]
+        if self.cap != 0 {
+            let bytes = |cap: usize| cap.saturating_mul(mem::size_of::<T>() + mem::size_of::<usize>());
+            if bytes(new_cap) >= TRACE_GROW_BYTES {
+                tracing::debug!(
+                    target: "vec_plus",
+                    nnz = self.cap,
+                    new_cap,
+                    bytes_moved = bytes(self.cap),
+                    "grow reallocation"
+                );
             }
+        }
+        self.try_set_capacity(new_cap)
+    }
+
+    /// growメソッドの実装
+    /// try_grow の panic する版
+    #[inline(always)]
+    fn grow(&mut self) {
+        self.try_grow().unwrap_or_else(|err| alloc_failure(err));
+    }
 
-            // selfに返却
-            self.val_ptr = NonNull::new_unchecked(val_ptr);
-            self.ind_ptr = NonNull::new_unchecked(ind_ptr);
-            self.cap = new_cap;
+    /// try_set_capacityメソッドの実装
+    /// 容量を new_cap にします。容量を変える操作は全てここを通します
+    /// - 未確保 (cap == 0) なら新規確保、確保済みなら再確保 (ZST の値側は確保しません)
+    /// - new_cap == 0 なら領域を解放して未確保の状態に戻す (大きさ 0 の確保は行いません)
+    /// - 失敗した場合は何も変更せずにエラーを返します (元の領域はそのまま使えます)
+    #[inline(always)]
+    fn try_set_capacity(&mut self, new_cap: usize) -> Result<(), SparseVecError> {
+        if new_cap == self.cap {
+            return Ok(());
+        }
+        if new_cap == 0 {
+            // 古い領域は Drop で解放されます
+            *self = RawDefaultSparseVec::new();
+            return Ok(());
         }
+        let (val_ptr, ind_ptr) = if self.cap == 0 {
+            self.try_alloc_pair(new_cap)?
+        } else {
+            self.try_realloc_pair(new_cap)?
+        };
+        self.val_ptr = val_ptr;
+        self.ind_ptr = ind_ptr;
+        self.cap = new_cap;
+        Ok(())
     }
 
+    /// set_capacityメソッドの実装
+    /// try_set_capacity の panic する版
     #[inline(always)]
-    fn cap_set(&mut self) {
-        unsafe {
-            let val_elem_size = mem::size_of::<T>();
-            let ind_elem_size = mem::size_of::<usize>();
+    fn set_capacity(&mut self, new_cap: usize) {
+        self.try_set_capacity(new_cap).unwrap_or_else(|err| alloc_failure(err));
+    }
 
-            let t_align = mem::align_of::<T>();
-            let usize_align = mem::align_of::<usize>();
+    /// 公開 API で容量を指定できるかどうか (ZST は false)
+    const PLANS_CAPACITY: bool = mem::size_of::<T>() != 0;
 
-            let new_val_layout = Layout::from_size_align(val_elem_size * self.cap, t_align).expect("Failed to create memory layout");
-            let new_ind_layout = Layout::from_size_align(ind_elem_size * self.cap, usize_align).expect("Failed to create memory layout");
-            let new_val_ptr = alloc(new_val_layout) as *mut T;
-            let new_ind_ptr = alloc(new_ind_layout) as *mut usize;
-            if new_val_ptr.is_null() || new_ind_ptr.is_null() {
-                oom();
-            }
-            self.val_ptr = NonNull::new_unchecked(new_val_ptr);
-            self.ind_ptr = NonNull::new_unchecked(new_ind_ptr);
+    /// try_plan_capacityメソッドの実装
+    /// 公開 API (with_capacity / reserve / shrink_to など) で指定された容量にします
+    /// - ZST は何もしません。値の領域は要らず、index の領域は物理要素を格納するときに grow で必要な分だけ確保します
+    ///   (() のように常にデフォルト値と等しい ZST は一度も確保しません)
+    #[inline(always)]
+    fn try_plan_capacity(&mut self, new_cap: usize) -> Result<(), SparseVecError> {
+        if !Self::PLANS_CAPACITY {
+            return Ok(());
         }
+        self.try_set_capacity(new_cap)
     }
 
+    /// plan_capacityメソッドの実装
+    /// try_plan_capacity の panic する版
     #[inline(always)]
-    fn re_cap_set(&mut self) {
-        unsafe {
-            let val_elem_size = mem::size_of::<T>();
-            let ind_elem_size = mem::size_of::<usize>();
+    fn plan_capacity(&mut self, new_cap: usize) {
+        self.try_plan_capacity(new_cap).unwrap_or_else(|err| alloc_failure(err));
+    }
 
-            let t_align = mem::align_of::<T>();
-            let usize_align = mem::align_of::<usize>();
+    /// capacityメソッドの実装
+    /// 公開 API の capacity で返す値 (ZST は usize::MAX)
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+        if Self::PLANS_CAPACITY { self.cap } else { usize::MAX }
+    }
 
-            let new_val_layout = Layout::from_size_align(val_elem_size * self.cap, t_align).expect("Failed to create memory layout");
-            let new_ind_layout = Layout::from_size_align(ind_elem_size * self.cap, usize_align).expect("Failed to create memory layout");
-            let new_val_ptr = realloc(self.val_ptr.as_ptr() as *mut u8, new_val_layout, val_elem_size * self.cap) as *mut T;
-            let new_ind_ptr = realloc(self.ind_ptr.as_ptr() as *mut u8, new_ind_layout, ind_elem_size * self.cap) as *mut usize;
-            if new_val_ptr.is_null() || new_ind_ptr.is_null() {
-                oom();
-            }
-            self.val_ptr = NonNull::new_unchecked(new_val_ptr);
-            self.ind_ptr = NonNull::new_unchecked(new_ind_ptr);
+    /// try_alloc_pairメソッドの実装
+    /// cap 要素ぶんの (値, index) の領域を新しく確保します
+    /// - 片方だけ確保できた場合はそれを解放してからエラーを返します
+    fn try_alloc_pair(&self, cap: usize) -> Result<(NonNull<T>, NonNull<usize>), SparseVecError> {
+        let (val_layout, ind_layout) = Self::try_layouts(cap)?;
+        unsafe {
+            // ZST の値側は大きさ 0 なので確保しない (大きさ 0 の alloc は許されません)
+            let val_ptr = if val_layout.size() == 0 {
+                NonNull::dangling()
+            } else {
+                let Some(val_ptr) = NonNull::new(alloc(val_layout) as *mut T) else {
+                    return Err(SparseVecError::AllocFailed { bytes: val_layout.size() });
+                };
+                val_ptr
+            };
+            let Some(ind_ptr) = NonNull::new(alloc(ind_layout) as *mut usize) else {
+                if val_layout.size() != 0 {
+                    dealloc(val_ptr.as_ptr() as *mut u8, val_layout);
+                }
+                return Err(SparseVecError::AllocFailed { bytes: ind_layout.size() });
+            };
+            Ok((val_ptr, ind_ptr))
         }
     }
 
-    fn deep_clone(&self, raw_len: usize) -> Self {
+    /// try_realloc_pairメソッドの実装
+    /// 確保済みの領域を new_cap 要素ぶんに再確保します (new_cap > 0)
+    /// - index 側は先に新しく確保しておき、値側の realloc が成功してからコピーします
+    ///   (どちらかが失敗しても元の領域は有効なまま)
+    /// - 縮める場合は new_cap 要素ぶんだけコピーします
+    fn try_realloc_pair(&self, new_cap: usize) -> Result<(NonNull<T>, NonNull<usize>), SparseVecError> {
+        let (old_val_layout, old_ind_layout) = Self::try_layouts(self.cap)?;
+        let (new_val_layout, new_ind_layout) = Self::try_layouts(new_cap)?;
         unsafe {
-            // self.cap 分のメモリを新規に確保 (alloc or realloc)
-            let val_elem_size = mem::size_of::<T>();
-            let ind_elem_size = mem::size_of::<usize>();
-            let t_align = mem::align_of::<T>();
-            let usize_align = mem::align_of::<usize>();
-            let val_layout = Layout::from_size_align(val_elem_size * self.cap, t_align).unwrap();
-            let ind_layout = Layout::from_size_align(ind_elem_size * self.cap, usize_align).unwrap();
-
-            let new_val_ptr = alloc(val_layout) as *mut T;
-            let new_ind_ptr = alloc(ind_layout) as *mut usize;
-
-            // 今の (val_ptr, ind_ptr) から raw_len 個ぶんコピーする
-            ptr::copy_nonoverlapping(self.val_ptr.as_ptr(), new_val_ptr, raw_len);
-            ptr::copy_nonoverlapping(self.ind_ptr.as_ptr(), new_ind_ptr, raw_len);
-
-            RawDefaultSparseVec {
-                val_ptr: NonNull::new_unchecked(new_val_ptr),
-                ind_ptr: NonNull::new_unchecked(new_ind_ptr),
-                cap: self.cap,
-                _marker: PhantomData,
+            let Some(ind_ptr) = NonNull::new(alloc(new_ind_layout) as *mut usize) else {
+                return Err(SparseVecError::AllocFailed { bytes: new_ind_layout.size() });
+            };
+            if new_cap < self.cap {
+                // 縮める場合に切り捨てる末尾 (呼び出し側が nnz 以上にするので未使用の領域)
+                poison_slots(self.val_ptr().add(new_cap), self.cap - new_cap);
             }
+            let val_ptr = if new_val_layout.size() == 0 {
+                // ZST の値側は確保していないので dangling のまま
+                self.val_ptr
+            } else {
+                let val_ptr = realloc(self.val_ptr.as_ptr() as *mut u8, old_val_layout, new_val_layout.size());
+                let Some(val_ptr) = NonNull::new(val_ptr as *mut T) else {
+                    dealloc(ind_ptr.as_ptr() as *mut u8, new_ind_layout);
+                    return Err(SparseVecError::AllocFailed { bytes: new_val_layout.size() });
+                };
+                val_ptr
+            };
+            ptr::copy_nonoverlapping(self.ind_ptr.as_ptr(), ind_ptr.as_ptr(), self.cap.min(new_cap));
+            poison_slots(self.ind_ptr(), self.cap);
+            dealloc(self.ind_ptr.as_ptr() as *mut u8, old_ind_layout);
+            Ok((val_ptr, ind_ptr))
         }
     }
 }
@@ -828,27 +4620,48 @@ unsafe impl<T: Sync> Sync for RawDefaultSparseVec<T> {}
 impl<T> Drop for RawDefaultSparseVec<T> {
     #[inline(always)]
     fn drop(&mut self) {
-        let val_elem_size = mem::size_of::<T>();
-        let ind_elem_size = mem::size_of::<usize>();
-        if self.cap != 0 && val_elem_size != 0 {
-            let t_align = mem::align_of::<T>();
-            let usize_align = mem::align_of::<usize>();
-            unsafe {
-                let val_layout = Layout::from_size_align(val_elem_size * self.cap, t_align).expect("Failed to create memory layout");
-                let ind_layout = Layout::from_size_align(ind_elem_size * self.cap, usize_align).expect("Failed to create memory layout");
-                dealloc(self.val_ptr.as_ptr() as *mut u8, val_layout);
-                dealloc(self.ind_ptr.as_ptr() as *mut u8, ind_layout);
+        if self.cap != 0 {
+            // 確保できた cap なのでレイアウトは必ず作れる
+            if let Ok((val_layout, ind_layout)) = Self::try_layouts(self.cap) {
+                unsafe {
+                    poison_slots(self.val_ptr(), self.cap);
+                    poison_slots(self.ind_ptr(), self.cap);
+                    if val_layout.size() != 0 {
+                        dealloc(self.val_ptr.as_ptr() as *mut u8, val_layout);
+                    }
+                    dealloc(self.ind_ptr.as_ptr() as *mut u8, ind_layout);
+                }
             }
         }
     }
 }
 
-/// OutOfMemoryへの対処用
-/// プロセスを終了させる
-/// 本来はpanic!を使用するべきだが、
-/// OOMの場合panic!を発生させるとTraceBackによるメモリ仕様が起きてしまうため
-/// 仕方なく強制終了させる
-/// 本来OOMはOSにより管理され発生前にKillされるはずなのであんまり意味はない。
-fn oom() {
-    ::std::process::exit(-9999);
-}
\ No newline at end of file
+/// poison_slots関数の実装
+/// ptr から count 要素ぶんを POISON_BYTE で上書きします (feature = "debug-poison" かつ debug ビルド)
+/// # Safety
+/// ptr から count 要素ぶんが確保済みの領域で、そこに drop が必要な値が残っていないこと
+#[cfg(all(feature = "debug-poison", debug_assertions))]
+#[inline(always)]
+unsafe fn poison_slots<U>(ptr: *mut U, count: usize) {
+    ptr::write_bytes(ptr, POISON_BYTE, count);
+}
+
+/// poison_slots関数の実装 (無効な場合は何もしません)
+/// # Safety
+/// 有効な場合と同じです
+#[cfg(not(all(feature = "debug-poison", debug_assertions)))]
+#[inline(always)]
+unsafe fn poison_slots<U>(_ptr: *mut U, _count: usize) {}
+
+/// alloc_failure関数の実装
+/// 容量を変える操作の panic する版から呼ばれ、エラーを handle_alloc_error / panic にします
+/// - AllocFailed: handle_alloc_error (Vec と同じ扱い、既定ではメッセージを出して abort)
+/// - それ以外 (CapacityOverflow): panic
+#[cold]
+fn alloc_failure(err: SparseVecError) -> ! {
+    if let SparseVecError::AllocFailed { bytes } = err {
+        // AllocFailed は要求サイズしか持たないので、報告用のレイアウトは align 1 で作ります
+        handle_alloc_error(Layout::from_size_align(bytes, 1).unwrap_or(Layout::new::<u8>()));
+    }
+    panic!("{err}");
+}
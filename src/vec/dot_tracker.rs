@@ -0,0 +1,165 @@
+use std::{marker::PhantomData, ops::Range};
+
+use num::ToPrimitive;
+
+use super::{default_sparse_vec::DefaultSparseVec, vec_trait::assert_same_len};
+
+/// DotTracker構造体の定義
+/// ベクタ u と固定のアイテム群の内積 (f64_dot と同じ値) を保持し、u の 1 要素の変更ごとに差分で更新します
+/// - 作成時にアイテム群の転置インデックス (index ごとに、その index を物理的に持つアイテムと値) を作ります
+/// - apply_update は変更した index を持つアイテムの数に比例する時間で済みます
+/// - 差分の加算を繰り返すと丸め誤差がたまるので、必要なら rebuild で計算し直します
+///
+/// 内積は物理要素同士の積の和 (Math と同じ) なので、u とアイテムのデフォルト値は 0 であること
+pub struct DotTracker<T> {
+    len: usize,
+    /// 物理要素を持つアイテムがある index (昇順)
+    keys: Vec<usize>,
+    /// keys[k] の転置リストは entries[offsets[k]..offsets[k + 1]]
+    offsets: Vec<usize>,
+    /// (アイテム番号, 値) をアイテム番号の昇順で並べたもの
+    entries: Vec<(usize, f64)>,
+    scores: Vec<f64>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Default + PartialEq + ToPrimitive> DotTracker<T> {
+    /// newメソッドの実装
+    /// u と items の各アイテムとの内積を計算し、転置インデックスを作ります O(アイテムの nnz の合計 log)
+    /// ! : u と items の len が異なる場合、またはデフォルト値が 0 でない場合は panic
+    pub fn new(u: &DefaultSparseVec<T>, items: &[DefaultSparseVec<T>]) -> Self {
+        assert_zero_default(u);
+        let mut postings = Vec::with_capacity(items.iter().map(|item| item.nnz()).sum());
+        for (item_id, item) in items.iter().enumerate() {
+            assert_same_len(u.len(), item.len());
+            assert_zero_default(item);
+            let (ind, val) = item.as_pairs();
+            postings.extend(ind.iter().zip(val).map(|(&index, elem)| (index, item_id, to_f64(elem))));
+        }
+        // 安定ソートなので、同じ index の中はアイテム番号の昇順のまま
+        postings.sort_by_key(|&(index, _, _)| index);
+
+        let mut keys = Vec::new();
+        let mut offsets = Vec::new();
+        let mut entries = Vec::with_capacity(postings.len());
+        for (index, item_id, value) in postings {
+            if keys.last() != Some(&index) {
+                keys.push(index);
+                offsets.push(entries.len());
+            }
+            entries.push((item_id, value));
+        }
+        offsets.push(entries.len());
+
+        let mut tracker = DotTracker {
+            len: u.len(),
+            keys,
+            offsets,
+            entries,
+            scores: vec![0.0; items.len()],
+            _marker: PhantomData,
+        };
+        tracker.rebuild(u);
+        tracker
+    }
+
+    /// apply_updateメソッドの実装
+    /// u の index 番目が old_value から new_value に変わったことを反映します
+    /// (new_value - old_value) * アイテムの値 を、index を物理的に持つアイテムの内積にだけ加算します
+    /// - u 自体の更新は呼び出し側で行います (前後どちらでも構いません)
+    /// - index が len 以上の場合は panic
+    pub fn apply_update(&mut self, index: usize, old_value: T, new_value: T) {
+        assert!(index < self.len, "index out of bounds: the len is {} but the index is {index}", self.len);
+        let delta = to_f64(&new_value) - to_f64(&old_value);
+        if delta == 0.0 {
+            return;
+        }
+        for k in self.postings(index) {
+            let (item_id, value) = self.entries[k];
+            self.scores[item_id] += delta * value;
+        }
+    }
+
+    /// rebuildメソッドの実装
+    /// 現在の u から全ての内積を計算し直します O(u の nnz log + 影響するアイテムの数)
+    /// 各内積の加算順序は f64_dot と同じ (index 昇順) なので、結果は f64_dot と一致します
+    /// ! : u の len が作成時と異なる場合、またはデフォルト値が 0 でない場合は panic
+    pub fn rebuild(&mut self, u: &DefaultSparseVec<T>) {
+        assert_same_len(self.len, u.len());
+        assert_zero_default(u);
+        self.scores.fill(0.0);
+        let (ind, val) = u.as_pairs();
+        for (&index, elem) in ind.iter().zip(val) {
+            let x = to_f64(elem);
+            for k in self.postings(index) {
+                let (item_id, value) = self.entries[k];
+                self.scores[item_id] += x * value;
+            }
+        }
+    }
+}
+
+impl<T> DotTracker<T> {
+    /// lenメソッドの実装
+    /// u とアイテムの len
+    #[must_use]
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// item_countメソッドの実装
+    /// アイテムの数
+    #[must_use]
+    #[inline(always)]
+    pub fn item_count(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// scoreメソッドの実装
+    /// item 番目のアイテムと u の現在の内積
+    /// - item がアイテムの数以上の場合は panic
+    #[must_use]
+    #[inline(always)]
+    pub fn score(&self, item: usize) -> f64 {
+        self.scores[item]
+    }
+
+    /// scoresメソッドの実装
+    /// 全てのアイテムの現在の内積 (アイテムの順番)
+    #[must_use]
+    #[inline(always)]
+    pub fn scores(&self) -> &[f64] {
+        &self.scores
+    }
+
+    /// postingsメソッドの実装
+    /// index を物理的に持つアイテムの (アイテム番号, 値) が入っている entries の範囲
+    #[inline(always)]
+    fn postings(&self, index: usize) -> Range<usize> {
+        match self.keys.binary_search(&index) {
+            Ok(k) => self.offsets[k]..self.offsets[k + 1],
+            Err(_) => 0..0,
+        }
+    }
+}
+
+/// to_f64関数の実装
+/// 内積の計算に使う f64 に変換します (f64_dot と同じく、変換できない場合は panic)
+#[inline(always)]
+fn to_f64<T: ToPrimitive>(elem: &T) -> f64 {
+    elem.to_f64().expect("value out of range for f64")
+}
+
+/// assert_zero_default関数の実装
+/// デフォルト値が 0 であることを確かめます
+#[inline(always)]
+fn assert_zero_default<T: Default + PartialEq + ToPrimitive>(vec: &DefaultSparseVec<T>) {
+    assert!(to_f64(vec.default_value()) == 0.0, "DotTracker requires vectors with a zero default");
+}
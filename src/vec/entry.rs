@@ -0,0 +1,190 @@
+use std::{
+    fmt::{self, Debug},
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut},
+};
+
+use super::default_sparse_vec::DefaultSparseVec;
+
+/// Entry列挙型の定義
+/// DefaultSparseVec::entry が返す、論理インデックス 1 つ分の場所
+/// - Occupied: 物理要素がある
+/// - Vacant: スパース分部 (デフォルト値)
+pub enum Entry<'a, T: Default + PartialEq> {
+    Occupied(OccupiedEntry<'a, T>),
+    Vacant(VacantEntry<'a, T>),
+}
+
+impl<'a, T: Default + PartialEq> Entry<'a, T> {
+    /// indexメソッドの実装
+    /// この Entry の論理インデックス
+    #[must_use]
+    #[inline(always)]
+    pub fn index(&self) -> usize {
+        match self {
+            Entry::Occupied(entry) => entry.index(),
+            Entry::Vacant(entry) => entry.index(),
+        }
+    }
+
+    /// or_insertメソッドの実装
+    /// Vacant なら elem を格納し、どちらの場合も OccupiedEntry を返します
+    #[inline(always)]
+    pub fn or_insert(self, elem: T) -> OccupiedEntry<'a, T> {
+        match self {
+            Entry::Occupied(entry) => entry,
+            Entry::Vacant(entry) => entry.insert(elem),
+        }
+    }
+
+    /// or_insert_withメソッドの実装
+    /// or_insert と同じですが、Vacant の場合だけ f を呼びます
+    #[inline(always)]
+    pub fn or_insert_with<F: FnOnce() -> T>(self, f: F) -> OccupiedEntry<'a, T> {
+        match self {
+            Entry::Occupied(entry) => entry,
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// and_modifyメソッドの実装
+    /// Occupied なら値に f を適用します (Vacant なら何もしません)
+    #[inline(always)]
+    pub fn and_modify<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// OccupiedEntry構造体の定義
+/// 物理要素がある場所 (Deref / DerefMut で値を読み書きできます)
+/// drop 時に値がデフォルト値 (SparsifyPolicy に従う) なら物理要素を取り除きます
+/// ! : mem::forget した場合は取り除かれず、デフォルト値の物理要素が残ることがあります
+pub struct OccupiedEntry<'a, T: Default + PartialEq> {
+    vec: &'a mut DefaultSparseVec<T>,
+    index: usize,
+    /// 物理位置
+    pos: usize,
+}
+
+impl<'a, T: Default + PartialEq> OccupiedEntry<'a, T> {
+    #[inline(always)]
+    pub(crate) fn new(vec: &'a mut DefaultSparseVec<T>, index: usize, pos: usize) -> Self {
+        OccupiedEntry { vec, index, pos }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn get(&self) -> &T {
+        &self.vec.as_pairs().1[self.pos]
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.vec.as_pairs_mut().1[self.pos]
+    }
+
+    /// insertメソッドの実装
+    /// 値を elem に置き換え、前の値を返します
+    /// - elem がデフォルト値なら drop 時に物理要素を取り除きます
+    #[inline(always)]
+    pub fn insert(&mut self, elem: T) -> T {
+        mem::replace(self.get_mut(), elem)
+    }
+
+    /// removeメソッドの実装
+    /// 物理要素を取り除き、その値を返します (以降はスパース分部になります)
+    #[inline(always)]
+    pub fn remove(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        let pos = this.pos;
+        this.vec.remove_at(pos)
+    }
+}
+
+impl<T: Default + PartialEq> Deref for OccupiedEntry<'_, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T: Default + PartialEq> DerefMut for OccupiedEntry<'_, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        self.get_mut()
+    }
+}
+
+impl<T: Default + PartialEq> Drop for OccupiedEntry<'_, T> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        if self.vec.is_default(self.get()) {
+            drop(self.vec.remove_at(self.pos));
+        }
+    }
+}
+
+/// VacantEntry構造体の定義
+/// 物理要素が無い (デフォルト値の) 場所
+pub struct VacantEntry<'a, T: Default + PartialEq> {
+    vec: &'a mut DefaultSparseVec<T>,
+    index: usize,
+}
+
+impl<'a, T: Default + PartialEq> VacantEntry<'a, T> {
+    #[inline(always)]
+    pub(crate) fn new(vec: &'a mut DefaultSparseVec<T>, index: usize) -> Self {
+        VacantEntry { vec, index }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// insertメソッドの実装
+    /// elem を物理要素として格納し (後ろの物理要素をずらします)、OccupiedEntry を返します
+    /// - 物理要素数の上限は CapPolicy に従います
+    /// - elem がデフォルト値なら、返した OccupiedEntry の drop 時に取り除きます
+    #[inline(always)]
+    pub fn insert(self, elem: T) -> OccupiedEntry<'a, T> {
+        let pos = self.vec.insert_vacant(self.index, elem);
+        OccupiedEntry::new(self.vec, self.index, pos)
+    }
+}
+
+impl<T: Default + PartialEq + Debug> Debug for Entry<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Occupied(entry) => f.debug_tuple("Entry").field(entry).finish(),
+            Entry::Vacant(entry) => f.debug_tuple("Entry").field(entry).finish(),
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Debug> Debug for OccupiedEntry<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("index", &self.index)
+            .field("value", self.get())
+            .finish()
+    }
+}
+
+impl<T: Default + PartialEq> Debug for VacantEntry<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VacantEntry").field("index", &self.index).finish()
+    }
+}
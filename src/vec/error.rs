@@ -0,0 +1,122 @@
+use std::{error::Error, fmt};
+
+use super::{cost::OpCost, format::FORMAT_VERSION};
+
+/// SparseVecError列挙型の定義
+/// スパースベクタの検査や fallible な操作で返すエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SparseVecError {
+    /// 物理要素の index が len 以上
+    IndexOutOfBounds { index: usize, len: usize },
+    /// 物理要素の index が狭義単調増加になっていない (position は物理位置)
+    UnsortedIndices { position: usize },
+    /// デフォルト値と等しい値が物理的に格納されている
+    StoredDefault { index: usize },
+    /// indices と values の要素数が一致しない
+    LengthMismatch { indices: usize, values: usize },
+    /// デシリアライズ時の上限 (DeserializeConfig) を超えた
+    LimitExceeded { field: &'static str, limit: usize },
+    /// set_max_nnz で設定した物理要素数の上限を超える
+    NnzCapExceeded { max_nnz: usize },
+    /// 読み込んだデータが形式に合わない
+    InvalidFormat { reason: &'static str },
+    /// 要求した容量のバイト数が isize::MAX を超える (len が usize::MAX に達した場合も含む)
+    CapacityOverflow,
+    /// アロケータが bytes バイトの確保に失敗した
+    AllocFailed { bytes: usize },
+    /// 読み込めないバージョン (新しい major) の形式
+    UnsupportedVersion { major: u8, minor: u8 },
+    /// 入力に同じ index が複数ある (DuplicatePolicy::Error)
+    DuplicateIndex { index: usize },
+    /// position 番目の入力のデフォルト値が一致しない (append_all)
+    DefaultMismatch { position: usize },
+    /// 2 本のベクタの物理要素の位置 (または len) が index で食い違う (SparsePairVec::zip)
+    SupportMismatch { index: usize },
+    /// index の物理要素を割る数が 0 (try_div_assign / try_rem_assign など)
+    DivisionByZero { index: usize },
+    /// cost_hint の見積もりが予算を超える (try_with_budget)
+    OverBudget { cost: OpCost, budget: OpCost },
+    /// 要素型の変換 (CastMode::Strict) で値を正確に表せない (index が None ならデフォルト値)
+    LossyCast { index: Option<usize> },
+    /// 2 本のベクタ (または mask) の len が一致しない
+    LenMismatch { len: usize, other: usize },
+}
+
+/// ValidationMode列挙型の定義
+/// validate でデフォルト値と等しい物理要素をどう扱うか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// エラー (SparseVecError::StoredDefault) にする
+    Strict,
+    /// 警告扱いにして、件数を Ok で返す
+    #[default]
+    Lenient,
+}
+
+impl fmt::Display for SparseVecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SparseVecError::IndexOutOfBounds { index, len } => {
+                write!(f, "stored index {index} out of bounds for len {len}")
+            }
+            SparseVecError::UnsortedIndices { position } => {
+                write!(f, "stored indices are not strictly increasing at position {position}")
+            }
+            SparseVecError::StoredDefault { index } => {
+                write!(f, "value equal to the default is stored at index {index}")
+            }
+            SparseVecError::LengthMismatch { indices, values } => {
+                write!(f, "{indices} indices but {values} values")
+            }
+            SparseVecError::LimitExceeded { field, limit } => {
+                write!(f, "{field} exceeds the configured limit of {limit}")
+            }
+            SparseVecError::NnzCapExceeded { max_nnz } => {
+                write!(f, "nnz cap of {max_nnz} exceeded")
+            }
+            SparseVecError::InvalidFormat { reason } => {
+                write!(f, "invalid format: {reason}")
+            }
+            SparseVecError::CapacityOverflow => {
+                write!(f, "capacity overflow")
+            }
+            SparseVecError::AllocFailed { bytes } => {
+                write!(f, "memory allocation of {bytes} bytes failed")
+            }
+            SparseVecError::UnsupportedVersion { major, minor } => {
+                write!(f, "format version {major}.{minor} is not supported (current is {FORMAT_VERSION})")
+            }
+            SparseVecError::DuplicateIndex { index } => {
+                write!(f, "index {index} appears more than once in the input")
+            }
+            SparseVecError::DefaultMismatch { position } => {
+                write!(f, "default value of input {position} does not match")
+            }
+            SparseVecError::SupportMismatch { index } => {
+                write!(f, "supports of the paired vectors differ at index {index}")
+            }
+            SparseVecError::DivisionByZero { index } => {
+                write!(f, "division by zero at index {index}")
+            }
+            SparseVecError::OverBudget { cost, budget } => {
+                write!(
+                    f,
+                    "estimated cost ({} bytes, {} entries) exceeds the budget ({} bytes, {} entries)",
+                    cost.bytes_moved, cost.entries_touched, budget.bytes_moved, budget.entries_touched
+                )
+            }
+            SparseVecError::LossyCast { index: Some(index) } => {
+                write!(f, "value at index {index} cannot be represented exactly in the target type")
+            }
+            SparseVecError::LossyCast { index: None } => {
+                write!(f, "default value cannot be represented exactly in the target type")
+            }
+            SparseVecError::LenMismatch { len, other } => {
+                write!(f, "length mismatch: len is {len} but the other len is {other}")
+            }
+        }
+    }
+}
+
+impl Error for SparseVecError {}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use super::error::SparseVecError;
+
+/// 現在のシリアライズ形式のバージョン
+/// - 1.0: serde は { len, default, indices, values }、圧縮形式のヘッダは MAGIC, major, TAG
+/// - 2.0: serde の先頭に version を追加、圧縮形式のヘッダに minor を追加
+pub const FORMAT_VERSION: FormatVersion = FormatVersion { major: 2, minor: 0 };
+
+/// FormatVersion構造体の定義
+/// シリアライズ形式 (serde と feature = "compress" の圧縮形式) のバージョン
+/// 書き出すデータのヘッダには常にこれを埋め込みます
+/// - major: 読み込み側が読めなくなる変更で増やします (新しい major のデータは読み込みません)
+/// - minor: 同じ major の読み込み側が読めるままの変更で増やします
+///
+/// 書き出したバイト列は同じバージョンの間は変わりません
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FormatVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+/// format_version関数の実装
+/// このクレートが書き出す形式のバージョン (FORMAT_VERSION)
+#[inline(always)]
+pub const fn format_version() -> FormatVersion {
+    FORMAT_VERSION
+}
+
+impl FormatVersion {
+    #[inline(always)]
+    pub const fn new(major: u8, minor: u8) -> Self {
+        FormatVersion { major, minor }
+    }
+
+    /// check_readableメソッドの実装
+    /// このバージョンのデータを読み込めるかどうか
+    /// - major が FORMAT_VERSION より新しい、または 0 の場合は UnsupportedVersion
+    #[inline(always)]
+    pub fn check_readable(self) -> Result<(), SparseVecError> {
+        if self.major == 0 || self.major > FORMAT_VERSION.major {
+            return Err(SparseVecError::UnsupportedVersion { major: self.major, minor: self.minor });
+        }
+        Ok(())
+    }
+}
+
+/// DeserializeConfig構造体の定義
+/// デシリアライズ時の上限
+/// - max_len: 論理的な長さ len の上限
+/// - max_nnz: 物理要素数 (indices / values の要素数) の上限
+/// - source_version: 入力を書き出した形式のバージョン
+///   None なら入力の version を読みます (map の形式で version が無い場合は 1.0 として読みます)
+///   seq の形式 (bincode など) の 1.x のデータは version を持たないので、Some(1.x) を指定して読みます
+///
+/// 上限を超える入力は、大きな領域を確保する前にエラーになります
+/// 信頼できない入力は seed() で作った DeserializeSeed (feature = "serde")、
+/// または read_compressed_with (feature = "compress") で読み込んでください
+/// (Deserialize の実装と read_compressed は上限無しの Default を使います)
+/// - 圧縮形式はヘッダに version を持つので、source_version は使いません
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializeConfig {
+    pub max_len: usize,
+    pub max_nnz: usize,
+    pub source_version: Option<FormatVersion>,
+}
+
+impl Default for DeserializeConfig {
+    #[inline(always)]
+    fn default() -> Self {
+        DeserializeConfig {
+            max_len: usize::MAX,
+            max_nnz: usize::MAX,
+            source_version: None,
+        }
+    }
+}
+
+impl DeserializeConfig {
+    #[inline(always)]
+    pub fn new(max_len: usize, max_nnz: usize) -> Self {
+        DeserializeConfig {
+            max_len,
+            max_nnz,
+            source_version: None,
+        }
+    }
+
+    /// upgrade_fromメソッドの実装
+    /// version の形式で書き出された入力を読み込む設定にします
+    #[inline(always)]
+    pub fn upgrade_from(self, version: FormatVersion) -> Self {
+        DeserializeConfig {
+            source_version: Some(version),
+            ..self
+        }
+    }
+
+    /// check_lenメソッドの実装
+    /// len が max_len を超える場合は LimitExceeded
+    #[cfg(any(feature = "serde", feature = "compress"))]
+    #[inline(always)]
+    pub(crate) fn check_len(&self, len: usize) -> Result<(), SparseVecError> {
+        if len > self.max_len {
+            return Err(SparseVecError::LimitExceeded { field: "len", limit: self.max_len });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FormatVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    fmt::{self, Debug},
+    mem,
+    ops::{Index, Range},
+};
+
+use super::{
+    default_sparse_vec::{coverage, DefaultSparseVec},
+    iter::Gaps,
+    normal_vec_trait::NormalVecMethods,
+    vec_trait::EdgeMode,
+};
+
+/// GapSparseVec構造体の定義
+/// 途中への insert / remove が多い用途向けの、物理要素にギャップ (空き) を持つスパースベクタ
+/// 物理要素をギャップの前 (front) と後ろ (back) の 2 つの配列に分けて持ちます
+/// - front: ギャップより前の物理要素 (論理インデックスの昇順)
+/// - back: ギャップより後ろの物理要素 (ギャップに近い順 = 論理インデックスの降順)
+/// - back のインデックスは shift を足すと論理インデックスになります
+///
+/// 編集位置までギャップを動かし (動かした距離だけ要素を移動)、後続のインデックスは shift の 1 回の加減算でずらします
+/// 近い位置に続けて insert / remove する場合は 1 回あたり償却 O(1) です (DefaultSparseVec は O(nnz) の移動)
+/// 読み込みは 2 つの配列をそれぞれ二分探索するので O(log nnz) のままです
+pub struct GapSparseVec<T: Default + PartialEq> {
+    front_ind: Vec<usize>,
+    front_val: Vec<T>,
+    back_ind: Vec<usize>,
+    back_val: Vec<T>,
+    /// back のインデックスに足す値 (wrapping)
+    shift: usize,
+    len: usize,
+    default: T,
+}
+
+impl<T: Default + PartialEq> GapSparseVec<T> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_default(T::default())
+    }
+
+    /// with_defaultメソッドの実装
+    /// スパース分部の値を default にした空のベクタを作ります
+    #[inline(always)]
+    pub fn with_default(default: T) -> Self {
+        GapSparseVec {
+            front_ind: Vec::new(),
+            front_val: Vec::new(),
+            back_ind: Vec::new(),
+            back_val: Vec::new(),
+            shift: 0,
+            len: 0,
+            default,
+        }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// nnzメソッドの実装
+    /// 物理要素数
+    #[must_use]
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        self.front_ind.len() + self.back_ind.len()
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    /// getメソッドの実装
+    /// スパース分部はデフォルト値への参照を返します O(log nnz)
+    #[must_use]
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        if let Ok(i) = self.front_ind.binary_search(&index) {
+            return Some(&self.front_val[i]);
+        }
+        match self.back_position(index) {
+            Ok(i) => Some(&self.back_val[i]),
+            Err(_) => Some(&self.default),
+        }
+    }
+
+    /// get_with_edgeメソッドの実装
+    /// index が範囲外 (負、または len 以上) の場合に mode に従って読みます O(log nnz)
+    /// (EdgeMode::Default ならデフォルト値、len が 0 ならどのモードでもデフォルト値)
+    #[must_use]
+    #[inline(always)]
+    pub fn get_with_edge(&self, index: isize, mode: EdgeMode) -> &T {
+        match mode.resolve(index, self.len) {
+            Some(index) => self.get(index).unwrap(),
+            None => &self.default,
+        }
+    }
+
+    /// iterメソッドの実装
+    /// 物理要素の (index, 値) を index の昇順で返します
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        let shift = self.shift;
+        self.front_ind
+            .iter()
+            .copied()
+            .zip(&self.front_val)
+            .chain(self.back_ind.iter().rev().map(move |&i| i.wrapping_add(shift)).zip(self.back_val.iter().rev()))
+    }
+
+    /// iter_denseメソッドの実装
+    /// スパース分部を含む全要素を論理インデックス順に返します
+    #[inline(always)]
+    pub fn iter_dense(&self) -> impl Iterator<Item = &T> {
+        let mut stored = self.iter().peekable();
+        (0..self.len).map(move |i| match stored.next_if(|(index, _)| *index == i) {
+            Some((_, elem)) => elem,
+            None => &self.default,
+        })
+    }
+
+    /// gapsメソッドの実装
+    /// 物理要素が無い (デフォルト値の) 論理インデックスの極大な範囲を昇順に返します O(nnz)
+    /// (物理要素の配列の「ギャップ」ではなく、DefaultSparseVec::gaps と同じ論理インデックスの範囲です)
+    #[inline(always)]
+    pub fn gaps(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        Gaps::new(self.iter().map(|(index, _)| index), self.len)
+    }
+
+    /// largest_gapメソッドの実装
+    /// gaps のうち最も長い範囲 (同じ長さなら前のもの)、範囲が無ければ None O(nnz)
+    #[must_use]
+    #[inline(always)]
+    pub fn largest_gap(&self) -> Option<Range<usize>> {
+        Gaps::new(self.iter().map(|(index, _)| index), self.len).largest()
+    }
+
+    /// coverageメソッドの実装
+    /// 1 - (gaps の長さの合計 / len) (len が 0 なら 1.0) O(1)
+    #[must_use]
+    #[inline(always)]
+    pub fn coverage(&self) -> f64 {
+        coverage(self.len, self.nnz())
+    }
+
+    /// pushメソッドの実装
+    /// 末尾に追加します (ギャップを末尾へ動かします)
+    #[inline(always)]
+    pub fn push(&mut self, elem: T) {
+        self.insert(self.len, elem);
+    }
+
+    /// insertメソッドの実装
+    /// index 番目に割り込ませます (Vec::insert と同じ意味)
+    /// ギャップを index へ動かし、後続のインデックスは shift で +1 します
+    /// - elem がデフォルト値なら物理領域には書き込みません
+    /// - index が len より大きい場合は panic
+    pub fn insert(&mut self, index: usize, elem: T) {
+        assert!(index <= self.len, "insertion index (is {index}) should be <= len (is {})", self.len);
+        self.move_gap(index);
+        self.len += 1;
+        self.shift = self.shift.wrapping_add(1);
+        if elem != self.default {
+            self.front_ind.push(index);
+            self.front_val.push(elem);
+        }
+    }
+
+    /// remove_storedメソッドの実装
+    /// index 番目を取り除き、後続を前に詰めます
+    /// 物理的に存在した場合はその値、スパース分部なら None を返します (デフォルト値を clone しません)
+    /// - index が len 以上の場合は panic
+    pub fn remove_stored(&mut self, index: usize) -> Option<T> {
+        assert!(index < self.len, "removal index (is {index}) should be < len (is {})", self.len);
+        self.move_gap(index);
+        let removed = if self.after_gap() == Some(index) {
+            self.back_ind.pop();
+            self.back_val.pop()
+        } else {
+            None
+        };
+        self.len -= 1;
+        self.shift = self.shift.wrapping_sub(1);
+        removed
+    }
+
+    /// move_gapメソッドの実装
+    /// front が index 未満、back が index 以上の物理要素になるようにギャップを動かします O(動かす距離)
+    fn move_gap(&mut self, index: usize) {
+        while self.front_ind.last().is_some_and(|&i| i >= index) {
+            let (i, elem) = (self.front_ind.pop().unwrap(), self.front_val.pop().unwrap());
+            self.back_ind.push(i.wrapping_sub(self.shift));
+            self.back_val.push(elem);
+        }
+        while self.after_gap().is_some_and(|i| i < index) {
+            let (i, elem) = (self.back_ind.pop().unwrap(), self.back_val.pop().unwrap());
+            self.front_ind.push(i.wrapping_add(self.shift));
+            self.front_val.push(elem);
+        }
+    }
+
+    /// after_gapメソッドの実装
+    /// ギャップの直後 (back の末尾) の物理要素の論理インデックス (back が空なら None)
+    #[inline(always)]
+    fn after_gap(&self) -> Option<usize> {
+        self.back_ind.last().map(|&index| index.wrapping_add(self.shift))
+    }
+
+    /// back_positionメソッドの実装
+    /// back から論理インデックス index を二分探索します (back は降順)
+    #[inline(always)]
+    fn back_position(&self, index: usize) -> Result<usize, usize> {
+        let shift = self.shift;
+        self.back_ind.binary_search_by(|&i| index.cmp(&i.wrapping_add(shift)))
+    }
+}
+
+impl<T: Default + PartialEq + Clone> GapSparseVec<T> {
+    /// popメソッドの実装
+    /// 空なら None、スパース分部ならデフォルト値の clone を返します
+    #[inline(always)]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        Some(self.remove(self.len - 1))
+    }
+
+    /// removeメソッドの実装
+    /// remove_stored と同じですが、スパース分部ならデフォルト値の clone を返します
+    #[inline(always)]
+    pub fn remove(&mut self, index: usize) -> T {
+        self.remove_stored(index).unwrap_or_else(|| self.default.clone())
+    }
+
+    /// setメソッドの実装
+    /// index 番目を elem にして、前の論理的な値を返します (DefaultSparseVec::set と同じ意味)
+    /// ギャップを index へ動かしてから書き込みます
+    /// - index が len 以上の場合は panic
+    pub fn set(&mut self, index: usize, elem: T) -> T {
+        assert!(index < self.len, "index out of bounds: the len is {} but the index is {index}", self.len);
+        self.move_gap(index);
+        let stored = self.after_gap() == Some(index);
+        match (stored, elem == self.default) {
+            (true, true) => {
+                self.back_ind.pop();
+                self.back_val.pop().unwrap()
+            }
+            (true, false) => mem::replace(self.back_val.last_mut().unwrap(), elem),
+            (false, true) => self.default.clone(),
+            (false, false) => {
+                self.front_ind.push(index);
+                self.front_val.push(elem);
+                self.default.clone()
+            }
+        }
+    }
+}
+
+impl<T: Default + PartialEq> Default for GapSparseVec<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default + PartialEq + Clone> Clone for GapSparseVec<T> {
+    fn clone(&self) -> Self {
+        GapSparseVec {
+            front_ind: self.front_ind.clone(),
+            front_val: self.front_val.clone(),
+            back_ind: self.back_ind.clone(),
+            back_val: self.back_val.clone(),
+            shift: self.shift,
+            len: self.len,
+            default: self.default.clone(),
+        }
+    }
+}
+
+impl<T: Default + PartialEq> Index<usize> for GapSparseVec<T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn index(&self, index: usize) -> &Self::Output {
+        let len = self.len;
+        self.get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the len is {len} but the index is {index}"))
+    }
+}
+
+/// 物理領域の値をそのまま移します O(nnz) (格納されたデフォルト値は取り除きます)
+/// 全ての物理要素は front に入り、ギャップは末尾になります
+impl<T: Default + PartialEq> From<DefaultSparseVec<T>> for GapSparseVec<T> {
+    fn from(svec: DefaultSparseVec<T>) -> Self {
+        let (len, default, indices, values) = svec.into_parts();
+        let (front_ind, front_val) = indices.into_iter().zip(values).filter(|(_, elem)| *elem != default).unzip();
+        GapSparseVec {
+            front_ind,
+            front_val,
+            back_ind: Vec::new(),
+            back_val: Vec::new(),
+            shift: 0,
+            len,
+            default,
+        }
+    }
+}
+
+/// ギャップを末尾へ動かしてから、物理領域の値をそのまま移します O(nnz)
+impl<T: Default + PartialEq> From<GapSparseVec<T>> for DefaultSparseVec<T> {
+    fn from(mut gvec: GapSparseVec<T>) -> Self {
+        gvec.move_gap(gvec.len);
+        DefaultSparseVec::from_parts(gvec.len, gvec.default, gvec.front_ind, gvec.front_val)
+            .expect("GapSparseVec keeps indices sorted and in bounds")
+    }
+}
+
+impl<T: Default + PartialEq + Clone> NormalVecMethods<T> for GapSparseVec<T> {
+    #[inline(always)]
+    fn n_push(&mut self, elem: T) {
+        self.push(elem);
+    }
+
+    #[inline(always)]
+    fn n_pop(&mut self) -> Option<T> {
+        self.pop()
+    }
+
+    #[inline(always)]
+    fn n_insert(&mut self, index: usize, elem: T) {
+        self.insert(index, elem);
+    }
+
+    #[inline(always)]
+    fn n_remove(&mut self, index: usize) -> T {
+        self.remove(index)
+    }
+
+    #[inline(always)]
+    fn n_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.iter_dense())
+    }
+}
+
+/// DefaultSparseVec と同じ表示 (密な形)
+impl<T: Default + PartialEq + Debug> Debug for GapSparseVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter_dense()).finish()
+    }
+}
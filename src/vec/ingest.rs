@@ -0,0 +1,157 @@
+use std::{fmt, ops::AddAssign};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use super::{default_sparse_vec::DefaultSparseVec, error::SparseVecError};
+
+/// DuplicatePolicy列挙型の定義
+/// ingest で同じ index が複数ある場合の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// 値を全て足す (入力順)
+    Sum,
+    /// 入力で最初のものを残す
+    First,
+    /// 入力で最後のものを残す (FromIterator<(usize, T)> と同じ)
+    #[default]
+    Last,
+    /// エラー (SparseVecError::DuplicateIndex) にする
+    Error,
+}
+
+/// OutOfRangePolicy列挙型の定義
+/// ingest で index が len 以上の場合の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfRangePolicy {
+    /// 捨てる
+    #[default]
+    Drop,
+    /// エラー (SparseVecError::IndexOutOfBounds) にする
+    Error,
+    /// len を最大の index + 1 に伸ばして残す
+    GrowLen,
+}
+
+/// IngestPolicy構造体の定義
+/// ingest の入力の扱い (既定は重複が Last、範囲外が Drop)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IngestPolicy {
+    pub duplicates: DuplicatePolicy,
+    pub out_of_range: OutOfRangePolicy,
+}
+
+impl IngestPolicy {
+    #[inline(always)]
+    pub fn new(duplicates: DuplicatePolicy, out_of_range: OutOfRangePolicy) -> Self {
+        IngestPolicy { duplicates, out_of_range }
+    }
+}
+
+/// IngestReport構造体の定義
+/// ingest の結果の件数 (データ品質の記録用)
+/// - pairs: 入力の (index, value) の数
+/// - duplicates_collapsed: 同じ index にまとめられて消えた数 (同じ index が k 個なら k - 1)
+/// - out_of_range_dropped: len 以上で捨てた数
+/// - out_of_range_kept: len 以上で、len を伸ばして残した数 (OutOfRangePolicy::GrowLen)
+/// - defaults_skipped: 重複をまとめた結果がデフォルト値で、格納しなかった index の数
+/// - stored: 格納した物理要素数 (= 結果の nnz)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct IngestReport {
+    pub pairs: usize,
+    pub duplicates_collapsed: usize,
+    pub out_of_range_dropped: usize,
+    pub out_of_range_kept: usize,
+    pub defaults_skipped: usize,
+    pub stored: usize,
+}
+
+impl fmt::Display for IngestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pairs: {}, stored: {}, duplicates collapsed: {}, out of range dropped: {}, out of range kept: {}, defaults skipped: {}",
+            self.pairs,
+            self.stored,
+            self.duplicates_collapsed,
+            self.out_of_range_dropped,
+            self.out_of_range_kept,
+            self.defaults_skipped
+        )
+    }
+}
+
+impl<T: Default + PartialEq + AddAssign> DefaultSparseVec<T> {
+    /// ingestメソッドの実装
+    /// 外部の COO データ (未ソート・重複・範囲外を含みうる) から作成し、何を直したかの件数を返します
+    /// 重複と範囲外の扱いは policy に従います (1 回のソートと 1 回の走査 O(n log n))
+    /// - 入力が既に index の昇順ならソートしません
+    /// - policy がエラーにする入力がある場合は panic (try_ingest はエラーを返します)
+    pub fn ingest<I>(len: usize, pairs: I, policy: IngestPolicy) -> (Self, IngestReport)
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        Self::try_ingest(len, pairs, policy).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// try_ingestメソッドの実装
+    /// ingest の panic しない版
+    /// - DuplicatePolicy::Error で重複がある: DuplicateIndex (最も小さい重複した index)
+    /// - OutOfRangePolicy::Error で範囲外がある: IndexOutOfBounds (入力で最初の範囲外の index)
+    pub fn try_ingest<I>(len: usize, pairs: I, policy: IngestPolicy) -> Result<(Self, IngestReport), SparseVecError>
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        let mut report = IngestReport::default();
+        let mut len = len;
+        let input_len = len;
+        let pairs = pairs.into_iter();
+        let mut kept = Vec::with_capacity(pairs.size_hint().0);
+        for (index, elem) in pairs {
+            report.pairs += 1;
+            if index >= input_len {
+                match policy.out_of_range {
+                    OutOfRangePolicy::Drop => {
+                        report.out_of_range_dropped += 1;
+                        continue;
+                    }
+                    OutOfRangePolicy::Error => {
+                        return Err(SparseVecError::IndexOutOfBounds { index, len: input_len });
+                    }
+                    OutOfRangePolicy::GrowLen => {
+                        report.out_of_range_kept += 1;
+                        len = len.max(index.checked_add(1).ok_or(SparseVecError::CapacityOverflow)?);
+                    }
+                }
+            }
+            kept.push((index, elem));
+        }
+        if !kept.is_sorted_by_key(|(index, _)| *index) {
+            // 安定ソートなので、同じ index の中では入力順のまま
+            kept.sort_by_key(|(index, _)| *index);
+        }
+
+        let default = T::default();
+        let mut entries = Vec::with_capacity(kept.len());
+        let mut kept = kept.into_iter().peekable();
+        while let Some((index, mut elem)) = kept.next() {
+            while let Some((_, next)) = kept.next_if(|(next, _)| *next == index) {
+                report.duplicates_collapsed += 1;
+                match policy.duplicates {
+                    DuplicatePolicy::Sum => elem += next,
+                    DuplicatePolicy::First => {}
+                    DuplicatePolicy::Last => elem = next,
+                    DuplicatePolicy::Error => return Err(SparseVecError::DuplicateIndex { index }),
+                }
+            }
+            if elem == default {
+                report.defaults_skipped += 1;
+            } else {
+                entries.push((index, elem));
+            }
+        }
+        report.stored = entries.len();
+        Ok((Self::from_sorted_iter_unchecked(len, entries), report))
+    }
+}
@@ -0,0 +1,537 @@
+use std::{iter::FusedIterator, marker::PhantomData, ops::Range, ptr, slice};
+#[cfg(debug_assertions)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::default_sparse_vec::{DefaultSparseVec, RawDefaultSparseVec};
+
+/// ModCheck構造体の定義 (crate 内部用)
+/// 借用するイテレータが作成時のベクタの変更回数を覚え、要素を返すたびに変わっていないことを確かめます
+/// - debug ビルドのみ、release ビルドでは何も持たず check も空です
+/// - 安全なコードでは借用規則により起こりません。生ポインタや unsafe な取得 (as_pairs_mut など) 経由の変更を検出します
+#[derive(Clone, Copy)]
+pub(crate) struct ModCheck<'a> {
+    #[cfg(debug_assertions)]
+    counter: Option<&'a AtomicUsize>,
+    #[cfg(debug_assertions)]
+    seen: usize,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> ModCheck<'a> {
+    #[cfg(debug_assertions)]
+    #[inline(always)]
+    pub(crate) fn new(counter: &'a AtomicUsize) -> Self {
+        ModCheck {
+            seen: counter.load(Ordering::Relaxed),
+            counter: Some(counter),
+            _marker: PhantomData,
+        }
+    }
+
+    /// 変更回数を持たない (何も確かめない) ModCheck
+    #[inline(always)]
+    pub(crate) fn none() -> Self {
+        ModCheck {
+            #[cfg(debug_assertions)]
+            counter: None,
+            #[cfg(debug_assertions)]
+            seen: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// ! : 作成後にベクタが変更されていれば panic します (debug ビルドのみ)
+    #[inline(always)]
+    pub(crate) fn check(&self) {
+        #[cfg(debug_assertions)]
+        if let Some(counter) = self.counter {
+            assert!(
+                counter.load(Ordering::Relaxed) == self.seen,
+                "vector mutated during iteration"
+            );
+        }
+    }
+}
+
+/// IterDense構造体の定義
+/// DefaultSparseVec の全要素を論理インデックス順に &T で返すイテレータ
+/// - スパース分部はデフォルト値への参照を返します
+/// - 残りの要素数は常に正確なので、collect は 1 回の確保で済みます
+pub struct IterDense<'a, T> {
+    ind: &'a [usize],
+    val: &'a [T],
+    default: &'a T,
+    /// 次に返す論理インデックス (前 / 後ろ、後ろは含まない)
+    front: usize,
+    back: usize,
+    mods: ModCheck<'a>,
+}
+
+/// Iter型の定義
+/// `&DefaultSparseVec<T>` の IntoIterator (for ループ) が返すイテレータの別名 (IterDense と同じ型)
+pub type Iter<'a, T> = IterDense<'a, T>;
+
+/// DenseIter型の定義
+/// iter_dense が返すイテレータの別名 (IterDense と同じ型)
+pub type DenseIter<'a, T> = IterDense<'a, T>;
+
+impl<'a, T> IterDense<'a, T> {
+    #[inline(always)]
+    pub(crate) fn new(len: usize, ind: &'a [usize], val: &'a [T], default: &'a T, mods: ModCheck<'a>) -> Self {
+        IterDense {
+            ind,
+            val,
+            default,
+            front: 0,
+            back: len,
+            mods,
+        }
+    }
+}
+
+impl<'a, T> Iterator for IterDense<'a, T> {
+    type Item = &'a T;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.mods.check();
+        if self.front == self.back {
+            return None;
+        }
+        let index = self.front;
+        self.front += 1;
+        match self.ind.first() {
+            Some(&stored) if stored == index => {
+                let elem = &self.val[0];
+                self.ind = &self.ind[1..];
+                self.val = &self.val[1..];
+                Some(elem)
+            }
+            _ => Some(self.default),
+        }
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterDense<'_, T> {
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.mods.check();
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        match self.ind.last() {
+            Some(&stored) if stored == self.back => {
+                let last = self.val.len() - 1;
+                let elem = &self.val[last];
+                self.ind = &self.ind[..last];
+                self.val = &self.val[..last];
+                Some(elem)
+            }
+            _ => Some(self.default),
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for IterDense<'_, T> {}
+
+impl<T> FusedIterator for IterDense<'_, T> {}
+
+/// SparseIter構造体の定義
+/// DefaultSparseVec::iter が返す、物理要素の (index, &値) を index の昇順で返すイテレータ
+/// - スパース分部を含みません (長さは nnz)
+/// - nth / last / 後ろからの取り出しは O(1) です
+#[derive(Clone)]
+pub struct SparseIter<'a, T> {
+    ind: slice::Iter<'a, usize>,
+    val: slice::Iter<'a, T>,
+    mods: ModCheck<'a>,
+}
+
+impl<'a, T> SparseIter<'a, T> {
+    #[inline(always)]
+    pub(crate) fn new(ind: &'a [usize], val: &'a [T], mods: ModCheck<'a>) -> Self {
+        SparseIter {
+            ind: ind.iter(),
+            val: val.iter(),
+            mods,
+        }
+    }
+}
+
+impl<'a, T> Iterator for SparseIter<'a, T> {
+    type Item = (usize, &'a T);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.mods.check();
+        Some((*self.ind.next()?, self.val.next()?))
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ind.size_hint()
+    }
+
+    #[inline(always)]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.mods.check();
+        Some((*self.ind.nth(n)?, self.val.nth(n)?))
+    }
+
+    #[inline(always)]
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+
+    #[inline(always)]
+    fn count(self) -> usize {
+        self.ind.len()
+    }
+}
+
+impl<T> DoubleEndedIterator for SparseIter<'_, T> {
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.mods.check();
+        Some((*self.ind.next_back()?, self.val.next_back()?))
+    }
+
+    #[inline(always)]
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        self.mods.check();
+        Some((*self.ind.nth_back(n)?, self.val.nth_back(n)?))
+    }
+}
+
+impl<T> ExactSizeIterator for SparseIter<'_, T> {}
+
+impl<T> FusedIterator for SparseIter<'_, T> {}
+
+/// SparseIterMut構造体の定義
+/// DefaultSparseVec::iter_mut が返す、物理要素の (index, &mut 値) を index の昇順で返すイテレータ
+/// - index は値で返すので、インデックス配列は書き換えられません
+/// - 値をデフォルト値にしても物理要素は残ります (as_pairs_mut と同じ)
+pub struct SparseIterMut<'a, T> {
+    ind: slice::Iter<'a, usize>,
+    val: slice::IterMut<'a, T>,
+    mods: ModCheck<'a>,
+}
+
+impl<'a, T> SparseIterMut<'a, T> {
+    #[inline(always)]
+    pub(crate) fn new(ind: &'a [usize], val: &'a mut [T], mods: ModCheck<'a>) -> Self {
+        SparseIterMut {
+            ind: ind.iter(),
+            val: val.iter_mut(),
+            mods,
+        }
+    }
+}
+
+impl<'a, T> Iterator for SparseIterMut<'a, T> {
+    type Item = (usize, &'a mut T);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.mods.check();
+        Some((*self.ind.next()?, self.val.next()?))
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ind.size_hint()
+    }
+
+    #[inline(always)]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.mods.check();
+        Some((*self.ind.nth(n)?, self.val.nth(n)?))
+    }
+
+    #[inline(always)]
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+
+    #[inline(always)]
+    fn count(self) -> usize {
+        self.ind.len()
+    }
+}
+
+impl<T> DoubleEndedIterator for SparseIterMut<'_, T> {
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.mods.check();
+        Some((*self.ind.next_back()?, self.val.next_back()?))
+    }
+
+    #[inline(always)]
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        self.mods.check();
+        Some((*self.ind.nth_back(n)?, self.val.nth_back(n)?))
+    }
+}
+
+impl<T> ExactSizeIterator for SparseIterMut<'_, T> {}
+
+impl<T> FusedIterator for SparseIterMut<'_, T> {}
+
+/// IntoIter構造体の定義
+/// DefaultSparseVec の全要素を論理インデックス順に所有権ごと返すイテレータ
+/// - 物理領域の所有権を持ちます (std の vec::IntoIter と同じく、元のベクタを借用しません)
+/// - スパース分部はデフォルト値の clone を返します
+/// - 途中で drop した場合、まだ返していない物理要素はちょうど 1 回ずつ drop されます
+/// - mem::forget した場合は残りの要素と領域がリークするだけで、二重解放は起きません
+pub struct IntoIter<T: Clone> {
+    buf: RawDefaultSparseVec<T>,
+    default: T,
+    /// まだ返していない物理位置の範囲 (後ろは含まない)
+    phys_front: usize,
+    phys_back: usize,
+    /// まだ返していない論理インデックスの範囲 (後ろは含まない)
+    front: usize,
+    back: usize,
+}
+
+impl<T: Clone> IntoIter<T> {
+    #[inline(always)]
+    pub(crate) fn new(buf: RawDefaultSparseVec<T>, raw_len: usize, len: usize, default: T) -> Self {
+        IntoIter {
+            buf,
+            default,
+            phys_front: 0,
+            phys_back: raw_len,
+            front: 0,
+            back: len,
+        }
+    }
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = T;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let index = self.front;
+        self.front += 1;
+        if self.phys_front < self.phys_back && unsafe { *self.buf.ind_ptr().add(self.phys_front) } == index {
+            self.phys_front += 1;
+            Some(unsafe { ptr::read(self.buf.val_ptr().add(self.phys_front - 1)) })
+        } else {
+            Some(self.default.clone())
+        }
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for IntoIter<T> {
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        if self.phys_front < self.phys_back && unsafe { *self.buf.ind_ptr().add(self.phys_back - 1) } == self.back {
+            self.phys_back -= 1;
+            Some(unsafe { ptr::read(self.buf.val_ptr().add(self.phys_back)) })
+        } else {
+            Some(self.default.clone())
+        }
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for IntoIter<T> {}
+
+impl<T: Clone> FusedIterator for IntoIter<T> {}
+
+impl<T: Clone> Drop for IntoIter<T> {
+    /// まだ返していない物理要素を drop します (領域は buf の drop で解放)
+    fn drop(&mut self) {
+        let remaining = self.phys_back - self.phys_front;
+        // drop 中に panic しても同じ要素を再び drop しないよう、先に範囲を空にしておく
+        self.phys_front = self.phys_back;
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                self.buf.val_ptr().add(self.phys_back - remaining),
+                remaining,
+            ));
+        }
+    }
+}
+
+/// Drain構造体の定義
+/// DefaultSparseVec::drain が返す、取り除いた論理範囲の要素を論理インデックス順に所有権ごと返すイテレータ
+/// - スパース分部はデフォルト値の clone を返します
+/// - drop 時にまだ返していない物理要素を drop し、後ろの要素を前に詰めます
+/// - mem::forget した場合、ベクタは範囲の始まりで切り詰められたままになります (リークするだけで壊れません)
+pub struct Drain<'a, T: Default + PartialEq + Clone> {
+    vec: &'a mut DefaultSparseVec<T>,
+    ind: *const usize,
+    val: *mut T,
+    /// まだ返していない物理位置の範囲 (後ろは含まない)
+    phys_front: usize,
+    phys_back: usize,
+    /// まだ返していない論理インデックスの範囲 (後ろは含まない)
+    front: usize,
+    back: usize,
+    /// drop 時に前へ詰める物理要素の範囲の始まり (range の後ろの最初の物理位置)
+    tail_start: usize,
+    /// 取り除く前の物理要素数
+    raw_len: usize,
+    /// 取り除く論理要素数
+    removed: usize,
+    new_len: usize,
+}
+
+impl<'a, T: Default + PartialEq + Clone> Drain<'a, T> {
+    #[inline(always)]
+    pub(crate) fn new(
+        vec: &'a mut DefaultSparseVec<T>,
+        ind: *const usize,
+        val: *mut T,
+        range: Range<usize>,
+        phys: Range<usize>,
+        raw_len: usize,
+        new_len: usize,
+    ) -> Self {
+        Drain {
+            vec,
+            ind,
+            val,
+            phys_front: phys.start,
+            phys_back: phys.end,
+            front: range.start,
+            back: range.end,
+            tail_start: phys.end,
+            raw_len,
+            removed: range.end - range.start,
+            new_len,
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Clone> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let index = self.front;
+        self.front += 1;
+        if self.phys_front < self.phys_back && unsafe { *self.ind.add(self.phys_front) } == index {
+            self.phys_front += 1;
+            Some(unsafe { ptr::read(self.val.add(self.phys_front - 1)) })
+        } else {
+            Some(self.vec.default_value().clone())
+        }
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: Default + PartialEq + Clone> DoubleEndedIterator for Drain<'_, T> {
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        if self.phys_front < self.phys_back && unsafe { *self.ind.add(self.phys_back - 1) } == self.back {
+            self.phys_back -= 1;
+            Some(unsafe { ptr::read(self.val.add(self.phys_back)) })
+        } else {
+            Some(self.vec.default_value().clone())
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Clone> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T: Default + PartialEq + Clone> FusedIterator for Drain<'_, T> {}
+
+impl<T: Default + PartialEq + Clone> Drop for Drain<'_, T> {
+    /// まだ返していない物理要素を drop してから、後ろの要素を前に詰めます
+    /// (drop が panic した場合は詰めずに、範囲の始まりで切り詰められたままになります)
+    fn drop(&mut self) {
+        let remaining = self.phys_back - self.phys_front;
+        // drop 中に panic しても同じ要素を再び drop しないよう、先に範囲を空にしておく
+        self.phys_front = self.phys_back;
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                self.val.add(self.phys_back - remaining),
+                remaining,
+            ));
+        }
+        self.vec.close_drain(self.tail_start, self.raw_len, self.removed, self.new_len);
+    }
+}
+
+/// Gaps構造体の定義
+/// gaps メソッドの本体 (物理要素が無い論理インデックスの極大な範囲を昇順に返します)
+/// 物理要素の index (昇順) を 1 回走査するだけなので O(nnz)
+pub(crate) struct Gaps<I> {
+    indices: I,
+    /// 次の範囲の始まりの候補 (直前の物理要素の index + 1)
+    next: usize,
+    len: usize,
+}
+
+impl<I: Iterator<Item = usize>> Gaps<I> {
+    #[inline(always)]
+    pub(crate) fn new(indices: I, len: usize) -> Self {
+        Gaps { indices, next: 0, len }
+    }
+
+    /// largestメソッドの実装
+    /// 最も長い範囲 (同じ長さなら前のもの)、範囲が無ければ None
+    #[inline(always)]
+    pub(crate) fn largest(self) -> Option<Range<usize>> {
+        self.fold(None, |largest: Option<Range<usize>>, gap| match largest {
+            Some(largest) if largest.len() >= gap.len() => Some(largest),
+            _ => Some(gap),
+        })
+    }
+}
+
+impl<I: Iterator<Item = usize>> Iterator for Gaps<I> {
+    type Item = Range<usize>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        for index in self.indices.by_ref() {
+            let start = self.next;
+            self.next = index + 1;
+            if index > start {
+                return Some(start..index);
+            }
+        }
+        if self.next < self.len {
+            let start = self.next;
+            self.next = self.len;
+            return Some(start..self.len);
+        }
+        None
+    }
+}
@@ -0,0 +1,98 @@
+/// sparse_vec! マクロ
+/// vec! と同じ感覚で DefaultSparseVec を作ります
+/// - `sparse_vec![0, 0, 5, 0, 7]`: 密なリテラル (デフォルト値は物理領域に入りません)
+/// - `sparse_vec![len = 100; 3 => 5, 42 => 7]`: 長さと (index => 値) の組
+/// - `sparse_vec![default = -1; len = 10; 2 => 9]`: デフォルト値を指定
+///
+/// 物理要素数ちょうどの領域を一度だけ確保し、push / insert の繰り返しにはなりません
+/// index が狭義単調増加でない、または len 以上の場合は実行時に panic します
+///
+/// 密なリテラル
+/// ```
+/// use vec_plus::sparse_vec;
+///
+/// let vec = sparse_vec![0, 0, 5, 0, 7];
+/// assert_eq!((vec.len(), vec.nnz()), (5, 2));
+/// assert_eq!(vec.to_dense(), [0, 0, 5, 0, 7]);
+/// ```
+///
+/// 長さと (index => 値) の組
+/// ```
+/// use vec_plus::sparse_vec;
+///
+/// let vec = sparse_vec![len = 100; 3 => 5, 42 => 7];
+/// assert_eq!((vec.len(), vec.nnz()), (100, 2));
+/// assert_eq!((vec[3], vec[42], vec[50]), (5, 7, 0));
+/// ```
+///
+/// デフォルト値の指定 (デフォルト値と等しい値は物理領域に入りません)
+/// ```
+/// use vec_plus::sparse_vec;
+///
+/// let vec = sparse_vec![default = -1; len = 10; 2 => 9, 5 => -1];
+/// assert_eq!((vec.default_value(), vec.nnz()), (&-1, 1));
+/// assert_eq!(vec.to_dense(), [-1, -1, 9, -1, -1, -1, -1, -1, -1, -1]);
+/// ```
+///
+/// index が狭義単調増加でない場合は panic
+/// ```should_panic
+/// use vec_plus::sparse_vec;
+///
+/// let _ = sparse_vec![len = 10; 5 => 1, 2 => 1];
+/// ```
+#[macro_export]
+macro_rules! sparse_vec {
+    (default = $default:expr; len = $len:expr; $($index:expr => $value:expr),* $(,)?) => {
+        $crate::vec::default_sparse_vec::DefaultSparseVec::__from_macro_parts(
+            $len,
+            $default,
+            ::std::vec![$($index),*],
+            ::std::vec![$($value),*],
+        )
+    };
+    (len = $len:expr; $($index:expr => $value:expr),* $(,)?) => {
+        $crate::vec::default_sparse_vec::DefaultSparseVec::__from_macro_parts(
+            $len,
+            ::std::default::Default::default(),
+            ::std::vec![$($index),*],
+            ::std::vec![$($value),*],
+        )
+    };
+    ($($elem:expr),* $(,)?) => {
+        $crate::vec::default_sparse_vec::DefaultSparseVec::__from_macro_dense(::std::vec![$($elem),*])
+    };
+}
+
+/// assert_sparse_eq! マクロ
+/// 2 つのベクタ (DefaultSparseVec / ビュー / Vec など SparseCompare を実装するもの) が論理的に等しく、
+/// どちらもデフォルト値を物理的に格納していないことを確かめます
+/// - `assert_sparse_eq!(a, b)`: 値を == で比べる
+/// - `assert_sparse_eq!(a, b, epsilon)`: 値を f64 にして差の絶対値が epsilon 以下なら等しいとみなす
+///
+/// 失敗すると Debug の全体ではなく、len / nnz / 格納されたデフォルト値の数と、
+/// 異なる位置の先頭 DIFF_SHOWN 個、差分が正規化だけかどうかを表示して panic します
+#[cfg(any(test, feature = "testing"))]
+#[macro_export]
+macro_rules! assert_sparse_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        if let ::std::option::Option::Some(diff) = $crate::vec::testing::diff(&$left, &$right) {
+            ::std::panic!(
+                "assertion `{} == {}` failed\n{}",
+                ::std::stringify!($left),
+                ::std::stringify!($right),
+                diff
+            );
+        }
+    };
+    ($left:expr, $right:expr, $epsilon:expr $(,)?) => {
+        if let ::std::option::Option::Some(diff) = $crate::vec::testing::diff_approx(&$left, &$right, $epsilon) {
+            ::std::panic!(
+                "assertion `{} ≈ {}` (epsilon = {}) failed\n{}",
+                ::std::stringify!($left),
+                ::std::stringify!($right),
+                $epsilon,
+                diff
+            );
+        }
+    };
+}
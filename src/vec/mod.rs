@@ -1,3 +1,40 @@
 pub mod vec_trait;
 pub mod default_sparse_vec;
-pub mod normal_vec_trait;
\ No newline at end of file
+pub mod adaptive_vec;
+pub mod iter;
+pub mod transparent;
+pub mod shared_sparse_vec;
+pub mod normal_vec_trait;
+pub mod error;
+pub mod format;
+pub mod sparse_vec_view;
+pub mod read_only;
+pub mod entry;
+pub mod ingest;
+pub mod auto_grow;
+pub mod gap_sparse_vec;
+pub mod summary;
+pub mod workspace;
+pub mod atomic_accumulator;
+pub mod dot_tracker;
+#[cfg(feature = "serde")]
+pub mod serde_impl;
+pub mod cost;
+pub mod sparse_pair_vec;
+pub mod sparse_vec_any;
+mod macros;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "shadow")]
+pub mod checked_sparse_vec;
+#[cfg(feature = "compress")]
+pub mod compress;
+#[cfg(feature = "rand")]
+pub mod sampling;
+// crate のテストでも使うので、feature が無くてもテストのビルドには含めます (alloc_audit も同じ)
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(any(test, feature = "testing"))]
+pub mod alloc_audit;
+#[cfg(test)]
+mod tests;
@@ -1,7 +1,11 @@
+/// NormalVecMethods trait の定義
+/// 密な Vec と同じ意味 (論理インデックス) の操作
+/// - dyn NormalVecMethods<T> として使えるよう、n_iter は Box に入れて返します
 pub trait NormalVecMethods<T> {
     fn n_push(&mut self, elem: T);
     fn n_pop(&mut self) -> Option<T>;
     fn n_insert(&mut self, index: usize, elem: T);
-    // fn n_remove(&mut self, index: usize) -> T;
-    // fn n_iter(&self) -> impl Iterator<Item = &T>;
-}
\ No newline at end of file
+    fn n_remove(&mut self, index: usize) -> T;
+    /// スパース分部のデフォルト値を含む全要素を論理インデックス順に返します
+    fn n_iter(&self) -> Box<dyn Iterator<Item = &T> + '_>;
+}
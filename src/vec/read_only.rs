@@ -0,0 +1,124 @@
+use std::fmt::{self, Debug};
+
+use super::{default_sparse_vec::DefaultSparseVec, iter::{IterDense, SparseIter}, sparse_vec_view::SparseVecView};
+
+/// ReadOnlySparseVec構造体の定義
+/// DefaultSparseVec を読み取り専用で借用するラッパー (Copy、O(1) で作成)
+/// DefaultSparseVec::read_only または From<&DefaultSparseVec<T>> で作成します
+/// 公開するメソッドは全て確保・変更・デフォルト値の clone を行いません
+/// (プラグインなどに本体を渡さずに読み取りだけを許可する用途)
+pub struct ReadOnlySparseVec<'a, T: Default + PartialEq> {
+    inner: &'a DefaultSparseVec<T>,
+}
+
+impl<'a, T: Default + PartialEq> ReadOnlySparseVec<'a, T> {
+    #[inline(always)]
+    pub(crate) fn new(inner: &'a DefaultSparseVec<T>) -> Self {
+        ReadOnlySparseVec { inner }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// nnzメソッドの実装
+    /// 物理要素数
+    #[must_use]
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        self.inner.nnz()
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn default_value(&self) -> &'a T {
+        self.inner.default_value()
+    }
+
+    /// getメソッドの実装
+    /// スパース分部はデフォルト値への参照を返します O(log nnz)
+    #[must_use]
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<&'a T> {
+        self.inner.get(index)
+    }
+
+    /// iterメソッドの実装
+    /// 物理要素の (index, 値) を index の昇順で返します
+    #[must_use]
+    #[inline(always)]
+    pub fn iter(&self) -> SparseIter<'a, T> {
+        self.inner.iter()
+    }
+
+    /// iter_denseメソッドの実装
+    /// スパース分部を含む全要素を論理インデックス順に返します
+    #[must_use]
+    #[inline(always)]
+    pub fn iter_dense(&self) -> IterDense<'a, T> {
+        self.inner.iter_dense()
+    }
+
+    /// as_pairsメソッドの実装
+    /// 物理要素の (インデックス配列, 値配列) を返します
+    #[must_use]
+    #[inline(always)]
+    pub fn as_pairs(&self) -> (&'a [usize], &'a [T]) {
+        self.inner.as_pairs()
+    }
+
+    /// viewメソッドの実装
+    /// 内積などに使う SparseVecView を返します O(1)
+    #[must_use]
+    #[inline(always)]
+    pub fn view(&self) -> SparseVecView<'a, T> {
+        self.inner.view()
+    }
+
+    /// reversed_viewメソッドの実装
+    /// 逆向きの SparseVecView を返します O(1)
+    #[must_use]
+    #[inline(always)]
+    pub fn reversed_view(&self) -> SparseVecView<'a, T> {
+        self.inner.reversed_view()
+    }
+
+    /// count_stored_defaultsメソッドの実装
+    /// デフォルト値と等しいのに物理的に格納されている要素の数 O(nnz)
+    #[must_use]
+    #[inline(always)]
+    pub fn count_stored_defaults(&self) -> usize {
+        self.inner.count_stored_defaults()
+    }
+}
+
+impl<T: Default + PartialEq> Clone for ReadOnlySparseVec<'_, T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Default + PartialEq> Copy for ReadOnlySparseVec<'_, T> {}
+
+impl<'a, T: Default + PartialEq> From<&'a DefaultSparseVec<T>> for ReadOnlySparseVec<'a, T> {
+    #[inline(always)]
+    fn from(inner: &'a DefaultSparseVec<T>) -> Self {
+        ReadOnlySparseVec::new(inner)
+    }
+}
+
+/// 本体と同じ表示 ({:?}、{:#?}、{:+?} のいずれも確保を行いません)
+impl<T: Default + PartialEq + Debug> Debug for ReadOnlySparseVec<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.inner, f)
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use super::default_sparse_vec::DefaultSparseVec;
+
+/// ランダムな抽出と並べ替え (feature = "rand")
+/// 同じ状態の rng からは常に同じ結果になります (HashSet は重複の判定だけに使い、順番には使いません)
+impl<T: Default + PartialEq> DefaultSparseVec<T> {
+    /// sample_indicesメソッドの実装
+    /// 論理インデックス 0..len から k 個を重複無しで一様に選び、昇順で返します O(k log k)
+    /// 物理要素の有無には関係なく、全ての位置が同じ確率で選ばれます
+    /// - k が len より大きい場合は panic
+    pub fn sample_indices<R: Rng + ?Sized>(&self, k: usize, rng: &mut R) -> Vec<usize> {
+        assert!(k <= self.len(), "cannot sample {k} indices from len {}", self.len());
+        let mut indices = floyd_sample(self.len(), k, rng);
+        indices.sort_unstable();
+        indices
+    }
+
+    /// shuffledメソッドの実装
+    /// 論理的な値を一様ランダムに並べ替えたベクタを返します O(nnz log nnz)
+    /// 一様な置換で物理要素が移る先は、len 個の位置から nnz 個を選んで一様に並べたものと同じ分布なので、
+    /// 物理要素の移り先だけを選びます (デフォルト値の位置は、物理要素が移ってこない限りデフォルト値のまま)
+    pub fn shuffled<R: Rng + ?Sized>(&self, rng: &mut R) -> Self
+    where
+        T: Clone,
+    {
+        let (_, val) = self.as_pairs();
+        let mut targets = floyd_sample(self.len(), val.len(), rng);
+        // floyd_sample の並びは一様な順番ではないので、Fisher-Yates で並べ替える
+        for i in (1..targets.len()).rev() {
+            targets.swap(i, rng.random_range(0..=i));
+        }
+        let mut entries: Vec<(usize, T)> = targets.into_iter().zip(val.iter().cloned()).collect();
+        entries.sort_unstable_by_key(|&(index, _)| index);
+        let (indices, values) = entries.into_iter().unzip();
+        DefaultSparseVec::from_parts(self.len(), self.default_value().clone(), indices, values)
+            .expect("shuffled targets are distinct and within len")
+    }
+}
+
+/// floyd_sample関数の実装
+/// 0..n から k 個を重複無しで一様に選びます (Floyd のアルゴリズム) O(k)
+/// 返り値の集合は一様ですが、並びは一様ではありません
+fn floyd_sample<R: Rng + ?Sized>(n: usize, k: usize, rng: &mut R) -> Vec<usize> {
+    let mut chosen = HashSet::with_capacity(k);
+    let mut out = Vec::with_capacity(k);
+    for j in n - k..n {
+        let t = rng.random_range(0..=j);
+        // t が既に選ばれていれば j を選ぶ (j はまだ選ばれていない)
+        let pick = if chosen.contains(&t) { j } else { t };
+        chosen.insert(pick);
+        out.push(pick);
+    }
+    out
+}
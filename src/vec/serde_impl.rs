@@ -0,0 +1,304 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    ser::{self, SerializeSeq, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{
+    default_sparse_vec::{DefaultSparseVec, SparsifyPolicy},
+    error::SparseVecError,
+    format::{FormatVersion, FORMAT_VERSION},
+};
+
+pub use super::format::DeserializeConfig;
+
+/// 入力の要素数の申告 (size_hint) を信用して一度に確保する最大要素数
+/// これを超える分は push で少しずつ確保します
+const PREALLOC_LIMIT: usize = 4096;
+
+const FIELDS: &[&str] = &["version", "len", "default", "indices", "values"];
+/// 形式 1.x の (version の無い) フィールド
+const LEGACY_FIELDS: &[&str] = &["len", "default", "indices", "values"];
+
+/// seed で DeserializeConfig の上限付きで読み込みます (DeserializeConfig は format モジュールにあります)
+impl DeserializeConfig {
+    /// seedメソッドの実装
+    /// この上限で DefaultSparseVec<T> を読み込む DeserializeSeed を返します
+    #[inline(always)]
+    pub fn seed<T>(self) -> SparseVecSeed<T> {
+        SparseVecSeed {
+            config: self,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// SparseVecSeed構造体の定義
+/// DeserializeConfig の上限付きで DefaultSparseVec<T> を読み込む DeserializeSeed
+pub struct SparseVecSeed<T> {
+    config: DeserializeConfig,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Serializeの実装
+/// { version, len, default, indices, values } の構造体として書き出します
+/// - version: FORMAT_VERSION の (major, minor)
+/// - indices と values は物理要素だけなので、サイズは nnz に比例します
+/// - デフォルト値と等しい物理要素 (get_mut / IndexMut で残ったもの) は書き出しません
+impl<T: Default + PartialEq + Serialize> Serialize for DefaultSparseVec<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // SparsifyPolicy は書き込み時の判定なので保存しません (値は既に判定済み)
+        // ただし Custom は読み込み側で同じ判定を復元できないことを知らせるためエラーにします
+        if let SparsifyPolicy::Custom(_) = self.sparsify_policy() {
+            return Err(ser::Error::custom("a DefaultSparseVec with SparsifyPolicy::Custom cannot be serialized"));
+        }
+        let mut state = serializer.serialize_struct("DefaultSparseVec", FIELDS.len())?;
+        state.serialize_field("version", &(FORMAT_VERSION.major, FORMAT_VERSION.minor))?;
+        state.serialize_field("len", &self.len())?;
+        state.serialize_field("default", self.default_value())?;
+        let (indices, values) = self.as_pairs();
+        let stored_defaults = self.count_stored_defaults();
+        if stored_defaults == 0 {
+            state.serialize_field("indices", indices)?;
+            state.serialize_field("values", values)?;
+        } else {
+            let count = indices.len() - stored_defaults;
+            state.serialize_field("indices", &NonDefault { vec: self, count, pick: |index, _| index })?;
+            state.serialize_field("values", &NonDefault { vec: self, count, pick: |_, elem| elem })?;
+        }
+        state.end()
+    }
+}
+
+/// NonDefault構造体の定義
+/// デフォルト値と等しい物理要素を飛ばして、indices か values (pick で選びます) を count 個の seq として書き出すラッパー
+struct NonDefault<'a, T: Default + PartialEq, E> {
+    vec: &'a DefaultSparseVec<T>,
+    count: usize,
+    pick: fn(&'a usize, &'a T) -> &'a E,
+}
+
+impl<T: Default + PartialEq, E: Serialize> Serialize for NonDefault<'_, T, E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.count))?;
+        let (indices, values) = self.vec.as_pairs();
+        for (index, elem) in indices.iter().zip(values) {
+            if elem != self.vec.default_value() {
+                seq.serialize_element((self.pick)(index, elem))?;
+            }
+        }
+        seq.end()
+    }
+}
+
+/// assemble関数の実装
+/// 読み込んだ部品からベクタを組み立てます
+/// from_parts の検査 (indices が狭義単調増加で len 未満、indices と values の要素数が同じ) に加えて、
+/// デフォルト値と等しい値があれば StoredDefault (壊れた、または書き換えられた入力として扱います)
+fn assemble<T: Default + PartialEq>(len: usize, default: T, indices: Vec<usize>, values: Vec<T>) -> Result<DefaultSparseVec<T>, SparseVecError> {
+    if let Some(&index) = values.iter().position(|elem| *elem == default).and_then(|position| indices.get(position)) {
+        return Err(SparseVecError::StoredDefault { index });
+    }
+    DefaultSparseVec::from_parts(len, default, indices, values)
+}
+
+impl<'de, T: Default + PartialEq + Deserialize<'de>> Deserialize<'de> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        DeserializeConfig::default().seed().deserialize(deserializer)
+    }
+}
+
+impl<'de, T: Default + PartialEq + Deserialize<'de>> DeserializeSeed<'de> for SparseVecSeed<T> {
+    type Value = DefaultSparseVec<T>;
+
+    #[inline(always)]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        if let Some(version) = self.config.source_version {
+            version.check_readable().map_err(de::Error::custom)?;
+        }
+        let fields = if self.legacy() { LEGACY_FIELDS } else { FIELDS };
+        deserializer.deserialize_struct("DefaultSparseVec", fields, self)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum Field {
+    Version,
+    Len,
+    Default,
+    Indices,
+    Values,
+}
+
+impl<'de, T: Default + PartialEq + Deserialize<'de>> Visitor<'de> for SparseVecSeed<T> {
+    type Value = DefaultSparseVec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sparse vector with version, len, default, indices and values")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        // 1.x は version を持たないので、要素の位置が 1 つずれる
+        let first = if self.legacy() {
+            0
+        } else {
+            let version: (u8, u8) = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            self.check_version(version)?;
+            1
+        };
+        let len = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(first, &self))?;
+        self.check_len(len)?;
+        let default = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(first + 1, &self))?;
+        let indices = seq
+            .next_element_seed(self.bounded("indices"))?
+            .ok_or_else(|| de::Error::invalid_length(first + 2, &self))?;
+        let values = seq
+            .next_element_seed(self.bounded("values"))?
+            .ok_or_else(|| de::Error::invalid_length(first + 3, &self))?;
+        assemble(len, default, indices, values).map_err(de::Error::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut version = None;
+        let mut len = None;
+        let mut default = None;
+        let mut indices = None;
+        let mut values = None;
+        while let Some(key) = map.next_key()? {
+            match key {
+                Field::Version => {
+                    if version.is_some() {
+                        return Err(de::Error::duplicate_field("version"));
+                    }
+                    let value = map.next_value()?;
+                    self.check_version(value)?;
+                    version = Some(value);
+                }
+                Field::Len => {
+                    if len.is_some() {
+                        return Err(de::Error::duplicate_field("len"));
+                    }
+                    let value = map.next_value()?;
+                    self.check_len(value)?;
+                    len = Some(value);
+                }
+                Field::Default => {
+                    if default.is_some() {
+                        return Err(de::Error::duplicate_field("default"));
+                    }
+                    default = Some(map.next_value()?);
+                }
+                Field::Indices => {
+                    if indices.is_some() {
+                        return Err(de::Error::duplicate_field("indices"));
+                    }
+                    indices = Some(map.next_value_seed(self.bounded("indices"))?);
+                }
+                Field::Values => {
+                    if values.is_some() {
+                        return Err(de::Error::duplicate_field("values"));
+                    }
+                    values = Some(map.next_value_seed(self.bounded("values"))?);
+                }
+            }
+        }
+        let len = len.ok_or_else(|| de::Error::missing_field("len"))?;
+        let default = default.ok_or_else(|| de::Error::missing_field("default"))?;
+        let indices = indices.ok_or_else(|| de::Error::missing_field("indices"))?;
+        let values = values.ok_or_else(|| de::Error::missing_field("values"))?;
+        assemble(len, default, indices, values).map_err(de::Error::custom)
+    }
+}
+
+impl<T> SparseVecSeed<T> {
+    /// legacyメソッドの実装
+    /// 入力が version を持たない 1.x の形式かどうか
+    #[inline(always)]
+    fn legacy(&self) -> bool {
+        self.config.source_version.is_some_and(|version| version.major == 1)
+    }
+
+    /// check_versionメソッドの実装
+    /// 入力の version が読み込めるものか確かめます
+    #[inline(always)]
+    fn check_version<E: de::Error>(&self, (major, minor): (u8, u8)) -> Result<(), E> {
+        FormatVersion::new(major, minor)
+            .check_readable()
+            .map_err(de::Error::custom)
+    }
+
+    #[inline(always)]
+    fn check_len<E: de::Error>(&self, len: usize) -> Result<(), E> {
+        self.config.check_len(len).map_err(de::Error::custom)
+    }
+
+    #[inline(always)]
+    fn bounded<E>(&self, field: &'static str) -> BoundedSeq<E> {
+        BoundedSeq {
+            field,
+            limit: self.config.max_nnz,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// BoundedSeq構造体の定義
+/// 要素数が limit を超えたらエラーにする Vec<E> 用の DeserializeSeed
+/// 入力が申告する要素数を信用せず、PREALLOC_LIMIT 個までしか先に確保しません
+struct BoundedSeq<E> {
+    field: &'static str,
+    limit: usize,
+    _marker: PhantomData<fn() -> E>,
+}
+
+impl<E> BoundedSeq<E> {
+    #[inline(always)]
+    fn exceeded<Er: de::Error>(&self) -> Er {
+        de::Error::custom(SparseVecError::LimitExceeded {
+            field: self.field,
+            limit: self.limit,
+        })
+    }
+}
+
+impl<'de, E: Deserialize<'de>> DeserializeSeed<'de> for BoundedSeq<E> {
+    type Value = Vec<E>;
+
+    #[inline(always)]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, E: Deserialize<'de>> Visitor<'de> for BoundedSeq<E> {
+    type Value = Vec<E>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a sequence of at most {} elements", self.limit)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let hint = seq.size_hint().unwrap_or(0);
+        if hint > self.limit {
+            return Err(self.exceeded());
+        }
+        let mut out = Vec::with_capacity(hint.min(PREALLOC_LIMIT));
+        while let Some(elem) = seq.next_element()? {
+            if out.len() == self.limit {
+                return Err(self.exceeded());
+            }
+            out.push(elem);
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    fmt::{self, Debug},
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+use num::ToPrimitive;
+
+use super::{
+    default_sparse_vec::DefaultSparseVec,
+    sparse_vec_view::SparseVecView,
+    vec_trait::{EdgeMode, Math, ReductionOrder, SignedElement, SignedMath, UnsignedElement, UnsignedMath},
+};
+
+/// 読み取り専用の共有スパースベクタ
+/// 物理領域を Arc で共有し、slice で作る部分ベクタはコピー無しで作成できます
+/// - 部分ベクタは (物理範囲 start..end, 論理インデックスのオフセット, len) だけを持ちます
+/// - clone / slice は O(1) / O(log nnz)
+/// - lifetime を持たないので、別スレッドや async タスクにそのまま渡せます
+pub struct SharedSparseVec<T: Default + PartialEq> {
+    ind: Arc<[usize]>,
+    val: Arc<[T]>,
+    default: Arc<T>,
+    start: usize,
+    end: usize,
+    index_offset: usize,
+    len: usize,
+}
+
+impl<T: Default + PartialEq> SharedSparseVec<T> {
+    /// lenメソッドの実装
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// nnzメソッドの実装
+    /// この範囲に含まれる物理要素数
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline(always)]
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    /// getメソッドの実装
+    /// 範囲外の場合は None
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        match self.stored_ind().binary_search(&(index + self.index_offset)) {
+            Ok(i) => Some(&self.val[self.start + i]),
+            Err(_) => Some(&self.default),
+        }
+    }
+
+    /// get_with_edgeメソッドの実装
+    /// index が範囲外 (負、または len 以上) の場合に mode に従って読みます O(log nnz)
+    /// (EdgeMode::Default ならデフォルト値、len が 0 ならどのモードでもデフォルト値)
+    #[must_use]
+    #[inline(always)]
+    pub fn get_with_edge(&self, index: isize, mode: EdgeMode) -> &T {
+        match mode.resolve(index, self.len) {
+            Some(index) => self.get(index).unwrap(),
+            None => &self.default,
+        }
+    }
+
+    /// sliceメソッドの実装
+    /// 論理範囲 range の部分ベクタを作成します
+    /// 物理領域は共有され、コピーは行いません O(log nnz)
+    /// - range が len を超える場合は panic
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Self {
+        let from = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let to = match range.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len,
+        };
+        assert!(from <= to && to <= self.len, "slice range out of bounds");
+
+        let ind = self.stored_ind();
+        let lo = ind.partition_point(|&i| i < self.index_offset + from);
+        let hi = ind.partition_point(|&i| i < self.index_offset + to);
+        SharedSparseVec {
+            ind: Arc::clone(&self.ind),
+            val: Arc::clone(&self.val),
+            default: Arc::clone(&self.default),
+            start: self.start + lo,
+            end: self.start + hi,
+            index_offset: self.index_offset + from,
+            len: to - from,
+        }
+    }
+
+    /// viewメソッドの実装
+    /// この範囲を読む SparseVecView を返します O(1)
+    /// (内積などを DefaultSparseVec のビューと同じ関数で扱えます)
+    #[must_use]
+    #[inline(always)]
+    pub fn view(&self) -> SparseVecView<'_, T> {
+        SparseVecView::with_index_offset(self.len, self.stored_ind(), self.stored_val(), &self.default, self.index_offset)
+    }
+
+    /// iterメソッドの実装
+    /// 物理要素を (この範囲での index, &T) で走査します
+    /// スパース分部を含みません
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        let offset = self.index_offset;
+        self.stored_ind()
+            .iter()
+            .map(move |&i| i - offset)
+            .zip(self.stored_val())
+    }
+
+    #[inline(always)]
+    fn stored_ind(&self) -> &[usize] {
+        &self.ind[self.start..self.end]
+    }
+
+    #[inline(always)]
+    fn stored_val(&self) -> &[T] {
+        &self.val[self.start..self.end]
+    }
+}
+
+impl<T: Default + PartialEq> Clone for SharedSparseVec<T> {
+    /// Arc を共有するだけなので T: Clone は不要です
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        SharedSparseVec {
+            ind: Arc::clone(&self.ind),
+            val: Arc::clone(&self.val),
+            default: Arc::clone(&self.default),
+            start: self.start,
+            end: self.end,
+            index_offset: self.index_offset,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Debug> Debug for SharedSparseVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.len).map(|i| self.get(i).unwrap()))
+            .finish()
+    }
+}
+
+impl<T: Default + PartialEq> From<DefaultSparseVec<T>> for SharedSparseVec<T> {
+    /// 物理領域を Arc に移します (値の clone は行いません)
+    fn from(svec: DefaultSparseVec<T>) -> Self {
+        let (len, default, indices, values) = svec.into_parts();
+        SharedSparseVec {
+            end: indices.len(),
+            ind: indices.into(),
+            val: values.into(),
+            default: Arc::new(default),
+            start: 0,
+            index_offset: 0,
+            len,
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Clone> From<&SharedSparseVec<T>> for DefaultSparseVec<T> {
+    /// この範囲の物理要素だけを clone して独立したベクタを作ります
+    fn from(shared: &SharedSparseVec<T>) -> Self {
+        DefaultSparseVec::from_parts(
+            shared.len,
+            (*shared.default).clone(),
+            shared.iter().map(|(i, _)| i).collect(),
+            shared.stored_val().to_vec(),
+        )
+        .expect("shared sparse vec is always well-formed")
+    }
+}
+
+impl<T: Default + PartialEq + Clone> From<SharedSparseVec<T>> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn from(shared: SharedSparseVec<T>) -> Self {
+        DefaultSparseVec::from(&shared)
+    }
+}
+
+/// 内積は SparseVecView の実装に委譲します (view はコピーを行いません)
+impl<T: Default + PartialEq + ToPrimitive> Math<T> for SharedSparseVec<T> {
+    #[inline(always)]
+    fn f64_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f64 {
+        self.view().f64_dot_with_order(&other.view(), order)
+    }
+
+    #[inline(always)]
+    fn f32_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f32 {
+        self.view().f32_dot_with_order(&other.view(), order)
+    }
+}
+
+impl<T: Default + PartialEq + UnsignedElement> UnsignedMath<T> for SharedSparseVec<T> {
+    #[inline(always)]
+    fn u128_dot(&self, other: &Self) -> u128 {
+        self.view().u128_dot(&other.view())
+    }
+}
+
+impl<T: Default + PartialEq + SignedElement> SignedMath<T> for SharedSparseVec<T> {
+    #[inline(always)]
+    fn i128_dot(&self, other: &Self) -> i128 {
+        self.view().i128_dot(&other.view())
+    }
+}
@@ -0,0 +1,212 @@
+use std::cmp::Ordering;
+
+use num::ToPrimitive;
+
+use super::{default_sparse_vec::DefaultSparseVec, error::SparseVecError, vec_trait::assert_same_len};
+
+/// SparsePairVec構造体の定義
+/// (A, B) の組を要素とするスパースベクタ (値と重みなど、同じ位置に物理要素を持つ 2 本のベクタ)
+/// インデックス配列を 1 つだけ持ち、値配列を 2 つ持ちます
+/// - 組のどちらかがデフォルト値でなければ、組ごと物理要素として格納します
+/// - 2 本の DefaultSparseVec よりインデックスのメモリが半分で、位置がずれることがありません
+#[derive(Debug, Clone)]
+pub struct SparsePairVec<A: Default + PartialEq, B: Default + PartialEq> {
+    ind: Vec<usize>,
+    val_a: Vec<A>,
+    val_b: Vec<B>,
+    len: usize,
+    default_a: A,
+    default_b: B,
+}
+
+impl<A: Default + PartialEq, B: Default + PartialEq> SparsePairVec<A, B> {
+    #[must_use]
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_default(A::default(), B::default())
+    }
+
+    /// with_defaultメソッドの実装
+    /// デフォルト値を (default_a, default_b) にした空のベクタ
+    #[must_use]
+    #[inline(always)]
+    pub fn with_default(default_a: A, default_b: B) -> Self {
+        SparsePairVec {
+            ind: Vec::new(),
+            val_a: Vec::new(),
+            val_b: Vec::new(),
+            len: 0,
+            default_a,
+            default_b,
+        }
+    }
+
+    /// zipメソッドの実装
+    /// 物理要素の位置 (サポート) が同じ 2 本のベクタから作ります
+    /// - len が異なる、またはサポートが異なる場合は SupportMismatch (index は最初に食い違う論理インデックス)
+    /// - 格納されたデフォルト値もサポートとして扱います (両方とも格納されていればそのまま組にします)
+    pub fn zip(a: DefaultSparseVec<A>, b: DefaultSparseVec<B>) -> Result<Self, SparseVecError> {
+        let (ind_a, ind_b) = (a.as_pairs().0, b.as_pairs().0);
+        if let Some(position) = ind_a.iter().zip(ind_b).position(|(x, y)| x != y) {
+            return Err(SparseVecError::SupportMismatch { index: ind_a[position].min(ind_b[position]) });
+        }
+        if ind_a.len() != ind_b.len() {
+            let common = ind_a.len().min(ind_b.len());
+            let index = ind_a.get(common).or(ind_b.get(common)).copied().unwrap_or_default();
+            return Err(SparseVecError::SupportMismatch { index });
+        }
+        if a.len() != b.len() {
+            return Err(SparseVecError::SupportMismatch { index: a.len().min(b.len()) });
+        }
+        let (len, default_a, ind, val_a) = a.into_parts();
+        let (_, default_b, _, val_b) = b.into_parts();
+        Ok(SparsePairVec { ind, val_a, val_b, len, default_a, default_b })
+    }
+
+    /// into_splitメソッドの実装
+    /// 2 本の DefaultSparseVec に分けます
+    /// - 片方だけがデフォルト値の組は、そちらのベクタでは格納しません
+    pub fn into_split(self) -> (DefaultSparseVec<A>, DefaultSparseVec<B>) {
+        let a = DefaultSparseVec::from_parts(self.len, self.default_a, self.ind.clone(), self.val_a)
+            .expect("SparsePairVec indices are sorted and in bounds");
+        let b = DefaultSparseVec::from_parts(self.len, self.default_b, self.ind, self.val_b)
+            .expect("SparsePairVec indices are sorted and in bounds");
+        (a, b)
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// nnzメソッドの実装
+    /// 物理要素 (組) の数
+    #[must_use]
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        self.ind.len()
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn default_values(&self) -> (&A, &B) {
+        (&self.default_a, &self.default_b)
+    }
+
+    /// pushメソッドの実装
+    /// 末尾に組 (a, b) を追加します
+    /// ! : len が usize::MAX に達している場合は panic
+    /// - 両方ともデフォルト値なら物理要素は増やさず、len だけ増やします
+    #[inline(always)]
+    pub fn push(&mut self, a: A, b: B) {
+        let index = self.len;
+        self.len = self.len.checked_add(1).expect("capacity overflow");
+        if a != self.default_a || b != self.default_b {
+            self.ind.push(index);
+            self.val_a.push(a);
+            self.val_b.push(b);
+        }
+    }
+
+    /// getメソッドの実装
+    /// `index` 番目の組 (スパース分部はデフォルト値の組)
+    /// - index が len 以上の場合は None
+    #[must_use]
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<(&A, &B)> {
+        if index >= self.len {
+            return None;
+        }
+        match self.ind.binary_search(&index) {
+            Ok(i) => Some((&self.val_a[i], &self.val_b[i])),
+            Err(_) => Some((&self.default_a, &self.default_b)),
+        }
+    }
+
+    /// iterメソッドの実装
+    /// 物理要素の (index, &a, &b) を index の昇順で返します (スパース分部を含みません)
+    #[inline(always)]
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (usize, &A, &B)> + ExactSizeIterator + '_ {
+        self.ind
+            .iter()
+            .zip(self.val_a.iter().zip(&self.val_b))
+            .map(|(&index, (a, b))| (index, a, b))
+    }
+
+    /// map_aメソッドの実装
+    /// A 側の値 (デフォルト値を含む) に f を適用した SparsePairVec を作ります
+    /// - 位置は変わりません (両方ともデフォルト値になった組もそのまま残ります)
+    pub fn map_a<C, F>(self, mut f: F) -> SparsePairVec<C, B>
+    where
+        C: Default + PartialEq,
+        F: FnMut(A) -> C,
+    {
+        SparsePairVec {
+            ind: self.ind,
+            val_a: self.val_a.into_iter().map(&mut f).collect(),
+            val_b: self.val_b,
+            len: self.len,
+            default_a: f(self.default_a),
+            default_b: self.default_b,
+        }
+    }
+
+    /// map_bメソッドの実装
+    /// map_a の B 側版
+    pub fn map_b<C, F>(self, mut f: F) -> SparsePairVec<A, C>
+    where
+        C: Default + PartialEq,
+        F: FnMut(B) -> C,
+    {
+        SparsePairVec {
+            ind: self.ind,
+            val_a: self.val_a,
+            val_b: self.val_b.into_iter().map(&mut f).collect(),
+            len: self.len,
+            default_a: self.default_a,
+            default_b: f(self.default_b),
+        }
+    }
+}
+
+impl<A: Default + PartialEq + ToPrimitive, B: Default + PartialEq + ToPrimitive> SparsePairVec<A, B> {
+    /// weighted_dotメソッドの実装
+    /// B で重み付けした A と other の内積 Σ a_i * b_i * other_i を f64 で計算します O(nnz + other.nnz)
+    /// 2 本のベクタで (a ⊙ b) · other を計算するのと同じく、両方に物理要素がある位置だけを足します
+    /// ! : len が異なる場合は panic
+    pub fn weighted_dot(&self, other: &DefaultSparseVec<A>) -> f64 {
+        assert_same_len(self.len, other.len());
+        fn to_f64<V: ToPrimitive>(v: &V) -> f64 {
+            v.to_f64().expect("value out of range for f64")
+        }
+        let (other_ind, other_val) = other.as_pairs();
+        let mut sum = 0.0;
+        let (mut i, mut j) = (0, 0);
+        while i < self.ind.len() && j < other_ind.len() {
+            match self.ind[i].cmp(&other_ind[j]) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    sum += to_f64(&self.val_a[i]) * to_f64(&self.val_b[i]) * to_f64(&other_val[j]);
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        sum
+    }
+}
+
+impl<A: Default + PartialEq, B: Default + PartialEq> Default for SparsePairVec<A, B> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
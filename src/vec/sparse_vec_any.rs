@@ -0,0 +1,260 @@
+use std::cmp::Ordering;
+
+use num::{traits::AsPrimitive, NumCast, ToPrimitive};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{default_sparse_vec::DefaultSparseVec, error::SparseVecError, vec_trait::assert_same_len};
+
+/// CastMode列挙型の定義
+/// 要素型を変換するときの精度の扱い
+/// - Strict: 変換先で値をそのまま表せない場合 (丸め、範囲外、小数部の切り捨て) はエラー
+/// - Lossy: `as` と同じ変換 (丸め、飽和、切り捨て) を行い、エラーにしません
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CastMode {
+    #[default]
+    Strict,
+    Lossy,
+}
+
+/// AnyElement trait の定義
+/// SparseVecAny の要素になれる型 (f32 / f64 / i32 / i64 / u64)
+/// downcast_ref などで具体的な型を指定するために使います
+pub trait AnyElement: Default + PartialEq + Copy + NumCast + ToPrimitive + 'static {
+    const ELEMENT_TYPE: ElementType;
+
+    fn downcast_ref(any: &SparseVecAny) -> Option<&DefaultSparseVec<Self>>;
+    fn downcast_mut(any: &mut SparseVecAny) -> Option<&mut DefaultSparseVec<Self>>;
+    fn into_any(vec: DefaultSparseVec<Self>) -> SparseVecAny;
+}
+
+/// sparse_vec_any! マクロ (モジュール内部用)
+/// (バリアント名, 要素型) の組から ElementType / SparseVecAny と、型ごとの AnyElement / From を作ります
+macro_rules! sparse_vec_any {
+    ($($variant:ident => $ty:ty),* $(,)?) => {
+        /// ElementType列挙型の定義
+        /// SparseVecAny の要素型
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        pub enum ElementType {
+            $($variant),*
+        }
+
+        /// SparseVecAny列挙型の定義
+        /// 要素型を実行時に選ぶ DefaultSparseVec (型引数を持たずに受け渡せます)
+        /// - 各バリアントの中身は通常の DefaultSparseVec なので、downcast_ref で取り出せば単相化された処理を使えます
+        /// - serde (feature = "serde") では `{"F64": {...}}` のようにバリアント名を付けて読み書きします
+        #[derive(Debug, Clone)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        pub enum SparseVecAny {
+            $($variant(DefaultSparseVec<$ty>)),*
+        }
+
+        impl SparseVecAny {
+            /// element_typeメソッドの実装
+            #[must_use]
+            #[inline(always)]
+            pub fn element_type(&self) -> ElementType {
+                match self {
+                    $(SparseVecAny::$variant(_) => ElementType::$variant),*
+                }
+            }
+        }
+
+        $(
+            impl AnyElement for $ty {
+                const ELEMENT_TYPE: ElementType = ElementType::$variant;
+
+                #[inline(always)]
+                fn downcast_ref(any: &SparseVecAny) -> Option<&DefaultSparseVec<Self>> {
+                    match any {
+                        SparseVecAny::$variant(vec) => Some(vec),
+                        #[allow(unreachable_patterns)]
+                        _ => None,
+                    }
+                }
+
+                #[inline(always)]
+                fn downcast_mut(any: &mut SparseVecAny) -> Option<&mut DefaultSparseVec<Self>> {
+                    match any {
+                        SparseVecAny::$variant(vec) => Some(vec),
+                        #[allow(unreachable_patterns)]
+                        _ => None,
+                    }
+                }
+
+                #[inline(always)]
+                fn into_any(vec: DefaultSparseVec<Self>) -> SparseVecAny {
+                    SparseVecAny::$variant(vec)
+                }
+            }
+
+            impl From<DefaultSparseVec<$ty>> for SparseVecAny {
+                #[inline(always)]
+                fn from(vec: DefaultSparseVec<$ty>) -> Self {
+                    SparseVecAny::$variant(vec)
+                }
+            }
+        )*
+
+        /// with_inner! マクロ (モジュール内部用)
+        /// SparseVecAny の中身の DefaultSparseVec<T> を $vec に束縛して $body を評価します (バリアントごとに単相化)
+        macro_rules! with_inner {
+            ($any:expr, $vec:ident => $body:expr) => {
+                match $any {
+                    $(SparseVecAny::$variant($vec) => $body),*
+                }
+            };
+        }
+
+        /// with_target! マクロ (モジュール内部用)
+        /// ElementType に対応する要素型を $target_ty に束縛して $body を評価します
+        macro_rules! with_target {
+            ($element_type:expr, $target_ty:ident => $body:expr) => {
+                match $element_type {
+                    $(ElementType::$variant => {
+                        type $target_ty = $ty;
+                        $body
+                    }),*
+                }
+            };
+        }
+    };
+}
+
+sparse_vec_any! {
+    F32 => f32,
+    F64 => f64,
+    I32 => i32,
+    I64 => i64,
+    U64 => u64,
+}
+
+impl SparseVecAny {
+    #[must_use]
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        with_inner!(self, vec => vec.len())
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        with_inner!(self, vec => vec.nnz())
+    }
+
+    /// get_f64メソッドの実装
+    /// `index` 番目の値を f64 で返します (index が len 以上なら Ok(None))
+    /// - f64 で正確に表せない値 (2^53 を超える i64 / u64 など) は LossyCast
+    pub fn get_f64(&self, index: usize) -> Result<Option<f64>, SparseVecError> {
+        with_inner!(self, vec => match vec.get(index) {
+            Some(elem) => exact_cast::<_, f64>(*elem).map(Some).ok_or(SparseVecError::LossyCast { index: Some(index) }),
+            None => Ok(None),
+        })
+    }
+
+    /// dot_f64メソッドの実装
+    /// 要素型に関係なく、各値を f64 に変換した内積を計算します (f64_dot と同じく両方に物理要素がある位置だけを足します)
+    /// ! : len が異なる場合は panic
+    /// - 同じ要素型どうしでも f64 に変換してから掛けます
+    #[must_use]
+    pub fn dot_f64(&self, other: &SparseVecAny) -> f64 {
+        with_inner!(self, a => with_inner!(other, b => dot_mixed(a, b)))
+    }
+
+    /// downcast_refメソッドの実装
+    /// 要素型が T なら中身の DefaultSparseVec<T> を返します
+    #[must_use]
+    #[inline(always)]
+    pub fn downcast_ref<T: AnyElement>(&self) -> Option<&DefaultSparseVec<T>> {
+        T::downcast_ref(self)
+    }
+
+    /// downcast_mutメソッドの実装
+    /// downcast_ref の可変版
+    #[inline(always)]
+    pub fn downcast_mut<T: AnyElement>(&mut self) -> Option<&mut DefaultSparseVec<T>> {
+        T::downcast_mut(self)
+    }
+
+    /// cast_toメソッドの実装
+    /// 要素型を target に変換したベクタを作ります O(nnz)
+    /// - CastMode::Strict で値 (デフォルト値を含む) を正確に表せない場合は、最初の位置を LossyCast で返します
+    ///   (デフォルト値の場合は index が None)
+    /// - 変換後にデフォルト値と等しくなった値は格納しません
+    pub fn cast_to(&self, target: ElementType, mode: CastMode) -> Result<SparseVecAny, SparseVecError> {
+        with_inner!(self, vec => with_target!(target, U => cast_vec::<_, U>(vec, mode).map(U::into_any)))
+    }
+}
+
+/// exact_cast関数の実装
+/// value を U で正確に表せる場合だけ変換します (U から戻して元の値に一致すること、NaN は NaN のまま)
+#[inline(always)]
+fn exact_cast<T: AnyElement, U: AnyElement>(value: T) -> Option<U> {
+    let cast: U = NumCast::from(value)?;
+    let back: T = NumCast::from(cast)?;
+    #[allow(clippy::eq_op)]
+    let both_nan = value != value && back != back;
+    (back == value || both_nan).then_some(cast)
+}
+
+/// cast_value関数の実装
+/// mode に従って 1 つの値を変換します
+#[inline(always)]
+fn cast_value<T, U>(value: T, mode: CastMode) -> Option<U>
+where
+    T: AnyElement + AsPrimitive<U>,
+    U: AnyElement,
+{
+    match mode {
+        CastMode::Strict => exact_cast(value),
+        CastMode::Lossy => Some(value.as_()),
+    }
+}
+
+/// cast_vec関数の実装
+/// cast_to の本体 (要素型ごとに単相化されます)
+fn cast_vec<T, U>(vec: &DefaultSparseVec<T>, mode: CastMode) -> Result<DefaultSparseVec<U>, SparseVecError>
+where
+    T: AnyElement + AsPrimitive<U>,
+    U: AnyElement,
+{
+    let default = cast_value(*vec.default_value(), mode).ok_or(SparseVecError::LossyCast { index: None })?;
+    let (ind, val) = vec.as_pairs();
+    let mut values = Vec::with_capacity(val.len());
+    for (&index, &elem) in ind.iter().zip(val) {
+        values.push(cast_value(elem, mode).ok_or(SparseVecError::LossyCast { index: Some(index) })?);
+    }
+    DefaultSparseVec::from_parts(vec.len(), default, ind.to_vec(), values)
+}
+
+/// dot_mixed関数の実装
+/// 要素型の異なる 2 本のベクタの f64 の内積 (dot_f64 の本体)
+fn dot_mixed<A: AnyElement, B: AnyElement>(a: &DefaultSparseVec<A>, b: &DefaultSparseVec<B>) -> f64 {
+    assert_same_len(a.len(), b.len());
+    fn to_f64<V: ToPrimitive>(v: &V) -> f64 {
+        v.to_f64().expect("value out of range for f64")
+    }
+    let (a_ind, a_val) = a.as_pairs();
+    let (b_ind, b_val) = b.as_pairs();
+    let mut sum = 0.0;
+    let (mut i, mut j) = (0, 0);
+    while i < a_ind.len() && j < b_ind.len() {
+        match a_ind[i].cmp(&b_ind[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                sum += to_f64(&a_val[i]) * to_f64(&b_val[j]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    sum
+}
@@ -0,0 +1,603 @@
+use std::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use num::ToPrimitive;
+
+use super::{
+    default_sparse_vec::{coverage, for_each_matched_sorted, pairwise_sum, DefaultSparseVec},
+    iter::Gaps,
+    read_only::ReadOnlySparseVec,
+    shared_sparse_vec::SharedSparseVec,
+    vec_trait::{assert_same_len, i128_mul_add, u128_mul_add, EdgeMode, Math, ReductionOrder, SignedElement, SignedMath, UnsignedElement, UnsignedMath},
+};
+
+/// SparseVecView構造体の定義
+/// DefaultSparseVec を読み取り専用で借用するビュー
+/// DefaultSparseVec::view / reversed_view で作成します (O(1)、コピーを行いません)
+/// - reversed の場合、ビューの index i は本体の len - 1 - i を指します
+///   (物理領域は後ろから読むので、ビューの index の昇順に走査できます)
+/// - 内積 (Math) はどちらの向きのビュー同士でも確保無しで計算します
+///   (DefaultSparseVec / SharedSparseVec の内積もビューを作ってここに委譲します)
+pub struct SparseVecView<'a, T> {
+    len: usize,
+    ind: &'a [usize],
+    val: &'a [T],
+    default: &'a T,
+    reversed: bool,
+    /// ind に格納された index からビューの index を得るために引く値 (SharedSparseVec の部分ベクタ用)
+    index_offset: usize,
+}
+
+impl<'a, T> SparseVecView<'a, T> {
+    #[inline(always)]
+    pub(crate) fn new(len: usize, ind: &'a [usize], val: &'a [T], default: &'a T) -> Self {
+        Self::with_index_offset(len, ind, val, default, 0)
+    }
+
+    #[inline(always)]
+    pub(crate) fn with_index_offset(len: usize, ind: &'a [usize], val: &'a [T], default: &'a T, index_offset: usize) -> Self {
+        SparseVecView {
+            len,
+            ind,
+            val,
+            default,
+            reversed: false,
+            index_offset,
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// nnzメソッドの実装
+    /// 物理要素数
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        self.ind.len()
+    }
+
+    #[inline(always)]
+    pub fn default_value(&self) -> &'a T {
+        self.default
+    }
+
+    /// is_reversedメソッドの実装
+    /// 本体と逆向きのビューかどうか
+    #[inline(always)]
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// reversedメソッドの実装
+    /// 向きを反転したビューを返します O(1)
+    #[inline(always)]
+    pub fn reversed(self) -> Self {
+        SparseVecView {
+            reversed: !self.reversed,
+            ..self
+        }
+    }
+
+    /// getメソッドの実装
+    /// ビューの index の値 (範囲外の場合は None) O(log nnz)
+    pub fn get(&self, index: usize) -> Option<&'a T> {
+        if index >= self.len {
+            return None;
+        }
+        let index = if self.reversed { self.len - 1 - index } else { index };
+        match self.ind.binary_search(&(index + self.index_offset)) {
+            Ok(i) => Some(&self.val[i]),
+            Err(_) => Some(self.default),
+        }
+    }
+
+    /// get_with_edgeメソッドの実装
+    /// index が範囲外 (負、または len 以上) の場合に mode に従って読みます O(log nnz)
+    /// (EdgeMode::Default ならデフォルト値、len が 0 ならどのモードでもデフォルト値) (ビューの向きの index です)
+    #[must_use]
+    #[inline(always)]
+    pub fn get_with_edge(&self, index: isize, mode: EdgeMode) -> &'a T {
+        match mode.resolve(index, self.len) {
+            Some(index) => self.get(index).unwrap(),
+            None => self.default,
+        }
+    }
+
+    /// iterメソッドの実装
+    /// 物理要素を (ビューの index, 値) でビューの index の昇順に返します
+    /// スパース分部を含みません
+    #[inline(always)]
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (usize, &'a T)> + ExactSizeIterator + '_ {
+        (0..self.nnz()).map(move |k| self.stored(k))
+    }
+
+    /// gapsメソッドの実装
+    /// 物理要素が無い (デフォルト値の) ビューの index の極大な範囲を昇順に返します O(nnz)
+    /// (DefaultSparseVec::gaps と同じで、reversed の場合はビューの向きの範囲です)
+    #[inline(always)]
+    pub fn gaps(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        Gaps::new(self.iter().map(|(index, _)| index), self.len)
+    }
+
+    /// largest_gapメソッドの実装
+    /// gaps のうち最も長い範囲 (同じ長さなら前のもの)、範囲が無ければ None O(nnz)
+    #[must_use]
+    #[inline(always)]
+    pub fn largest_gap(&self) -> Option<Range<usize>> {
+        Gaps::new(self.iter().map(|(index, _)| index), self.len).largest()
+    }
+
+    /// coverageメソッドの実装
+    /// 1 - (gaps の長さの合計 / len) (len が 0 なら 1.0) O(1)
+    #[must_use]
+    #[inline(always)]
+    pub fn coverage(&self) -> f64 {
+        coverage(self.len, self.nnz())
+    }
+
+    /// to_ownedメソッドの実装
+    /// ビューと論理的に等しい (ビューの向きの) DefaultSparseVec を作ります O(nnz)
+    /// - 物理要素の値だけを clone します。デフォルト値と等しい物理要素は格納しません
+    /// - v.as_view().to_owned() は v と論理的に等しくなります (len / デフォルト値 / 全 index の値)
+    ///
+    /// ! : ToOwned trait ではありません。SparseVecView は Clone なので ToOwned は標準の実装 (ビューのコピー) と衝突し、
+    ///     また ToOwned が要求する `DefaultSparseVec: Borrow<SparseVecView>` は、ビューを格納していないので参照を返せません
+    ///     (この inherent メソッドが ToOwned::to_owned より優先されます)
+    #[must_use]
+    pub fn to_owned(&self) -> DefaultSparseVec<T>
+    where
+        T: Default + PartialEq + Clone,
+    {
+        let (indices, values) = self
+            .iter()
+            .filter(|(_, elem)| *elem != self.default)
+            .map(|(index, elem)| (index, elem.clone()))
+            .unzip();
+        DefaultSparseVec::from_parts(self.len, self.default.clone(), indices, values)
+            .expect("sparse vec view is always well-formed")
+    }
+
+    /// storedメソッドの実装
+    /// ビューの index の昇順で k 番目の物理要素 (k < nnz)
+    #[inline(always)]
+    fn stored(&self, k: usize) -> (usize, &'a T) {
+        if self.reversed {
+            let i = self.ind.len() - 1 - k;
+            (self.len - 1 - (self.ind[i] - self.index_offset), &self.val[i])
+        } else {
+            (self.ind[k] - self.index_offset, &self.val[k])
+        }
+    }
+
+    /// for_each_matchedメソッドの実装
+    /// 両方に物理的に存在するビューの index について、ビューの index 昇順に f(self の値, other の値) を呼び出します
+    /// 逆向きのビューは物理領域を後ろから読むので、どちらの向きの組み合わせでも 1 回のマージです
+    /// ! : len が異なる場合は panic
+    /// - 同じ向きで index_offset も同じなら格納された index をそのまま比べられるので、
+    ///   DefaultSparseVec と同じ for_each_matched_sorted (大きな nnz ではブロック単位の走査) を使います
+    fn for_each_matched<F>(&self, other: &Self, mut f: F)
+    where
+        F: FnMut(&T, &T),
+    {
+        assert_same_len(self.len, other.len);
+        if !self.reversed && !other.reversed && self.index_offset == other.index_offset {
+            for_each_matched_sorted(self.ind, self.val, other.ind, other.val, f);
+            return;
+        }
+        let mut i = 0;
+        let mut j = 0;
+        while i < self.nnz() && j < other.nnz() {
+            let (a, a_val) = self.stored(i);
+            let (b, b_val) = other.stored(j);
+            if a < b {
+                i += 1;
+            } else if a > b {
+                j += 1;
+            } else {
+                f(a_val, b_val);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+}
+
+impl<T> Clone for SparseVecView<'_, T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SparseVecView<'_, T> {}
+
+/// AsSparseView trait の定義
+/// SparseVecView として読めるもの (AsRef<SparseVecView> の代わりです)
+/// fn f<T>(v: impl AsSparseView<T>) と書くと、所有する DefaultSparseVec / SharedSparseVec、
+/// 借用する ReadOnlySparseVec / SparseVecView、それらへの参照のどれでも受け取れます
+/// - as_view はコピーを行いません O(1)
+pub trait AsSparseView<T> {
+    fn as_view(&self) -> SparseVecView<'_, T>;
+}
+
+impl<T, S: AsSparseView<T> + ?Sized> AsSparseView<T> for &S {
+    #[inline(always)]
+    fn as_view(&self) -> SparseVecView<'_, T> {
+        (**self).as_view()
+    }
+}
+
+impl<T> AsSparseView<T> for SparseVecView<'_, T> {
+    #[inline(always)]
+    fn as_view(&self) -> SparseVecView<'_, T> {
+        *self
+    }
+}
+
+impl<T: Default + PartialEq> AsSparseView<T> for DefaultSparseVec<T> {
+    #[inline(always)]
+    fn as_view(&self) -> SparseVecView<'_, T> {
+        self.view()
+    }
+}
+
+impl<T: Default + PartialEq> AsSparseView<T> for ReadOnlySparseVec<'_, T> {
+    #[inline(always)]
+    fn as_view(&self) -> SparseVecView<'_, T> {
+        self.view()
+    }
+}
+
+impl<T: Default + PartialEq> AsSparseView<T> for SharedSparseVec<T> {
+    #[inline(always)]
+    fn as_view(&self) -> SparseVecView<'_, T> {
+        self.view()
+    }
+}
+
+impl<T: Debug> Debug for SparseVecView<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.len).map(|i| self.get(i).unwrap()))
+            .finish()
+    }
+}
+
+impl<T: ToPrimitive> Math<T> for SparseVecView<'_, T> {
+    /// 積は各値を f64 に変換してから計算します (加算はビューの index 昇順)
+    #[inline(always)]
+    fn f64_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f64 {
+        let to_f64 = |v: &T| v.to_f64().expect("value out of range for f64");
+        match order {
+            ReductionOrder::Ascending => {
+                let mut sum: f64 = 0.0;
+                self.for_each_matched(other, |a, b| sum += to_f64(a) * to_f64(b));
+                sum
+            }
+            ReductionOrder::PairwiseTree => {
+                let mut products = Vec::new();
+                self.for_each_matched(other, |a, b| products.push(to_f64(a) * to_f64(b)));
+                pairwise_sum(&products)
+            }
+        }
+    }
+
+    /// 積は各値を f32 に変換してから計算します (加算はビューの index 昇順)
+    #[inline(always)]
+    fn f32_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f32 {
+        let to_f32 = |v: &T| v.to_f32().expect("value out of range for f32");
+        match order {
+            ReductionOrder::Ascending => {
+                let mut sum: f32 = 0.0;
+                self.for_each_matched(other, |a, b| sum += to_f32(a) * to_f32(b));
+                sum
+            }
+            ReductionOrder::PairwiseTree => {
+                let mut products = Vec::new();
+                self.for_each_matched(other, |a, b| products.push(to_f32(a) * to_f32(b)));
+                pairwise_sum(&products)
+            }
+        }
+    }
+}
+
+impl<T: UnsignedElement> UnsignedMath<T> for SparseVecView<'_, T> {
+    #[inline(always)]
+    fn u128_dot(&self, other: &Self) -> u128 {
+        let mut sum: u128 = 0;
+        self.for_each_matched(other, |a, b| sum = u128_mul_add(sum, a.widen(), b.widen()));
+        sum
+    }
+}
+
+impl<T: SignedElement> SignedMath<T> for SparseVecView<'_, T> {
+    #[inline(always)]
+    fn i128_dot(&self, other: &Self) -> i128 {
+        let mut sum: i128 = 0;
+        self.for_each_matched(other, |a, b| sum = i128_mul_add(sum, a.widen(), b.widen()));
+        sum
+    }
+}
+
+/// SparseVecViewMut構造体の定義
+/// DefaultSparseVec の論理範囲 [start, end) を可変借用するビュー
+/// DefaultSparseVec::split_at_mut_view / disjoint_views_mut で作成します
+/// ! : 範囲内の論理位置をずらす操作 (insert / remove) はできません
+/// ! : mem::forget されたビューのオーバーフローバッファは本体に反映されません
+///
+/// - index はビュー先頭からの相対位置 (slice::split_at_mut と同じ)
+/// - 既に物理的に存在する要素はその場で書き換えます
+/// - 新しい要素はビューのオーバーフローバッファに溜め、全ビューの drop 後に本体へマージします
+/// - 物理領域とデフォルト値は参照ではなくポインタで持ちます
+///   (最後のビューは drop 中に本体を書き換えるので、本体を指す参照をフィールドに残せません)
+pub struct SparseVecViewMut<'a, T: Default + PartialEq> {
+    start: usize,
+    end: usize,
+    ind: *const usize,
+    val: *mut T,
+    stored: usize,
+    default: *const T,
+    overflow: Vec<(usize, T)>,
+    merge: Arc<ViewMerge<T>>,
+    _marker: PhantomData<&'a mut DefaultSparseVec<T>>,
+}
+
+/// 物理領域はビュー間で重ならず (T: Send)、デフォルト値は全ビューから読む (T: Sync) ので両方が必要です
+/// ```compile_fail
+/// use std::cell::Cell;
+/// use vec_plus::vec::default_sparse_vec::DefaultSparseVec;
+///
+/// fn assert_send<S: Send>(_: &S) {}
+/// let mut vec = DefaultSparseVec::from(vec![Cell::new(0u32); 4]);
+/// let (left, _right) = vec.split_at_mut_view(2);
+/// assert_send(&left);
+/// ```
+unsafe impl<T: Send + Sync + Default + PartialEq> Send for SparseVecViewMut<'_, T> {}
+unsafe impl<T: Send + Sync + Default + PartialEq> Sync for SparseVecViewMut<'_, T> {}
+
+/// ViewMerge構造体の定義
+/// 同じ本体から作られたビューが共有するマージ状態
+/// 最後に drop されたビューが pending を本体にマージします
+/// budget は CapPolicy::Error のときに全ビューで追加できる物理要素数の残り
+/// compact はデフォルト値が物理領域に書かれた可能性があること (マージの前に本体から取り除きます)
+pub(crate) struct ViewMerge<T: Default + PartialEq> {
+    parent: *mut DefaultSparseVec<T>,
+    live: AtomicUsize,
+    budget: AtomicUsize,
+    compact: AtomicBool,
+    pending: Mutex<Vec<(usize, T)>>,
+}
+
+// parent は全ビューが drop されるまで可変借用されたままで、
+// 最後の 1 つのビューだけが触るので T: Send で十分
+unsafe impl<T: Send + Default + PartialEq> Send for ViewMerge<T> {}
+unsafe impl<T: Send + Default + PartialEq> Sync for ViewMerge<T> {}
+
+impl<T: Default + PartialEq> ViewMerge<T> {
+    #[inline(always)]
+    pub(crate) fn new(parent: *mut DefaultSparseVec<T>, live: usize, budget: usize) -> Arc<Self> {
+        Arc::new(ViewMerge {
+            parent,
+            live: AtomicUsize::new(live),
+            budget: AtomicUsize::new(budget),
+            compact: AtomicBool::new(false),
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+impl<'a, T: Default + PartialEq> SparseVecViewMut<'a, T> {
+    /// newメソッドの実装 (crate 内部用)
+    /// ! : ind / val は stored 個の、他のビューと重ならない物理領域で、default と共に 'a の間有効であること
+    #[inline(always)]
+    pub(crate) unsafe fn new(
+        start: usize,
+        end: usize,
+        ind: *const usize,
+        val: *mut T,
+        stored: usize,
+        default: *const T,
+        merge: Arc<ViewMerge<T>>,
+    ) -> Self {
+        SparseVecViewMut {
+            start,
+            end,
+            ind,
+            val,
+            stored,
+            default,
+            overflow: Vec::new(),
+            merge,
+            _marker: PhantomData,
+        }
+    }
+
+    /// lenメソッドの実装
+    /// ビューの論理的な長さ
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// offsetメソッドの実装
+    /// ビュー先頭の本体での論理インデックス
+    #[inline(always)]
+    pub fn offset(&self) -> usize {
+        self.start
+    }
+
+    /// nnzメソッドの実装
+    /// 範囲内のデフォルト値でない要素数 (オーバーフローバッファを含む) O(範囲内の物理要素数)
+    /// - デフォルト値を書き込んだ物理要素は数えません (drop 後のマージで本体から取り除きます)
+    pub fn nnz(&self) -> usize {
+        let default = self.default();
+        self.val().iter().chain(self.overflow.iter().map(|(_, elem)| elem)).filter(|elem| *elem != default).count()
+    }
+
+    /// getメソッドの実装
+    /// 範囲外の場合は None
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        let index = self.start + index;
+        if let Ok(i) = self.ind().binary_search(&index) {
+            return Some(&self.val()[i]);
+        }
+        match self.overflow.binary_search_by_key(&index, |(i, _)| *i) {
+            Ok(i) => Some(&self.overflow[i].1),
+            Err(_) => Some(self.default()),
+        }
+    }
+
+    /// get_stored_mutメソッドの実装
+    /// 物理的に存在する要素の可変参照を返します
+    /// 存在しない (デフォルト値の) 場合は None で、密にはしません
+    /// - デフォルト値を書き込んだ場合は、drop 後のマージで本体から取り除きます
+    pub fn get_stored_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len() {
+            return None;
+        }
+        let index = self.start + index;
+        self.mark_compact();
+        if let Ok(i) = self.ind().binary_search(&index) {
+            return Some(&mut self.val_mut()[i]);
+        }
+        match self.overflow.binary_search_by_key(&index, |(i, _)| *i) {
+            Ok(i) => Some(&mut self.overflow[i].1),
+            Err(_) => None,
+        }
+    }
+
+    /// setメソッドの実装
+    /// index の値を elem にします
+    ///
+    /// - 物理的に存在する場合はその場で上書き
+    ///   (デフォルト値を書き込んだ場合は、drop 後のマージで本体から取り除きます)
+    /// - オーバーフローバッファにある場合は上書き (デフォルト値ならバッファから取り除きます)
+    /// - どちらにも無い場合はオーバーフローバッファに追加 (デフォルト値なら何もしない)
+    /// - index がビューの範囲外の場合は panic
+    /// - 本体の CapPolicy が Error で、全ビューの追加分が上限を超える場合は panic
+    pub fn set(&mut self, index: usize, elem: T) {
+        assert!(index < self.len(), "index out of bounds: the len is {} but the index is {index}", self.len());
+        let index = self.start + index;
+        let is_default = elem == *self.default();
+        if let Ok(i) = self.ind().binary_search(&index) {
+            if is_default {
+                self.mark_compact();
+            }
+            self.val_mut()[i] = elem;
+            return;
+        }
+        match self.overflow.binary_search_by_key(&index, |(i, _)| *i) {
+            Ok(i) if is_default => {
+                self.overflow.remove(i);
+                self.merge.budget.fetch_add(1, Ordering::AcqRel);
+            }
+            Ok(i) => self.overflow[i].1 = elem,
+            Err(i) => {
+                if !is_default {
+                    let reserved = self.merge.budget.fetch_update(Ordering::AcqRel, Ordering::Acquire, |b| b.checked_sub(1));
+                    assert!(reserved.is_ok(), "nnz cap exceeded");
+                    self.overflow.insert(i, (index, elem));
+                }
+            }
+        }
+    }
+
+    /// iter_mutメソッドの実装
+    /// 範囲内の既存の物理要素を (相対 index, &mut T) で走査します
+    /// オーバーフローバッファの要素は含みません
+    /// - デフォルト値を書き込んだ場合は、drop 後のマージで本体から取り除きます
+    #[inline(always)]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> + '_ {
+        self.mark_compact();
+        let start = self.start;
+        let ind = unsafe { std::slice::from_raw_parts(self.ind, self.stored) };
+        ind.iter().map(move |&i| i - start).zip(self.val_mut().iter_mut())
+    }
+
+    #[inline(always)]
+    fn ind(&self) -> &[usize] {
+        unsafe { std::slice::from_raw_parts(self.ind, self.stored) }
+    }
+
+    #[inline(always)]
+    fn val(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.val, self.stored) }
+    }
+
+    #[inline(always)]
+    fn val_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.val, self.stored) }
+    }
+
+    #[inline(always)]
+    fn default(&self) -> &T {
+        unsafe { &*self.default }
+    }
+
+    /// mark_compactメソッドの実装
+    /// 物理領域にデフォルト値が書かれた可能性があることを記録します
+    #[inline(always)]
+    fn mark_compact(&self) {
+        if self.stored > 0 {
+            self.merge.compact.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<T: Default + PartialEq> Drop for SparseVecViewMut<'_, T> {
+    fn drop(&mut self) {
+        if !self.overflow.is_empty() {
+            let mut pending = self.merge.pending.lock().unwrap_or_else(|e| e.into_inner());
+            pending.append(&mut self.overflow);
+        }
+        if self.merge.live.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // 最後のビュー: 他のビューはもう本体に触れない
+            // (live の AcqRel で、他のビューの書き込みと compact の記録はここから見えます)
+            let mut pending = std::mem::take(
+                &mut *self.merge.pending.lock().unwrap_or_else(|e| e.into_inner()),
+            );
+            let compact = self.merge.compact.load(Ordering::Relaxed);
+            if pending.is_empty() && !compact {
+                return;
+            }
+            pending.sort_unstable_by_key(|(i, _)| *i);
+            let parent = unsafe { &mut *self.merge.parent };
+            if compact {
+                parent.compact_stored_defaults();
+            }
+            parent.merge_unstored_sorted(pending);
+        }
+    }
+}
+
+impl<T: Default + PartialEq + Debug> Debug for SparseVecViewMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SparseVecViewMut")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("nnz", &self.nnz())
+            .finish()
+    }
+}
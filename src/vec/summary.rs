@@ -0,0 +1,73 @@
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// SparseStats構造体の定義
+/// DefaultSparseVec::stats の結果 (O(1) で求まるサイズの情報だけ)
+/// - density: nnz / len (len が 0 の場合は 0.0)
+/// - cap: capacity と同じ
+/// - heap_bytes: heap_size_bytes と同じ (物理領域として確保しているバイト数)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SparseStats {
+    pub len: usize,
+    pub nnz: usize,
+    pub cap: usize,
+    pub density: f64,
+    pub heap_bytes: usize,
+}
+
+/// ログ向けの 1 行表示
+impl fmt::Display for SparseStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "len: {}, nnz: {}, cap: {}, density: {:.4}, heap: {} bytes",
+            self.len, self.nnz, self.cap, self.density, self.heap_bytes
+        )
+    }
+}
+
+/// SparseVecSummary構造体の定義
+/// DefaultSparseVec::summarize の結果
+/// - density: nnz / len (len が 0 の場合は 0.0)
+/// - min / max / mean: 物理要素の値の統計 (f64 に変換、NaN は無視、物理要素が無い場合は None)
+/// - stored_defaults: デフォルト値と等しい物理要素の数 (正規化されていない分)
+/// - first_index / last_index: 最初と最後の物理要素の論理インデックス
+/// - largest_gap: 物理要素が無い論理位置が連続する最大の長さ (先頭と末尾を含む)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SparseVecSummary {
+    pub len: usize,
+    pub nnz: usize,
+    pub density: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub stored_defaults: usize,
+    pub first_index: Option<usize>,
+    pub last_index: Option<usize>,
+    pub largest_gap: usize,
+}
+
+impl fmt::Display for SparseVecSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "len: {}, nnz: {}, density: {:.4}", self.len, self.nnz, self.density)?;
+        match (self.min, self.max, self.mean) {
+            (Some(min), Some(max), Some(mean)) => {
+                writeln!(f, "min: {min}, max: {max}, mean: {mean}")?
+            }
+            _ => writeln!(f, "min: -, max: -, mean: -")?,
+        }
+        match (self.first_index, self.last_index) {
+            (Some(first), Some(last)) => writeln!(f, "stored range: {first}..={last}")?,
+            _ => writeln!(f, "stored range: -")?,
+        }
+        write!(
+            f,
+            "stored defaults: {}, largest gap: {}",
+            self.stored_defaults, self.largest_gap
+        )
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt::{self, Debug};
+
+use num::ToPrimitive;
+
+use super::{default_sparse_vec::DefaultSparseVec, gap_sparse_vec::GapSparseVec, read_only::ReadOnlySparseVec, sparse_vec_view::SparseVecView};
+
+/// 差分で表示する異なる位置の最大数
+pub const DIFF_SHOWN: usize = 8;
+
+/// SparseCompare trait の定義
+/// assert_sparse_eq! で比べられるベクタ (論理インデックスの値と、正規化の状態)
+/// - 密なもの (Vec / スライス) は nnz を持たず、格納されたデフォルト値もありません
+pub trait SparseCompare<T> {
+    fn compare_len(&self) -> usize;
+    /// スパース分部を含む全要素を論理インデックス順に返します
+    fn compare_iter(&self) -> Box<dyn Iterator<Item = &T> + '_>;
+    /// 物理要素数 (密なものは None)
+    fn compare_nnz(&self) -> Option<usize>;
+    /// デフォルト値と等しいのに物理的に格納されている要素の数
+    fn compare_stored_defaults(&self) -> usize;
+}
+
+impl<T, S: SparseCompare<T> + ?Sized> SparseCompare<T> for &S {
+    fn compare_len(&self) -> usize {
+        (**self).compare_len()
+    }
+
+    fn compare_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        (**self).compare_iter()
+    }
+
+    fn compare_nnz(&self) -> Option<usize> {
+        (**self).compare_nnz()
+    }
+
+    fn compare_stored_defaults(&self) -> usize {
+        (**self).compare_stored_defaults()
+    }
+}
+
+impl<T: Default + PartialEq> SparseCompare<T> for DefaultSparseVec<T> {
+    fn compare_len(&self) -> usize {
+        self.len()
+    }
+
+    fn compare_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.iter_dense())
+    }
+
+    fn compare_nnz(&self) -> Option<usize> {
+        Some(self.nnz())
+    }
+
+    fn compare_stored_defaults(&self) -> usize {
+        self.count_stored_defaults()
+    }
+}
+
+impl<T: Default + PartialEq> SparseCompare<T> for ReadOnlySparseVec<'_, T> {
+    fn compare_len(&self) -> usize {
+        self.len()
+    }
+
+    fn compare_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.iter_dense())
+    }
+
+    fn compare_nnz(&self) -> Option<usize> {
+        Some(self.nnz())
+    }
+
+    fn compare_stored_defaults(&self) -> usize {
+        self.count_stored_defaults()
+    }
+}
+
+/// GapSparseVec はデフォルト値を格納しないので、格納されたデフォルト値は常に 0 です
+impl<T: Default + PartialEq> SparseCompare<T> for GapSparseVec<T> {
+    fn compare_len(&self) -> usize {
+        self.len()
+    }
+
+    fn compare_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.iter_dense())
+    }
+
+    fn compare_nnz(&self) -> Option<usize> {
+        Some(self.nnz())
+    }
+
+    fn compare_stored_defaults(&self) -> usize {
+        0
+    }
+}
+
+impl<T: PartialEq> SparseCompare<T> for SparseVecView<'_, T> {
+    fn compare_len(&self) -> usize {
+        self.len()
+    }
+
+    fn compare_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new((0..self.len()).map(|index| self.get(index).unwrap()))
+    }
+
+    fn compare_nnz(&self) -> Option<usize> {
+        Some(self.nnz())
+    }
+
+    fn compare_stored_defaults(&self) -> usize {
+        self.iter().filter(|(_, elem)| *elem == self.default_value()).count()
+    }
+}
+
+impl<T> SparseCompare<T> for [T] {
+    fn compare_len(&self) -> usize {
+        self.len()
+    }
+
+    fn compare_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.iter())
+    }
+
+    fn compare_nnz(&self) -> Option<usize> {
+        None
+    }
+
+    fn compare_stored_defaults(&self) -> usize {
+        0
+    }
+}
+
+impl<T> SparseCompare<T> for Vec<T> {
+    fn compare_len(&self) -> usize {
+        self.len()
+    }
+
+    fn compare_iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.iter())
+    }
+
+    fn compare_nnz(&self) -> Option<usize> {
+        None
+    }
+
+    fn compare_stored_defaults(&self) -> usize {
+        0
+    }
+}
+
+/// SparseDiffKind列挙型の定義
+/// 差分の種類 (上ほど優先して報告します)
+/// - Length: len が異なる
+/// - Content: 論理的な値が異なる位置がある
+/// - Normalization: 論理的には等しいが、デフォルト値を物理的に格納している (正規化されていない)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparseDiffKind {
+    Length,
+    Content,
+    Normalization,
+}
+
+/// SparseDiff構造体の定義
+/// diff / diff_approx の結果 (Display で assert_sparse_eq! の失敗メッセージになります)
+/// - positions: 異なる論理位置のうち先頭 DIFF_SHOWN 個の (index, 左の値, 右の値) (値は Debug 表示)
+/// - differing: 共通の長さの範囲で異なる位置の総数
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseDiff {
+    pub kind: SparseDiffKind,
+    pub len: (usize, usize),
+    pub nnz: (Option<usize>, Option<usize>),
+    pub stored_defaults: (usize, usize),
+    pub differing: usize,
+    pub positions: Vec<(usize, String, String)>,
+}
+
+impl fmt::Display for SparseDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            SparseDiffKind::Length => "length",
+            SparseDiffKind::Content => "content",
+            SparseDiffKind::Normalization => "normalization only (logically equal, stored defaults)",
+        };
+        let nnz = |nnz: Option<usize>| nnz.map_or("-".to_string(), |nnz| nnz.to_string());
+        writeln!(f, "sparse vectors differ: {kind}")?;
+        writeln!(f, "  len: left {}, right {}", self.len.0, self.len.1)?;
+        writeln!(f, "  nnz: left {}, right {}", nnz(self.nnz.0), nnz(self.nnz.1))?;
+        write!(f, "  stored defaults: left {}, right {}", self.stored_defaults.0, self.stored_defaults.1)?;
+        if self.differing > 0 {
+            write!(f, "\n  differing positions: {} (first {} shown)", self.differing, self.positions.len())?;
+            for (index, left, right) in &self.positions {
+                write!(f, "\n    [{index}] left: {left}, right: {right}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// diff関数の実装
+/// a と b を論理インデックスごとに == で比べ、異なれば SparseDiff を返します
+/// 論理的に等しくても、どちらかがデフォルト値を物理的に格納していれば Normalization として返します
+pub fn diff<T, A, B>(a: &A, b: &B) -> Option<SparseDiff>
+where
+    T: PartialEq + Debug,
+    A: SparseCompare<T> + ?Sized,
+    B: SparseCompare<T> + ?Sized,
+{
+    diff_by(a, b, |x, y| x == y)
+}
+
+/// diff_approx関数の実装
+/// diff と同じですが、値は f64 に変換して差の絶対値が epsilon 以下なら等しいとみなします
+/// (正規化の判定は各ベクタの書き込み時の判定のままです)
+pub fn diff_approx<T, A, B>(a: &A, b: &B, epsilon: f64) -> Option<SparseDiff>
+where
+    T: ToPrimitive + Debug,
+    A: SparseCompare<T> + ?Sized,
+    B: SparseCompare<T> + ?Sized,
+{
+    diff_by(a, b, |x, y| match (x.to_f64(), y.to_f64()) {
+        (Some(x), Some(y)) => x == y || (x - y).abs() <= epsilon,
+        _ => false,
+    })
+}
+
+/// diff_by関数の実装
+/// diff / diff_approx の本体 (eq で値を比べます)
+fn diff_by<T, A, B, F>(a: &A, b: &B, eq: F) -> Option<SparseDiff>
+where
+    T: Debug,
+    A: SparseCompare<T> + ?Sized,
+    B: SparseCompare<T> + ?Sized,
+    F: Fn(&T, &T) -> bool,
+{
+    let mut differing = 0;
+    let mut positions = Vec::new();
+    for (index, (x, y)) in a.compare_iter().zip(b.compare_iter()).enumerate() {
+        if !eq(x, y) {
+            differing += 1;
+            if positions.len() < DIFF_SHOWN {
+                positions.push((index, format!("{x:?}"), format!("{y:?}")));
+            }
+        }
+    }
+    let len = (a.compare_len(), b.compare_len());
+    let stored_defaults = (a.compare_stored_defaults(), b.compare_stored_defaults());
+    let kind = if len.0 != len.1 {
+        SparseDiffKind::Length
+    } else if differing > 0 {
+        SparseDiffKind::Content
+    } else if stored_defaults != (0, 0) {
+        SparseDiffKind::Normalization
+    } else {
+        return None;
+    };
+    Some(SparseDiff {
+        kind,
+        len,
+        nnz: (a.compare_nnz(), b.compare_nnz()),
+        stored_defaults,
+        differing,
+        positions,
+    })
+}
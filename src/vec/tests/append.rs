@@ -0,0 +1,122 @@
+//! append のテスト (other の値を移動して other を空にすること、Miri で二重解放が無いことを確かめられます)
+//! append_all のテスト (途中の shard でエラーになった場合に、受け手が呼び出し前と全く同じ状態のままであること)
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::vec::{
+    default_sparse_vec::{CapPolicy, DefaultSparseVec},
+    error::{SparseVecError, ValidationMode},
+};
+
+fn strings(values: &[&str]) -> DefaultSparseVec<String> {
+    DefaultSparseVec::from(values.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+}
+
+#[test]
+fn append_strings_moves_values() {
+    let mut vec = strings(&["a", "", "b"]);
+    let mut other = strings(&["", "c", "", "d"]);
+    let other_cap = other.capacity();
+    vec.append(&mut other);
+    assert_eq!(vec.to_dense(), ["a", "", "b", "", "c", "", "d"]);
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+    // other は空になり、容量を残したまま使えます
+    assert_eq!((other.len(), other.nnz(), other.capacity()), (0, 0, other_cap));
+    other.push("e".into());
+    assert_eq!(other.to_dense(), ["e"]);
+    drop(other);
+    vec[4].push('x');
+    assert_eq!(vec.get(4).map(String::as_str), Some("cx"));
+}
+
+#[test]
+fn append_bytes_vectors() {
+    let mut vec = DefaultSparseVec::from(vec![vec![1u8], vec![]]);
+    let mut other = DefaultSparseVec::from(vec![vec![], vec![2u8, 3]]);
+    vec.append(&mut other);
+    assert_eq!(vec.to_dense(), [vec![1], vec![], vec![], vec![2, 3]]);
+}
+
+#[test]
+fn append_empty_in_both_directions() {
+    let mut vec = strings(&["a", ""]);
+    let mut empty = DefaultSparseVec::new();
+    vec.append(&mut empty);
+    assert_eq!(vec.to_dense(), ["a", ""]);
+    empty.append(&mut vec);
+    assert_eq!(empty.to_dense(), ["a", ""]);
+    assert!(vec.is_empty());
+}
+
+/// 受け手の状態 (領域のアドレスと容量、len、物理要素のビット列) を丸ごと写します
+#[allow(clippy::type_complexity)]
+fn snapshot<T: Default + PartialEq + Clone>(vec: &DefaultSparseVec<T>) -> (*const usize, *const T, usize, usize, Vec<usize>, Vec<T>) {
+    let (ind, val) = vec.as_pairs();
+    (ind.as_ptr(), val.as_ptr(), vec.capacity(), vec.len(), ind.to_vec(), val.to_vec())
+}
+
+#[test]
+fn append_all_concatenates_shards() {
+    let mut vec = strings(&["a", ""]);
+    let shards = [strings(&["", "b"]), DefaultSparseVec::new(), strings(&["c", "", ""])];
+    assert_eq!(vec.append_all(&shards), Ok((7, 3)));
+    assert_eq!(vec.to_dense(), ["a", "", "", "b", "c", "", ""]);
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+    // shard は clone するだけで変わりません
+    assert_eq!(shards[0].to_dense(), ["", "b"]);
+}
+
+#[test]
+fn append_all_rolls_back_on_a_bad_shard_in_the_middle() {
+    let mut vec = strings(&["a", "", "b"]);
+    vec.reserve(1);
+    let before = snapshot(&vec);
+
+    // デフォルト値の違う shard
+    let shards = [strings(&["c"]), DefaultSparseVec::from_vec_with_default(vec!["x".to_string()], "y".into()), strings(&["d"])];
+    assert_eq!(vec.append_all(&shards), Err(SparseVecError::DefaultMismatch { position: 1 }));
+    assert_eq!(snapshot(&vec), before);
+
+    // len があふれる shard
+    let huge = DefaultSparseVec::<String>::with_nnz_capacity(usize::MAX - 2, 0);
+    let shards = [strings(&["c", "d"]), huge, strings(&["e"])];
+    assert_eq!(vec.append_all(&shards), Err(SparseVecError::CapacityOverflow));
+    assert_eq!(snapshot(&vec), before);
+
+    assert_eq!(vec.to_dense(), ["a", "", "b"]);
+
+    // CapPolicy::Error の上限を超える shard
+    let mut vec = DefaultSparseVec::from(vec![1i64, 0, 2]);
+    vec.set_max_nnz(Some(3));
+    vec.set_cap_policy(CapPolicy::Error);
+    let before = snapshot(&vec);
+    let shards = [DefaultSparseVec::from(vec![0i64]), DefaultSparseVec::from(vec![0, 3]), DefaultSparseVec::from(vec![4])];
+    assert_eq!(vec.append_all(&shards), Err(SparseVecError::NnzCapExceeded { max_nnz: 3 }));
+    assert_eq!(snapshot(&vec), before);
+}
+
+/// 2 回目の clone で panic する値
+#[derive(Debug, Default, PartialEq)]
+struct PanicOnSecondClone(u32);
+
+impl Clone for PanicOnSecondClone {
+    fn clone(&self) -> Self {
+        assert!(self.0 != 2, "clone panicked");
+        PanicOnSecondClone(self.0)
+    }
+}
+
+#[test]
+fn append_all_truncates_back_when_clone_panics() {
+    let mut vec = DefaultSparseVec::from(vec![PanicOnSecondClone(1), PanicOnSecondClone(0)]);
+    let shards = [
+        DefaultSparseVec::from(vec![PanicOnSecondClone(0), PanicOnSecondClone(1)]),
+        DefaultSparseVec::from(vec![PanicOnSecondClone(2)]),
+    ];
+    let result = catch_unwind(AssertUnwindSafe(|| vec.append_all(&shards)));
+    assert!(result.is_err());
+    // 書き込んだ分を取り除いて元の len に戻ります
+    assert_eq!(vec.len(), 2);
+    assert_eq!(vec.iter().map(|(index, elem)| (index, elem.0)).collect::<Vec<_>>(), [(0, 1)]);
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+}
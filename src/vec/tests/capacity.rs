@@ -0,0 +1,136 @@
+//! 容量を変える操作のテスト (reserve / shrink_to_fit / with_capacity / try_reserve)
+//! 未確保の状態からの確保と、容量 0 への縮小を Miri で確かめられる大きさにしています
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::vec::{
+    alloc_audit::{assert_allocation_audit, is_counting},
+    default_sparse_vec::DefaultSparseVec,
+    error::SparseVecError,
+};
+
+use super::panic_message;
+
+fn strings(values: &[&str]) -> DefaultSparseVec<String> {
+    DefaultSparseVec::from(values.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+}
+
+#[test]
+fn reserve_on_empty_vector() {
+    let mut vec = DefaultSparseVec::<String>::new();
+    assert_eq!(vec.capacity(), 0);
+    vec.reserve(3);
+    assert!(vec.capacity() >= 3);
+    let cap = vec.capacity();
+    for s in ["a", "b", "c"] {
+        vec.push(s.into());
+    }
+    // 予約した容量の範囲では再確保しません
+    assert_eq!(vec.capacity(), cap);
+    vec.reserve(10);
+    assert!(vec.capacity() >= 13);
+    assert_eq!(vec.to_dense(), ["a", "b", "c"]);
+}
+
+#[test]
+fn reserve_zero_on_empty_vector_does_not_allocate() {
+    let mut vec = DefaultSparseVec::<String>::new();
+    vec.reserve(0);
+    vec.reserve_exact(0);
+    assert_eq!(vec.capacity(), 0);
+    vec.push("a".into());
+    assert_eq!(vec.to_dense(), ["a"]);
+}
+
+#[test]
+fn shrink_to_zero_and_reuse() {
+    let mut vec = strings(&["", "a", "b"]);
+    vec.reserve(8);
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 2);
+    vec.clear();
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 0);
+    // 未確保の状態に戻ったベクタに、もう一度確保できます
+    vec.reserve(1);
+    vec.push("c".into());
+    assert_eq!(vec.to_dense(), ["c"]);
+}
+
+#[test]
+fn shrink_step_to_zero() {
+    let mut vec = DefaultSparseVec::from(vec![1u64, 0]);
+    vec.reserve(4);
+    vec.set(0, 0);
+    while !vec.shrink_step(usize::MAX) {}
+    assert_eq!(vec.capacity(), 0);
+    vec.push(2);
+    assert_eq!(vec.to_dense(), [0, 0, 2]);
+}
+
+#[test]
+fn try_reserve_overflow_leaves_vector_intact() {
+    let mut vec = DefaultSparseVec::from(vec![0u64, 1, 2]);
+    let cap = vec.capacity();
+    // 要素数は usize に収まってもバイト数が isize::MAX を超える
+    for additional in [usize::MAX / 8 + 1, usize::MAX / 16, usize::MAX] {
+        assert_eq!(vec.try_reserve(additional), Err(SparseVecError::CapacityOverflow), "try_reserve({additional})");
+        assert_eq!(vec.try_reserve_exact(additional), Err(SparseVecError::CapacityOverflow), "try_reserve_exact({additional})");
+    }
+    assert_eq!(vec.capacity(), cap);
+    assert_eq!(vec.to_dense(), [0, 1, 2]);
+    vec.push(3);
+    assert_eq!(vec.to_dense(), [0, 1, 2, 3]);
+}
+
+#[test]
+fn try_reserve_success_grows_capacity() {
+    let mut vec = DefaultSparseVec::<String>::new();
+    assert_eq!(vec.try_reserve_exact(3), Ok(()));
+    assert_eq!(vec.capacity(), 3);
+    assert_eq!(vec.try_reserve(4), Ok(()));
+    assert!(vec.capacity() >= 4);
+}
+
+#[test]
+fn reserve_overflow_panics_without_change() {
+    let mut vec = DefaultSparseVec::from(vec![0u64, 1]);
+    let err = catch_unwind(AssertUnwindSafe(|| vec.reserve(usize::MAX / 8 + 1))).unwrap_err();
+    let message = panic_message(&*err);
+    assert!(message.contains("capacity overflow"), "unexpected panic message: {message}");
+    assert_eq!(vec.to_dense(), [0, 1]);
+}
+
+#[test]
+fn with_capacity_zero_is_unallocated() {
+    let mut vec = DefaultSparseVec::<String>::with_capacity(0);
+    assert_eq!(vec.capacity(), 0);
+    assert_eq!(vec.heap_size_bytes(), 0);
+    vec.push(String::new());
+    assert_eq!(vec.capacity(), 0);
+    vec.push("a".into());
+    assert!(vec.capacity() >= 1);
+    assert_eq!(vec.to_dense(), ["", "a"]);
+}
+
+#[test]
+fn with_capacity_then_shrink_and_push() {
+    let mut vec = DefaultSparseVec::<String>::with_capacity(1);
+    assert_eq!(vec.capacity(), 1);
+    vec.push("a".into());
+    vec.push("b".into());
+    assert_eq!(vec.to_dense(), ["a", "b"]);
+
+    let mut vec = DefaultSparseVec::<String>::with_capacity(8);
+    assert_eq!(vec.capacity(), 8);
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 0);
+    vec.push("c".into());
+    assert_eq!(vec.to_dense(), ["c"]);
+}
+
+#[test]
+fn allocation_audit_table() {
+    assert!(is_counting());
+    assert_allocation_audit();
+}
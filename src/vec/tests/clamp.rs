@@ -0,0 +1,54 @@
+//! clamp_in_place のテスト
+//! 固定のシードで作ったベクタ (デフォルト値が範囲の内側と外側の両方) を dense の clamp と比べます
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+use super::SplitMix64;
+
+#[test]
+fn clamp_matches_dense() {
+    let mut rng = SplitMix64(223);
+    for _ in 0..200 {
+        let len = rng.below(30);
+        let default = rng.below(21) as i32 - 10;
+        let dense: Vec<i32> = (0..len).map(|_| if rng.below(2) == 0 { rng.below(41) as i32 - 20 } else { default }).collect();
+        let min = rng.below(21) as i32 - 10;
+        let max = min + rng.below(10) as i32;
+
+        let mut vec = DefaultSparseVec::from_vec_with_default(dense.clone(), default);
+        vec.clamp_in_place(min, max);
+        let expected: Vec<i32> = dense.iter().map(|elem| (*elem).clamp(min, max)).collect();
+        assert_eq!(vec.to_dense(), expected, "{dense:?} (default {default}) into [{min}, {max}]");
+        assert_eq!(*vec.default_value(), default.clamp(min, max));
+        assert_eq!(vec.count_stored_defaults(), 0);
+        assert_eq!(vec.len(), len);
+    }
+}
+
+#[test]
+fn default_outside_the_range_is_clamped() {
+    // スパース分部 (デフォルト値 0) も [2, 5] に収まり、2 になった物理要素は取り除かれます
+    let mut vec = DefaultSparseVec::from(vec![0, 7, 0, 1, 3, 0]);
+    vec.clamp_in_place(2, 5);
+    assert_eq!(vec.to_dense(), [2, 5, 2, 2, 3, 2]);
+    assert_eq!((vec.default_value(), vec.nnz()), (&2, 2));
+
+    let mut vec = DefaultSparseVec::from(vec![0.0, -3.5, 0.0, 0.25]);
+    vec.clamp_in_place(-1.0, -0.5);
+    assert_eq!(vec.to_dense(), [-0.5, -1.0, -0.5, -0.5]);
+    assert_eq!(vec.nnz(), 1);
+}
+
+#[test]
+fn default_inside_the_range_is_kept() {
+    let mut vec = DefaultSparseVec::from(vec![0, 9, 0, -9, 4]);
+    vec.clamp_in_place(-5, 5);
+    assert_eq!(vec.to_dense(), [0, 5, 0, -5, 4]);
+    assert_eq!(vec.default_value(), &0);
+}
+
+#[test]
+#[should_panic(expected = "min must be less than or equal to max")]
+fn min_greater_than_max_panics() {
+    DefaultSparseVec::from(vec![1, 2]).clamp_in_place(3, 2);
+}
@@ -0,0 +1,55 @@
+//! clear のテスト (格納された値をその場で 1 回ずつ drop し、容量を残すこと)
+
+use std::{cell::Cell, rc::Rc};
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+use super::Counted;
+
+#[test]
+fn clear_drops_each_element_exactly_once() {
+    let drops = Rc::new(Cell::new(0));
+    let mut vec = DefaultSparseVec::new();
+    for id in [0, 1, 0, 2, 3, 0] {
+        vec.push(Counted::new(id, &drops));
+    }
+    // デフォルト値と等しい値は push の時点で drop されます
+    assert_eq!(drops.get(), 3);
+    let cap = vec.capacity();
+    vec.clear();
+    assert_eq!(drops.get(), 6);
+    assert_eq!((vec.len(), vec.nnz(), vec.capacity()), (0, 0, cap));
+    drop(vec);
+    assert_eq!(drops.get(), 6);
+}
+
+#[test]
+fn clear_then_reuse_and_drop() {
+    let drops = Rc::new(Cell::new(0));
+    let mut vec = DefaultSparseVec::new();
+    vec.push(Counted::new(1, &drops));
+    vec.clear();
+    vec.push(Counted::new(2, &drops));
+    vec.push(Counted::new(3, &drops));
+    assert_eq!(drops.get(), 1);
+    drop(vec);
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn clear_strings_keeps_vector_usable() {
+    let mut vec = DefaultSparseVec::from(vec![String::new(), "a".into(), "b".into()]);
+    vec.clear();
+    vec.clear();
+    vec.push("c".into());
+    assert_eq!(vec.to_dense(), ["c"]);
+}
+
+#[test]
+fn clear_long_sparse_vector_is_cheap() {
+    // 物理要素だけを drop するので len に比例しません (len 回 pop していた頃は終わりませんでした)
+    let mut vec = DefaultSparseVec::<String>::with_nnz_capacity(usize::MAX / 2, 2);
+    vec.set(7, "a".into());
+    vec.clear();
+    assert!(vec.is_empty());
+}
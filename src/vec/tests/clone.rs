@@ -0,0 +1,71 @@
+//! clone のテスト (格納された値を T::clone で複製すること、Miri で二重解放が無いことを確かめられます)
+
+use std::{
+    cell::Cell,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, error::ValidationMode};
+
+fn strings() -> DefaultSparseVec<String> {
+    DefaultSparseVec::from(vec![String::new(), "a".into(), String::new(), "bb".into(), "ccc".into()])
+}
+
+#[test]
+fn clone_mutate_and_drop_strings() {
+    let original = strings();
+    let mut copy = original.clone();
+    assert_eq!(copy.to_dense(), original.to_dense());
+    assert_eq!(copy.as_pairs().0, original.as_pairs().0);
+
+    // 片方を変更しても、もう片方は変わりません
+    copy[1].push('x');
+    copy.push("d".into());
+    copy.insert(0, "e".into());
+    copy.remove(4);
+    assert_eq!(copy.to_dense(), ["e", "", "ax", "", "ccc", "d"]);
+    assert_eq!(original.to_dense(), ["", "a", "", "bb", "ccc"]);
+    assert_eq!(copy.validate(ValidationMode::Strict), Ok(0));
+
+    drop(original);
+    assert_eq!(copy.pop().as_deref(), Some("d"));
+    drop(copy);
+}
+
+#[test]
+fn clone_empty_and_zst() {
+    let empty = DefaultSparseVec::<String>::new();
+    assert!(empty.clone().is_empty());
+    let mut units = DefaultSparseVec::<()>::new();
+    units.push(());
+    assert_eq!(units.clone().len(), 1);
+}
+
+/// 3 回目の clone で panic する値
+#[derive(Debug, Default, PartialEq)]
+struct PanicOnThirdClone(u32);
+
+thread_local! {
+    static CLONES: Cell<u32> = const { Cell::new(0) };
+}
+
+impl Clone for PanicOnThirdClone {
+    fn clone(&self) -> Self {
+        let clones = CLONES.with(|c| {
+            c.set(c.get() + 1);
+            c.get()
+        });
+        assert!(clones != 3, "clone panicked");
+        PanicOnThirdClone(self.0)
+    }
+}
+
+#[test]
+fn clone_panic_drops_only_cloned_values() {
+    let vec = DefaultSparseVec::from(vec![PanicOnThirdClone(1), PanicOnThirdClone(0), PanicOnThirdClone(2), PanicOnThirdClone(3)]);
+    CLONES.with(|c| c.set(0));
+    assert!(catch_unwind(AssertUnwindSafe(|| vec.clone())).is_err());
+    // 元のベクタはそのまま使えます
+    assert_eq!(vec.nnz(), 3);
+    assert_eq!(vec.get(3), Some(&PanicOnThirdClone(3)));
+}
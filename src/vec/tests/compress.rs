@@ -0,0 +1,151 @@
+//! 圧縮形式 (feature = "compress") のテスト
+//! 固定のシードで作ったベクタの往復と、壊したデータが InvalidFormat になること (壊れたベクタを作らないこと) を確かめます
+
+use std::{fmt::Debug, io::ErrorKind};
+
+use crate::vec::{
+    compress::{crc32, CompressedValue},
+    default_sparse_vec::DefaultSparseVec,
+    error::SparseVecError,
+};
+
+use super::SplitMix64;
+
+/// 長さ、密度、デフォルト値がばらばらのベクタ
+/// value は乱数から値を作ります (デフォルト値も value で作ります)
+fn random_vecs<T: Default + PartialEq + Clone>(seed: u64, value: impl Fn(u64) -> T) -> Vec<DefaultSparseVec<T>> {
+    let mut rng = SplitMix64(seed);
+    (0..40)
+        .map(|round| {
+            let len = rng.below(300);
+            let density = rng.below(101);
+            let default = if round % 4 == 0 { value(rng.next()) } else { T::default() };
+            let dense = (0..len)
+                .map(|_| if rng.below(100) < density { value(rng.next()) } else { default.clone() })
+                .collect();
+            DefaultSparseVec::from_vec_with_default(dense, default)
+        })
+        .collect()
+}
+
+fn round_trip<T: Default + PartialEq + Clone + Debug + CompressedValue>(seed: u64, value: impl Fn(u64) -> T) {
+    for (round, vec) in random_vecs(seed, value).into_iter().enumerate() {
+        let mut bytes = Vec::new();
+        vec.write_compressed(&mut bytes, round as i32 % 5).unwrap();
+        let loaded = DefaultSparseVec::<T>::read_compressed(&bytes[..]).unwrap();
+        assert_eq!(loaded, vec, "seed {seed}, round {round}");
+        assert_eq!(loaded.default_value(), vec.default_value());
+        assert_eq!(loaded.nnz(), vec.nnz());
+    }
+}
+
+#[test]
+fn round_trip_integers() {
+    round_trip(1, |bits| bits as u8);
+    round_trip(2, |bits| bits as u16);
+    round_trip(3, |bits| bits as u32);
+    round_trip(4, |bits| bits);
+    round_trip(5, |bits| bits as i8);
+    round_trip(6, |bits| bits as i16);
+    round_trip(7, |bits| bits as i32);
+    round_trip(8, |bits| bits as i64);
+}
+
+#[test]
+fn round_trip_floats_and_bool() {
+    // NaN は等しくならないので、有限の値だけにします
+    round_trip(9, |bits| (bits >> 40) as f32 / 1000.0 - 8000.0);
+    round_trip(10, |bits| f64::from_bits(bits >> 2));
+    round_trip(11, |bits| bits & 1 == 1);
+}
+
+/// 読み込みのエラーから SparseVecError を取り出します (ErrorKind::InvalidData であること)
+fn read_err(bytes: &[u8]) -> SparseVecError {
+    let err = DefaultSparseVec::<i32>::read_compressed(bytes).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData, "{err}");
+    *err.into_inner().unwrap().downcast::<SparseVecError>().unwrap()
+}
+
+fn invalid(reason: &'static str) -> SparseVecError {
+    SparseVecError::InvalidFormat { reason }
+}
+
+/// 末尾の CRC-32 を付け直します (CRC より前を書き換えて、形式の検査まで届かせるため)
+fn reseal(bytes: &mut Vec<u8>) {
+    bytes.truncate(bytes.len() - 4);
+    let crc = crc32(bytes);
+    bytes.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// index [2, 5, 9] (差分 2, 3, 4) の i32 のベクタを書き込んだバイト列と、index の差分の先頭の位置
+fn sample() -> (Vec<u8>, usize) {
+    let vec = DefaultSparseVec::from(vec![0i32, 0, 7, 0, 0, -1, 0, 0, 0, 3]);
+    let mut bytes = Vec::new();
+    vec.write_compressed(&mut bytes, 0).unwrap();
+    // MAGIC, major, minor, TAG, len, nnz, default, index のバイト数
+    let deltas = 4 + 3 + 8 + 8 + i32::SIZE + 8;
+    assert_eq!(bytes[deltas..deltas + 3], [2, 3, 4]);
+    (bytes, deltas)
+}
+
+#[test]
+fn bad_checksum() {
+    let (mut bytes, deltas) = sample();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    assert_eq!(read_err(&bytes), invalid("checksum mismatch"));
+
+    // CRC より前の 1 バイトが変わっても同じです
+    let (mut bytes, _) = sample();
+    bytes[deltas + 1] = 1;
+    assert_eq!(read_err(&bytes), invalid("checksum mismatch"));
+}
+
+#[test]
+fn non_monotonic_indices() {
+    // 2 番目の差分が 0 (index 2 が 2 回)
+    let (mut bytes, deltas) = sample();
+    bytes[deltas + 1] = 0;
+    reseal(&mut bytes);
+    assert_eq!(read_err(&bytes), invalid("non-monotonic indices"));
+
+    // 最後の index が len を超える
+    let (mut bytes, deltas) = sample();
+    bytes[deltas + 2] = 100;
+    reseal(&mut bytes);
+    assert_eq!(read_err(&bytes), invalid("index out of range"));
+}
+
+#[test]
+fn truncated_block() {
+    let (bytes, _) = sample();
+    // どこで切れても、壊れたベクタを作らずに InvalidFormat になります
+    for cut in 0..bytes.len() {
+        match read_err(&bytes[..cut]) {
+            SparseVecError::InvalidFormat { .. } => {}
+            err => panic!("cut at {cut}: {err:?}"),
+        }
+    }
+    // ヘッダの後ろで切れたデータ
+    let (bytes, deltas) = sample();
+    assert_eq!(read_err(&bytes[..deltas + 1]), invalid("truncated"));
+    assert_eq!(read_err(&bytes[..bytes.len() - 5]), invalid("truncated"));
+}
+
+#[test]
+fn corrupt_value_block() {
+    // zstd のフレームの先頭 (magic number) を書き換えて CRC を付け直す
+    // (値のバイト列そのものは CRC でしか守れないので、ここでは展開できないブロックを確かめます)
+    let (mut bytes, deltas) = sample();
+    bytes[deltas + 3 + 8] ^= 0xff;
+    reseal(&mut bytes);
+    assert_eq!(read_err(&bytes), invalid("corrupt value block"));
+}
+
+#[test]
+fn value_type_mismatch() {
+    let (bytes, _) = sample();
+    let err = DefaultSparseVec::<u32>::read_compressed(&bytes[..]).unwrap_err();
+    let err = *err.into_inner().unwrap().downcast::<SparseVecError>().unwrap();
+    assert_eq!(err, invalid("value type mismatch"));
+}
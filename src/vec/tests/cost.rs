@@ -0,0 +1,67 @@
+//! cost_hint (OpKind::Sort) のテスト
+//! build_value_index を並びのばらばらな値で呼び、debug ビルドの検査 (実際の比較回数が見積もり以下) に掛けます
+
+use crate::vec::{
+    cost::{OpCost, OpKind},
+    default_sparse_vec::DefaultSparseVec,
+    error::SparseVecError,
+};
+
+use super::SplitMix64;
+
+/// 物理要素 nnz 個 (index は 1 つおき) の i64 のベクタ
+fn stored(values: impl IntoIterator<Item = i64>) -> DefaultSparseVec<i64> {
+    let values: Vec<i64> = values.into_iter().collect();
+    let indices = (0..values.len()).map(|i| 2 * i).collect();
+    DefaultSparseVec::from_parts(values.len() * 2, 0, indices, values).unwrap()
+}
+
+#[test]
+fn sort_estimate() {
+    let usize_bytes = std::mem::size_of::<usize>();
+    let hint = |nnz: usize| stored((0..nnz as i64).map(|elem| elem + 1)).cost_hint(OpKind::Sort);
+    assert_eq!(hint(0), OpCost::new(0, 0));
+    // 4 * nnz * (ceil(log2 nnz) + 1) 回の比較
+    assert_eq!(hint(1), OpCost::new(5 * usize_bytes, 8));
+    assert_eq!(hint(8), OpCost::new((8 + 128) * usize_bytes, 256));
+    assert_eq!(hint(9), OpCost::new((9 + 180) * usize_bytes, 360));
+    // len やスパース分部には関係しません
+    let vec = DefaultSparseVec::from_parts(1000, 0, vec![0, 500, 999], vec![3, 1, 2]).unwrap();
+    assert_eq!(vec.cost_hint(OpKind::Sort), hint(3));
+}
+
+#[test]
+fn sort_estimate_covers_build_value_index() {
+    let mut rng = SplitMix64(273);
+    for nnz in (0..64).chain([100, 257, 1000, 2048]) {
+        let n = nnz as i64;
+        let patterns: [Vec<i64>; 6] = [
+            (1..=n).collect(),
+            (1..=n).rev().collect(),
+            vec![7; nnz],
+            (0..n).map(|i| if i < n / 2 { i + 1 } else { n - i }).collect(),
+            (0..nnz).map(|_| rng.below(3) as i64 + 1).collect(),
+            (0..nnz).map(|_| rng.next() as i64 | 1).collect(),
+        ];
+        for values in patterns {
+            // debug ビルドでは、見積もりを超えると build_value_index が panic します
+            let mut vec = stored(values);
+            vec.build_value_index();
+            assert!(vec.has_value_index());
+        }
+    }
+}
+
+#[test]
+fn try_with_budget_refuses_sort() {
+    let mut vec = stored(1..=100);
+    let cost = vec.cost_hint(OpKind::Sort);
+    let budget = OpCost::new(usize::MAX, cost.entries_touched - 1);
+    assert_eq!(
+        vec.try_with_budget(budget, OpKind::Sort, |vec| vec.build_value_index()),
+        Err(SparseVecError::OverBudget { cost, budget })
+    );
+    assert!(!vec.has_value_index());
+    assert_eq!(vec.try_with_budget(cost, OpKind::Sort, |vec| vec.build_value_index()), Ok(()));
+    assert!(vec.has_value_index());
+}
@@ -0,0 +1,110 @@
+//! decay_in_place / decay_and_add のテスト
+//! 密な Vec で計算した参照と比べます (値は 1/8 刻み、factor は 0.5 なので計算は厳密です)
+//! epsilon 未満になった物理要素が取り除かれ、格納される値が全て epsilon 以上であることも確かめます
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, error::ValidationMode};
+
+use super::SplitMix64;
+
+const FACTOR: f64 = 0.5;
+const EPSILON: f64 = 0.2;
+
+/// 1/8 刻みの [-2, 2] の値 (密度はランダム)
+fn random_dense(rng: &mut SplitMix64, len: usize) -> Vec<f64> {
+    let density = rng.below(101);
+    (0..len)
+        .map(|_| if rng.below(100) < density { (rng.below(33) as f64 - 16.0) / 8.0 } else { 0.0 })
+        .collect()
+}
+
+/// 減衰後 (と加算後) の値が epsilon 未満なら 0 にした参照
+fn reference(acc: &[f64], events: &[f64]) -> Vec<f64> {
+    acc.iter()
+        .zip(events)
+        .map(|(elem, event)| elem * FACTOR + event)
+        .map(|elem| if elem.abs() < EPSILON { 0.0 } else { elem })
+        .collect()
+}
+
+/// 格納された値が全て epsilon 以上で、物理要素が参照の 0 でない位置と一致すること
+fn assert_pruned(vec: &DefaultSparseVec<f64>, expected: &[f64], what: &str) {
+    assert_eq!(vec.to_dense(), expected, "{what}");
+    let stored: Vec<usize> = expected.iter().enumerate().filter(|(_, elem)| **elem != 0.0).map(|(index, _)| index).collect();
+    assert_eq!(vec.as_pairs().0, stored, "{what}");
+    assert!(vec.as_pairs().1.iter().all(|elem| elem.abs() >= EPSILON), "{what}");
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+}
+
+#[test]
+fn decay_in_place_matches_dense() {
+    let mut rng = SplitMix64(266);
+    for round in 0..100 {
+        let len = rng.below(50);
+        let acc = random_dense(&mut rng, len);
+        let mut vec = DefaultSparseVec::from(acc.clone());
+        vec.decay_in_place(FACTOR, EPSILON);
+        assert_pruned(&vec, &reference(&acc, &vec![0.0; len]), &format!("round {round}"));
+    }
+}
+
+#[test]
+fn decay_and_add_matches_dense() {
+    let mut rng = SplitMix64(2266);
+    for round in 0..100 {
+        let len = rng.below(50);
+        let acc = random_dense(&mut rng, len);
+        let events = random_dense(&mut rng, len);
+        let mut vec = DefaultSparseVec::from(acc.clone());
+        vec.decay_and_add(FACTOR, &DefaultSparseVec::from(events.clone()), EPSILON);
+        assert_pruned(&vec, &reference(&acc, &events), &format!("round {round}"));
+    }
+}
+
+#[test]
+fn entries_below_epsilon_are_pruned() {
+    // 0.25 * 0.5 と 0.375 * 0.5 は epsilon 未満、0.5 * 0.5 は残ります
+    let mut vec = DefaultSparseVec::from(vec![0.25, 0.0, -0.375, 0.5, -4.0]);
+    vec.decay_in_place(FACTOR, EPSILON);
+    assert_eq!(vec.as_pairs(), (&[3, 4][..], &[0.25, -2.0][..]));
+    // もう一度減衰すると 0.125 も取り除かれます
+    vec.decay_in_place(FACTOR, EPSILON);
+    assert_eq!(vec.as_pairs(), (&[4][..], &[-1.0][..]));
+
+    // decay_and_add
+    // - index 0: 0.25 * 0.5 + 0.0 は取り除く
+    // - index 1: 新しい 0.125 も epsilon 未満なので追加しない
+    // - index 2: -0.375 * 0.5 + 0.25 = 0.0625 は取り除く
+    // - index 3: 0.5 * 0.5 - 0.25 = 0 は取り除く
+    // - index 4: -4.0 * 0.5 + 0.5 = -1.5 は残す
+    let mut vec = DefaultSparseVec::from(vec![0.25, 0.0, -0.375, 0.5, -4.0]);
+    vec.decay_and_add(FACTOR, &DefaultSparseVec::from(vec![0.0, 0.125, 0.25, -0.25, 0.5]), EPSILON);
+    assert_eq!(vec.as_pairs(), (&[4][..], &[-1.5][..]));
+}
+
+#[test]
+fn zero_is_pruned_and_nan_is_kept() {
+    let mut vec = DefaultSparseVec::from(vec![1.0, f64::NAN, 2.0]);
+    vec.decay_in_place(0.0, 0.0);
+    // 0 は epsilon が 0 でも取り除き、NaN は残します
+    assert_eq!(vec.as_pairs().0, [1]);
+    assert!(vec.as_pairs().1[0].is_nan());
+}
+
+#[test]
+#[should_panic(expected = "decay_in_place requires a zero default")]
+fn decay_in_place_non_zero_default_panics() {
+    DefaultSparseVec::from_vec_with_default(vec![1.0, 2.0], 1.0).decay_in_place(FACTOR, EPSILON);
+}
+
+#[test]
+#[should_panic(expected = "decay_and_add requires new_events to have a zero default")]
+fn decay_and_add_non_zero_default_panics() {
+    let events = DefaultSparseVec::from_vec_with_default(vec![1.0, 2.0], 1.0);
+    DefaultSparseVec::from(vec![1.0, 2.0]).decay_and_add(FACTOR, &events, EPSILON);
+}
+
+#[test]
+#[should_panic(expected = "different lengths (2 and 1)")]
+fn decay_and_add_len_mismatch_panics() {
+    DefaultSparseVec::from(vec![1.0, 2.0]).decay_and_add(FACTOR, &DefaultSparseVec::from(vec![1.0]), EPSILON);
+}
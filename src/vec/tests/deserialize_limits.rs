@@ -0,0 +1,155 @@
+//! デシリアライズの上限 (DeserializeConfig) のテスト
+//! 大きな len / nnz を申告するヘッダを読ませて、エラーになることと、その間に同時に確保したバイト数 (peak_bytes) が
+//! 申告された大きさによらず小さいことを確かめます
+
+/// 申告された大きさで確保していないことの目安 (実際の入力の大きさ + 固定の小さな確保)
+const PEAK_LIMIT: usize = 64 * 1024;
+
+#[cfg(feature = "serde")]
+mod serde {
+    use ::serde::de::DeserializeSeed;
+    use bincode::Options;
+
+    use crate::vec::{alloc_audit::peak_bytes, default_sparse_vec::DefaultSparseVec, serde_impl::DeserializeConfig};
+
+    use super::PEAK_LIMIT;
+
+    fn bincode_options() -> impl Options {
+        bincode::DefaultOptions::new().with_fixint_encoding().allow_trailing_bytes()
+    }
+
+    /// bincode の DefaultSparseVec<i64> のうち、indices の要素数 (2^40) までを書いたバイト列
+    fn oversized_bincode() -> Vec<u8> {
+        let mut bytes = vec![2, 0];
+        bytes.extend_from_slice(&(1u64 << 50).to_le_bytes());
+        bytes.extend_from_slice(&0i64.to_le_bytes());
+        bytes.extend_from_slice(&(1u64 << 40).to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn json_len_over_limit() {
+        let json = br#"{"version":[2,0],"len":1000000000000,"default":0,"indices":[],"values":[]}"#;
+        let config = DeserializeConfig::new(1 << 20, 1 << 10);
+        let err = config.seed::<i64>().deserialize(&mut serde_json::Deserializer::from_slice(json)).unwrap_err();
+        assert!(err.to_string().contains("len exceeds the configured limit of 1048576"), "{err}");
+        // 上限無しなら読み込めます (len は確保に使いません)
+        let vec: DefaultSparseVec<i64> = serde_json::from_slice(json).unwrap();
+        assert_eq!((vec.len(), vec.nnz()), (1_000_000_000_000, 0));
+    }
+
+    #[test]
+    fn json_nnz_over_limit() {
+        let indices: Vec<usize> = (0..11).collect();
+        let values: Vec<i64> = (1..12).collect();
+        let json = format!(r#"{{"version":[2,0],"len":20,"default":0,"indices":{indices:?},"values":{values:?}}}"#);
+        let config = DeserializeConfig::new(usize::MAX, 10);
+        let err = config.seed::<i64>().deserialize(&mut serde_json::Deserializer::from_str(&json)).unwrap_err();
+        assert!(err.to_string().contains("indices exceeds the configured limit of 10"), "{err}");
+        let config = DeserializeConfig::new(usize::MAX, 11);
+        let vec = config.seed::<i64>().deserialize(&mut serde_json::Deserializer::from_str(&json)).unwrap();
+        assert_eq!(vec.nnz(), 11);
+    }
+
+    #[test]
+    fn oversized_seq_header_is_rejected_before_allocating() {
+        let bytes = oversized_bincode();
+        let config = DeserializeConfig::new(usize::MAX, 1000);
+        let (result, peak) = peak_bytes(|| {
+            config.seed::<i64>().deserialize(&mut bincode::Deserializer::from_slice(&bytes, bincode_options()))
+        });
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("indices exceeds the configured limit of 1000"), "{err}");
+        assert!(peak < PEAK_LIMIT, "peak {peak} bytes");
+    }
+
+    #[test]
+    fn oversized_seq_header_without_limits_allocates_in_chunks() {
+        // 上限が無くても、申告された要素数では一度に確保しません (入力が足りずにエラー)
+        let bytes = oversized_bincode();
+        let (result, peak) = peak_bytes(|| bincode_options().deserialize::<DefaultSparseVec<i64>>(&bytes));
+        assert!(result.is_err());
+        assert!(peak < PEAK_LIMIT, "peak {peak} bytes");
+    }
+}
+
+#[cfg(feature = "compress")]
+mod compress {
+    use std::io::{self, Read};
+
+    use crate::vec::{
+        alloc_audit::peak_bytes,
+        compress::CompressedValue,
+        default_sparse_vec::DefaultSparseVec,
+        error::SparseVecError,
+        format::DeserializeConfig,
+    };
+
+    use super::PEAK_LIMIT;
+
+    /// DefaultSparseVec<i64> の圧縮形式のヘッダ (index のバイト数まで)
+    fn header(len: u64, nnz: u64, index_bytes: u64) -> Vec<u8> {
+        let mut bytes = b"VPSZ".to_vec();
+        bytes.extend_from_slice(&[2, 0, i64::TAG]);
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&nnz.to_le_bytes());
+        bytes.extend_from_slice(&0i64.to_le_bytes());
+        bytes.extend_from_slice(&index_bytes.to_le_bytes());
+        bytes
+    }
+
+    fn read_with(r: impl Read, config: DeserializeConfig) -> (SparseVecError, usize) {
+        let (result, peak) = peak_bytes(|| DefaultSparseVec::<i64>::read_compressed_with(r, config));
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        (*err.into_inner().unwrap().downcast::<SparseVecError>().unwrap(), peak)
+    }
+
+    #[test]
+    fn len_and_nnz_over_limit() {
+        let config = DeserializeConfig::new(1 << 20, 1000);
+        let (err, peak) = read_with(&header(1 << 50, 1 << 40, 1 << 41)[..], config);
+        assert_eq!(err, SparseVecError::LimitExceeded { field: "len", limit: 1 << 20 });
+        assert!(peak < PEAK_LIMIT, "peak {peak} bytes");
+
+        let (err, peak) = read_with(&header(1 << 20, 1 << 40, 1 << 41)[..], config);
+        assert_eq!(err, SparseVecError::LimitExceeded { field: "nnz", limit: 1000 });
+        assert!(peak < PEAK_LIMIT, "peak {peak} bytes");
+    }
+
+    #[test]
+    fn blocks_larger_than_nnz_allows_are_not_read() {
+        // 終わらない入力でも、ヘッダから決まる大きさまでしか読みません
+        let endless = |header: Vec<u8>| io::Cursor::new(header).chain(io::repeat(0x80));
+        let config = DeserializeConfig::new(1 << 20, 1000);
+        let (err, peak) = read_with(endless(header(1 << 20, 1000, 1 << 40)), config);
+        assert_eq!(err, SparseVecError::InvalidFormat { reason: "index stream larger than nnz" });
+        assert!(peak < PEAK_LIMIT, "peak {peak} bytes");
+
+        // index のブロックの後ろの値のブロックのバイト数 (0x8080...) も nnz から決まる上限を超える
+        let (err, peak) = read_with(endless(header(1 << 20, 1000, 10_000)), config);
+        assert_eq!(err, SparseVecError::InvalidFormat { reason: "value block larger than nnz" });
+        assert!(peak < PEAK_LIMIT, "peak {peak} bytes");
+    }
+
+    #[test]
+    fn truncated_input_without_limits() {
+        // 上限が無くても、申告された nnz では確保せずに入力の終わりでエラーになります
+        let (err, peak) = read_with(&header(1 << 50, 1 << 40, 1 << 42)[..], DeserializeConfig::default());
+        assert_eq!(err, SparseVecError::InvalidFormat { reason: "truncated" });
+        assert!(peak < PEAK_LIMIT, "peak {peak} bytes");
+    }
+
+    #[test]
+    fn within_limits_round_trips() {
+        let vec = DefaultSparseVec::from(vec![0i64, 5, 0, -3, 0]);
+        let mut bytes = Vec::new();
+        vec.write_compressed(&mut bytes, 0).unwrap();
+        let config = DeserializeConfig::new(5, 2);
+        assert_eq!(DefaultSparseVec::<i64>::read_compressed_with(&bytes[..], config).unwrap(), vec);
+        let (err, _) = read_with(&bytes[..], DeserializeConfig::new(4, 2));
+        assert_eq!(err, SparseVecError::LimitExceeded { field: "len", limit: 4 });
+        let (err, _) = read_with(&bytes[..], DeserializeConfig::new(5, 1));
+        assert_eq!(err, SparseVecError::LimitExceeded { field: "nnz", limit: 1 });
+    }
+}
@@ -0,0 +1,158 @@
+//! try_div_assign / try_rem_assign / try_div_scalar / try_rem_scalar と *_or_default のテスト
+//! 密な Vec で計算した参照と比べ、割る数が 0 (other の物理要素、other のデフォルト値、scalar) の場合と
+//! len が異なる場合に、エラーを返して何も変更しないことを確かめます
+
+use crate::vec::{
+    default_sparse_vec::DefaultSparseVec,
+    error::{SparseVecError, ValidationMode},
+};
+
+use super::SplitMix64;
+
+/// デフォルト値でない位置だけを割った結果の参照 (割る数が 0 の位置はデフォルト値)
+fn reference(dense: &[i32], default: i32, divisors: &[i32], op: fn(i32, i32) -> i32) -> Vec<i32> {
+    dense
+        .iter()
+        .zip(divisors)
+        .map(|(&elem, &divisor)| match (elem == default, divisor) {
+            (true, _) => default,
+            (false, 0) => default,
+            (false, divisor) => op(elem, divisor),
+        })
+        .collect()
+}
+
+/// 結果がデフォルト値になった物理要素が残っていないこと
+fn assert_compact(vec: &DefaultSparseVec<i32>) {
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+}
+
+#[test]
+fn random_vectors_match_dense() {
+    let mut rng = SplitMix64(275);
+    for round in 0..200 {
+        let len = rng.below(30);
+        let mut random = |default: i32| -> Vec<i32> {
+            let density = rng.below(101);
+            (0..len).map(|_| if rng.below(100) < density { rng.below(21) as i32 - 10 } else { default }).collect()
+        };
+        let dense = random(0);
+        // 割る数のデフォルト値は 0 以外 (0 の位置は物理要素として入ります)
+        let divisors = random(3);
+        let vec = DefaultSparseVec::from(dense.clone());
+        let other = DefaultSparseVec::from_vec_with_default(divisors.clone(), 3);
+
+        for (name, op) in [("div", (|a, b| a / b) as fn(i32, i32) -> i32), ("rem", |a, b| a % b)] {
+            let expected = reference(&dense, 0, &divisors, op);
+            let first_zero = (0..len).find(|&index| dense[index] != 0 && divisors[index] == 0);
+
+            let mut result = vec.clone();
+            let outcome = if name == "div" { result.try_div_assign(&other) } else { result.try_rem_assign(&other) };
+            match first_zero {
+                Some(index) => {
+                    assert_eq!(outcome, Err(SparseVecError::DivisionByZero { index }), "round {round} {name}");
+                    assert_eq!(result.to_dense(), dense, "round {round} {name}");
+                }
+                None => {
+                    assert_eq!(outcome, Ok(()), "round {round} {name}");
+                    assert_eq!(result.to_dense(), expected, "round {round} {name}");
+                }
+            }
+            assert_compact(&result);
+
+            let mut result = vec.clone();
+            if name == "div" {
+                result.div_assign_or_default(&other);
+            } else {
+                result.rem_assign_or_default(&other);
+            }
+            assert_eq!(result.to_dense(), expected, "round {round} {name}_or_default");
+            assert_compact(&result);
+        }
+    }
+}
+
+#[test]
+fn stored_zero_divisor() {
+    let mut vec = DefaultSparseVec::from(vec![0, 8, 0, 9, 7]);
+    let other = DefaultSparseVec::from_vec_with_default(vec![1, 2, 0, 0, 0], 1);
+    // index 2 は自分のスパース分部なので割りません (エラーにもなりません)
+    assert_eq!(vec.try_div_assign(&other), Err(SparseVecError::DivisionByZero { index: 3 }));
+    assert_eq!(vec.try_rem_assign(&other), Err(SparseVecError::DivisionByZero { index: 3 }));
+    assert_eq!(vec.to_dense(), [0, 8, 0, 9, 7]);
+    vec.div_assign_or_default(&other);
+    assert_eq!(vec.to_dense(), [0, 4, 0, 0, 0]);
+    assert_eq!(vec.as_pairs().0, [1]);
+}
+
+#[test]
+fn default_zero_divisor() {
+    // other の物理要素が無い位置は other のデフォルト値 (0) で割ることになります
+    let mut vec = DefaultSparseVec::from(vec![6, 0, 0, 5]);
+    let other = DefaultSparseVec::from(vec![2, 0, 0, 0]);
+    assert_eq!(vec.try_div_assign(&other), Err(SparseVecError::DivisionByZero { index: 3 }));
+    assert_eq!(vec.try_rem_assign(&other), Err(SparseVecError::DivisionByZero { index: 3 }));
+    assert_eq!(vec.to_dense(), [6, 0, 0, 5]);
+    let mut rem = vec.clone();
+    rem.rem_assign_or_default(&other);
+    assert_eq!(rem.to_dense(), [0, 0, 0, 0]);
+    assert_eq!(rem.nnz(), 0);
+    vec.div_assign_or_default(&other);
+    assert_eq!(vec.to_dense(), [3, 0, 0, 0]);
+
+    // 自分のデフォルト値が 0 でない場合、割る数が 0 の位置は自分のデフォルト値になります
+    let mut vec = DefaultSparseVec::from_vec_with_default(vec![6, -1, 5], -1);
+    vec.div_assign_or_default(&DefaultSparseVec::from(vec![2, 0, 0]));
+    assert_eq!(vec.to_dense(), [3, -1, -1]);
+    assert_eq!(vec.nnz(), 1);
+}
+
+#[test]
+fn zero_scalar_divisor() {
+    let mut vec = DefaultSparseVec::from(vec![0, 0, 8, 0, 9]);
+    assert_eq!(vec.try_div_scalar(0), Err(SparseVecError::DivisionByZero { index: 2 }));
+    assert_eq!(vec.try_rem_scalar(0), Err(SparseVecError::DivisionByZero { index: 2 }));
+    assert_eq!(vec.to_dense(), [0, 0, 8, 0, 9]);
+    // 物理要素が無ければ 0 で割ってもエラーになりません
+    let mut empty = DefaultSparseVec::<i32>::from(vec![0; 4]);
+    assert_eq!(empty.try_div_scalar(0), Ok(()));
+    assert_eq!(empty.try_rem_scalar(0), Ok(()));
+
+    assert_eq!(vec.try_div_scalar(3), Ok(()));
+    assert_eq!(vec.to_dense(), [0, 0, 2, 0, 3]);
+    assert_eq!(vec.try_rem_scalar(2), Ok(()));
+    assert_eq!(vec.to_dense(), [0, 0, 0, 0, 1]);
+    assert_eq!(vec.as_pairs().0, [4]);
+}
+
+#[test]
+fn float_division_by_zero_is_an_error() {
+    let mut vec = DefaultSparseVec::from(vec![1.5, 0.0, 3.0]);
+    assert_eq!(vec.try_div_assign(&DefaultSparseVec::from(vec![0.5, 0.0, 0.0])), Err(SparseVecError::DivisionByZero { index: 2 }));
+    assert_eq!(vec.try_div_scalar(0.0), Err(SparseVecError::DivisionByZero { index: 0 }));
+    assert_eq!(vec.to_dense(), [1.5, 0.0, 3.0]);
+}
+
+#[test]
+fn len_mismatch() {
+    let mut vec = DefaultSparseVec::from(vec![4, 0, 6]);
+    let other = DefaultSparseVec::from(vec![2, 2]);
+    assert_eq!(vec.try_div_assign(&other), Err(SparseVecError::LenMismatch { len: 3, other: 2 }));
+    assert_eq!(vec.try_rem_assign(&other), Err(SparseVecError::LenMismatch { len: 3, other: 2 }));
+    // len の検査は割る数の検査より先です
+    let zeros = DefaultSparseVec::from(vec![0; 4]);
+    assert_eq!(vec.try_div_assign(&zeros), Err(SparseVecError::LenMismatch { len: 3, other: 4 }));
+    assert_eq!(vec.to_dense(), [4, 0, 6]);
+}
+
+#[test]
+#[should_panic(expected = "divisor length mismatch (3 and 2)")]
+fn div_assign_or_default_len_mismatch_panics() {
+    DefaultSparseVec::from(vec![4, 0, 6]).div_assign_or_default(&DefaultSparseVec::from(vec![2, 2]));
+}
+
+#[test]
+#[should_panic(expected = "divisor length mismatch (3 and 2)")]
+fn rem_assign_or_default_len_mismatch_panics() {
+    DefaultSparseVec::from(vec![4, 0, 6]).rem_assign_or_default(&DefaultSparseVec::from(vec![2, 2]));
+}
@@ -0,0 +1,172 @@
+use crate::vec::{
+    adaptive_vec::AdaptiveVec,
+    default_sparse_vec::DefaultSparseVec,
+    shared_sparse_vec::SharedSparseVec,
+    vec_trait::{Math, ReductionOrder, SignedMath, UnsignedMath},
+};
+
+fn pair() -> (DefaultSparseVec<i64>, DefaultSparseVec<i64>) {
+    let a = DefaultSparseVec::from(vec![0, 2, 0, -3, 4, 0, 5, 0]);
+    let b = DefaultSparseVec::from(vec![1, 7, 0, 2, 0, 0, -1, 9]);
+    (a, b)
+}
+
+#[test]
+fn owned_shared_and_view_agree() {
+    let (a, b) = pair();
+    // 2*7 + (-3)*2 + 5*(-1)
+    let expected = 3;
+    assert_eq!(a.i128_dot(&b), expected);
+    assert_eq!(a.view().i128_dot(&b.view()), expected);
+    let (sa, sb) = (SharedSparseVec::from(a.clone()), SharedSparseVec::from(b.clone()));
+    assert_eq!(sa.i128_dot(&sb), expected);
+    assert_eq!(a.f64_dot(&b), expected as f64);
+    assert_eq!(sa.f64_dot_with_order(&sb, ReductionOrder::PairwiseTree), expected as f64);
+}
+
+#[test]
+fn shared_slices_with_different_offsets() {
+    let (a, b) = pair();
+    let (sa, sb) = (SharedSparseVec::from(a), SharedSparseVec::from(b));
+    // a[1..5] = [2, 0, -3, 4], b[3..7] = [2, 0, 0, -1]
+    let (x, y) = (sa.slice(1..5), sb.slice(3..7));
+    assert_eq!(x.i128_dot(&y), 2 * 2 - 4);
+    assert_eq!(x.view().i128_dot(&y.view()), 2 * 2 - 4);
+}
+
+#[test]
+fn reversed_view_dot() {
+    let a = DefaultSparseVec::from(vec![1i64, 0, 3]);
+    let b = DefaultSparseVec::from(vec![0i64, 2, 5]);
+    // reversed b = [5, 2, 0]
+    assert_eq!(a.view().i128_dot(&b.reversed_view()), 5);
+    assert_eq!(a.reversed_view().i128_dot(&b.reversed_view()), 15);
+}
+
+#[test]
+#[should_panic(expected = "different lengths")]
+fn length_mismatch_panics() {
+    let a = DefaultSparseVec::from(vec![1i64, 0, 3]);
+    let b = DefaultSparseVec::from(vec![1i64, 0]);
+    let _ = a.i128_dot(&b);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn large_nnz_uses_the_same_order() {
+    // 両方の nnz がブロック単位の走査に切り替わる大きさ (1 << 20) 以上
+    let n = 1_600_000;
+    let a: DefaultSparseVec<f64> = (0..n).map(|i| if i % 3 == 0 { 0.0 } else { 1.0 / (i as f64 + 1.0) }).collect::<Vec<_>>().into();
+    let b: DefaultSparseVec<f64> = (0..n).map(|i| if i % 5 == 0 { 0.0 } else { (i % 7) as f64 + 0.5 }).collect::<Vec<_>>().into();
+    assert!(a.nnz() >= 1 << 20 && b.nnz() >= 1 << 20);
+    let mut expected = 0.0;
+    for i in 0..n {
+        let (x, y) = (*a.get(i).unwrap(), *b.get(i).unwrap());
+        if x != 0.0 && y != 0.0 {
+            expected += x * y;
+        }
+    }
+    assert_eq!(a.f64_dot(&b).to_bits(), expected.to_bits());
+}
+
+#[test]
+fn u128_dot_is_exact_near_u64_max() {
+    let a = DefaultSparseVec::from(vec![u64::MAX, 0, u64::MAX - 1, 3]);
+    let b = DefaultSparseVec::from(vec![u64::MAX, 5, 2, 0]);
+    let max = u64::MAX as u128;
+    // u128::MAX - 2 (u64 の積の和として u128 に収まるほぼ最大の値)
+    let expected = max * max + (max - 1) * 2;
+    assert_eq!(expected, u128::MAX - 2);
+    assert_eq!(a.u128_dot(&b), expected);
+    assert_eq!(a.view().u128_dot(&b.view()), expected);
+    assert_eq!(SharedSparseVec::from(a.clone()).u128_dot(&SharedSparseVec::from(b.clone())), expected);
+    assert_eq!(AdaptiveVec::from(a).u128_dot(&AdaptiveVec::from(b)), expected);
+}
+
+#[test]
+fn u64_dot_is_exact_when_the_result_fits() {
+    let a = DefaultSparseVec::from(vec![u64::MAX / 2, 1, 0]);
+    let b = DefaultSparseVec::from(vec![2, 1, 9]);
+    assert_eq!(a.u64_dot(&b), u64::MAX);
+    let c = DefaultSparseVec::from(vec![u64::MAX, 0]);
+    let d = DefaultSparseVec::from(vec![1, u64::MAX]);
+    assert_eq!(c.u64_dot(&d), u64::MAX);
+}
+
+#[test]
+#[should_panic(expected = "out of range for u64")]
+fn u64_dot_panics_when_the_result_overflows() {
+    let a = DefaultSparseVec::from(vec![u64::MAX, 1]);
+    let b = DefaultSparseVec::from(vec![2u64, 0]);
+    let _ = a.u64_dot(&b);
+}
+
+#[test]
+fn narrow_unsigned_dots() {
+    // 中間の積 (200 * 200) が u8 を超えても、結果が収まれば正確です
+    let a = DefaultSparseVec::from(vec![200u8, 0, 1]);
+    let b = DefaultSparseVec::from(vec![200u8, 9, 0]);
+    let c = DefaultSparseVec::from(vec![1u8, 9, 55]);
+    assert_eq!(a.u32_dot(&b), 40_000);
+    assert_eq!(a.u16_dot(&b), 40_000);
+    assert_eq!(a.u8_dot(&c), 255);
+    let x = DefaultSparseVec::from(vec![u32::MAX, 0]);
+    let y = DefaultSparseVec::from(vec![1u32, 3]);
+    assert_eq!(x.u32_dot(&y), u32::MAX);
+    assert_eq!(x.view().u16_dot(&DefaultSparseVec::from(vec![0u32, 3]).view()), 0);
+}
+
+#[test]
+#[should_panic(expected = "u8_dot result out of range for u8, use u128_dot")]
+fn u8_dot_panics_when_the_result_overflows() {
+    let a = DefaultSparseVec::from(vec![16u8, 1]);
+    let _ = a.u8_dot(&a.clone());
+}
+
+#[test]
+fn narrow_signed_dots() {
+    let a = DefaultSparseVec::from(vec![-100i8, 0, 28]);
+    let b = DefaultSparseVec::from(vec![100i8, 5, -1]);
+    assert_eq!(a.i64_dot(&b), -10_028);
+    assert_eq!(a.i32_dot(&b), -10_028);
+    assert_eq!(a.i16_dot(&b), -10_028);
+    // (-128) * 1 = i8::MIN
+    let c = DefaultSparseVec::from(vec![i8::MIN, 0]);
+    let d = DefaultSparseVec::from(vec![1i8, 7]);
+    assert_eq!(c.i8_dot(&d), i8::MIN);
+    let e = DefaultSparseVec::from(vec![i64::MIN, i64::MAX]);
+    let f = DefaultSparseVec::from(vec![1i64, 1]);
+    assert_eq!(e.i64_dot(&f), -1);
+    assert_eq!(SharedSparseVec::from(e).i64_dot(&SharedSparseVec::from(f)), -1);
+}
+
+#[test]
+#[should_panic(expected = "i16_dot result out of range for i16, use i128_dot")]
+fn i16_dot_panics_when_the_result_overflows() {
+    let a = DefaultSparseVec::from(vec![-200i16, 0]);
+    let _ = a.i16_dot(&a.clone());
+}
+
+#[test]
+fn i128_dot_is_exact_near_i64_limits() {
+    let a = DefaultSparseVec::from(vec![i64::MIN, 0, i64::MAX, -1]);
+    let b = DefaultSparseVec::from(vec![i64::MIN, 7, i64::MIN, i64::MAX]);
+    let (min, max) = (i64::MIN as i128, i64::MAX as i128);
+    let expected = min * min + max * min - max;
+    assert_eq!(a.i128_dot(&b), expected);
+    assert_eq!(AdaptiveVec::from(a).i128_dot(&AdaptiveVec::from(b)), expected);
+}
+
+#[test]
+#[should_panic(expected = "u128_dot overflowed u128")]
+fn u128_dot_overflow_panics() {
+    let a = DefaultSparseVec::from(vec![u128::MAX, 2]);
+    let _ = a.u128_dot(&a.clone());
+}
+
+#[test]
+fn float_dot_of_integer_vector() {
+    let a = DefaultSparseVec::from(vec![1u32, 0, 3]);
+    let b = DefaultSparseVec::from(vec![2u32, 5, 4]);
+    assert_eq!(a.f64_dot(&b), 14.0);
+}
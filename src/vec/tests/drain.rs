@@ -0,0 +1,135 @@
+//! drain のテスト
+//! スパースな区間 [0, 50) と密な区間 [50, 70) を持つベクタで、各種の範囲を Vec::drain と比べます
+
+use std::{cell::Cell, mem, ops::RangeBounds, rc::Rc};
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, error::ValidationMode};
+
+use super::Counted;
+
+/// [0, 50) は 10 個おきに値、[50, 70) は全て値
+fn sample() -> Vec<i32> {
+    (0..70).map(|i| if i >= 50 || i % 10 == 3 { i + 1 } else { 0 }).collect()
+}
+
+/// drain した要素と残りを Vec::drain と比べます
+fn check<R: RangeBounds<usize> + Clone + std::fmt::Debug>(range: R) {
+    let mut dense = sample();
+    let mut vec = DefaultSparseVec::from(dense.clone());
+    let drained: Vec<i32> = vec.drain(range.clone()).collect();
+    let expected: Vec<i32> = dense.drain(range.clone()).collect();
+    assert_eq!(drained, expected, "{range:?}");
+    assert_eq!(vec.to_dense(), dense, "{range:?}");
+    assert_eq!(vec.len(), dense.len());
+    assert_eq!(vec.nnz(), dense.iter().filter(|elem| **elem != 0).count());
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+}
+
+#[test]
+fn middle_of_the_sparse_region() {
+    check(10..30);
+    check(4..13);
+    check(14..23);
+}
+
+#[test]
+fn middle_of_the_dense_region() {
+    check(55..65);
+    check(60..=60);
+    check(50..70);
+}
+
+#[test]
+fn across_both_regions() {
+    check(45..60);
+    check(3..66);
+}
+
+#[test]
+fn empty_range() {
+    check(20..20);
+    check(55..55);
+    check(70..70);
+    check(0..0);
+}
+
+#[test]
+fn inclusive_and_open_ranges() {
+    check(..=55);
+    check(..=0);
+    check(..=69);
+    check(33..);
+    check(..);
+}
+
+#[test]
+fn drained_from_both_ends() {
+    let mut dense = sample();
+    let mut vec = DefaultSparseVec::from(dense.clone());
+    let mut drain = vec.drain(40..60);
+    assert_eq!(drain.len(), 20);
+    assert_eq!(drain.next_back(), Some(60));
+    assert_eq!(drain.next(), Some(0));
+    assert_eq!(drain.nth(2), Some(44));
+    assert_eq!(drain.len(), 15);
+    drop(drain);
+    dense.drain(40..60);
+    assert_eq!(vec.to_dense(), dense);
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+}
+
+#[test]
+fn partial_consumption_drops_the_rest_once() {
+    let drops = Rc::new(Cell::new(0));
+    let mut vec = DefaultSparseVec::new();
+    for id in [1, 0, 2, 3, 0, 4, 5] {
+        vec.push(Counted::new(id, &drops));
+    }
+    drops.set(0);
+    let mut drain = vec.drain(1..6);
+    // index 1 はスパース分部 (デフォルト値の clone は数えません)
+    assert_eq!(drain.next().map(|elem| elem.id), Some(0));
+    assert_eq!(drain.next().map(|elem| elem.id), Some(2));
+    assert_eq!(drops.get(), 1);
+    drop(drain);
+    // 返していない物理要素 (3, 4) だけが drop されます
+    assert_eq!(drops.get(), 3);
+    assert_eq!(vec.iter().map(|(index, elem)| (index, elem.id)).collect::<Vec<_>>(), [(0, 1), (1, 5)]);
+    assert_eq!(vec.len(), 2);
+    drop(vec);
+    assert_eq!(drops.get(), 5);
+}
+
+#[test]
+fn forget_leaves_the_vector_truncated_but_valid() {
+    let mut vec = DefaultSparseVec::from(sample());
+    let mut drain = vec.drain(30..60);
+    assert_eq!(drain.next(), Some(0));
+    mem::forget(drain);
+    // range の始まりで切り詰められたままです (後ろの要素はリークするだけ)
+    let expected: Vec<i32> = sample()[..30].to_vec();
+    assert_eq!(vec.to_dense(), expected);
+    assert_eq!(vec.len(), 30);
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+    // そのまま使い続けられます
+    vec.push(100);
+    vec.insert(0, 7);
+    assert_eq!(vec.len(), 32);
+    assert_eq!(vec.get(31), Some(&100));
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+}
+
+#[test]
+#[should_panic(expected = "drain range end 71 out of bounds for len 70")]
+fn end_past_len_panics() {
+    let mut vec = DefaultSparseVec::from(sample());
+    vec.drain(60..=70);
+}
+
+#[test]
+#[should_panic(expected = "drain range starts at 5 but ends at 4")]
+fn start_after_end_panics() {
+    let mut vec = DefaultSparseVec::from(sample());
+    #[allow(clippy::reversed_empty_ranges)]
+    vec.drain(5..4);
+}
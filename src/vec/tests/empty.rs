@@ -0,0 +1,149 @@
+//! 空のベクタの適合テスト
+//! DefaultSparseVec / SharedSparseVec / AdaptiveVec の公開メソッドを空のベクタで呼び、ドキュメント通りに動くこと
+//! (None / 空のイテレータ / 空の集計を返すか、範囲外として Vec と同じく index と len を含むメッセージで panic すること)
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::vec::{
+    adaptive_vec::AdaptiveVec,
+    default_sparse_vec::DefaultSparseVec,
+    shared_sparse_vec::SharedSparseVec,
+    vec_trait::{Math, SignedMath},
+};
+
+use super::panic_message;
+
+/// f が panic し、そのメッセージが expected を含むこと
+fn assert_panics_with(expected: &str, f: impl FnOnce()) {
+    let err = catch_unwind(AssertUnwindSafe(f)).unwrap_err();
+    let message = panic_message(&*err);
+    assert!(message.contains(expected), "expected a panic containing {expected:?}, got {message:?}");
+}
+
+#[test]
+fn default_sparse_vec_reads() {
+    let vec = DefaultSparseVec::<i64>::new();
+    assert_eq!((vec.len(), vec.nnz(), vec.capacity()), (0, 0, 0));
+    assert!(vec.is_empty());
+    assert_eq!(vec.get(0), None);
+    assert_eq!((vec.first(), vec.last()), (None, None));
+    assert!(!vec.contains(&0));
+    assert_eq!(vec.iter().count(), 0);
+    assert_eq!(vec.iter_dense().count(), 0);
+    assert_eq!(vec.gaps().count(), 0);
+    assert_eq!(vec.largest_gap(), None);
+    assert_eq!(vec.top_k(3), []);
+    assert_eq!(vec.count_default(), 0);
+    assert_eq!(vec.density(), 0.0);
+    assert!(vec.to_dense().is_empty());
+    assert!(vec.to_sparse_map().is_empty());
+    assert!(vec.softmax(0.0).is_empty());
+    assert_eq!(vec.heap_size_bytes(), 0);
+    assert_eq!(format!("{vec:?}"), format!("{:?}", DefaultSparseVec::<i64>::default()));
+}
+
+#[test]
+fn default_sparse_vec_summaries() {
+    let vec = DefaultSparseVec::<f64>::new();
+    let summary = vec.summarize();
+    assert_eq!((summary.len, summary.nnz, summary.stored_defaults, summary.largest_gap), (0, 0, 0, 0));
+    assert_eq!((summary.min, summary.max, summary.mean), (None, None, None));
+    assert_eq!((summary.first_index, summary.last_index), (None, None));
+    let stats = vec.stats();
+    assert_eq!((stats.len, stats.nnz, stats.cap, stats.heap_bytes), (0, 0, 0, 0));
+    assert!(!summary.to_string().is_empty());
+    assert!(!vec.tuning_report().is_empty());
+}
+
+#[test]
+fn default_sparse_vec_writes() {
+    let mut vec = DefaultSparseVec::<String>::new();
+    assert_eq!(vec.pop(), None);
+    assert_eq!(vec.pop_front(), None);
+    vec.truncate(0);
+    vec.clear();
+    vec.retain(|_, _| false);
+    vec.shrink_to_fit();
+    assert_eq!(vec.drain(..).count(), 0);
+    assert!(vec.clone().is_empty());
+    let (left, right) = vec.partition(|_, _| true);
+    assert!(left.is_empty() && right.is_empty());
+    {
+        let (left, right) = vec.split_at_mut_view(0);
+        assert!(left.is_empty() && right.is_empty());
+    }
+    let mut other = DefaultSparseVec::new();
+    vec.append(&mut other);
+    assert_eq!((vec.len(), vec.capacity()), (0, 0));
+}
+
+#[test]
+fn default_sparse_vec_reserved_capacity() {
+    let mut vec = DefaultSparseVec::<String>::with_capacity(4);
+    assert!(vec.clone().is_empty());
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 0);
+}
+
+#[test]
+fn default_sparse_vec_out_of_bounds() {
+    let mut vec = DefaultSparseVec::<i64>::new();
+    assert_panics_with("insertion index (is 1) should be <= len (is 0)", || vec.clone().insert(1, 5));
+    assert_panics_with("removal index (is 0) should be < len (is 0)", || {
+        vec.clone().remove(0);
+    });
+    assert_panics_with("the len is 0 but the index is 0", || {
+        let _ = vec.clone()[0];
+    });
+    // 長さ 0 の位置への insert は push と同じ
+    vec.insert(0, 5);
+    assert_eq!(vec.to_dense(), [5]);
+}
+
+#[test]
+fn dots_of_empty_vectors() {
+    let (a, b) = (DefaultSparseVec::<i64>::new(), DefaultSparseVec::<i64>::new());
+    assert_eq!(a.i128_dot(&b), 0);
+    assert_eq!(a.f64_dot(&b), 0.0);
+    let one = DefaultSparseVec::from(vec![1i64]);
+    assert_panics_with("different lengths (0 and 1)", || {
+        let _ = a.f64_dot(&one);
+    });
+    let (sa, sone) = (SharedSparseVec::from(a.clone()), SharedSparseVec::from(one.clone()));
+    assert_eq!(sa.i128_dot(&sa.clone()), 0);
+    assert_panics_with("different lengths (0 and 1)", || {
+        let _ = sa.i128_dot(&sone);
+    });
+    let (aa, aone) = (AdaptiveVec::from(a), AdaptiveVec::from(one));
+    assert_eq!(aa.f64_dot(&aa.clone()), 0.0);
+    assert_panics_with("different lengths (1 and 0)", || {
+        let _ = aone.i128_dot(&aa);
+    });
+}
+
+#[test]
+fn shared_sparse_vec() {
+    let vec = SharedSparseVec::from(DefaultSparseVec::<i64>::new());
+    assert_eq!((vec.len(), vec.nnz()), (0, 0));
+    assert!(vec.is_empty());
+    assert_eq!(vec.get(0), None);
+    assert_eq!(vec.iter().count(), 0);
+    let slice = vec.slice(0..0);
+    assert!(slice.is_empty());
+    assert_eq!(slice.view().len(), 0);
+}
+
+#[test]
+fn adaptive_vec() {
+    let mut vec = AdaptiveVec::<i64>::default();
+    assert_eq!((vec.len(), vec.nnz()), (0, 0));
+    assert!(vec.is_empty());
+    assert_eq!(vec.density(), 0.0);
+    assert_eq!(vec.get(0), None);
+    assert_eq!(vec.pop(), None);
+    vec.prune();
+    assert_panics_with("the len is 0 but the index is 0", || vec.clone().set(0, 1));
+    assert_panics_with("insertion index (is 2) should be <= len (is 0)", || vec.clone().insert(2, 1));
+    assert!(vec.clone().into_sparse().is_empty());
+    assert!(vec.into_dense().is_empty());
+}
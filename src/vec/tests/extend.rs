@@ -0,0 +1,39 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::vec::default_sparse_vec::{CapPolicy, DefaultSparseVec};
+
+use super::panic_message;
+
+#[test]
+fn extend_pairs_grows_len_to_last_index() {
+    let mut vec = DefaultSparseVec::from(vec![0i32, 1]);
+    vec.extend([(5, 7), (0, 2), (5, 8)]);
+    assert_eq!(vec.len(), 6);
+    assert_eq!(vec.to_dense(), vec![2, 1, 0, 0, 0, 8]);
+}
+
+#[test]
+fn extend_pairs_usize_max_index_panics_without_change() {
+    let mut vec = DefaultSparseVec::from(vec![0i32, 1]);
+    let err = catch_unwind(AssertUnwindSafe(|| vec.extend([(usize::MAX, 3)]))).unwrap_err();
+    let message = panic_message(&*err);
+    assert!(message.contains("usize::MAX"), "unexpected panic message: {message}");
+    assert_eq!(vec.len(), 2);
+    assert_eq!(vec.to_dense(), vec![0, 1]);
+}
+
+#[test]
+fn extend_pairs_over_cap_error_keeps_len() {
+    let mut vec = DefaultSparseVec::from(vec![0i32, 1, 0]);
+    vec.set_max_nnz(Some(2));
+    vec.set_cap_policy(CapPolicy::Error);
+    let result = catch_unwind(AssertUnwindSafe(|| vec.extend([(4, 2), (9, 3)])));
+    assert!(result.is_err());
+    assert_eq!(vec.len(), 3);
+    assert_eq!(vec.to_dense(), vec![0, 1, 0]);
+
+    // 上限内なら通常通り伸びる
+    vec.extend([(4, 2)]);
+    assert_eq!(vec.len(), 5);
+    assert_eq!(vec.nnz(), 2);
+}
@@ -0,0 +1,99 @@
+//! FromIterator (値 / (index, 値) の組) を Result<_, E> に collect するテスト
+//! 途中でエラーを返すイテレータから collect して、エラーがそのまま返ることと、作った値がちょうど 1 回ずつ drop されることを確かめます
+
+use std::{cell::Cell, rc::Rc};
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+use super::Counted;
+
+/// イテレータが返すエラー (drop を数える値を持ちます)
+#[derive(Debug, PartialEq)]
+struct Failed {
+    at: usize,
+    payload: Counted,
+}
+
+/// 8 要素のうち fail_at 番目 (無ければ最後まで) でエラーを返すイテレータ
+/// - 1 つ進めるごとに値を作ります (作った数を created に数えます)
+/// - id は 0 (デフォルト値) と 0 以外が混ざります
+fn source<'a>(
+    fail_at: Option<usize>,
+    created: &'a Cell<usize>,
+    drops: &'a Rc<Cell<u32>>,
+) -> impl Iterator<Item = Result<Counted, Failed>> + 'a {
+    (0..8).map(move |at| {
+        created.set(created.get() + 1);
+        let elem = Counted::new(id_at(at), drops);
+        if Some(at) == fail_at {
+            Err(Failed { at, payload: elem })
+        } else {
+            Ok(elem)
+        }
+    })
+}
+
+fn id_at(at: usize) -> u32 {
+    if at % 3 == 1 { 0 } else { at as u32 + 1 }
+}
+
+fn ids(vec: &DefaultSparseVec<Counted>) -> Vec<u32> {
+    vec.iter_dense().map(|elem| elem.id).collect()
+}
+
+#[test]
+fn values_error_midway() {
+    for fail_at in 0..8 {
+        let created = Cell::new(0);
+        let drops = Rc::new(Cell::new(0));
+        let result: Result<DefaultSparseVec<Counted>, Failed> = source(Some(fail_at), &created, &drops).collect();
+        let err = result.unwrap_err();
+        assert_eq!((err.at, err.payload.id), (fail_at, id_at(fail_at)));
+        // エラーの後ろは進めません
+        assert_eq!(created.get(), fail_at + 1);
+        // 途中まで作ったベクタの値は drop され、エラーの値だけが残ります
+        assert_eq!(drops.get() as usize, fail_at, "fail_at {fail_at}");
+        drop(err);
+        assert_eq!(drops.get() as usize, created.get());
+    }
+}
+
+#[test]
+fn values_without_error() {
+    let created = Cell::new(0);
+    let drops = Rc::new(Cell::new(0));
+    let vec: DefaultSparseVec<Counted> = source(None, &created, &drops).collect::<Result<_, _>>().unwrap();
+    assert_eq!(ids(&vec), [1, 0, 3, 4, 0, 6, 7, 0]);
+    // デフォルト値と等しい値は push の時点で drop されます
+    assert_eq!((vec.nnz(), drops.get()), (5, 3));
+    drop(vec);
+    assert_eq!(drops.get() as usize, created.get());
+}
+
+#[test]
+fn pairs_error_midway() {
+    for fail_at in 0..8 {
+        let created = Cell::new(0);
+        let drops = Rc::new(Cell::new(0));
+        // index は逆順 (ソートが必要な入力)
+        let pairs = source(Some(fail_at), &created, &drops).enumerate().map(|(at, elem)| elem.map(|elem| (10 - at, elem)));
+        let result: Result<DefaultSparseVec<Counted>, Failed> = pairs.collect();
+        let err = result.unwrap_err();
+        assert_eq!((err.at, err.payload.id), (fail_at, id_at(fail_at)));
+        assert_eq!(created.get(), fail_at + 1);
+        assert_eq!(drops.get() as usize, fail_at, "fail_at {fail_at}");
+        drop(err);
+        assert_eq!(drops.get() as usize, created.get());
+    }
+}
+
+#[test]
+fn pairs_without_error() {
+    let created = Cell::new(0);
+    let drops = Rc::new(Cell::new(0));
+    let pairs = source(None, &created, &drops).enumerate().map(|(at, elem)| elem.map(|elem| (10 - at, elem)));
+    let vec: DefaultSparseVec<Counted> = pairs.collect::<Result<_, _>>().unwrap();
+    assert_eq!(ids(&vec), [0, 0, 0, 0, 7, 6, 0, 4, 3, 0, 1]);
+    drop(vec);
+    assert_eq!(drops.get() as usize, created.get());
+}
@@ -0,0 +1,180 @@
+//! シリアライズ形式のゴールデンテスト
+//! golden/ の記録したバイト列と、書き出したバイト列が 1 バイトも違わないこと、記録したバイト列を読み込めることを確かめます
+//! - 形式を意図して変えた場合だけ、FORMAT_VERSION を上げてから VEC_PLUS_BLESS=1 で記録し直します
+//! - *_v1_0_* は形式 1.0 で書き出したデータ (upgrade_from で読み込みます)
+//! - *_v3_0_* は未来の major のデータ (読み込まずに UnsupportedVersion になること)
+
+use std::path::PathBuf;
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/vec/tests/golden").join(name)
+}
+
+/// 記録したバイト列を読みます
+fn golden(name: &str) -> Vec<u8> {
+    std::fs::read(golden_path(name)).unwrap_or_else(|err| panic!("missing golden file {name}: {err}"))
+}
+
+/// bytes が記録したバイト列と同じであること (VEC_PLUS_BLESS が設定されていれば記録し直します)
+fn assert_golden(name: &str, bytes: &[u8]) {
+    if std::env::var_os("VEC_PLUS_BLESS").is_some() {
+        std::fs::write(golden_path(name), bytes).unwrap();
+        return;
+    }
+    assert!(golden(name) == bytes, "serialized bytes of {name} changed (bump FORMAT_VERSION and re-bless if intended)");
+}
+
+/// 各要素型の代表的なベクタ
+/// - index の差分が 1 バイトと 2 バイト以上の可変長整数になる位置
+/// - 先頭と末尾の位置、最小値と最大値
+fn sample<T: Default + PartialEq>(default: T, values: Vec<T>) -> DefaultSparseVec<T> {
+    let indices = [0, 1, 5, 130, 299, 20_000].into_iter().take(values.len()).collect();
+    DefaultSparseVec::from_parts(20_001, default, indices, values).unwrap()
+}
+
+#[cfg(feature = "compress")]
+mod compress {
+    use std::io::ErrorKind;
+
+    use crate::vec::{
+        compress::{upgrade_from, CompressedValue},
+        default_sparse_vec::DefaultSparseVec,
+        error::SparseVecError,
+        format::FormatVersion,
+    };
+
+    use super::{assert_golden, golden, sample};
+
+    fn check<T: Default + PartialEq + CompressedValue + std::fmt::Debug>(name: &str, vec: DefaultSparseVec<T>) {
+        let mut bytes = Vec::new();
+        vec.write_compressed(&mut bytes, 3).unwrap();
+        assert_golden(name, &bytes);
+        assert_eq!(DefaultSparseVec::<T>::read_compressed(&golden(name)[..]).unwrap(), vec, "{name}");
+    }
+
+    #[test]
+    fn unsigned() {
+        check("compress_v2_0_u8.bin", sample(0u8, vec![1, u8::MAX, 7, 2, 200, 9]));
+        check("compress_v2_0_u16.bin", sample(0u16, vec![1, u16::MAX, 7, 2, 300, 9]));
+        check("compress_v2_0_u32.bin", sample(0u32, vec![1, u32::MAX, 7, 2, 70_000, 9]));
+        check("compress_v2_0_u64.bin", sample(0u64, vec![1, u64::MAX, 7, 2, 1 << 40, 9]));
+    }
+
+    #[test]
+    fn signed() {
+        check("compress_v2_0_i8.bin", sample(0i8, vec![i8::MIN, i8::MAX, -1, 2, 100, -9]));
+        check("compress_v2_0_i16.bin", sample(0i16, vec![i16::MIN, i16::MAX, -1, 2, 300, -9]));
+        check("compress_v2_0_i32.bin", sample(0i32, vec![i32::MIN, i32::MAX, -1, 2, 70_000, -9]));
+        check("compress_v2_0_i64.bin", sample(0i64, vec![i64::MIN, i64::MAX, -1, 2, 1 << 40, -9]));
+    }
+
+    #[test]
+    fn float_and_bool() {
+        check("compress_v2_0_f32.bin", sample(0.0f32, vec![f32::MIN, f32::MAX, -1.5, f32::EPSILON, 1e-30, 0.1]));
+        check("compress_v2_0_f64.bin", sample(0.0f64, vec![f64::MIN, f64::MAX, -1.5, f64::EPSILON, 1e-300, 0.1]));
+        check("compress_v2_0_bool.bin", sample(false, vec![true, true, true]));
+        // デフォルト値が 0 ではないベクタ
+        check("compress_v2_0_i32_default.bin", sample(-1i32, vec![0, 5, 0]));
+    }
+
+    #[test]
+    fn v1_0_blob_loads_through_upgrade_from() {
+        let v1 = golden("compress_v1_0_i32.bin");
+        let expected = DefaultSparseVec::<i32>::read_compressed(&golden("compress_v2_0_i32.bin")[..]).unwrap();
+        let upgraded = upgrade_from(FormatVersion::new(1, 0), &v1).unwrap();
+        assert_eq!(upgraded, golden("compress_v2_0_i32.bin"));
+        assert_eq!(DefaultSparseVec::<i32>::read_compressed(&upgraded[..]).unwrap(), expected);
+        // read_compressed も古い major を upgrade_from で読み込みます
+        assert_eq!(DefaultSparseVec::<i32>::read_compressed(&v1[..]).unwrap(), expected);
+        // 宣言したバージョンとヘッダが合わない
+        assert_eq!(
+            upgrade_from(FormatVersion::new(1, 0), &golden("compress_v2_0_i32.bin")),
+            Err(SparseVecError::InvalidFormat { reason: "version mismatch" })
+        );
+    }
+
+    #[test]
+    fn newer_major_is_refused() {
+        let v3 = golden("compress_v3_0_i32.bin");
+        let err = DefaultSparseVec::<i32>::read_compressed(&v3[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let inner = err.into_inner().unwrap().downcast::<SparseVecError>().unwrap();
+        assert_eq!(*inner, SparseVecError::UnsupportedVersion { major: 3, minor: 0 });
+        assert_eq!(
+            upgrade_from(FormatVersion::new(3, 0), &v3),
+            Err(SparseVecError::UnsupportedVersion { major: 3, minor: 0 })
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde {
+    use ::serde::{de::DeserializeSeed, Deserialize, Serialize};
+    use bincode::Options;
+
+    use crate::vec::{default_sparse_vec::DefaultSparseVec, format::FormatVersion, serde_impl::DeserializeConfig};
+
+    use super::{assert_golden, golden, sample};
+
+    /// bincode::serialize と同じ設定 (固定長の整数、seq の形式)
+    fn bincode_options() -> impl Options {
+        bincode::DefaultOptions::new().with_fixint_encoding().allow_trailing_bytes()
+    }
+
+    fn check<T>(name: &str, vec: DefaultSparseVec<T>)
+    where
+        T: Default + PartialEq + Serialize + for<'de> Deserialize<'de> + std::fmt::Debug,
+    {
+        let json = serde_json::to_vec(&vec).unwrap();
+        assert_golden(&format!("{name}.json"), &json);
+        let loaded: DefaultSparseVec<T> = serde_json::from_slice(&golden(&format!("{name}.json"))).unwrap();
+        assert_eq!(loaded, vec, "{name}.json");
+
+        let bin = bincode_options().serialize(&vec).unwrap();
+        assert_golden(&format!("{name}.bincode"), &bin);
+        let loaded: DefaultSparseVec<T> = bincode_options().deserialize(&golden(&format!("{name}.bincode"))).unwrap();
+        assert_eq!(loaded, vec, "{name}.bincode");
+    }
+
+    #[test]
+    fn element_types() {
+        check("serde_v2_0_u8", sample(0u8, vec![1, u8::MAX, 7, 2, 200, 9]));
+        check("serde_v2_0_i64", sample(0i64, vec![i64::MIN, i64::MAX, -1, 2, 1 << 40, -9]));
+        check("serde_v2_0_f64", sample(0.0f64, vec![f64::MIN, f64::MAX, -1.5, f64::EPSILON, 1e-300, 0.1]));
+        check("serde_v2_0_bool", sample(false, vec![true, true, true]));
+        check("serde_v2_0_string", sample(String::new(), vec!["a".into(), "日本語".into(), "\"quoted\"".into()]));
+        check("serde_v2_0_i32_default", sample(-1i32, vec![0, 5, 0]));
+    }
+
+    #[test]
+    fn v1_0_blobs_load_through_upgrade_from() {
+        let expected = sample(0i64, vec![i64::MIN, i64::MAX, -1, 2, 1 << 40, -9]);
+        let config = DeserializeConfig::default().upgrade_from(FormatVersion::new(1, 0));
+
+        // map の形式は version が無ければ 1.0 として読みます
+        let json = golden("serde_v1_0_i64.json");
+        let loaded: DefaultSparseVec<i64> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(loaded, expected);
+        let loaded = config.seed().deserialize(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+        assert_eq!(loaded, expected);
+
+        // seq の形式は version を持たないので、upgrade_from で指定します
+        let bin = golden("serde_v1_0_i64.bincode");
+        let loaded = config
+            .seed()
+            .deserialize(&mut bincode::Deserializer::from_slice(&bin, bincode_options()))
+            .unwrap();
+        assert_eq!(loaded, expected);
+    }
+
+    #[test]
+    fn newer_major_is_refused() {
+        let json = br#"{"version":[3,0],"len":1,"default":0,"indices":[],"values":[]}"#;
+        let err = serde_json::from_slice::<DefaultSparseVec<i64>>(json).unwrap_err();
+        assert!(err.to_string().contains("3.0"), "{err}");
+        let config = DeserializeConfig::default().upgrade_from(FormatVersion::new(3, 0));
+        assert!(config.seed::<i64>().deserialize(&mut serde_json::Deserializer::from_slice(json)).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! ingest / try_ingest のテスト
+//! 未ソート、重複 (合計がデフォルト値に戻るものを含む)、範囲外、デフォルト値を含む入力で、各 policy の結果と IngestReport の件数を確かめます
+
+use crate::vec::{
+    default_sparse_vec::DefaultSparseVec,
+    error::SparseVecError,
+    ingest::{DuplicatePolicy, IngestPolicy, IngestReport, OutOfRangePolicy},
+};
+
+use super::SplitMix64;
+
+/// len 10 に対する入力
+/// - 範囲外: 12 (2 回), 10
+/// - 重複: 3 (3 回), 5 (2 回), 7 (2 回、合計は 0)
+/// - デフォルト値: (0, 0), (5, 0)
+const MESSY: [(usize, i32); 12] =
+    [(7, 2), (3, 1), (12, 5), (3, 4), (0, 0), (9, -1), (10, 8), (7, -2), (3, 2), (5, 0), (5, 6), (12, 1)];
+
+fn report(duplicates_collapsed: usize, out_of_range: (usize, usize), defaults_skipped: usize, stored: usize) -> IngestReport {
+    IngestReport {
+        pairs: MESSY.len(),
+        duplicates_collapsed,
+        out_of_range_dropped: out_of_range.0,
+        out_of_range_kept: out_of_range.1,
+        defaults_skipped,
+        stored,
+    }
+}
+
+fn ingest(duplicates: DuplicatePolicy, out_of_range: OutOfRangePolicy) -> (Vec<i32>, IngestReport) {
+    let (vec, report) = DefaultSparseVec::ingest(10, MESSY, IngestPolicy::new(duplicates, out_of_range));
+    assert_eq!(vec.count_stored_defaults(), 0);
+    assert_eq!(vec.nnz(), report.stored);
+    (vec.to_dense(), report)
+}
+
+#[test]
+fn sum_and_drop() {
+    assert_eq!(
+        ingest(DuplicatePolicy::Sum, OutOfRangePolicy::Drop),
+        (vec![0, 0, 0, 7, 0, 6, 0, 0, 0, -1], report(4, (3, 0), 2, 3))
+    );
+}
+
+#[test]
+fn first_and_drop() {
+    assert_eq!(
+        ingest(DuplicatePolicy::First, OutOfRangePolicy::Drop),
+        (vec![0, 0, 0, 1, 0, 0, 0, 2, 0, -1], report(4, (3, 0), 2, 3))
+    );
+}
+
+#[test]
+fn last_and_drop_is_the_default_policy() {
+    let expected = (vec![0, 0, 0, 2, 0, 6, 0, -2, 0, -1], report(4, (3, 0), 1, 4));
+    assert_eq!(ingest(DuplicatePolicy::Last, OutOfRangePolicy::Drop), expected);
+    let (vec, report) = DefaultSparseVec::ingest(10, MESSY, IngestPolicy::default());
+    assert_eq!((vec.to_dense(), report), expected);
+}
+
+#[test]
+fn sum_and_grow_len() {
+    let (vec, report) = DefaultSparseVec::ingest(10, MESSY, IngestPolicy::new(DuplicatePolicy::Sum, OutOfRangePolicy::GrowLen));
+    // 12 の 2 つもまとめられ (重複 5)、len は 13 になります
+    assert_eq!(vec.to_dense(), [0, 0, 0, 7, 0, 6, 0, 0, 0, -1, 8, 0, 6]);
+    assert_eq!(report, self::report(5, (0, 3), 2, 5));
+}
+
+#[test]
+fn error_policies() {
+    let policy = IngestPolicy::new(DuplicatePolicy::Error, OutOfRangePolicy::Drop);
+    assert_eq!(DefaultSparseVec::try_ingest(10, MESSY, policy), Err(SparseVecError::DuplicateIndex { index: 3 }));
+    // 範囲外は入力順で最初のもの
+    let policy = IngestPolicy::new(DuplicatePolicy::Sum, OutOfRangePolicy::Error);
+    assert_eq!(DefaultSparseVec::try_ingest(10, MESSY, policy), Err(SparseVecError::IndexOutOfBounds { index: 12, len: 10 }));
+    // 重複も範囲外も無ければエラーにしません
+    let policy = IngestPolicy::new(DuplicatePolicy::Error, OutOfRangePolicy::Error);
+    let (vec, report) = DefaultSparseVec::try_ingest(4, [(3, 1), (0, 2), (1, 0)], policy).unwrap();
+    assert_eq!(vec.to_dense(), [2, 0, 0, 1]);
+    assert_eq!(
+        report,
+        IngestReport { pairs: 3, duplicates_collapsed: 0, out_of_range_dropped: 0, out_of_range_kept: 0, defaults_skipped: 1, stored: 2 }
+    );
+}
+
+#[test]
+#[should_panic(expected = "index 3 appears more than once in the input")]
+fn ingest_panics_on_error_policy() {
+    let _ = DefaultSparseVec::ingest(10, MESSY, IngestPolicy::new(DuplicatePolicy::Error, OutOfRangePolicy::Drop));
+}
+
+#[test]
+fn empty_and_sorted_input() {
+    let (vec, report) = DefaultSparseVec::<i32>::ingest(5, [], IngestPolicy::default());
+    assert_eq!((vec.len(), vec.nnz(), report), (5, 0, IngestReport::default()));
+
+    // 既にソート済みの入力も同じ結果になります
+    let mut sorted = MESSY;
+    sorted.sort_by_key(|(index, _)| *index);
+    let policy = IngestPolicy::new(DuplicatePolicy::Sum, OutOfRangePolicy::Drop);
+    let (vec, report) = DefaultSparseVec::ingest(10, sorted, policy);
+    assert_eq!((vec.to_dense(), report), ingest(DuplicatePolicy::Sum, OutOfRangePolicy::Drop));
+}
+
+#[test]
+fn random_input_matches_dense_accumulation() {
+    let mut rng = SplitMix64(264);
+    for _ in 0..100 {
+        let len = rng.below(20) + 1;
+        let pairs: Vec<(usize, i32)> = (0..rng.below(40)).map(|_| (rng.below(len + 5), rng.below(5) as i32 - 2)).collect();
+        let (vec, report) = DefaultSparseVec::ingest(len, pairs.iter().copied(), IngestPolicy::new(DuplicatePolicy::Sum, OutOfRangePolicy::Drop));
+
+        let mut dense = vec![0; len];
+        let mut seen = vec![false; len];
+        let mut collapsed = 0;
+        for &(index, elem) in pairs.iter().filter(|(index, _)| *index < len) {
+            dense[index] += elem;
+            collapsed += usize::from(seen[index]);
+            seen[index] = true;
+        }
+        let distinct = seen.iter().filter(|seen| **seen).count();
+        let stored = dense.iter().filter(|elem| **elem != 0).count();
+        assert_eq!(vec.to_dense(), dense, "{pairs:?}");
+        assert_eq!(
+            report,
+            IngestReport {
+                pairs: pairs.len(),
+                duplicates_collapsed: collapsed,
+                out_of_range_dropped: pairs.iter().filter(|(index, _)| *index >= len).count(),
+                out_of_range_kept: 0,
+                defaults_skipped: distinct - stored,
+                stored,
+            },
+            "{pairs:?}"
+        );
+    }
+}
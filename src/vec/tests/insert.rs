@@ -0,0 +1,102 @@
+//! insert のテスト (デフォルト値の挿入は物理要素を動かさず、後ろの index だけをずらすこと)
+
+use crate::vec::{
+    default_sparse_vec::DefaultSparseVec,
+    error::{SparseVecError, ValidationMode},
+};
+
+const DENSE: [i32; 7] = [0, 3, 0, 5, 7, 0, 9];
+
+/// 全ての位置に elem を insert した結果が Vec::insert と一致すること
+fn assert_insert_matches_vec(elem: i32) {
+    for index in 0..=DENSE.len() {
+        let mut expected = DENSE.to_vec();
+        expected.insert(index, elem);
+        let mut vec = DefaultSparseVec::from(DENSE.to_vec());
+        vec.insert(index, elem);
+        assert_eq!(vec.to_dense(), expected, "insert({index}, {elem})");
+        assert_eq!(vec.nnz(), expected.iter().filter(|&&x| x != 0).count());
+        assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+    }
+}
+
+#[test]
+fn insert_default_at_every_position() {
+    assert_insert_matches_vec(0);
+}
+
+#[test]
+fn insert_non_default_at_every_position() {
+    assert_insert_matches_vec(4);
+}
+
+#[test]
+fn insert_default_does_not_move_stored_values() {
+    let mut vec = DefaultSparseVec::from(vec![String::new(), "a".into(), String::new(), "b".into()]);
+    let cap = vec.capacity();
+    vec.insert(2, String::new());
+    vec.insert(0, String::new());
+    assert_eq!(vec.as_pairs().0, [2, 5]);
+    assert_eq!(vec.capacity(), cap);
+    assert_eq!(vec.to_dense(), ["", "", "a", "", "", "b"]);
+}
+
+/// 物理要素が index 2, 5, 6, 8 にある len 11 のベクタ (0 は default に置き換えます)
+const STORED: [i32; 11] = [0, 0, 3, 0, 0, 5, 7, 0, 9, 0, 0];
+
+/// 物理要素に対する挿入位置と、挿入後の物理要素の index (非デフォルト値 / デフォルト値を挿入した場合)
+const POSITIONS: [(&str, usize, [usize; 5], [usize; 4]); 9] = [
+    ("at 0, before the first stored", 0, [0, 3, 6, 7, 9], [3, 6, 7, 9]),
+    ("just before the first stored", 1, [1, 3, 6, 7, 9], [3, 6, 7, 9]),
+    ("on the first stored", 2, [2, 3, 6, 7, 9], [3, 6, 7, 9]),
+    ("between stored", 4, [2, 4, 6, 7, 9], [2, 6, 7, 9]),
+    ("on a stored index", 5, [2, 5, 6, 7, 9], [2, 6, 7, 9]),
+    ("on a stored index right after another", 6, [2, 5, 6, 7, 9], [2, 5, 7, 9]),
+    ("on the last stored", 8, [2, 5, 6, 8, 9], [2, 5, 6, 9]),
+    ("right after the last stored", 9, [2, 5, 6, 8, 9], [2, 5, 6, 8]),
+    ("at len", 11, [2, 5, 6, 8, 11], [2, 5, 6, 8]),
+];
+
+/// POSITIONS の各行を Vec::insert と比べます (insert / try_insert の両方)
+fn check_positions(default: i32) {
+    let dense: Vec<i32> = STORED.iter().map(|&elem| if elem == 0 { default } else { elem }).collect();
+    for (what, index, stored_after_value, stored_after_default) in POSITIONS {
+        for (elem, stored_after) in [(4, &stored_after_value[..]), (default, &stored_after_default[..])] {
+            let mut expected = dense.clone();
+            expected.insert(index, elem);
+
+            let mut vec = DefaultSparseVec::from_vec_with_default(dense.clone(), default);
+            vec.insert(index, elem);
+            assert_eq!(vec.to_dense(), expected, "insert {elem} {what} (default {default})");
+            assert_eq!(vec.as_pairs().0, stored_after, "insert {elem} {what} (default {default})");
+            assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+
+            let mut vec = DefaultSparseVec::from_vec_with_default(dense.clone(), default);
+            assert_eq!(vec.try_insert(index, elem), Ok(()));
+            assert_eq!(vec.to_dense(), expected, "try_insert {elem} {what} (default {default})");
+        }
+    }
+}
+
+#[test]
+fn insert_positions_relative_to_stored_entries() {
+    check_positions(0);
+    check_positions(-1);
+}
+
+#[test]
+fn insert_past_len() {
+    let mut vec = DefaultSparseVec::from(STORED.to_vec());
+    assert_eq!(vec.try_insert(12, 4), Err(SparseVecError::IndexOutOfBounds { index: 12, len: 11 }));
+    assert_eq!(vec, STORED.to_vec());
+    let mut empty = DefaultSparseVec::<i32>::new();
+    assert_eq!(empty.try_insert(1, 0), Err(SparseVecError::IndexOutOfBounds { index: 1, len: 0 }));
+    empty.insert(0, 4);
+    assert_eq!(empty.to_dense(), [4]);
+}
+
+#[test]
+#[should_panic(expected = "insertion index (is 12) should be <= len (is 11)")]
+fn insert_past_len_panics() {
+    DefaultSparseVec::from(STORED.to_vec()).insert(12, 4);
+}
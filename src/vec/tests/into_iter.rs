@@ -0,0 +1,105 @@
+//! IntoIter のテスト (格納された値をちょうど 1 回ずつ drop すること)
+//! 全部、途中まで、1 つも消費しない場合と、mem::forget した場合を確かめます
+
+use std::{cell::Cell, mem, rc::Rc};
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+use super::Counted;
+
+/// [1, 0, 2, 0, 0, 3] (物理要素 3 つ) と、その drop の回数
+fn sample() -> (DefaultSparseVec<Counted>, Rc<Cell<u32>>) {
+    let drops = Rc::new(Cell::new(0));
+    let mut vec = DefaultSparseVec::new();
+    for id in [1, 0, 2, 0, 0, 3] {
+        vec.push(Counted::new(id, &drops));
+    }
+    // デフォルト値と等しい値は push の時点で drop されるので、数え直します
+    drops.set(0);
+    (vec, drops)
+}
+
+fn ids(items: &[Counted]) -> Vec<u32> {
+    items.iter().map(|elem| elem.id).collect()
+}
+
+#[test]
+fn full_consumption() {
+    let (vec, drops) = sample();
+    let iter = vec.into_iter();
+    assert_eq!(iter.len(), 6);
+    let items: Vec<_> = iter.collect();
+    // 値は移動するだけで、iterator の drop で drop されません
+    assert_eq!(drops.get(), 0);
+    assert_eq!(ids(&items), [1, 0, 2, 0, 0, 3]);
+    drop(items);
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn full_consumption_from_the_back() {
+    let (vec, drops) = sample();
+    let items: Vec<_> = vec.into_iter().rev().collect();
+    assert_eq!(ids(&items), [3, 0, 0, 2, 0, 1]);
+    assert_eq!(drops.get(), 0);
+    drop(items);
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn partial_consumption() {
+    let (vec, drops) = sample();
+    let mut iter = vec.into_iter();
+    assert_eq!(iter.next().map(|elem| elem.id), Some(1));
+    assert_eq!(drops.get(), 1);
+    let last = iter.next_back().unwrap();
+    assert_eq!((last.id, iter.len()), (3, 4));
+    // 残りの物理要素 (2) だけが drop されます
+    drop(iter);
+    assert_eq!(drops.get(), 2);
+    drop(last);
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn partial_consumption_of_the_sparse_part_only() {
+    let (vec, drops) = sample();
+    let mut iter = vec.into_iter();
+    iter.next();
+    // スパース分部 (index 1) はデフォルト値の clone なので数えません
+    assert_eq!(iter.next().map(|elem| elem.id), Some(0));
+    assert_eq!(drops.get(), 1);
+    drop(iter);
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn zero_consumption() {
+    let (vec, drops) = sample();
+    drop(vec.into_iter());
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // 残りの要素と領域をリークさせるテストなので、Miri のリーク検出に掛かります
+fn forget_leaks_without_dropping() {
+    let (vec, drops) = sample();
+    let mut iter = vec.into_iter();
+    drop(iter.next());
+    assert_eq!(drops.get(), 1);
+    mem::forget(iter);
+    // 残りは drop されません (二重解放もありません)
+    assert_eq!(drops.get(), 1);
+}
+
+#[test]
+fn strings() {
+    let vec = DefaultSparseVec::from(vec!["a".to_string(), String::new(), "b".into(), String::new()]);
+    let mut iter = vec.into_iter();
+    assert_eq!(iter.next_back().as_deref(), Some(""));
+    assert_eq!(iter.next().as_deref(), Some("a"));
+    drop(iter);
+
+    let vec = DefaultSparseVec::from(vec!["a".to_string(), String::new(), "b".into()]);
+    assert_eq!(vec.into_iter().collect::<Vec<_>>(), ["a", "", "b"]);
+}
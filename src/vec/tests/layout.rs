@@ -0,0 +1,46 @@
+use std::mem::size_of;
+
+use crate::vec::default_sparse_vec::{CapPolicy, DefaultSparseVec, SparsifyPolicy};
+
+const WORD: usize = size_of::<usize>();
+
+#[test]
+fn plain_vector_is_buffer_plus_three_words_and_extras_pointer() {
+    // 物理領域 (val_ptr / ind_ptr / cap) + raw_len + len + default (u64) + extras
+    // debug ビルドでは変更回数の AtomicUsize が 1 ワード増えます
+    let debug_words = if cfg!(debug_assertions) { 1 } else { 0 };
+    assert_eq!(size_of::<DefaultSparseVec<u64>>(), (7 + debug_words) * WORD);
+}
+
+#[test]
+fn settings_survive_mutation_and_clone() {
+    let mut vec = DefaultSparseVec::from(vec![0.0f64, 1.0, 0.5, 0.0, 3.0]);
+    vec.set_max_nnz(Some(3));
+    vec.set_cap_policy(CapPolicy::Error);
+    vec.set_sparsify_policy(SparsifyPolicy::AbsTolerance(0.1));
+    vec.build_value_index();
+    assert!(vec.has_value_index());
+
+    let copy = vec.clone();
+    assert!(copy.has_value_index());
+    assert_eq!(copy.max_nnz(), Some(3));
+    assert_eq!(copy.cap_policy(), CapPolicy::Error);
+
+    // 変更でキャッシュ (索引) だけが破棄され、設定は残る
+    vec.push(0.05);
+    assert!(!vec.has_value_index());
+    assert_eq!(vec.nnz(), 3);
+    assert_eq!(vec.max_nnz(), Some(3));
+    assert_eq!(vec.cap_policy(), CapPolicy::Error);
+    assert!(matches!(vec.sparsify_policy(), SparsifyPolicy::AbsTolerance(_)));
+    assert!(vec.try_push(2.0).is_err());
+}
+
+#[test]
+fn plain_vector_defaults() {
+    let vec = DefaultSparseVec::<f64>::new();
+    assert_eq!(vec.max_nnz(), None);
+    assert_eq!(vec.cap_policy(), CapPolicy::default());
+    assert!(matches!(vec.sparsify_policy(), SparsifyPolicy::Exact));
+    assert!(!vec.has_value_index());
+}
@@ -0,0 +1,59 @@
+//! sparse_vec! のテスト
+//! - 3 つの形式の結果を、同じ内容を set で作ったベクタと比べます
+//! - 実行時の panic (index の並び、len 以上の index)
+//! - コンパイルエラーになる書き方 (trybuild、macros_ui/ の .stderr と比べます)
+//!   コンパイラのメッセージが変わった場合は TRYBUILD=overwrite で記録し直します
+
+use crate::{sparse_vec, vec::default_sparse_vec::DefaultSparseVec};
+
+#[test]
+fn dense_form() {
+    let vec = sparse_vec![0, 0, 5, 0, 7];
+    assert_eq!(vec.to_dense(), [0, 0, 5, 0, 7]);
+    assert_eq!(vec.as_pairs(), (&[2, 4][..], &[5, 7][..]));
+    assert_eq!(vec.capacity(), 2);
+
+    let empty: DefaultSparseVec<i32> = sparse_vec![];
+    assert!(empty.is_empty());
+    let trailing = sparse_vec![String::new(), "a".to_string(),];
+    assert_eq!(trailing.to_dense(), ["", "a"]);
+}
+
+#[test]
+fn pairs_form() {
+    let vec = sparse_vec![len = 100; 3 => 5, 42 => 7,];
+    let mut expected = DefaultSparseVec::with_nnz_capacity(100, 2);
+    expected.set(3, 5);
+    expected.set(42, 7);
+    assert_eq!(vec, expected);
+    assert_eq!(vec.as_pairs(), (&[3, 42][..], &[5, 7][..]));
+
+    let empty: DefaultSparseVec<u8> = sparse_vec![len = 4;];
+    assert_eq!((empty.len(), empty.nnz()), (4, 0));
+}
+
+#[test]
+fn default_form() {
+    let vec = sparse_vec![default = -1; len = 6; 0 => 9, 2 => -1, 5 => 0];
+    assert_eq!(vec.to_dense(), [9, -1, -1, -1, -1, 0]);
+    assert_eq!((vec.default_value(), vec.nnz()), (&-1, 2));
+}
+
+#[test]
+#[should_panic(expected = "sparse_vec!: stored indices are not strictly increasing at position 1")]
+fn unsorted_indices_panic() {
+    let _ = sparse_vec![len = 10; 5 => 1, 5 => 2];
+}
+
+#[test]
+#[should_panic(expected = "sparse_vec!: stored index 10 out of bounds for len 10")]
+fn index_past_len_panics() {
+    let _ = sparse_vec![len = 10; 10 => 1];
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // コンパイラを起動するので Miri では実行しません
+fn compile_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("src/vec/tests/macros_ui/*.rs");
+}
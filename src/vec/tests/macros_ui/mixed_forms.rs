@@ -0,0 +1,5 @@
+use vec_plus::sparse_vec;
+
+fn main() {
+    let _ = sparse_vec![1, 2 => 3];
+}
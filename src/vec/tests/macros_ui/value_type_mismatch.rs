@@ -0,0 +1,5 @@
+use vec_plus::{sparse_vec, vec::default_sparse_vec::DefaultSparseVec};
+
+fn main() {
+    let _: DefaultSparseVec<i32> = sparse_vec![len = 3; 0 => 1, 2 => "a"];
+}
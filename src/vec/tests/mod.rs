@@ -0,0 +1,108 @@
+//! vec モジュールのテスト
+//! トピックごとにファイルを分けます (未定義動作の修正に関するテストは Miri で実行できる大きさにします)
+
+use std::{cell::Cell, rc::Rc};
+
+mod append;
+mod capacity;
+mod clamp;
+mod clear;
+mod clone;
+#[cfg(feature = "compress")]
+mod compress;
+mod cost;
+mod decay;
+#[cfg(any(feature = "compress", feature = "serde"))]
+mod deserialize_limits;
+mod division;
+mod dot;
+mod drain;
+mod empty;
+mod extend;
+mod from_iter;
+#[cfg(any(feature = "compress", feature = "serde"))]
+mod golden;
+mod ingest;
+mod insert;
+mod into_iter;
+mod layout;
+mod macros;
+#[cfg(debug_assertions)]
+mod mod_check;
+mod no_panic;
+mod normal_vec;
+mod normalize;
+mod partition;
+#[cfg(all(feature = "debug-poison", debug_assertions))]
+mod poison;
+mod pop;
+mod push_front;
+mod reduction_order;
+mod scatter_add;
+mod select;
+mod send_sync;
+mod shrink_step;
+mod sparse_eq;
+mod swap_remove;
+mod top_k;
+mod views_mut;
+mod zst;
+
+/// 確保の回数を数えるテスト (count_allocations) のためのアロケータ
+/// 数えるのはスレッドごとなので、並列に実行する他のテストの確保は混ざりません
+#[global_allocator]
+static ALLOC: super::alloc_audit::CountingAlloc = super::alloc_audit::CountingAlloc;
+
+/// drop された回数を数える値 (id 0 がデフォルト値)
+/// - clone は同じカウンタを共有します (デフォルト値はカウンタを持たないので数えません)
+#[derive(Debug, Default, Clone)]
+struct Counted {
+    id: u32,
+    drops: Option<Rc<Cell<u32>>>,
+}
+
+impl Counted {
+    fn new(id: u32, drops: &Rc<Cell<u32>>) -> Self {
+        Counted { id, drops: Some(Rc::clone(drops)) }
+    }
+}
+
+impl PartialEq for Counted {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        if let Some(drops) = &self.drops {
+            drops.set(drops.get() + 1);
+        }
+    }
+}
+
+/// panic の payload (&str / String) からメッセージを取り出します
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload.downcast_ref::<String>().map_or("", String::as_str),
+    }
+}
+
+/// SplitMix64 (テスト用の決定的な乱数)
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// [0, n) の値 (n > 0)
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
@@ -0,0 +1,59 @@
+//! 反復中の変更の検出 (ModCheck) のテスト (debug ビルドのみ)
+//! 安全なコードでは借用規則により起こらないので、生ポインタ経由でイテレータの生存中にベクタを変更します
+//! - 借用規則を意図して破る操作 (検出したい誤用そのもの) なので、Miri では実行しません
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+fn sample() -> DefaultSparseVec<i32> {
+    DefaultSparseVec::from(vec![0, 1, 0, 2, 3])
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+#[should_panic(expected = "vector mutated during iteration")]
+fn iter_panics_after_raw_mutation() {
+    let mut vec = sample();
+    let raw: *mut DefaultSparseVec<i32> = &mut vec;
+    let mut iter = unsafe { (*raw).iter() };
+    assert_eq!(iter.next(), Some((1, &1)));
+    // as_pairs_mut は値を書き換えられる取得なので、変更回数を増やします
+    unsafe { (*raw).as_pairs_mut().1[1] = 5 };
+    let _ = iter.next();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+#[should_panic(expected = "vector mutated during iteration")]
+fn iter_dense_panics_after_raw_mutation() {
+    let mut vec = sample();
+    let raw: *mut DefaultSparseVec<i32> = &mut vec;
+    let mut iter = unsafe { (*raw).iter_dense() };
+    assert_eq!(iter.next(), Some(&0));
+    unsafe { (*raw).set(0, 9) };
+    let _ = iter.next_back();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+#[should_panic(expected = "vector mutated during iteration")]
+fn iter_mut_panics_after_raw_mutation() {
+    let mut vec = sample();
+    let raw: *mut DefaultSparseVec<i32> = &mut vec;
+    let mut iter = unsafe { (*raw).iter_mut() };
+    assert!(iter.next().is_some());
+    unsafe { (*raw).push(4) };
+    let _ = iter.nth(1);
+}
+
+#[test]
+fn iteration_without_mutation_does_not_panic() {
+    let mut vec = sample();
+    assert_eq!(vec.iter().count(), 3);
+    assert_eq!(vec.iter_dense().rev().count(), 5);
+    vec.iter_mut().for_each(|(_, elem)| *elem += 1);
+    // イテレータを drop した後の変更は検出の対象外です
+    let mut iter = vec.iter();
+    assert!(iter.next().is_some());
+    vec.push(7);
+    assert_eq!(vec.iter().map(|(_, elem)| *elem).collect::<Vec<_>>(), [2, 3, 4, 7]);
+}
@@ -0,0 +1,93 @@
+//! try_ の API (try_reserve / try_reserve_exact / try_push / try_insert) が panic しないことのテスト
+//! - 実行時: 失敗する入力でもエラーを返し、ベクタを変更しないこと
+//! - リンク時 (feature = "no-panic-check"、debug_assertions が無効なビルドのみ):
+//!   `cargo test --profile no-panic-check --features no-panic-check --lib no_panic`
+//!   各呼び出しを「drop されると未定義のシンボルを呼ぶガード」で囲みます。
+//!   unwind する経路が 1 つでも残っていると、ガードの drop が残ってリンクに失敗します
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, error::SparseVecError};
+
+#[test]
+fn try_reserve_overflow_is_an_error() {
+    let mut vec = DefaultSparseVec::from(vec![0u64, 1, 2]);
+    assert_eq!(vec.try_reserve(usize::MAX), Err(SparseVecError::CapacityOverflow));
+    assert_eq!(vec.try_reserve_exact(usize::MAX), Err(SparseVecError::CapacityOverflow));
+    assert_eq!(vec.try_reserve(isize::MAX as usize), Err(SparseVecError::CapacityOverflow));
+    assert_eq!(vec.collect_dense(), [0, 1, 2]);
+}
+
+#[test]
+fn try_insert_out_of_bounds_is_an_error() {
+    let mut vec = DefaultSparseVec::from(vec![0u64, 1]);
+    assert_eq!(vec.try_insert(3, 5), Err(SparseVecError::IndexOutOfBounds { index: 3, len: 2 }));
+    assert_eq!(vec.collect_dense(), [0, 1]);
+}
+
+#[test]
+fn try_push_over_nnz_cap_is_an_error() {
+    let mut vec = DefaultSparseVec::from(vec![1u64, 0]);
+    vec.set_max_nnz(Some(1));
+    assert_eq!(vec.try_push(2), Err(SparseVecError::NnzCapExceeded { max_nnz: 1 }));
+    assert_eq!(vec.try_insert(0, 2), Err(SparseVecError::NnzCapExceeded { max_nnz: 1 }));
+    // デフォルト値は物理要素を増やさないので上限に関係なく追加できます
+    assert_eq!(vec.try_push(0), Ok(()));
+    assert_eq!(vec.collect_dense(), [1, 0, 0]);
+}
+
+#[cfg(all(feature = "no-panic-check", not(debug_assertions)))]
+mod link_check {
+    use std::{hint::black_box, mem};
+
+    use crate::vec::{default_sparse_vec::DefaultSparseVec, error::SparseVecError};
+
+    /// NoPanic構造体の定義
+    /// drop されると未定義のシンボルを呼ぶガード (unwind の経路が残っているとリンクに失敗します)
+    struct NoPanic;
+
+    extern "C" {
+        #[link_name = "\n\nERROR_try_path_may_panic\n\n"]
+        fn may_panic() -> !;
+    }
+
+    impl Drop for NoPanic {
+        #[inline(always)]
+        fn drop(&mut self) {
+            unsafe { may_panic() }
+        }
+    }
+
+    /// no_panic! マクロ
+    /// ガードで囲んだ #[inline(never)] の関数を作ります
+    macro_rules! no_panic {
+        ($name:ident($($arg:ident: $ty:ty),*) => $body:expr) => {
+            #[inline(never)]
+            fn $name($($arg: $ty),*) -> Result<(), SparseVecError> {
+                let guard = NoPanic;
+                let result = $body;
+                mem::forget(guard);
+                result
+            }
+        };
+    }
+
+    no_panic!(reserve(vec: &mut DefaultSparseVec<u64>, additional: usize) => vec.try_reserve(additional));
+    no_panic!(reserve_exact(vec: &mut DefaultSparseVec<u64>, additional: usize) => vec.try_reserve_exact(additional));
+    no_panic!(push(vec: &mut DefaultSparseVec<u64>, elem: u64) => vec.try_push(elem));
+    no_panic!(insert(vec: &mut DefaultSparseVec<u64>, index: usize, elem: u64) => vec.try_insert(index, elem));
+    no_panic!(push_f64(vec: &mut DefaultSparseVec<f64>, elem: f64) => vec.try_push(elem));
+
+    #[test]
+    fn try_paths_link_without_unwinding() {
+        let mut vec = DefaultSparseVec::new();
+        assert_eq!(reserve(&mut vec, black_box(4)), Ok(()));
+        assert_eq!(reserve_exact(&mut vec, black_box(usize::MAX)), Err(SparseVecError::CapacityOverflow));
+        assert_eq!(push(&mut vec, black_box(3)), Ok(()));
+        assert_eq!(insert(&mut vec, black_box(0), black_box(1)), Ok(()));
+        assert_eq!(insert(&mut vec, black_box(9), black_box(1)), Err(SparseVecError::IndexOutOfBounds { index: 9, len: 2 }));
+        assert_eq!(vec.collect_dense(), [1, 3]);
+
+        let mut floats = DefaultSparseVec::new();
+        assert_eq!(push_f64(&mut floats, black_box(0.5)), Ok(()));
+        assert_eq!(floats.collect_dense(), [0.5]);
+    }
+}
@@ -0,0 +1,47 @@
+//! NormalVecMethods のテスト (n_push が push と同じ物理要素を格納すること、dyn で Vec と同じ結果になること)
+
+use crate::vec::{adaptive_vec::AdaptiveVec, default_sparse_vec::DefaultSparseVec, gap_sparse_vec::GapSparseVec, normal_vec_trait::NormalVecMethods};
+
+#[test]
+fn n_push_stores_the_same_entries_as_push() {
+    let input = ["", "a", "", "", "b", "c", ""];
+    let mut pushed = DefaultSparseVec::new();
+    let mut n_pushed = DefaultSparseVec::new();
+    for s in input {
+        pushed.push(s.to_string());
+        n_pushed.n_push(s.to_string());
+    }
+    assert_eq!(n_pushed.as_pairs(), pushed.as_pairs());
+    assert_eq!(n_pushed.as_pairs().0, [1, 4, 5]);
+    assert_eq!(n_pushed.len(), input.len());
+}
+
+/// dyn NormalVecMethods<String> を通した操作が Vec と同じ結果になること
+fn drive(vec: &mut dyn NormalVecMethods<String>) -> Vec<String> {
+    for s in ["a", "", "b", "", ""] {
+        vec.n_push(s.to_string());
+    }
+    vec.n_insert(1, String::new());
+    vec.n_insert(0, "c".into());
+    assert_eq!(vec.n_remove(2), "");
+    assert_eq!(vec.n_remove(2), "");
+    assert_eq!(vec.n_pop().as_deref(), Some(""));
+    vec.n_iter().cloned().collect()
+}
+
+#[test]
+fn dyn_normal_vec_matches_vec() {
+    let mut expected: Vec<String> = ["a", "", "b", "", ""].iter().map(|s| s.to_string()).collect();
+    expected.insert(1, String::new());
+    expected.insert(0, "c".into());
+    expected.remove(2);
+    expected.remove(2);
+    expected.pop();
+
+    assert_eq!(drive(&mut DefaultSparseVec::new()), expected);
+    assert_eq!(drive(&mut AdaptiveVec::default()), expected);
+    assert_eq!(drive(&mut GapSparseVec::new()), expected);
+    // CheckedSparseVec は各操作をシャドウの Vec と比べます
+    #[cfg(feature = "shadow")]
+    assert_eq!(drive(&mut crate::vec::checked_sparse_vec::CheckedSparseVec::new()), expected);
+}
@@ -0,0 +1,147 @@
+//! softmax / normalize_l1_in_place / normalize_l2_in_place のテスト
+//! 密な Vec で計算した参照と比べます (softmax はスパース分部の len - nnz 個のデフォルト値も重みに含めます)
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+use super::SplitMix64;
+
+/// 値の相対誤差の許容範囲
+const TOLERANCE: f64 = 1e-12;
+
+fn assert_close(actual: &[f64], expected: &[f64], what: &str) {
+    assert_eq!(actual.len(), expected.len(), "{what}");
+    for (index, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!((a - e).abs() <= TOLERANCE * e.abs().max(f64::MIN_POSITIVE), "{what}: index {index}: {a} != {e}");
+    }
+}
+
+/// 長さ、密度、デフォルト値がばらばらの i32 の密なベクタと、そのデフォルト値
+fn random_dense(rng: &mut SplitMix64) -> (Vec<i32>, i32) {
+    let len = rng.below(40) + 1;
+    let density = rng.below(101);
+    let default = if rng.below(2) == 0 { 0 } else { rng.below(11) as i32 - 5 };
+    let dense = (0..len)
+        .map(|_| if rng.below(100) < density { rng.below(41) as i32 - 20 } else { default })
+        .collect();
+    (dense, default)
+}
+
+/// 全要素 (デフォルト値を含む) の softmax
+fn dense_softmax(dense: &[f64]) -> Vec<f64> {
+    let max = dense.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let total: f64 = dense.iter().map(|elem| (elem - max).exp()).sum();
+    dense.iter().map(|elem| (elem - max).exp() / total).collect()
+}
+
+#[test]
+fn softmax_matches_dense() {
+    let mut rng = SplitMix64(255);
+    for round in 0..200 {
+        let (dense, default) = random_dense(&mut rng);
+        let vec = DefaultSparseVec::from_vec_with_default(dense.clone(), default);
+        let expected = dense_softmax(&dense.iter().map(|&elem| f64::from(elem)).collect::<Vec<_>>());
+        let result = vec.softmax(0.0);
+        assert_eq!(result.len(), dense.len());
+        assert_close(&result.to_dense(), &expected, &format!("round {round}"));
+        assert!((result.iter_dense().sum::<f64>() - 1.0).abs() < 1e-12, "round {round}");
+    }
+}
+
+#[test]
+fn softmax_includes_the_default_mass() {
+    // 重みは 1, 1, 1, 3 (合計 6)
+    // 物理要素だけの softmax なら index 3 は 1.0 になります
+    let vec = DefaultSparseVec::from(vec![0.0, 0.0, 0.0, 3f64.ln()]);
+    let result = vec.softmax(0.0);
+    assert_close(&result.to_dense(), &[1.0 / 6.0, 1.0 / 6.0, 1.0 / 6.0, 0.5], "default mass");
+    // スパース分部の確率がデフォルト値になり、物理要素は増えません
+    assert_close(&[*result.default_value()], &[1.0 / 6.0], "default");
+    assert_eq!(result.nnz(), 1);
+
+    // デフォルト値が 0 でない場合も同じです
+    let vec = DefaultSparseVec::from_vec_with_default(vec![2.0, 2.0 + 3f64.ln(), 2.0, 2.0], 2.0);
+    assert_close(&vec.softmax(0.0).to_dense(), &[1.0 / 6.0, 0.5, 1.0 / 6.0, 1.0 / 6.0], "non-zero default");
+}
+
+#[test]
+fn softmax_prunes_below_epsilon() {
+    // 重みは e^-10 (スパース分部 2 つ)、e^-1、1
+    let vec = DefaultSparseVec::from(vec![0.0, 9.0, 0.0, 10.0]);
+    let expected = dense_softmax(&[0.0, 9.0, 0.0, 10.0]);
+    let result = vec.softmax(1e-3);
+    assert_eq!(*result.default_value(), 0.0);
+    assert_close(&result.to_dense(), &[0.0, expected[1], 0.0, expected[3]], "epsilon");
+    // epsilon 以上なら残します
+    assert_close(&vec.softmax(expected[0]).to_dense(), &expected, "epsilon equal to the default mass");
+}
+
+#[test]
+fn softmax_does_not_overflow() {
+    let vec = DefaultSparseVec::from(vec![0.0, 1000.0, 0.0, 1000.0]);
+    assert_close(&vec.softmax(0.0).to_dense(), &dense_softmax(&[0.0, 1000.0, 0.0, 1000.0]), "large values");
+    assert_close(&vec.softmax(0.0).to_dense(), &[0.0, 0.5, 0.0, 0.5], "large values");
+    // 全てスパース分部
+    let vec = DefaultSparseVec::<f64>::from(vec![0.0; 4]);
+    assert_close(&vec.softmax(0.0).to_dense(), &[0.25; 4], "all default");
+}
+
+#[test]
+fn normalize_l1_matches_dense() {
+    let mut rng = SplitMix64(1255);
+    for round in 0..200 {
+        let (dense, _) = random_dense(&mut rng);
+        let dense: Vec<f64> = dense.into_iter().map(f64::from).collect();
+        let norm: f64 = dense.iter().map(|elem| elem.abs()).sum();
+        let expected: Vec<f64> = if norm == 0.0 { dense.clone() } else { dense.iter().map(|elem| elem / norm).collect() };
+        let mut vec = DefaultSparseVec::from(dense);
+        vec.normalize_l1_in_place();
+        assert_close(&vec.to_dense(), &expected, &format!("round {round}"));
+    }
+}
+
+#[test]
+fn normalize_l2_matches_dense() {
+    let mut rng = SplitMix64(2255);
+    for round in 0..200 {
+        let (dense, _) = random_dense(&mut rng);
+        let dense: Vec<f64> = dense.into_iter().map(f64::from).collect();
+        let norm = dense.iter().map(|elem| elem * elem).sum::<f64>().sqrt();
+        let expected: Vec<f64> = if norm == 0.0 { dense.clone() } else { dense.iter().map(|elem| elem / norm).collect() };
+        let mut vec = DefaultSparseVec::from(dense);
+        vec.normalize_l2_in_place();
+        assert_close(&vec.to_dense(), &expected, &format!("round {round}"));
+    }
+}
+
+#[test]
+fn normalize_l2_does_not_overflow() {
+    // 2 乗するとオーバーフローする値
+    let mut vec = DefaultSparseVec::from(vec![0.0, f64::MAX / 2.0, 0.0, -f64::MAX / 2.0]);
+    vec.normalize_l2_in_place();
+    let half = 0.5f64.sqrt();
+    assert_close(&vec.to_dense(), &[0.0, half, 0.0, -half], "large values");
+
+    let mut vec = DefaultSparseVec::from(vec![3.0f32, 0.0, 4.0]);
+    vec.normalize_l2_in_place();
+    assert_eq!(vec.to_dense(), [0.6, 0.0, 0.8]);
+}
+
+#[test]
+fn all_default_is_unchanged() {
+    let mut vec = DefaultSparseVec::<f64>::from(vec![0.0; 5]);
+    vec.normalize_l1_in_place();
+    vec.normalize_l2_in_place();
+    assert_eq!((vec.len(), vec.nnz()), (5, 0));
+}
+
+#[test]
+#[should_panic(expected = "normalize_l1_in_place requires a zero default")]
+fn normalize_l1_non_zero_default_panics() {
+    DefaultSparseVec::from_vec_with_default(vec![1.0, 2.0], 1.0).normalize_l1_in_place();
+}
+
+#[test]
+#[should_panic(expected = "normalize_l2_in_place requires a zero default")]
+fn normalize_l2_non_zero_default_panics() {
+    DefaultSparseVec::from_vec_with_default(vec![1.0, 2.0], 1.0).normalize_l2_in_place();
+}
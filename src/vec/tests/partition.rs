@@ -0,0 +1,101 @@
+//! partition / partition_compact のテスト
+//! 値だけで決まる pred (スパース分部の振り分け先が区間内で変わらない) で dense の参照実装と比べます
+
+use std::cell::Cell;
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+fn samples() -> Vec<Vec<i32>> {
+    vec![
+        vec![],
+        vec![0, 0, 0],
+        vec![4, -2, 7],
+        vec![0, 3, 0, 0, -5, 0, 8, 0, 0, 0, 1, -1, 0],
+        (0..40).map(|i| if i % 7 == 3 { i - 20 } else { 0 }).collect(),
+    ]
+}
+
+/// dense での mask split (振り分けられなかった位置はデフォルト値)
+fn dense_partition(dense: &[i32], pred: impl Fn(usize, &i32) -> bool) -> (Vec<i32>, Vec<i32>) {
+    dense.iter().enumerate().map(|(i, v)| if pred(i, v) { (*v, 0) } else { (0, *v) }).unzip()
+}
+
+/// dense での compact split (振り分けられた位置を前に詰める)
+fn dense_partition_compact(dense: &[i32], pred: impl Fn(usize, &i32) -> bool) -> (Vec<i32>, Vec<i32>) {
+    let (mut selected, mut rest) = (Vec::new(), Vec::new());
+    for (i, v) in dense.iter().enumerate() {
+        if pred(i, v) { selected.push(*v) } else { rest.push(*v) }
+    }
+    (selected, rest)
+}
+
+const PREDS: [fn(usize, &i32) -> bool; 4] = [
+    |_, v| *v > 0,
+    |_, v| *v <= 0,
+    |_, _| true,
+    // 物理要素だけ index で振り分け、デフォルト値は常に false
+    |i, v| *v != 0 && i % 2 == 0,
+];
+
+#[test]
+fn partition_matches_dense() {
+    for dense in samples() {
+        let vec = DefaultSparseVec::from(dense.clone());
+        for pred in PREDS {
+            let (selected, rest) = vec.partition(pred);
+            let (expected_selected, expected_rest) = dense_partition(&dense, pred);
+            assert_eq!(selected.to_dense(), expected_selected, "{dense:?}");
+            assert_eq!(rest.to_dense(), expected_rest, "{dense:?}");
+        }
+    }
+}
+
+#[test]
+fn partition_compact_matches_dense() {
+    for dense in samples() {
+        let vec = DefaultSparseVec::from(dense.clone());
+        for pred in PREDS {
+            let (selected, rest) = vec.partition_compact(pred);
+            let (expected_selected, expected_rest) = dense_partition_compact(&dense, pred);
+            assert_eq!(selected.to_dense(), expected_selected, "{dense:?}");
+            assert_eq!(rest.to_dense(), expected_rest, "{dense:?}");
+            assert_eq!(selected.len() + rest.len(), vec.len());
+            assert_eq!(selected.nnz() + rest.nnz(), vec.nnz());
+        }
+    }
+}
+
+#[test]
+fn default_is_evaluated_once_per_gap_run() {
+    // 区間: [0, 1000) / [1001, 5000) / [5001, 10_000)
+    let mut vec = DefaultSparseVec::from(vec![0i32; 10_000]);
+    vec.set(1000, 3);
+    vec.set(5000, -4);
+    let calls = Cell::new(0);
+    let pred = |index: usize, elem: &i32| {
+        if *elem == 0 {
+            calls.set(calls.get() + 1);
+        }
+        index < 3000
+    };
+
+    let (selected, rest) = vec.partition_compact(pred);
+    assert_eq!(calls.get(), 3);
+    // 区間の先頭の index で振り分け先が決まります ([1001, 5000) は区間全体が selected)
+    assert_eq!((selected.len(), rest.len()), (5000, 5000));
+    assert_eq!(selected.iter().map(|(i, v)| (i, *v)).collect::<Vec<_>>(), [(1000, 3)]);
+    assert_eq!(rest.iter().map(|(i, v)| (i, *v)).collect::<Vec<_>>(), [(0, -4)]);
+
+    calls.set(0);
+    let _ = vec.partition(pred);
+    assert_eq!(calls.get(), 0);
+}
+
+#[test]
+fn outputs_keep_the_default() {
+    let vec = DefaultSparseVec::from_vec_with_default(vec![5, 0, 5, 1], 5);
+    let (selected, rest) = vec.partition_compact(|_, v| *v == 5);
+    assert_eq!((selected.default_value(), rest.default_value()), (&5, &5));
+    assert_eq!(selected.to_dense(), [5, 5]);
+    assert_eq!(rest.to_dense(), [0, 1]);
+}
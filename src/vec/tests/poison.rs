@@ -0,0 +1,60 @@
+//! debug-poison のテスト (feature = "debug-poison" かつ debug ビルド)
+//! 物理要素を取り除いた後、容量内に残る領域を val_ptr / ind_ptr から読み、POISON_BYTE で上書きされていることを確かめます
+
+use crate::vec::default_sparse_vec::{DefaultSparseVec, POISON_BYTE};
+
+/// 物理位置 from..to の値と index の領域が全て POISON_BYTE であること
+fn assert_poisoned(vec: &DefaultSparseVec<u64>, from: usize, to: usize, what: &str) {
+    assert!(to <= vec.capacity(), "{what}");
+    let bytes = |ptr: *const u8, size: usize| unsafe { std::slice::from_raw_parts(ptr.add(from * size), (to - from) * size) };
+    let val = bytes(vec.val_ptr().cast(), size_of::<u64>());
+    let ind = bytes(vec.ind_ptr().cast(), size_of::<usize>());
+    assert!(val.iter().chain(ind).all(|&byte| byte == POISON_BYTE), "{what}: {val:x?} {ind:x?}");
+}
+
+/// 物理要素 6 個 (index 0, 2, 4, 6, 8, 10) の len 11 のベクタ
+fn sample() -> DefaultSparseVec<u64> {
+    DefaultSparseVec::from(vec![1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6])
+}
+
+#[test]
+fn truncate_and_pop() {
+    let mut vec = sample();
+    vec.truncate(5);
+    assert_eq!(vec.nnz(), 3);
+    assert_poisoned(&vec, 3, 6, "truncate");
+    // 残した物理要素はそのままです
+    assert_eq!(vec.as_pairs(), (&[0, 2, 4][..], &[1, 2, 3][..]));
+
+    vec.pop();
+    assert_poisoned(&vec, 2, 3, "pop");
+    assert_eq!(vec.as_pairs(), (&[0, 2][..], &[1, 2][..]));
+}
+
+#[test]
+fn clear() {
+    let mut vec = sample();
+    let cap = vec.capacity();
+    vec.clear();
+    assert_eq!(vec.capacity(), cap);
+    assert_poisoned(&vec, 0, 6, "clear");
+}
+
+#[test]
+fn drain() {
+    let mut vec = sample();
+    // 物理要素 2 つ (index 4, 6) を取り除き、後ろの 2 つを前に詰めます
+    drop(vec.drain(3..7));
+    assert_eq!(vec.as_pairs(), (&[0, 2, 4, 6][..], &[1, 2, 5, 6][..]));
+    assert_poisoned(&vec, 4, 6, "drain");
+}
+
+#[test]
+fn append_leaves_the_other_poisoned() {
+    let mut vec = sample();
+    let mut other = DefaultSparseVec::from(vec![7, 0, 8]);
+    vec.append(&mut other);
+    assert_eq!((other.len(), other.nnz()), (0, 0));
+    assert_poisoned(&other, 0, 2, "append");
+    assert_eq!(vec.as_pairs().1, [1, 2, 3, 4, 5, 6, 7, 8]);
+}
@@ -0,0 +1,58 @@
+//! pop のテスト (末尾の論理要素が物理的に格納されているかどうかの判定)
+
+use std::rc::Rc;
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, error::ValidationMode, normal_vec_trait::NormalVecMethods};
+
+/// pop を繰り返した結果が、密な入力を逆順にしたものと一致すること
+fn assert_pops_in_reverse(dense: &[&str]) {
+    let mut vec = DefaultSparseVec::from(dense.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+    let mut popped = Vec::new();
+    while let Some(elem) = vec.pop() {
+        assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+        popped.push(elem);
+    }
+    let expected: Vec<_> = dense.iter().rev().map(|s| s.to_string()).collect();
+    assert_eq!(popped, expected);
+    assert_eq!((vec.len(), vec.nnz()), (0, 0));
+}
+
+#[test]
+fn pop_with_leading_defaults() {
+    assert_pops_in_reverse(&["", "a", "b"]);
+    assert_pops_in_reverse(&["", "", "", "a"]);
+}
+
+#[test]
+fn pop_with_trailing_and_interleaved_defaults() {
+    assert_pops_in_reverse(&["a", "b", ""]);
+    assert_pops_in_reverse(&["", "a", "", "b", "", ""]);
+    assert_pops_in_reverse(&["", ""]);
+    assert_pops_in_reverse(&[]);
+}
+
+#[test]
+fn pop_leading_default_keeps_tail_in_range() {
+    // [0, 5, 7] から pop すると 7 を返し、len の外に物理要素が残りません
+    let mut vec = DefaultSparseVec::from(vec![0, 5, 7]);
+    assert_eq!(vec.pop(), Some(7));
+    assert_eq!(vec.as_pairs(), (&[1][..], &[5][..]));
+    assert_eq!(vec.n_pop(), Some(5));
+    assert_eq!(vec.n_pop(), Some(0));
+    assert_eq!(vec.n_pop(), None);
+}
+
+#[test]
+fn partial_pop_then_drop_releases_all_values() {
+    let value = Rc::new(1);
+    let mut vec = DefaultSparseVec::new();
+    for i in 0..6 {
+        vec.push(if i % 2 == 0 { None } else { Some(Rc::clone(&value)) });
+    }
+    assert_eq!(Rc::strong_count(&value), 4);
+    assert_eq!(vec.pop(), Some(Some(Rc::clone(&value))));
+    assert_eq!(vec.pop(), Some(None));
+    assert_eq!(Rc::strong_count(&value), 3);
+    drop(vec);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
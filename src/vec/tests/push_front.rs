@@ -0,0 +1,70 @@
+//! push_front / pop_front のテスト
+//! 固定のシードで作った操作列 (push / pop の両端、デフォルト値を含む) を VecDeque と比べます
+
+use std::collections::VecDeque;
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, error::ValidationMode};
+
+use super::SplitMix64;
+
+#[test]
+fn random_sequences_match_vec_deque() {
+    for seed in 0..20 {
+        let mut rng = SplitMix64(seed);
+        let mut vec = DefaultSparseVec::new();
+        let mut deque = VecDeque::new();
+        for step in 0..300 {
+            // 値の半分はデフォルト値 (0)
+            let elem = if rng.below(2) == 0 { 0 } else { rng.below(100) as i32 + 1 };
+            match rng.below(5) {
+                0 | 1 => {
+                    vec.push_front(elem);
+                    deque.push_front(elem);
+                }
+                2 => assert_eq!(vec.pop_front(), deque.pop_front(), "seed {seed}, step {step}"),
+                3 => {
+                    vec.push(elem);
+                    deque.push_back(elem);
+                }
+                _ => assert_eq!(vec.pop(), deque.pop_back(), "seed {seed}, step {step}"),
+            }
+            assert_eq!(vec.len(), deque.len(), "seed {seed}, step {step}");
+        }
+        assert!(vec.iter_dense().eq(deque.iter()), "seed {seed}");
+        assert_eq!(vec.nnz(), deque.iter().filter(|elem| **elem != 0).count());
+        assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+    }
+}
+
+#[test]
+fn drain_from_the_front() {
+    let mut rng = SplitMix64(207);
+    let dense: Vec<i32> = (0..100).map(|_| if rng.below(3) == 0 { rng.below(9) as i32 + 1 } else { 0 }).collect();
+    let mut vec = DefaultSparseVec::from(dense.clone());
+    let mut deque = VecDeque::from(dense);
+    while let Some(elem) = deque.pop_front() {
+        assert_eq!(vec.pop_front(), Some(elem));
+        assert_eq!(vec.as_pairs().0.first().copied(), deque.iter().position(|elem| *elem != 0));
+    }
+    assert_eq!(vec.pop_front(), None);
+    assert!(vec.is_empty());
+}
+
+#[test]
+fn non_zero_default_and_strings() {
+    let mut vec = DefaultSparseVec::with_default(7);
+    vec.push_front(7);
+    vec.push_front(1);
+    vec.push_front(7);
+    assert_eq!((vec.to_dense(), vec.nnz()), (vec![7, 1, 7], 1));
+    assert_eq!(vec.pop_front(), Some(7));
+    assert_eq!(vec.pop_front(), Some(1));
+    assert_eq!(vec.nnz(), 0);
+
+    let mut vec = DefaultSparseVec::<String>::new();
+    vec.push_front("a".into());
+    vec.push_front(String::new());
+    vec.push_front("b".into());
+    assert_eq!(vec.pop_front().as_deref(), Some("b"));
+    assert_eq!(vec.to_dense(), ["", "a"]);
+}
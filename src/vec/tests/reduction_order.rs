@@ -0,0 +1,108 @@
+//! ReductionOrder のテスト
+//! 固定のシードで作ったベクタの f64 / f32 の内積のビット列を、記録しておいたハッシュと比べます
+//! (加算順序が変わるとビット列が変わるので、最適化やリファクタリングで順序が崩れたことを検出できます)
+
+use crate::vec::{
+    adaptive_vec::AdaptiveVec,
+    default_sparse_vec::DefaultSparseVec,
+    shared_sparse_vec::SharedSparseVec,
+    vec_trait::{Math, ReductionOrder},
+};
+
+use super::SplitMix64;
+
+impl SplitMix64 {
+    /// [-1, 1) の一様な値を約 density の割合で、残りを 0.0 にします
+    fn sparse_f64(&mut self, len: usize, density: f64) -> Vec<f64> {
+        (0..len)
+            .map(|_| {
+                let keep = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+                let value = (self.next() >> 11) as f64 / (1u64 << 52) as f64 - 1.0;
+                if keep < density { value } else { 0.0 }
+            })
+            .collect()
+    }
+}
+
+/// FNV-1a で u64 の列を 1 つのハッシュにまとめます
+fn fnv1a(words: impl IntoIterator<Item = u64>) -> u64 {
+    words.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, word| {
+        word.to_le_bytes().iter().fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3))
+    })
+}
+
+const SEED: u64 = 0x5eed_0209;
+const LENS: [usize; 8] = [0, 1, 7, 8, 9, 100, 1000, 5000];
+const ORDERS: [ReductionOrder; 2] = [ReductionOrder::Ascending, ReductionOrder::PairwiseTree];
+
+/// LENS の各長さについて 2 本のベクタを作ります (密度は 0.3 と 0.6)
+fn pairs() -> Vec<(Vec<f64>, Vec<f64>)> {
+    let mut rng = SplitMix64(SEED);
+    LENS.iter().map(|&len| (rng.sparse_f64(len, 0.3), rng.sparse_f64(len, 0.6))).collect()
+}
+
+/// 両方が 0 でない位置の積を index 昇順に並べます
+fn products<F: Copy + PartialEq + Default + std::ops::Mul<Output = F>>(a: &[F], b: &[F]) -> Vec<F> {
+    a.iter().zip(b).filter(|(x, y)| **x != F::default() && **y != F::default()).map(|(&x, &y)| x * y).collect()
+}
+
+/// pairwise_sum と同じ分割 (8 個以下は順に加算、それ以上は len / 2 で分割) の参照実装
+fn reference_pairwise(values: &[f64]) -> f64 {
+    if values.len() <= 8 {
+        values.iter().fold(0.0, |acc, &v| acc + v)
+    } else {
+        let (left, right) = values.split_at(values.len() / 2);
+        reference_pairwise(left) + reference_pairwise(right)
+    }
+}
+
+#[test]
+fn f64_orders_match_the_reference_sums() {
+    for (a, b) in pairs() {
+        let products = products(&a, &b);
+        let (x, y) = (DefaultSparseVec::from(a), DefaultSparseVec::from(b));
+        let ascending = products.iter().fold(0.0, |acc, &v| acc + v);
+        assert_eq!(x.f64_dot_with_order(&y, ReductionOrder::Ascending).to_bits(), ascending.to_bits());
+        assert_eq!(x.f64_dot(&y).to_bits(), ascending.to_bits());
+        let pairwise = reference_pairwise(&products);
+        assert_eq!(x.f64_dot_with_order(&y, ReductionOrder::PairwiseTree).to_bits(), pairwise.to_bits());
+    }
+}
+
+#[test]
+fn f64_bit_patterns_are_pinned() {
+    let bits = pairs().into_iter().flat_map(|(a, b)| {
+        let (x, y) = (DefaultSparseVec::from(a), DefaultSparseVec::from(b));
+        ORDERS.map(|order| x.f64_dot_with_order(&y, order).to_bits())
+    });
+    assert_eq!(fnv1a(bits), F64_HASH);
+}
+
+#[test]
+fn f32_bit_patterns_are_pinned() {
+    let bits = pairs().into_iter().flat_map(|(a, b)| {
+        let narrow = |v: Vec<f64>| DefaultSparseVec::from(v.into_iter().map(|x| x as f32).collect::<Vec<_>>());
+        let (x, y) = (narrow(a), narrow(b));
+        ORDERS.map(|order| x.f32_dot_with_order(&y, order).to_bits() as u64)
+    });
+    assert_eq!(fnv1a(bits), F32_HASH);
+}
+
+#[test]
+fn all_containers_give_the_same_bits() {
+    for (a, b) in pairs() {
+        let (x, y) = (DefaultSparseVec::from(a), DefaultSparseVec::from(b));
+        let (sx, sy) = (SharedSparseVec::from(x.clone()), SharedSparseVec::from(y.clone()));
+        let (ax, ay) = (AdaptiveVec::from(x.clone()), AdaptiveVec::from(y.clone()));
+        for order in ORDERS {
+            let expected = x.f64_dot_with_order(&y, order).to_bits();
+            assert_eq!(x.view().f64_dot_with_order(&y.view(), order).to_bits(), expected, "{order:?}");
+            assert_eq!(sx.f64_dot_with_order(&sy, order).to_bits(), expected, "{order:?}");
+            assert_eq!(ax.f64_dot_with_order(&ay, order).to_bits(), expected, "{order:?}");
+        }
+    }
+}
+
+/// 記録したハッシュ (加算順序を意図して変えた場合だけ更新します)
+const F64_HASH: u64 = 0x26e3_6815_b4b5_69fa;
+const F32_HASH: u64 = 0x5f1b_61ea_ba1c_2e03;
@@ -0,0 +1,106 @@
+//! from_events / scatter_add のテスト
+//! 固定のシードで作ったイベント列 (同じ index の重複や、合計がデフォルト値に戻るものを含む) を dense の加算と比べます
+
+use crate::vec::{
+    default_sparse_vec::{CapPolicy, DefaultSparseVec},
+    error::SparseVecError,
+    workspace::SparseWorkspace,
+};
+
+use super::SplitMix64;
+
+/// 長さ len のイベント列 (index の重複が多くなるよう、値は小さい範囲にします)
+fn events(rng: &mut SplitMix64, len: usize) -> Vec<(usize, i64)> {
+    let count = if len == 0 { 0 } else { rng.below(3 * len + 1) };
+    (0..count).map(|_| (rng.below(len), rng.below(7) as i64 - 3)).collect()
+}
+
+/// dense での加算 (参照実装)
+fn dense_add(dense: &mut [i64], events: &[(usize, i64)]) {
+    for &(index, elem) in events {
+        dense[index] += elem;
+    }
+}
+
+#[test]
+fn from_events_matches_dense() {
+    let mut rng = SplitMix64(208);
+    for _ in 0..100 {
+        let len = rng.below(40);
+        let events = events(&mut rng, len);
+        let mut expected = vec![0; len];
+        dense_add(&mut expected, &events);
+        let vec = DefaultSparseVec::from_events(len, events.iter().copied());
+        assert_eq!(vec.to_dense(), expected, "{events:?}");
+        assert_eq!(vec.count_stored_defaults(), 0);
+        assert_eq!(DefaultSparseVec::try_from_events(len, events), Ok(vec));
+    }
+}
+
+#[test]
+fn scatter_add_matches_dense() {
+    let mut rng = SplitMix64(209);
+    let mut ws = SparseWorkspace::new();
+    for round in 0..100 {
+        let len = rng.below(40);
+        let default = if round % 3 == 0 { 2 } else { 0 };
+        let dense: Vec<i64> = (0..len).map(|_| if rng.below(3) == 0 { rng.below(5) as i64 - 2 } else { default }).collect();
+        let events = events(&mut rng, len);
+        let mut expected = dense.clone();
+        dense_add(&mut expected, &events);
+
+        let mut vec = DefaultSparseVec::from_vec_with_default(dense.clone(), default);
+        vec.scatter_add(events.iter().copied());
+        assert_eq!(vec.to_dense(), expected, "{dense:?} + {events:?}");
+        assert_eq!(vec.count_stored_defaults(), 0);
+
+        let mut fallible = DefaultSparseVec::from_vec_with_default(dense.clone(), default);
+        assert_eq!(fallible.try_scatter_add(events.iter().copied()), Ok(()));
+        assert_eq!(fallible, vec);
+
+        let mut with_ws = DefaultSparseVec::from_vec_with_default(dense, default);
+        with_ws.scatter_add_with_workspace(events, &mut ws);
+        assert_eq!(with_ws, vec);
+    }
+}
+
+#[test]
+fn out_of_range_index_is_an_error() {
+    let events = [(1, 5i64), (7, 1), (3, 2), (4, 1)];
+    assert_eq!(
+        DefaultSparseVec::try_from_events(4, events),
+        Err(SparseVecError::IndexOutOfBounds { index: 4, len: 4 })
+    );
+
+    let mut vec = DefaultSparseVec::from(vec![0i64, 1, 0, 2]);
+    let before = vec.clone();
+    assert_eq!(vec.try_scatter_add(events), Err(SparseVecError::IndexOutOfBounds { index: 4, len: 4 }));
+    // エラーの場合は何も変更しません
+    assert_eq!(vec, before);
+    assert_eq!(vec.as_pairs(), before.as_pairs());
+}
+
+#[test]
+fn nnz_cap_is_an_error() {
+    let mut vec = DefaultSparseVec::from(vec![0i64, 1, 0, 2]);
+    vec.set_max_nnz(Some(2));
+    vec.set_cap_policy(CapPolicy::Error);
+    assert_eq!(vec.try_scatter_add([(0, 1)]), Err(SparseVecError::NnzCapExceeded { max_nnz: 2 }));
+    assert_eq!(vec.to_dense(), [0, 1, 0, 2]);
+    // 既存の物理要素への加算は物理要素を増やしません
+    assert_eq!(vec.try_scatter_add([(1, 4), (3, -2)]), Ok(()));
+    assert_eq!(vec.to_dense(), [0, 5, 0, 0]);
+}
+
+#[test]
+#[should_panic(expected = "stored index 9 out of bounds for len 3")]
+fn from_events_panics_on_out_of_range_index() {
+    let _ = DefaultSparseVec::from_events(3, [(0, 1i64), (9, 1)]);
+}
+
+#[test]
+#[should_panic(expected = "stored index 3 out of bounds for len 3")]
+fn scatter_add_panics_on_out_of_range_index() {
+    let mut vec = DefaultSparseVec::from(vec![0i64, 1, 0]);
+    vec.scatter_add([(3, 1)]);
+}
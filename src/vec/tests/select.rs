@@ -0,0 +1,75 @@
+//! select / compress_select のテスト
+//! 固定のシードで作ったベクタと mask で、dense の boolean indexing と比べます
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, error::SparseVecError};
+
+use super::SplitMix64;
+
+/// (ベクタ, mask) の組 (ベクタのデフォルト値と mask のデフォルト値の両方を変えます)
+fn cases() -> Vec<(DefaultSparseVec<i32>, DefaultSparseVec<bool>)> {
+    let mut rng = SplitMix64(206);
+    (0..60)
+        .map(|round| {
+            let len = rng.below(50);
+            let default = if round % 3 == 0 { 4 } else { 0 };
+            let dense = (0..len).map(|_| if rng.below(3) == 0 { rng.below(9) as i32 - 4 } else { default }).collect();
+            let mask_default = round % 2 == 0;
+            let mask = (0..len).map(|_| if rng.below(4) == 0 { !mask_default } else { mask_default }).collect();
+            (
+                DefaultSparseVec::from_vec_with_default(dense, default),
+                DefaultSparseVec::from_vec_with_default(mask, mask_default),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn select_matches_dense() {
+    for (vec, mask) in cases() {
+        let default = *vec.default_value();
+        let expected: Vec<i32> =
+            vec.to_dense().into_iter().zip(mask.to_dense()).map(|(elem, keep)| if keep { elem } else { default }).collect();
+        let selected = vec.select(&mask);
+        assert_eq!(selected.to_dense(), expected, "{:?} / {:?}", vec.to_dense(), mask.to_dense());
+        assert_eq!(selected.default_value(), &default);
+        assert_eq!(selected.count_stored_defaults(), 0);
+        assert_eq!(vec.try_select(&mask), Ok(selected));
+    }
+}
+
+#[test]
+fn compress_select_matches_dense() {
+    for (vec, mask) in cases() {
+        let expected: Vec<i32> =
+            vec.to_dense().into_iter().zip(mask.to_dense()).filter(|(_, keep)| *keep).map(|(elem, _)| elem).collect();
+        let selected = vec.compress_select(&mask);
+        assert_eq!(selected.to_dense(), expected, "{:?} / {:?}", vec.to_dense(), mask.to_dense());
+        assert_eq!(selected.default_value(), vec.default_value());
+        assert_eq!(selected.count_stored_defaults(), 0);
+        assert_eq!(vec.try_compress_select(&mask), Ok(selected));
+    }
+}
+
+#[test]
+fn mask_of_another_length_is_an_error() {
+    let vec = DefaultSparseVec::from(vec![1, 0, 2]);
+    let mask = DefaultSparseVec::from(vec![true, false]);
+    let err = SparseVecError::LenMismatch { len: 3, other: 2 };
+    assert_eq!(vec.try_select(&mask), Err(err.clone()));
+    assert_eq!(vec.try_compress_select(&mask), Err(err));
+    assert_eq!(vec.try_select(&DefaultSparseVec::new()), Err(SparseVecError::LenMismatch { len: 3, other: 0 }));
+}
+
+#[test]
+#[should_panic(expected = "length mismatch: len is 3 but the other len is 4")]
+fn select_panics_on_another_length() {
+    let vec = DefaultSparseVec::from(vec![1, 0, 2]);
+    let _ = vec.select(&DefaultSparseVec::from(vec![true; 4]));
+}
+
+#[test]
+#[should_panic(expected = "length mismatch: len is 3 but the other len is 1")]
+fn compress_select_panics_on_another_length() {
+    let vec = DefaultSparseVec::from(vec![1, 0, 2]);
+    let _ = vec.compress_select(&DefaultSparseVec::from(vec![true]));
+}
@@ -0,0 +1,38 @@
+use std::{cell::Cell, sync::Arc, thread};
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, shared_sparse_vec::SharedSparseVec, sparse_vec_view::{SparseVecView, SparseVecViewMut}};
+
+fn assert_send<S: Send>() {}
+fn assert_sync<S: Sync>() {}
+
+#[test]
+fn sync_element_is_send_and_sync() {
+    assert_send::<DefaultSparseVec<u32>>();
+    assert_sync::<DefaultSparseVec<u32>>();
+    assert_send::<SparseVecView<'static, u32>>();
+    assert_send::<SparseVecViewMut<'static, String>>();
+    assert_sync::<SparseVecViewMut<'static, String>>();
+    assert_send::<SharedSparseVec<u32>>();
+    assert_sync::<SharedSparseVec<u32>>();
+}
+
+#[test]
+fn send_only_element_is_send() {
+    // Cell<u32> は Send だが Sync ではないので、DefaultSparseVec も Send だけ
+    // (Sync にならないことは DefaultSparseVec の Sync 実装の compile_fail で確かめます)
+    assert_send::<DefaultSparseVec<Cell<u32>>>();
+}
+
+#[test]
+fn shared_read_across_threads() {
+    let vec = Arc::new(DefaultSparseVec::from(vec![0u32, 3, 0, 5]));
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let vec = Arc::clone(&vec);
+            thread::spawn(move || vec.iter().map(|(_, elem)| *elem).sum::<u32>())
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 8);
+    }
+}
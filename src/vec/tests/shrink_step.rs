@@ -0,0 +1,88 @@
+use std::mem;
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+/// 1 要素 (インデックスと u64 の値) のバイト数
+const ENTRY: usize = mem::size_of::<usize>() + mem::size_of::<u64>();
+
+/// 物理要素 n 個で、容量に余りのあるベクタ
+fn numbers(n: usize) -> DefaultSparseVec<u64> {
+    let mut vec = DefaultSparseVec::with_capacity(n * 4);
+    for i in 0..n as u64 {
+        vec.push(0);
+        vec.push(i + 1);
+    }
+    vec
+}
+
+/// shrink_step が true を返すまでの呼び出し回数
+fn steps(vec: &mut DefaultSparseVec<u64>, max_bytes: usize) -> usize {
+    let mut steps = 1;
+    while !vec.shrink_step(max_bytes) {
+        steps += 1;
+    }
+    steps
+}
+
+#[test]
+fn shrink_step_finishes_with_exact_capacity() {
+    let mut vec = numbers(5);
+    assert_eq!(steps(&mut vec, 1), 5);
+    assert_eq!(vec.capacity(), 5);
+    assert_eq!(vec.get(3), Some(&2));
+    assert_eq!(vec.nnz(), 5);
+}
+
+#[test]
+fn every_call_stays_within_max_bytes() {
+    // 値もインデックスと同じく少しずつコピーするので、最後の呼び出しも max_bytes 分しかコピーしません
+    // (1 回でも多くコピーすれば、呼び出し回数は total / max_bytes の切り上げより少なくなります)
+    for (nnz, max_bytes) in [(100, 10 * ENTRY), (100, 10 * ENTRY + ENTRY - 1), (25, 10 * ENTRY), (7, ENTRY), (3, 64 * ENTRY)] {
+        let mut vec = numbers(nnz);
+        let expected = vec.to_dense();
+        assert_eq!(steps(&mut vec, max_bytes), nnz.div_ceil(max_bytes / ENTRY), "nnz {nnz}, max_bytes {max_bytes}");
+        assert_eq!(vec.capacity(), nnz);
+        assert_eq!(vec.to_dense(), expected);
+    }
+}
+
+#[test]
+fn shrink_step_interleaved_with_reads() {
+    let mut vec = numbers(4);
+    let expected = vec.to_dense();
+    while !vec.shrink_step(ENTRY) {
+        assert_eq!(vec.to_dense(), expected);
+        assert_eq!(vec.get(7), Some(&4));
+    }
+    assert_eq!(vec.to_dense(), expected);
+}
+
+#[test]
+fn shrink_step_interleaved_with_writes() {
+    let mut vec = numbers(4);
+    assert!(!vec.shrink_step(ENTRY));
+    // 変更操作でコピー途中の状態は破棄され、次の呼び出しからやり直す
+    *vec.iter_mut().next().unwrap().1 = 10;
+    assert!(!vec.shrink_step(ENTRY));
+    vec.push(20);
+    assert!(!vec.shrink_step(ENTRY));
+    vec.remove(2);
+    assert!(!vec.shrink_step(ENTRY));
+    vec.set(4, 30);
+    while !vec.shrink_step(2 * ENTRY) {}
+    assert_eq!(vec.capacity(), vec.nnz());
+    assert_eq!(vec.to_dense(), [0, 10, 2, 0, 30, 0, 4, 20]);
+}
+
+#[test]
+fn shrink_step_then_drop_mid_copy() {
+    let mut vec = numbers(4);
+    assert!(!vec.shrink_step(ENTRY));
+    drop(vec);
+
+    let mut vec = numbers(4);
+    assert!(!vec.shrink_step(ENTRY));
+    vec.clear();
+    assert!(vec.shrink_step(ENTRY));
+    assert_eq!(vec.capacity(), 0);
+}
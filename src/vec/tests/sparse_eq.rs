@@ -0,0 +1,91 @@
+//! testing モジュール (assert_sparse_eq! / diff / diff_approx) のテスト
+
+use crate::{
+    assert_sparse_eq,
+    vec::{
+        default_sparse_vec::DefaultSparseVec,
+        gap_sparse_vec::GapSparseVec,
+        testing::{diff, diff_approx, SparseDiffKind, DIFF_SHOWN},
+    },
+};
+
+fn sample() -> DefaultSparseVec<i32> {
+    DefaultSparseVec::from(vec![0, 3, 0, 0, 5, 0])
+}
+
+#[test]
+fn equal_across_representations() {
+    let vec = sample();
+    let dense = vec![0, 3, 0, 0, 5, 0];
+    assert_sparse_eq!(vec, dense);
+    assert_sparse_eq!(vec, dense[..]);
+    assert_sparse_eq!(vec.view(), vec);
+    assert_sparse_eq!(vec.read_only(), &vec);
+    assert_sparse_eq!(vec.reversed_view(), vec![0, 5, 0, 0, 3, 0]);
+    let mut gap = GapSparseVec::new();
+    dense.iter().for_each(|&x| gap.push(x));
+    assert_sparse_eq!(gap, vec);
+    assert_sparse_eq!(DefaultSparseVec::<i32>::new(), Vec::<i32>::new());
+}
+
+#[test]
+fn length_diff() {
+    let diff = diff(&sample(), &vec![0, 3, 0]).unwrap();
+    assert_eq!(diff.kind, SparseDiffKind::Length);
+    assert_eq!(diff.len, (6, 3));
+    assert_eq!(diff.nnz, (Some(2), None));
+    assert_eq!(diff.differing, 0);
+}
+
+#[test]
+fn content_diff_lists_the_first_positions() {
+    let left = DefaultSparseVec::from(vec![0i32; 20]);
+    let right: Vec<i32> = (0..20).collect();
+    let diff = diff(&left, &right).unwrap();
+    assert_eq!(diff.kind, SparseDiffKind::Content);
+    // index 0 だけが等しい
+    assert_eq!(diff.differing, 19);
+    assert_eq!(diff.positions.len(), DIFF_SHOWN);
+    assert_eq!(diff.positions[0], (1, "0".to_string(), "1".to_string()));
+    let message = diff.to_string();
+    assert!(message.starts_with("sparse vectors differ: content"), "{message}");
+    assert!(message.contains("differing positions: 19 (first 8 shown)"), "{message}");
+    assert!(message.contains("[8] left: 0, right: 8"), "{message}");
+    assert!(!message.contains("[9]"), "{message}");
+}
+
+#[test]
+fn normalization_diff_for_stored_defaults() {
+    let mut vec = sample();
+    // IndexMut は物理要素にデフォルト値を書いても削除しません
+    vec[1] = 0;
+    let diff = diff(&vec, &vec![0, 0, 0, 0, 5, 0]).unwrap();
+    assert_eq!(diff.kind, SparseDiffKind::Normalization);
+    assert_eq!(diff.stored_defaults, (1, 0));
+    assert_eq!((diff.differing, diff.positions.len()), (0, 0));
+    assert!(diff.to_string().contains("normalization only"));
+}
+
+#[test]
+fn approx_diff_uses_epsilon() {
+    let vec = DefaultSparseVec::from(vec![0.0, 1.0, 0.0, 2.5]);
+    let close = vec![0.0, 1.0 + 1e-9, 0.0, 2.5 - 1e-9];
+    assert_eq!(diff_approx(&vec, &close, 1e-6), None);
+    assert_sparse_eq!(vec, close, 1e-6);
+    let far = diff_approx(&vec, &close, 1e-12).unwrap();
+    assert_eq!((far.kind, far.differing), (SparseDiffKind::Content, 2));
+    // NaN はどの epsilon でも等しくなりません
+    assert!(diff_approx(&vec![f64::NAN], &vec![f64::NAN], 1.0).is_some());
+}
+
+#[test]
+#[should_panic(expected = "assertion `sample() == vec![0, 3, 0, 0, 4, 0]` failed\nsparse vectors differ: content")]
+fn assert_sparse_eq_panics_with_the_diff() {
+    assert_sparse_eq!(sample(), vec![0, 3, 0, 0, 4, 0]);
+}
+
+#[test]
+#[should_panic(expected = "(epsilon = 0.1) failed")]
+fn assert_sparse_eq_epsilon_panics_with_the_diff() {
+    assert_sparse_eq!(DefaultSparseVec::from(vec![1.0, 0.0]), vec![1.5, 0.0], 0.1);
+}
@@ -0,0 +1,74 @@
+//! swap_remove のテスト
+//! 取り除く位置と最後の要素がそれぞれデフォルト値かどうかの 4 通りと、最後の要素、長さ 1 のベクタを Vec::swap_remove と比べます
+
+use crate::vec::{default_sparse_vec::DefaultSparseVec, error::ValidationMode};
+
+fn check(dense: &[i32], index: usize) {
+    let mut expected = dense.to_vec();
+    let mut vec = DefaultSparseVec::from(expected.clone());
+    let removed = vec.swap_remove(index);
+    assert_eq!(removed, expected.swap_remove(index), "{dense:?} at {index}");
+    assert_eq!(vec.to_dense(), expected, "{dense:?} at {index}");
+    assert_eq!(vec.nnz(), expected.iter().filter(|elem| **elem != 0).count());
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+}
+
+#[test]
+fn value_replaced_by_value() {
+    // 物理要素の上書き
+    check(&[1, 0, 2, 0, 3], 2);
+    check(&[1, 0, 2, 0, 3], 0);
+}
+
+#[test]
+fn value_replaced_by_default() {
+    // 物理要素の削除
+    check(&[1, 0, 2, 0, 0], 2);
+    check(&[1, 0, 2, 0, 0], 0);
+}
+
+#[test]
+fn default_replaced_by_value() {
+    // 物理要素の追加
+    check(&[1, 0, 2, 0, 3], 1);
+    check(&[0, 0, 0, 0, 3], 0);
+}
+
+#[test]
+fn default_replaced_by_default() {
+    // 物理領域は変わりません
+    check(&[1, 0, 2, 0, 0], 1);
+    check(&[0, 0, 0, 0, 0], 3);
+}
+
+#[test]
+fn last_element() {
+    check(&[1, 0, 2, 0, 3], 4);
+    check(&[1, 0, 2, 0, 0], 4);
+}
+
+#[test]
+fn single_element() {
+    check(&[5], 0);
+    check(&[0], 0);
+    let mut vec = DefaultSparseVec::from(vec![String::from("a")]);
+    assert_eq!(vec.swap_remove(0), "a");
+    assert!(vec.is_empty());
+    assert_eq!(vec.nnz(), 0);
+}
+
+#[test]
+fn strings() {
+    let mut vec = DefaultSparseVec::from(vec![String::from("a"), String::new(), "b".into(), "c".into()]);
+    assert_eq!(vec.swap_remove(1), "");
+    assert_eq!(vec.to_dense(), ["a", "c", "b"]);
+    assert_eq!(vec.swap_remove(0), "a");
+    assert_eq!(vec.to_dense(), ["b", "c"]);
+    assert_eq!(vec.validate(ValidationMode::Strict), Ok(0));
+}
+
+#[test]
+#[should_panic(expected = "swap_remove index (is 3) should be < len (is 3)")]
+fn out_of_bounds_panics() {
+    DefaultSparseVec::from(vec![1, 0, 2]).swap_remove(3);
+}
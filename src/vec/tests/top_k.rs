@@ -0,0 +1,96 @@
+//! top_k / build_value_index のテスト
+//! 索引を作ってから各種の変更を行い、索引が破棄されて top_k が変更後の値を返すことを確かめます
+
+use crate::vec::default_sparse_vec::DefaultSparseVec;
+
+fn sample() -> DefaultSparseVec<i32> {
+    DefaultSparseVec::from(vec![0, 5, 0, -2, 9, 0, 5, 1, 0, 7])
+}
+
+/// 物理要素を値の降順 (同じ値は index の昇順) に並べた参照実装
+fn expected_top_k(vec: &DefaultSparseVec<i32>, k: usize) -> Vec<(usize, i32)> {
+    let mut pairs: Vec<(usize, i32)> = vec.iter().map(|(index, elem)| (index, *elem)).collect();
+    pairs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    pairs.truncate(k);
+    pairs
+}
+
+fn assert_top_k(vec: &DefaultSparseVec<i32>, what: &str) {
+    for k in 0..=vec.nnz() + 1 {
+        let top: Vec<(usize, i32)> = vec.top_k(k).into_iter().map(|(index, elem)| (index, *elem)).collect();
+        assert_eq!(top, expected_top_k(vec, k), "after {what}, k = {k}");
+    }
+}
+
+/// 索引を作ってから mutate を行い、索引が破棄されていることを確かめます
+fn check(what: &str, mutate: impl FnOnce(&mut DefaultSparseVec<i32>)) {
+    let mut vec = sample();
+    vec.build_value_index();
+    assert!(vec.has_value_index());
+    assert_top_k(&vec, "build_value_index");
+    mutate(&mut vec);
+    assert!(!vec.has_value_index(), "{what} kept a stale value index");
+    assert_top_k(&vec, what);
+    // 作り直した索引でも同じ結果になります
+    vec.build_value_index();
+    assert_top_k(&vec, what);
+}
+
+#[test]
+fn index_matches_selection() {
+    let mut vec = sample();
+    assert_top_k(&vec, "nothing");
+    vec.build_value_index();
+    assert_top_k(&vec, "build_value_index");
+    assert_eq!(vec.top_k(3).into_iter().map(|(index, elem)| (index, *elem)).collect::<Vec<_>>(), [(4, 9), (9, 7), (1, 5)]);
+    vec.drop_value_index();
+    assert!(!vec.has_value_index());
+    assert_top_k(&vec, "drop_value_index");
+}
+
+#[test]
+fn set_invalidates() {
+    check("set of a stored index", |vec| {
+        vec.set(7, 100);
+    });
+    check("set of a sparse index", |vec| {
+        vec.set(0, 8);
+    });
+    check("set to the default", |vec| {
+        vec.set(4, 0);
+    });
+}
+
+#[test]
+fn insert_and_remove_invalidate() {
+    check("insert", |vec| vec.insert(0, 6));
+    check("insert of the default", |vec| vec.insert(2, 0));
+    check("remove", |vec| {
+        vec.remove(4);
+    });
+    check("push", |vec| vec.push(50));
+    check("pop", |vec| {
+        vec.pop();
+    });
+}
+
+#[test]
+fn views_invalidate() {
+    check("split_at_mut_view", |vec| {
+        let (mut left, mut right) = vec.split_at_mut_view(5);
+        left.set(1, -10);
+        right.set(2, 20);
+    });
+    check("disjoint_views_mut", |vec| {
+        let mut views = vec.disjoint_views_mut(&[3, 6]);
+        views[1].set(0, 3);
+        views[2].set(2, 0);
+    });
+}
+
+#[test]
+fn mutable_access_invalidates() {
+    check("as_pairs_mut", |vec| vec.as_pairs_mut().1[0] = -50);
+    check("iter_mut", |vec| vec.iter_mut().for_each(|(_, elem)| *elem = -*elem));
+    check("index_mut", |vec| vec[9] = -1);
+}
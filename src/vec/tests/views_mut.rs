@@ -0,0 +1,88 @@
+//! split_at_mut_view / disjoint_views_mut のテスト
+//! (最後のビューの drop で本体にマージする経路を Miri で確かめられる大きさにしています)
+
+use std::thread;
+
+use crate::vec::default_sparse_vec::{CapPolicy, DefaultSparseVec};
+
+#[test]
+fn split_set_drop_round_trip() {
+    let mut vec = DefaultSparseVec::from(vec![0i32, 1, 0, 0, 2, 0]);
+    {
+        let (mut left, mut right) = vec.split_at_mut_view(3);
+        assert_eq!((left.len(), right.len(), right.offset()), (3, 3, 3));
+        // 既存の要素の上書きと、オーバーフローバッファへの追加
+        left.set(1, 10);
+        left.set(2, 20);
+        right.set(1, 30);
+        right.set(2, 40);
+        assert_eq!(left.get(2), Some(&20));
+        assert_eq!(right.get(0), Some(&0));
+        assert_eq!((left.nnz(), right.nnz()), (2, 2));
+        drop(left);
+        // マージは最後のビューの drop まで行いません
+        right.set(0, 50);
+    }
+    assert_eq!(vec.to_dense(), vec![0, 10, 20, 50, 30, 40]);
+    assert_eq!(vec.nnz(), 5);
+    assert!(vec.as_pairs().0.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn set_default_removes_the_element() {
+    let mut vec = DefaultSparseVec::from(vec![String::new(), "a".into(), String::new(), "b".into()]);
+    {
+        let mut views = vec.disjoint_views_mut(&[2]);
+        // 既存の要素にデフォルト値を書くと物理要素を削除します
+        views[0].set(1, String::new());
+        // オーバーフローバッファの要素にデフォルト値を書いた場合も同じです
+        views[1].set(0, "c".into());
+        views[1].set(0, String::new());
+        assert_eq!((views[0].nnz(), views[1].nnz()), (0, 1));
+        assert_eq!(views[0].get(1).map(String::as_str), Some(""));
+    }
+    assert_eq!(vec.nnz(), 1);
+    assert_eq!(vec.as_pairs().0, [3]);
+    assert_eq!(vec.to_dense(), ["", "", "", "b"]);
+}
+
+#[test]
+fn defaults_written_through_iter_mut_are_compacted() {
+    let mut vec = DefaultSparseVec::from(vec![1i32, 2, 0, 3, 4]);
+    {
+        let (mut left, mut right) = vec.split_at_mut_view(2);
+        left.iter_mut().for_each(|(i, elem)| if i == 0 { *elem = 0 });
+        *right.get_stored_mut(2).unwrap() = 0;
+        assert_eq!((left.nnz(), right.nnz()), (1, 1));
+    }
+    assert_eq!(vec.as_pairs(), (&[1, 3][..], &[2, 3][..]));
+    assert_eq!(vec.to_dense(), vec![0, 2, 0, 3, 0]);
+}
+
+#[test]
+fn views_on_threads() {
+    let mut vec = DefaultSparseVec::from(vec![0u64; 8]);
+    vec.set(5, 1);
+    thread::scope(|scope| {
+        for (k, mut view) in vec.disjoint_views_mut(&[2, 4, 6]).into_iter().enumerate() {
+            scope.spawn(move || {
+                for i in 0..view.len() {
+                    view.set(i, (k * 10 + i) as u64);
+                }
+            });
+        }
+    });
+    assert_eq!(vec.to_dense(), vec![0, 1, 10, 11, 20, 21, 30, 31]);
+    assert_eq!(vec.nnz(), 7);
+}
+
+#[test]
+#[should_panic(expected = "nnz cap exceeded")]
+fn views_share_the_nnz_budget() {
+    let mut vec = DefaultSparseVec::from(vec![0i32; 4]);
+    vec.set_max_nnz(Some(1));
+    vec.set_cap_policy(CapPolicy::Error);
+    let (mut left, mut right) = vec.split_at_mut_view(2);
+    left.set(0, 1);
+    right.set(0, 2);
+}
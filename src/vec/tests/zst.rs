@@ -0,0 +1,121 @@
+//! 大きさ 0 の要素型 (ZST) のテスト
+//! 値の領域は確保せず、index の領域は物理要素を格納するときだけ確保すること (Miri で確かめられます)
+//! - 容量を指定する API (with_capacity / reserve / shrink_to など) は ZST では何もしません
+//! - 常にデフォルト値と等しい () は一度も確保しません
+
+use crate::vec::{alloc_audit::count_allocations, default_sparse_vec::DefaultSparseVec, error::ValidationMode};
+
+#[test]
+fn unit_push_pop_insert_remove() {
+    let mut vec = DefaultSparseVec::<()>::new();
+    for _ in 0..5 {
+        vec.push(());
+    }
+    vec.insert(2, ());
+    vec.insert(6, ());
+    vec.remove(0);
+    vec.swap_remove(1);
+    assert_eq!(vec.pop(), Some(()));
+    assert_eq!((vec.len(), vec.nnz(), vec.capacity(), vec.heap_size_bytes()), (4, 0, usize::MAX, 0));
+    assert_eq!(vec.iter_dense().count(), 4);
+    assert_eq!(vec.clone().into_iter().count(), 4);
+    vec.clear();
+    assert_eq!(vec.pop(), None);
+}
+
+/// 1 バリアントの enum (デフォルト値と常に等しいので格納されません)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Only {
+    #[default]
+    One,
+}
+
+#[test]
+fn single_variant_enum_is_never_stored() {
+    let mut vec = DefaultSparseVec::new();
+    vec.push(Only::One);
+    vec.insert(0, Only::One);
+    assert_eq!(vec.remove(1), Only::One);
+    assert_eq!((vec.len(), vec.nnz(), vec.heap_size_bytes()), (1, 0, 0));
+}
+
+/// デフォルト値と等しくならない ZST (物理的に格納されます)
+#[derive(Debug, Default, Clone, Copy)]
+struct NeverEqual;
+
+impl PartialEq for NeverEqual {
+    fn eq(&self, _: &Self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn stored_zst_uses_only_the_index_buffer() {
+    let mut vec = DefaultSparseVec::new();
+    for _ in 0..20 {
+        vec.push(NeverEqual);
+    }
+    assert_eq!(vec.nnz(), 20);
+    assert_eq!(vec.capacity(), usize::MAX);
+    assert!(vec.heap_size_bytes() >= 20 * std::mem::size_of::<usize>());
+
+    vec.insert(3, NeverEqual);
+    vec.remove(0);
+    vec.truncate(15);
+    assert_eq!(vec.pop().map(|_| ()), Some(()));
+    assert_eq!(vec.drain(2..5).count(), 3);
+    assert_eq!(vec.as_pairs().0, (0..11).collect::<Vec<_>>());
+    assert_eq!(vec.validate(ValidationMode::Lenient), Ok(0));
+
+    let copy = vec.clone();
+    assert_eq!(copy.nnz(), 11);
+    assert_eq!(copy.into_iter().count(), 11);
+    // 容量を指定する API は index の領域も変えません
+    let heap = vec.heap_size_bytes();
+    let ((), count) = count_allocations(|| {
+        vec.reserve(100);
+        vec.shrink_to_fit();
+    });
+    assert_eq!((count, vec.heap_size_bytes()), (0, heap));
+    // 容量を超えて格納する場合は、index の領域を必要な分だけ確保します
+    vec.extend(std::iter::repeat_n(NeverEqual, 100));
+    assert_eq!(vec.nnz(), 111);
+}
+
+#[test]
+fn capacity_apis_never_allocate_for_unit() {
+    let (mut vec, count) = count_allocations(|| {
+        for vec in [
+            DefaultSparseVec::<()>::with_capacity(1 << 20),
+            DefaultSparseVec::with_default_and_capacity((), 1 << 20),
+            DefaultSparseVec::with_nnz_capacity(1000, 1 << 20),
+            DefaultSparseVec::from_density_estimate(1000, 1.0),
+        ] {
+            assert_eq!((vec.nnz(), vec.heap_size_bytes()), (0, 0));
+        }
+        let mut vec = DefaultSparseVec::builder().len(1000).nnz(1 << 20).build();
+        vec.reserve(1 << 20);
+        vec.reserve_exact(1 << 20);
+        vec.try_reserve(usize::MAX - 1).unwrap();
+        vec.try_reserve_exact(usize::MAX).unwrap();
+        vec.shrink_to(10);
+        vec.shrink_to_fit();
+        assert!(vec.shrink_step(0));
+        for _ in 0..100 {
+            vec.push(());
+        }
+        vec.insert(5, ());
+        vec.remove(0);
+        vec.extend([(), (), ()]);
+        vec
+    });
+    assert_eq!(count, 0);
+    assert_eq!((vec.len(), vec.nnz(), vec.capacity(), vec.heap_size_bytes()), (1103, 0, usize::MAX, 0));
+    assert_eq!(vec.stats().cap, usize::MAX);
+    let ((), count) = count_allocations(|| {
+        vec.truncate(3);
+        vec.clear();
+        vec.shrink_to_fit();
+    });
+    assert_eq!(count, 0);
+}
@@ -0,0 +1,95 @@
+use super::iter::{IterDense, ModCheck};
+
+/// TransparentWrapper trait の定義
+/// Self が #[repr(transparent)] で T を 1 つだけ包む newtype であることを表す marker trait です
+/// DefaultSparseVec::transmute_values / into_inner_values / as_wrapped_view で、
+/// 値を clone せずに DefaultSparseVec<T> と DefaultSparseVec<Self> を読み替えるのに使います
+///
+/// # Safety
+/// 実装する型は次の全てを満たす必要があります
+/// - #[repr(transparent)] で、サイズが 0 でないフィールドは T 型の 1 つだけ
+///   (それ以外のフィールドは PhantomData などサイズ 0 かつアライメント 1 の型のみ)
+/// - 全ての T の値が Self の有効な値であり、逆も同じ (値に追加の不変条件を持たない)
+/// - Drop を実装しない (読み替えた後は T の drop と Self の drop のどちらかしか呼ばれません)
+///
+/// 次は安全性ではなく正しさの条件です
+/// - Self の PartialEq は T の PartialEq と同じ結果を返すこと
+///   (異なる場合、読み替えたベクタにデフォルト値と等しい物理要素が残ることがあります)
+///
+/// 例: `#[repr(transparent)] struct Score(f32);` に対して `unsafe impl TransparentWrapper<f32> for Score {}`
+pub unsafe trait TransparentWrapper<T> {}
+
+/// WrappedView構造体の定義
+/// DefaultSparseVec<T> を DefaultSparseVec<U> (U: TransparentWrapper<T>) として読む借用ビュー
+/// 物理領域をそのまま参照するので、作成は O(1) でコピーを行いません
+pub struct WrappedView<'a, U> {
+    len: usize,
+    ind: &'a [usize],
+    val: &'a [U],
+    default: &'a U,
+}
+
+impl<'a, U> WrappedView<'a, U> {
+    #[inline(always)]
+    pub(crate) fn new(len: usize, ind: &'a [usize], val: &'a [U], default: &'a U) -> Self {
+        WrappedView { len, ind, val, default }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// nnzメソッドの実装
+    /// 物理要素数
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        self.ind.len()
+    }
+
+    #[inline(always)]
+    pub fn default_value(&self) -> &'a U {
+        self.default
+    }
+
+    /// getメソッドの実装
+    /// 範囲外の場合は None
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<&'a U> {
+        if index >= self.len {
+            return None;
+        }
+        match self.ind.binary_search(&index) {
+            Ok(i) => Some(&self.val[i]),
+            Err(_) => Some(self.default),
+        }
+    }
+
+    /// as_pairsメソッドの実装
+    /// DefaultSparseVec::as_pairs と同じ (インデックス配列, 値配列) の組
+    #[inline(always)]
+    pub fn as_pairs(&self) -> (&'a [usize], &'a [U]) {
+        (self.ind, self.val)
+    }
+
+    /// iter_denseメソッドの実装
+    /// スパース分部を含む全要素を論理インデックス順に返します
+    #[inline(always)]
+    pub fn iter_dense(&self) -> IterDense<'a, U> {
+        IterDense::new(self.len, self.ind, self.val, self.default, ModCheck::none())
+    }
+}
+
+impl<U> Clone for WrappedView<'_, U> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<U> Copy for WrappedView<'_, U> {}
@@ -1,15 +1,230 @@
-#[allow(unused_variables)]
+/// 浮動小数点の内積の加算順序
+/// どちらの順序も入力が同じならプラットフォームに依らず同じビット列の結果になります
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReductionOrder {
+    /// index 昇順に 1 つずつ加算する
+    #[default]
+    Ascending,
+    /// index 昇順に並べた積を固定の分割で 2 分木状に加算する
+    /// 誤差が O(log n) に抑えられる
+    PairwiseTree,
+}
+
+/// 範囲外の index を読むときの扱い (get_with_edge)
+/// 畳み込みやステンシルの端の処理を呼び出し側で分岐せずに書くためのものです
+/// len 4 のベクタ [a, b, c, d] の index -2..6 はそれぞれ次のように読みます
+/// - Default: 0 0 | a b c d | 0 0 (0 はデフォルト値)
+/// - Clamp: a a | a b c d | d d
+/// - Wrap: c d | a b c d | a b
+/// - Reflect: c b | a b c d | c b (端の要素は繰り返さない、numpy.pad の reflect と同じ)
+///
+/// ! : len が 0 の場合は、どのモードでもデフォルト値を読みます
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgeMode {
+    #[default]
+    Default,
+    Clamp,
+    Wrap,
+    Reflect,
+}
+
+impl EdgeMode {
+    /// resolveメソッドの実装
+    /// 長さ len のベクタで index を読むときの、実際の index (デフォルト値を読む場合は None)
+    /// - len が isize::MAX を超えていても正しく計算します
+    #[must_use]
+    pub fn resolve(self, index: isize, len: usize) -> Option<usize> {
+        if index >= 0 && (index as usize) < len {
+            return Some(index as usize);
+        }
+        if len == 0 {
+            return None;
+        }
+        let (index, len) = (index as i128, len as i128);
+        let resolved = match self {
+            EdgeMode::Default => return None,
+            EdgeMode::Clamp => index.clamp(0, len - 1),
+            EdgeMode::Wrap => index.rem_euclid(len),
+            EdgeMode::Reflect => {
+                // 周期 2 (len - 1) で折り返す (len が 1 なら常に 0)
+                let period = 2 * (len - 1);
+                if period == 0 {
+                    0
+                } else {
+                    let m = index.rem_euclid(period);
+                    if m < len { m } else { period - m }
+                }
+            }
+        };
+        Some(resolved as usize)
+    }
+}
+
+/// 浮動小数点の内積の実装
+/// 浮動小数点の内積 (f32_dot / f64_dot) は index 昇順に加算します (ReductionOrder::Ascending)
+/// 並列化は行わないため、結果は常に決定的です
+/// 積は各値を f64 / f32 に変換してから計算します (整数の要素型でも使えます)
+/// ! : 2 つのベクタの len が異なる場合は panic します (空のベクタ同士の内積は 0)
+/// - 整数の内積は要素型ごとに UnsignedMath / SignedMath に分かれています
 pub trait Math<T> {
-    fn u128_dot(&self, other: &Self) -> u128 {unimplemented!()}
-    fn u64_dot(&self, other: &Self) -> u64 {unimplemented!()}
-    fn u32_dot(&self, other: &Self) -> u32 {unimplemented!()}
-    fn u16_dot(&self, other: &Self) -> u16 {unimplemented!()}
-    fn u8_dot(&self, other: &Self) -> u8 {unimplemented!()}
-    fn i128_dot(&self, other: &Self) -> i128 {unimplemented!()}
-    fn i64_dot(&self, other: &Self) -> i64 {unimplemented!()}
-    fn i32_dot(&self, other: &Self) -> i32 {unimplemented!()}
-    fn i16_dot(&self, other: &Self) -> i16 {unimplemented!()}
-    fn i8_dot(&self, other: &Self) -> i8 {unimplemented!()}
-    fn f64_dot(&self, other: &Self) -> f64 {unimplemented!()}
-    fn f32_dot(&self, other: &Self) -> f32 {unimplemented!()}
-}
\ No newline at end of file
+    fn f64_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f64;
+    fn f32_dot_with_order(&self, other: &Self, order: ReductionOrder) -> f32;
+
+    #[inline(always)]
+    fn f64_dot(&self, other: &Self) -> f64 {
+        self.f64_dot_with_order(other, ReductionOrder::Ascending)
+    }
+
+    #[inline(always)]
+    fn f32_dot(&self, other: &Self) -> f32 {
+        self.f32_dot_with_order(other, ReductionOrder::Ascending)
+    }
+}
+
+/// 符号なし整数の内積の実装
+/// 要素型が符号なし整数 (UnsignedElement) のベクタにだけ実装されます
+/// (f64 などの要素型で呼ぶとコンパイルエラーになり、小数部の切り捨てや負の値での panic は起きません)
+/// u128_dot は各値を u128 に広げてから積を計算するので、u64 同士の積もあふれません
+/// ! : 2 つのベクタの len が異なる場合は panic します (空のベクタ同士の内積は 0)
+/// - u128 に収まらない積や和 (u128 の要素型の場合) は panic
+///
+/// ```compile_fail
+/// use vec_plus::vec::{default_sparse_vec::DefaultSparseVec, vec_trait::UnsignedMath};
+/// let a = DefaultSparseVec::from(vec![1.5f64, 0.0]);
+/// let _ = a.u64_dot(&a.clone());
+/// ```
+pub trait UnsignedMath<T> {
+    fn u128_dot(&self, other: &Self) -> u128;
+
+    /// u128_dot で計算してから u64 に変換します
+    /// ! : 結果が u64 に収まらない場合は panic (途中の積や和が u64 を超えても、最終結果が収まれば正確です)
+    #[inline(always)]
+    fn u64_dot(&self, other: &Self) -> u64 {
+        u64::try_from(self.u128_dot(other)).expect("u64_dot result out of range for u64, use u128_dot")
+    }
+
+    /// u128_dot で計算してから u32 に変換します
+    /// ! : 結果が u32 に収まらない場合は panic
+    #[inline(always)]
+    fn u32_dot(&self, other: &Self) -> u32 {
+        u32::try_from(self.u128_dot(other)).expect("u32_dot result out of range for u32, use u128_dot")
+    }
+
+    /// u128_dot で計算してから u16 に変換します
+    /// ! : 結果が u16 に収まらない場合は panic
+    #[inline(always)]
+    fn u16_dot(&self, other: &Self) -> u16 {
+        u16::try_from(self.u128_dot(other)).expect("u16_dot result out of range for u16, use u128_dot")
+    }
+
+    /// u128_dot で計算してから u8 に変換します
+    /// ! : 結果が u8 に収まらない場合は panic
+    #[inline(always)]
+    fn u8_dot(&self, other: &Self) -> u8 {
+        u8::try_from(self.u128_dot(other)).expect("u8_dot result out of range for u8, use u128_dot")
+    }
+}
+
+/// 符号付き整数の内積の実装
+/// 要素型が符号付き整数 (SignedElement) のベクタにだけ実装されます
+/// i128_dot は各値を i128 に広げてから積を計算するので、i64 同士の積もあふれません
+/// ! : 2 つのベクタの len が異なる場合は panic します (空のベクタ同士の内積は 0)
+/// - i128 に収まらない積や和 (i128 の要素型の場合) は panic
+///
+/// ```compile_fail
+/// use vec_plus::vec::{default_sparse_vec::DefaultSparseVec, vec_trait::SignedMath};
+/// let a = DefaultSparseVec::from(vec![-1.5f32, 0.0]);
+/// let _ = a.i128_dot(&a.clone());
+/// ```
+pub trait SignedMath<T> {
+    fn i128_dot(&self, other: &Self) -> i128;
+
+    /// i128_dot で計算してから i64 に変換します
+    /// ! : 結果が i64 に収まらない場合は panic (途中の積や和が i64 を超えても、最終結果が収まれば正確です)
+    #[inline(always)]
+    fn i64_dot(&self, other: &Self) -> i64 {
+        i64::try_from(self.i128_dot(other)).expect("i64_dot result out of range for i64, use i128_dot")
+    }
+
+    /// i128_dot で計算してから i32 に変換します
+    /// ! : 結果が i32 に収まらない場合は panic
+    #[inline(always)]
+    fn i32_dot(&self, other: &Self) -> i32 {
+        i32::try_from(self.i128_dot(other)).expect("i32_dot result out of range for i32, use i128_dot")
+    }
+
+    /// i128_dot で計算してから i16 に変換します
+    /// ! : 結果が i16 に収まらない場合は panic
+    #[inline(always)]
+    fn i16_dot(&self, other: &Self) -> i16 {
+        i16::try_from(self.i128_dot(other)).expect("i16_dot result out of range for i16, use i128_dot")
+    }
+
+    /// i128_dot で計算してから i8 に変換します
+    /// ! : 結果が i8 に収まらない場合は panic
+    #[inline(always)]
+    fn i8_dot(&self, other: &Self) -> i8 {
+        i8::try_from(self.i128_dot(other)).expect("i8_dot result out of range for i8, use i128_dot")
+    }
+}
+
+/// UnsignedElement trait の定義
+/// UnsignedMath の内積を持つ要素型 (u8 / u16 / u32 / u64 / u128 / usize)
+/// 外部の型には実装できません
+pub trait UnsignedElement: sealed::Sealed {
+    /// 値をそのまま u128 に広げます (失敗しません)
+    fn widen(&self) -> u128;
+}
+
+/// SignedElement trait の定義
+/// SignedMath の内積を持つ要素型 (i8 / i16 / i32 / i64 / i128 / isize)
+/// 外部の型には実装できません
+pub trait SignedElement: sealed::Sealed {
+    /// 値をそのまま i128 に広げます (失敗しません)
+    fn widen(&self) -> i128;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_integer_element {
+    ($trait:ident => $wide:ty: $($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl $trait for $ty {
+                #[inline(always)]
+                fn widen(&self) -> $wide {
+                    *self as $wide
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_element!(UnsignedElement => u128: u8, u16, u32, u64, u128, usize);
+impl_integer_element!(SignedElement => i128: i8, i16, i32, i64, i128, isize);
+
+/// u128_mul_add関数の実装 (crate 内部用)
+/// sum + a * b (u128_dot の 1 項)
+/// ! : u128 に収まらない場合は panic
+#[inline(always)]
+pub(crate) fn u128_mul_add(sum: u128, a: u128, b: u128) -> u128 {
+    a.checked_mul(b).and_then(|product| sum.checked_add(product)).expect("u128_dot overflowed u128")
+}
+
+/// i128_mul_add関数の実装 (crate 内部用)
+/// sum + a * b (i128_dot の 1 項)
+/// ! : i128 に収まらない場合は panic
+#[inline(always)]
+pub(crate) fn i128_mul_add(sum: i128, a: i128, b: i128) -> i128 {
+    a.checked_mul(b).and_then(|product| sum.checked_add(product)).expect("i128_dot overflowed i128")
+}
+
+/// assert_same_len関数の実装
+/// 内積の 2 つのベクタの len が等しいことを確かめます
+#[inline(always)]
+pub(crate) fn assert_same_len(a: usize, b: usize) {
+    assert!(a == b, "dot product of vectors with different lengths ({a} and {b})");
+}
@@ -0,0 +1,179 @@
+use std::{mem, ops::AddAssign};
+
+use super::{default_sparse_vec::DefaultSparseVec, error::SparseVecError};
+
+/// 安定ソートで挿入ソートに切り替える長さ
+const INSERTION_RUN: usize = 16;
+
+/// SparseWorkspace構造体の定義
+/// merge 系の操作で使い回す作業領域
+/// (index, value) の組のバッファを 2 つ持ちます
+/// - updates: 入力を集めて、ソート・集約した結果を置く
+/// - scratch: 安定ソートの作業用
+///
+/// 同じ大きさの操作を繰り返す場合、一度必要な大きさまで伸びた後は確保を行いません
+/// (書き込み先のベクタの容量が足りている場合)
+pub struct SparseWorkspace<T> {
+    updates: Vec<(usize, T)>,
+    scratch: Vec<(usize, T)>,
+}
+
+impl<T> SparseWorkspace<T> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        SparseWorkspace {
+            updates: Vec::new(),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// with_capacityメソッドの実装
+    /// 1 回の操作で cap 個までの (index, value) を確保無しで扱える作業領域を作ります
+    #[inline(always)]
+    pub fn with_capacity(cap: usize) -> Self {
+        SparseWorkspace {
+            updates: Vec::with_capacity(cap),
+            scratch: Vec::with_capacity(cap),
+        }
+    }
+
+    /// capacityメソッドの実装
+    /// 確保無しで扱える (index, value) の数
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.updates.capacity().min(self.scratch.capacity())
+    }
+
+    /// clearメソッドの実装
+    /// 残っている値を drop します (容量はそのまま)
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.updates.clear();
+        self.scratch.clear();
+    }
+
+    /// shrinkメソッドの実装
+    /// 値を drop し、確保済みの領域を解放します
+    #[inline(always)]
+    pub fn shrink(&mut self) {
+        self.clear();
+        self.updates.shrink_to_fit();
+        self.scratch.shrink_to_fit();
+    }
+}
+
+impl<T> Default for SparseWorkspace<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default> SparseWorkspace<T> {
+    /// sort_updatesメソッドの実装
+    /// updates を index 順に安定ソートします (scratch を作業領域に使うので確保は行いません)
+    /// - 短い区間を挿入ソートしてから、updates と scratch の間で交互にマージします
+    fn sort_updates(&mut self) {
+        let n = self.updates.len();
+        if self.updates.windows(2).all(|pair| pair[0].0 <= pair[1].0) {
+            return;
+        }
+        for run in self.updates.chunks_mut(INSERTION_RUN) {
+            for i in 1..run.len() {
+                let mut j = i;
+                while j > 0 && run[j - 1].0 > run[j].0 {
+                    run.swap(j - 1, j);
+                    j -= 1;
+                }
+            }
+        }
+        self.scratch.clear();
+        self.scratch.resize_with(n, || (0, T::default()));
+        let mut width = INSERTION_RUN;
+        while width < n {
+            merge_runs(&mut self.updates, &mut self.scratch, width);
+            mem::swap(&mut self.updates, &mut self.scratch);
+            width *= 2;
+        }
+        self.scratch.clear();
+    }
+}
+
+/// merge_runs関数の実装
+/// src の長さ width の隣り合う区間の組を、dst にマージします
+/// 要素は swap で移すので、src には dst の古い値が残ります
+fn merge_runs<T>(src: &mut [(usize, T)], dst: &mut [(usize, T)], width: usize) {
+    let n = src.len();
+    let mut start = 0;
+    while start < n {
+        let mid = (start + width).min(n);
+        let end = (start + 2 * width).min(n);
+        let (mut i, mut j) = (start, mid);
+        for slot in &mut dst[start..end] {
+            // 同じ index は左の区間を先に取るので安定
+            if j == end || (i < mid && src[i].0 <= src[j].0) {
+                mem::swap(slot, &mut src[i]);
+                i += 1;
+            } else {
+                mem::swap(slot, &mut src[j]);
+                j += 1;
+            }
+        }
+        start = end;
+    }
+}
+
+/// 作業領域を使う merge 系の操作
+/// 結果は書き込み先のベクタの物理領域にその場で合成します
+impl<T: Default + PartialEq + Clone> DefaultSparseVec<T> {
+    /// merge_with_workspaceメソッドの実装
+    /// updates を ws に集めてから merge_with と同じ合成を行います
+    /// ! : updates の index が狭義単調増加でない、または len 以上の場合は panic (ベクタは変更しません)
+    pub fn merge_with_workspace<I, F>(&mut self, updates: I, combine: F, ws: &mut SparseWorkspace<T>)
+    where
+        I: IntoIterator<Item = (usize, T)>,
+        F: FnMut(&mut T, T),
+    {
+        if let Err(err) = self.try_merge_with_workspace(updates, combine, ws) {
+            panic!("{err}");
+        }
+    }
+
+    /// try_merge_with_workspaceメソッドの実装
+    /// merge_with_workspace の panic しない版 (エラーの場合、ws は空になります)
+    pub fn try_merge_with_workspace<I, F>(&mut self, updates: I, combine: F, ws: &mut SparseWorkspace<T>) -> Result<(), SparseVecError>
+    where
+        I: IntoIterator<Item = (usize, T)>,
+        F: FnMut(&mut T, T),
+    {
+        ws.clear();
+        ws.updates.extend(updates);
+        let result = self
+            .check_updates(&ws.updates)
+            .and_then(|()| self.merge_in_place(&mut ws.updates, combine));
+        ws.updates.clear();
+        result
+    }
+}
+
+impl<T: Default + PartialEq + Clone + AddAssign> DefaultSparseVec<T> {
+    /// scatter_add_with_workspaceメソッドの実装
+    /// scatter_add と同じ加算を、イベントの整列と集約に ws を使って行います
+    /// 同じ index のイベントは scatter_add と同じ順番で加算します
+    /// - index が len 以上のイベントがある場合は panic
+    pub fn scatter_add_with_workspace<I>(&mut self, events: I, ws: &mut SparseWorkspace<T>)
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        ws.clear();
+        ws.updates.extend(events);
+        ws.sort_updates();
+        let result = self
+            .aggregate_sorted(self.len(), &mut ws.updates)
+            .and_then(|()| self.merge_in_place(&mut ws.updates, |acc, elem| *acc += elem));
+        ws.updates.clear();
+        if let Err(err) = result {
+            panic!("{err}");
+        }
+    }
+}